@@ -10,7 +10,7 @@ use std::cell::RefCell;
 use std::cmp::max;
 use std::fmt;
 use wasmer_vm::{
-    raise_user_trap, resume_panic, wasmer_call_trampoline, Export, ExportFunction,
+    raise_user_trap, resume_panic, wasmer_call_trampoline, Export, ExportFunction, HostFunctionEnv,
     VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext, VMFunctionBody, VMFunctionKind,
     VMTrampoline,
 };
@@ -85,7 +85,8 @@ impl Function {
         // The engine linker will replace the address with one pointing to a
         // generated dynamic trampoline.
         let address = std::ptr::null() as *const VMFunctionBody;
-        let vmctx = Box::into_raw(Box::new(dynamic_ctx)) as *mut VMContext;
+        let (host_env, vmctx) = HostFunctionEnv::new(Box::new(dynamic_ctx));
+        let vmctx = vmctx as *mut VMContext;
 
         Self {
             store: store.clone(),
@@ -96,6 +97,7 @@ impl Function {
                 vmctx,
                 signature: ty.clone(),
                 call_trampoline: None,
+                host_env: Some(host_env),
             },
         }
     }
@@ -135,7 +137,8 @@ impl Function {
         // The engine linker will replace the address with one pointing to a
         // generated dynamic trampoline.
         let address = std::ptr::null() as *const VMFunctionBody;
-        let vmctx = Box::into_raw(Box::new(dynamic_ctx)) as *mut VMContext;
+        let (host_env, vmctx) = HostFunctionEnv::new(Box::new(dynamic_ctx));
+        let vmctx = vmctx as *mut VMContext;
 
         Self {
             store: store.clone(),
@@ -146,6 +149,7 @@ impl Function {
                 vmctx,
                 signature: ty.clone(),
                 call_trampoline: None,
+                host_env: Some(host_env),
             },
         }
     }
@@ -188,6 +192,7 @@ impl Function {
                 signature,
                 kind: VMFunctionKind::Static,
                 call_trampoline: None,
+                host_env: None,
             },
         }
     }
@@ -229,8 +234,8 @@ impl Function {
         // Wasm-defined functions have a `VMContext`.
         // In the case of Host-defined functions `VMContext` is whatever environment
         // the user want to attach to the function.
-        let box_env = Box::new(env);
-        let vmctx = Box::into_raw(box_env) as *mut _ as *mut VMContext;
+        let (host_env, vmctx) = HostFunctionEnv::new(Box::new(env));
+        let vmctx = vmctx as *mut _ as *mut VMContext;
         let signature = function.ty();
 
         Self {
@@ -242,6 +247,7 @@ impl Function {
                 vmctx,
                 signature,
                 call_trampoline: None,
+                host_env: Some(host_env),
             },
         }
     }
@@ -334,6 +340,14 @@ impl Function {
         self.ty().results().len()
     }
 
+    /// Returns whether or not these two functions refer to the same
+    /// function body and context, such as a function and the one read
+    /// back from a table.
+    pub fn same(&self, other: &Self) -> bool {
+        self.exported.address == other.exported.address
+            && self.exported.vmctx == other.exported.vmctx
+    }
+
     /// Call the [`Function`] function.
     ///
     /// Depending on where the Function is defined, it will call it.
@@ -424,6 +438,7 @@ impl Function {
             self.exported.address,
             self.exported.vmctx,
             self.exported.kind,
+            self.exported.host_env.clone(),
             self.definition.clone(),
         ))
     }
@@ -5,7 +5,7 @@ use crate::{MemoryType, MemoryView};
 use std::convert::TryInto;
 use std::slice;
 use std::sync::Arc;
-use wasmer_types::{Pages, ValueType};
+use wasmer_types::{Bytes, Pages, ValueType};
 use wasmer_vm::{Export, ExportMemory, Memory as RuntimeMemory, MemoryError};
 
 /// A WebAssembly `memory` instance.
@@ -45,6 +45,69 @@ impl Memory {
         })
     }
 
+    /// Creates a new host `Memory` from a custom implementation of the
+    /// [`vm::Memory`] trait, such as a [`vm::BufferMemory`] backed by a
+    /// custom allocator.
+    ///
+    /// The style of the memory must be compatible with the style the
+    /// modules importing it were compiled for, or instantiating them
+    /// fails. The [`Tunables`] of the store choose the latter, so a
+    /// custom [`Tunables`] is usually needed as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the size of the memory doesn't match its
+    /// [`VMMemoryDefinition`] or its [`MemoryType`].
+    ///
+    /// # Usage:
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use wasmer::{Memory, MemoryType, Pages, Store};
+    /// # use wasmer::vm::BufferMemory;
+    /// # let store = Store::default();
+    /// let ty = MemoryType::new(Pages(1), Some(Pages(4)), false);
+    /// let buffer = BufferMemory::new(&ty, Vec::new())?;
+    /// let memory = Memory::from_vm_memory(&store, Arc::new(buffer))?;
+    /// assert_eq!(memory.size(), Pages(1));
+    /// # Ok::<(), wasmer::MemoryError>(())
+    /// ```
+    ///
+    /// [`vm::Memory`]: crate::vm::Memory
+    /// [`vm::BufferMemory`]: crate::vm::BufferMemory
+    /// [`Tunables`]: crate::BaseTunables
+    /// [`VMMemoryDefinition`]: crate::vm::VMMemoryDefinition
+    pub fn from_vm_memory(
+        store: &Store,
+        memory: Arc<dyn RuntimeMemory>,
+    ) -> Result<Self, MemoryError> {
+        let size = memory.size();
+        let ty = memory.ty();
+        let definition = memory.vmmemory();
+        let current_length = unsafe { definition.as_ref() }.current_length;
+        if Bytes::from(current_length) != size.bytes() {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "the memory definition has {} bytes but the memory has {} pages",
+                    current_length, size.0
+                ),
+            });
+        }
+        if size < ty.minimum || matches!(ty.maximum, Some(maximum) if size > maximum) {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "the memory has {} pages, which is out of the limits of its type",
+                    size.0
+                ),
+            });
+        }
+
+        Ok(Self {
+            store: store.clone(),
+            memory,
+        })
+    }
+
     /// Returns the [`MemoryType`] of the `Memory`.
     pub fn ty(&self) -> &MemoryType {
         self.memory.ty()
@@ -5,7 +5,9 @@ use crate::types::{Val, ValFuncRef};
 use crate::RuntimeError;
 use crate::TableType;
 use std::sync::Arc;
-use wasmer_vm::{Export, ExportTable, Table as RuntimeTable, VMCallerCheckedAnyfunc};
+use wasmer_vm::{
+    Export, ExportTable, HostFunctionEnv, Table as RuntimeTable, VMCallerCheckedAnyfunc,
+};
 
 /// A WebAssembly `table` instance.
 ///
@@ -26,8 +28,20 @@ fn set_table_item(
     table: &dyn RuntimeTable,
     item_index: u32,
     item: VMCallerCheckedAnyfunc,
+    host_env: Option<HostFunctionEnv>,
 ) -> Result<(), RuntimeError> {
-    table.set(item_index, item).map_err(|e| e.into())
+    table
+        .set_with_host_env(item_index, item, host_env)
+        .map_err(|e| e.into())
+}
+
+/// The environment the table must keep alive for `val`, if it's a host
+/// function owning one.
+fn host_env(val: &Val) -> Option<HostFunctionEnv> {
+    match val {
+        Val::FuncRef(f) => f.exported.host_env.clone(),
+        _ => None,
+    }
 }
 
 impl Table {
@@ -48,7 +62,7 @@ impl Table {
 
         let num_elements = table.size();
         for i in 0..num_elements {
-            set_table_item(table.as_ref(), i, item.clone())?;
+            set_table_item(table.as_ref(), i, item.clone(), host_env(&init))?;
         }
 
         Ok(Self {
@@ -70,13 +84,18 @@ impl Table {
     /// Retrieves an element of the table at the provided `index`.
     pub fn get(&self, index: u32) -> Option<Val> {
         let item = self.table.get(index)?;
-        Some(ValFuncRef::from_checked_anyfunc(item, &self.store))
+        let host_env = self.table.get_host_env(index);
+        Some(ValFuncRef::from_checked_anyfunc(
+            item,
+            host_env,
+            &self.store,
+        ))
     }
 
     /// Sets an element `val` in the Table at the provided `index`.
     pub fn set(&self, index: u32, val: Val) -> Result<(), RuntimeError> {
         let item = val.into_checked_anyfunc(&self.store)?;
-        set_table_item(self.table.as_ref(), index, item)
+        set_table_item(self.table.as_ref(), index, item, host_env(&val))
     }
 
     /// Retrieves the size of the `Table` (in elements)
@@ -98,7 +117,7 @@ impl Table {
         match self.table.grow(delta) {
             Some(len) => {
                 for i in 0..delta {
-                    set_table_item(self.table.as_ref(), len + i, item.clone())?;
+                    set_table_item(self.table.as_ref(), len + i, item.clone(), host_env(&init))?;
                 }
                 Ok(len)
             }
@@ -2,10 +2,10 @@ use crate::exports::Exports;
 use crate::externals::Extern;
 use crate::module::Module;
 use crate::store::Store;
-use crate::InstantiationError;
+use crate::{InstantiationError, SnapshotError};
 use std::fmt;
 use wasmer_engine::Resolver;
-use wasmer_vm::{InstanceHandle, VMContext};
+use wasmer_vm::{HostFunctionEnv, InstanceHandle, InstanceSnapshot, VMContext};
 
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
@@ -19,6 +19,10 @@ use wasmer_vm::{InstanceHandle, VMContext};
 pub struct Instance {
     handle: InstanceHandle,
     module: Module,
+    /// The slot of the instance, if it's pooled, which can't be reused
+    /// while the instance is referenced.
+    #[allow(dead_code)]
+    slot: Option<HostFunctionEnv>,
     /// The exports for an instance.
     pub exports: Exports,
 }
@@ -71,9 +75,49 @@ impl Instance {
     ///  * Link errors that happen when plugging the imports into the instance
     ///  * Runtime errors that happen when running the module `start` function.
     pub fn new(module: &Module, resolver: &dyn Resolver) -> Result<Self, InstantiationError> {
-        let store = module.store();
-
         let handle = module.instantiate(resolver)?;
+        Ok(Self::from_handle(module, handle))
+    }
+
+    /// Creates a new `Instance` of `module` with the state of the
+    /// [`InstanceSnapshot`] taken by [`Instance::snapshot`], and a
+    /// set of imports resolved by the [`Resolver`].
+    ///
+    /// The data and element initializers and the `start` function are not
+    /// run again, their effects are already part of the snapshot. On Linux,
+    /// the memories of the snapshot are mapped copy-on-write, so creating
+    /// an instance doesn't copy them.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Store, Module, Instance};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(&store, "(module (memory 1))")?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// let snapshot = instance.snapshot()?;
+    /// let fork = Instance::from_snapshot(&module, &snapshot, &imports! {})?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Same as [`Instance::new`], and a [`LinkError::Resource`] if the
+    /// snapshot was taken from another module or can't be restored.
+    ///
+    /// [`LinkError::Resource`]: crate::LinkError::Resource
+    pub fn from_snapshot(
+        module: &Module,
+        snapshot: &InstanceSnapshot,
+        resolver: &dyn Resolver,
+    ) -> Result<Self, InstantiationError> {
+        let handle = module.instantiate_from_snapshot(snapshot, resolver)?;
+        Ok(Self::from_handle(module, handle))
+    }
+
+    /// Wraps an already instantiated `InstanceHandle` of the given `module`.
+    pub(crate) fn from_handle(module: &Module, handle: InstanceHandle) -> Self {
+        let store = module.store();
 
         let exports = module
             .exports()
@@ -85,11 +129,22 @@ impl Instance {
             })
             .collect::<Exports>();
 
-        Ok(Self {
+        Self {
+            slot: handle.host_env(),
             handle,
             module: module.clone(),
             exports,
-        })
+        }
+    }
+
+    /// Captures the state of the memories, tables and globals defined by
+    /// this instance, to create new instances from with
+    /// [`Instance::from_snapshot`].
+    ///
+    /// Imported memories, tables and globals are not part of the snapshot,
+    /// and the tables may only hold functions defined by this instance.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        unsafe { self.handle.snapshot() }
     }
 
     /// Gets the [`Module`] associated with this instance.
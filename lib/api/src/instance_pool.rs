@@ -0,0 +1,305 @@
+use crate::instance::Instance;
+use crate::module::Module;
+use crate::{InstantiationError, LinkError};
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use wasmer_engine::Resolver;
+use wasmer_vm::{HostFunctionEnv, InstanceHandle};
+
+/// A pool of reusable instance slots for a single [`Module`].
+///
+/// Creating an [`Instance`] maps fresh linear memories, tables and a
+/// `VMContext` every time. A pool keeps those around once an instance
+/// is dropped: its memories are zeroed with `madvise(MADV_DONTNEED)`,
+/// its tables cleared, and the slot is handed to the next instantiation,
+/// which then only needs to bind the imports and run the initializers.
+///
+/// Slots are allocated with the [`Tunables`] of the module's store the first
+/// time they are needed, up to the capacity of the pool, or up front with
+/// [`InstancePool::reserve`]. With [`PoolingTunables`], their memories come
+/// from slots pre-reserved by the store.
+///
+/// A slot is only reused once nothing references the instance anymore:
+/// if a clone of the instance or one of its functions, held by an export
+/// or by a table, outlives the [`PooledInstance`], the slot is retired
+/// instead, as reusing it would give access to the next instance, and
+/// freed once the last of them is dropped. The slots whose memories,
+/// tables or globals are still referenced are never reused nor freed.
+///
+/// [`Tunables`]: crate::Tunables
+/// [`PoolingTunables`]: crate::PoolingTunables
+///
+/// ```
+/// # use wasmer::{imports, Store, Module, InstancePool};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let module = Module::new(&store, "(module (memory 1))")?;
+/// let pool = InstancePool::new(&module, 16);
+/// for _ in 0..100 {
+///     let instance = pool.instantiate(&imports! {})?;
+///     // ... use `instance`, it goes back to the pool when dropped.
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InstancePool {
+    inner: Arc<InstancePoolInner>,
+}
+
+struct InstancePoolInner {
+    module: Module,
+    capacity: usize,
+    slots: Mutex<Slots>,
+}
+
+struct Slots {
+    /// Slots ready to be reused.
+    free: Vec<InstanceHandle>,
+    /// The number of slots allocated so far, in use or free.
+    allocated: usize,
+}
+
+/// A slot in use by an instance of the pool.
+///
+/// It's the environment of the functions of the instance, so it's shared
+/// by the instance with its exports and the tables holding its functions,
+/// and it's released when the last of them is dropped.
+struct Slot {
+    handle: InstanceHandle,
+    pool: Weak<InstancePoolInner>,
+    /// Whether the [`PooledInstance`] was dropped while the slot was still
+    /// referenced, in which case it doesn't count in the capacity of the
+    /// pool anymore and it's freed instead of reused.
+    retired: AtomicBool,
+}
+
+/// This is correct because the handle is only used once the slot is
+/// dropped, by the thread dropping it.
+unsafe impl Sync for Slot {}
+
+impl InstancePool {
+    /// Creates a new pool of at most `capacity` instances of `module`.
+    pub fn new(module: &Module, capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(InstancePoolInner {
+                module: module.clone(),
+                capacity,
+                slots: Mutex::new(Slots {
+                    free: Vec::with_capacity(capacity),
+                    allocated: 0,
+                }),
+            }),
+        }
+    }
+
+    /// Gets the [`Module`] instantiated by this pool.
+    pub fn module(&self) -> &Module {
+        &self.inner.module
+    }
+
+    /// Returns the maximum number of live instances of this pool.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity
+    }
+
+    /// Returns the number of slots allocated so far, in use or free.
+    pub fn allocated(&self) -> usize {
+        self.inner.slots.lock().unwrap().allocated
+    }
+
+    /// Allocates up to `count` new slots up front, within the capacity of
+    /// the pool, so instantiating the module doesn't allocate anything.
+    ///
+    /// The slots are linked with the imports resolved by `resolver`, but
+    /// they are replaced by the imports of each instantiation. Nothing is
+    /// initialized nor run.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`LinkError`] if the imports can't be resolved or the
+    /// slots can't be allocated.
+    pub fn reserve(&self, count: usize, resolver: &dyn Resolver) -> Result<(), InstantiationError> {
+        for _ in 0..count {
+            {
+                let mut slots = self.inner.slots.lock().unwrap();
+                if slots.allocated == self.inner.capacity {
+                    break;
+                }
+                slots.allocated += 1;
+            }
+            match self.inner.module.allocate(resolver) {
+                Ok(handle) => self.inner.recycle(handle),
+                Err(err) => {
+                    self.inner.slots.lock().unwrap().allocated -= 1;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Instantiates the module in a free slot of the pool, allocating
+    /// a new slot if there is none.
+    ///
+    /// ## Errors
+    ///
+    /// Same as [`Instance::new`], plus a [`LinkError::Resource`] if all
+    /// the slots of the pool are in use.
+    pub fn instantiate(
+        &self,
+        resolver: &dyn Resolver,
+    ) -> Result<PooledInstance, InstantiationError> {
+        let module = &self.inner.module;
+        let free = {
+            let mut slots = self.inner.slots.lock().unwrap();
+            match slots.free.pop() {
+                Some(handle) => Some(handle),
+                None if slots.allocated < self.inner.capacity => {
+                    slots.allocated += 1;
+                    None
+                }
+                None => {
+                    return Err(InstantiationError::Link(LinkError::Resource(format!(
+                        "all the {} slots of the instance pool are in use",
+                        self.inner.capacity
+                    ))))
+                }
+            }
+        };
+
+        let (handle, reused) = match free {
+            Some(handle) => (handle, true),
+            None => match module.allocate(resolver) {
+                Ok(handle) => (handle, false),
+                Err(err) => {
+                    self.inner.slots.lock().unwrap().allocated -= 1;
+                    return Err(err);
+                }
+            },
+        };
+
+        // The functions of the instance hold its slot, so it isn't reused
+        // while they are referenced.
+        let slot = Arc::new(Slot {
+            handle: handle.clone(),
+            pool: Arc::downgrade(&self.inner),
+            retired: AtomicBool::new(false),
+        });
+        let result = unsafe {
+            handle.set_host_env(Some(&HostFunctionEnv::from_owner(slot.clone())));
+            if reused {
+                module.reinstantiate(&handle, resolver)
+            } else {
+                module.finish_instantiation(&handle)
+            }
+        };
+        if let Err(err) = result {
+            // The tables of other instances may hold functions of the
+            // instance, even if it failed.
+            self.inner.retire(&slot);
+            return Err(err);
+        }
+
+        Ok(PooledInstance {
+            instance: ManuallyDrop::new(Instance::from_handle(module, handle)),
+            slot,
+            pool: self.inner.clone(),
+        })
+    }
+}
+
+impl InstancePoolInner {
+    /// Retires `slot` if something else references it, so it's freed
+    /// instead of reused once they are dropped.
+    fn retire(&self, slot: &Arc<Slot>) {
+        if Arc::strong_count(slot) > 1 && !slot.retired.swap(true, Ordering::SeqCst) {
+            self.slots.lock().unwrap().allocated -= 1;
+        }
+    }
+
+    /// Resets the slot of `handle` and puts it back in the free list.
+    fn recycle(&self, handle: InstanceHandle) {
+        // The memories, tables and globals still referenced can't be reset,
+        // and may refer to the `VMContext` of the slot: it's left allocated.
+        let reset = if handle.is_exclusive() {
+            unsafe { handle.reset() }
+        } else {
+            Err("the slot is still referenced".to_string())
+        };
+        let mut slots = self.slots.lock().unwrap();
+        match reset {
+            Ok(()) => slots.free.push(handle),
+            // Slots whose memories or tables can't be recycled are dropped.
+            Err(_) => slots.allocated -= 1,
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        match self.pool.upgrade() {
+            Some(pool) if !self.retired.load(Ordering::SeqCst) => pool.recycle(self.handle.clone()),
+            // Nothing references the functions of the instance anymore, but
+            // the memories, tables and globals still referenced may refer to
+            // its `VMContext`, in which case it's left allocated.
+            _ if self.handle.is_exclusive() => unsafe { self.handle.dealloc() },
+            _ => {}
+        }
+    }
+}
+
+impl Drop for InstancePoolInner {
+    fn drop(&mut self) {
+        // Nothing references the free slots anymore.
+        for handle in self.slots.get_mut().unwrap().free.drain(..) {
+            unsafe { handle.dealloc() };
+        }
+    }
+}
+
+impl fmt::Debug for InstancePool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstancePool")
+            .field("capacity", &self.inner.capacity)
+            .field("allocated", &self.allocated())
+            .finish()
+    }
+}
+
+/// An [`Instance`] living in a slot of an [`InstancePool`].
+///
+/// The slot is reset and returned to the pool when this value is dropped,
+/// unless the instance is still referenced.
+pub struct PooledInstance {
+    instance: ManuallyDrop<Instance>,
+    slot: Arc<Slot>,
+    pool: Arc<InstancePoolInner>,
+}
+
+impl Deref for PooledInstance {
+    type Target = Instance;
+
+    fn deref(&self) -> &Instance {
+        &self.instance
+    }
+}
+
+impl Drop for PooledInstance {
+    fn drop(&mut self) {
+        // The instance holds references to its slot and its exports.
+        unsafe { ManuallyDrop::drop(&mut self.instance) };
+        // The slot is recycled when `self.slot` is dropped, unless it's
+        // still referenced.
+        self.pool.retire(&self.slot);
+    }
+}
+
+impl fmt::Debug for PooledInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.instance.fmt(f)
+    }
+}
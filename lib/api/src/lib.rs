@@ -28,8 +28,11 @@ mod exports;
 mod externals;
 mod import_object;
 mod instance;
+mod instance_pool;
 mod module;
 mod native;
+#[cfg(feature = "compiler")]
+mod preinit;
 mod ptr;
 mod store;
 mod tunables;
@@ -45,17 +48,32 @@ pub mod internals {
     pub use crate::externals::{WithEnv, WithoutEnv};
 }
 
+pub mod vm {
+    //! The vm module re-exports the `wasmer-vm` types needed to provide
+    //! custom memories and tables, along with a custom [`BaseTunables`].
+    //!
+    //! [`BaseTunables`]: crate::BaseTunables
+
+    pub use wasmer_vm::{
+        BufferMemory, LinearMemory, LinearTable, Memory, MemoryBuffer, MemoryError, MemoryStyle,
+        Table, TableStyle, VMMemoryDefinition, VMTableDefinition,
+    };
+}
+
 pub use crate::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::externals::{
     Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
 };
 pub use crate::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
 pub use crate::instance::Instance;
+pub use crate::instance_pool::{InstancePool, PooledInstance};
 pub use crate::module::Module;
 pub use crate::native::NativeFunc;
+#[cfg(feature = "compiler")]
+pub use crate::preinit::{preinitialize, preinitialize_with, PreInitError};
 pub use crate::ptr::{Array, Item, WasmPtr};
 pub use crate::store::{Store, StoreObject};
-pub use crate::tunables::Tunables;
+pub use crate::tunables::{PoolingTunables, Tunables};
 pub use crate::types::{
     ExportType, ExternRef, ExternType, FunctionType, GlobalType, HostInfo, HostRef, ImportType,
     MemoryType, Mutability, TableType, Val, ValType,
@@ -65,19 +83,22 @@ pub use crate::utils::is_wasm;
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
-    wasmparser, CompilerConfig, FunctionMiddleware, FunctionMiddlewareGenerator,
+    wasmparser, CompilerConfig, FunctionMiddleware, FunctionMiddlewareGenerator, MiddlewareError,
     MiddlewareReaderState,
 };
-pub use wasmer_compiler::{CpuFeature, Features, Target};
+pub use wasmer_compiler::{CompileError, CpuFeature, Features, Target};
 pub use wasmer_engine::{
-    ChainableNamedResolver, DeserializeError, Engine, FrameInfo, InstantiationError, LinkError,
-    NamedResolver, NamedResolverChain, Resolver, RuntimeError, SerializeError,
+    ChainableNamedResolver, DeserializeError, Engine, FrameInfo, ImportError, InstantiationError,
+    LinkError, NamedResolver, NamedResolverChain, Resolver, RuntimeError, SerializeError,
+    Tunables as BaseTunables,
 };
 pub use wasmer_types::{
     Atomically, Bytes, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
     WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
-pub use wasmer_vm::{raise_user_trap, Export, MemoryError};
+pub use wasmer_vm::{
+    raise_user_trap, Export, InstanceSnapshot, MemoryError, ModuleInfo, SnapshotError,
+};
 #[cfg(feature = "wat")]
 pub use wat::parse_bytes as wat2wasm;
 
@@ -113,6 +134,9 @@ pub use wasmer_compiler_llvm::LLVM;
 #[cfg(feature = "jit")]
 pub use wasmer_engine_jit::{JITArtifact, JITEngine, JIT};
 
+#[cfg(all(feature = "jit", feature = "compiler"))]
+pub use wasmer_engine_jit::TierUpPolicy;
+
 #[cfg(feature = "native")]
 pub use wasmer_engine_native::{Native, NativeArtifact, NativeEngine};
 
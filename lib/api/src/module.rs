@@ -1,6 +1,6 @@
 use crate::store::Store;
 use crate::types::{ExportType, ImportType};
-use crate::InstantiationError;
+use crate::{InstantiationError, LinkError};
 use std::fmt;
 use std::io;
 use std::path::Path;
@@ -10,7 +10,7 @@ use wasmer_compiler::CompileError;
 #[cfg(feature = "wat")]
 use wasmer_compiler::WasmError;
 use wasmer_engine::{Artifact, DeserializeError, Resolver, SerializeError};
-use wasmer_vm::{ExportsIterator, ImportsIterator, InstanceHandle, ModuleInfo};
+use wasmer_vm::{ExportsIterator, ImportsIterator, InstanceHandle, InstanceSnapshot, ModuleInfo};
 
 #[derive(Error, Debug)]
 pub enum IoCompileError {
@@ -251,6 +251,33 @@ impl Module {
         Ok(Self::from_artifact(store, artifact))
     }
 
+    /// Returns a `Module` sharing the compiled code of this one, to be
+    /// instantiated in another `store`.
+    ///
+    /// The compiled code is linked against the engine that produced
+    /// it, so `None` is returned if `store` doesn't use the same engine.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    /// let other_store = Store::new(&**store.engine());
+    /// let shared = module.share_with(&other_store).unwrap();
+    /// assert!(Store::same(shared.store(), &other_store));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn share_with(&self, store: &Store) -> Option<Self> {
+        if self.store.engine().shared_id() != store.engine().shared_id() {
+            return None;
+        }
+
+        Some(Self::from_artifact(store, self.artifact.clone()))
+    }
+
     fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Self {
         Self {
             store: store.clone(),
@@ -278,6 +305,67 @@ impl Module {
         }
     }
 
+    /// Instantiates this module with the state captured in `snapshot`,
+    /// instead of running its initializers and start function.
+    pub(crate) fn instantiate_from_snapshot(
+        &self,
+        snapshot: &InstanceSnapshot,
+        resolver: &dyn Resolver,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        unsafe {
+            let instance_handle =
+                self.artifact
+                    .instantiate(self.store.tunables(), resolver, Box::new(()))?;
+            instance_handle
+                .restore(snapshot)
+                .map_err(|e| InstantiationError::Link(LinkError::Resource(e)))?;
+
+            Ok(instance_handle)
+        }
+    }
+
+    /// Allocates an instance of this module, linked with the imports
+    /// resolved by `resolver`, without initializing it, to be finished by
+    /// [`Module::finish_instantiation`] or used by
+    /// [`Module::reinstantiate`].
+    pub(crate) fn allocate(
+        &self,
+        resolver: &dyn Resolver,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        unsafe {
+            self.artifact
+                .instantiate(self.store.tunables(), resolver, Box::new(()))
+        }
+    }
+
+    /// Initializes an instance allocated by [`Module::allocate`] and runs
+    /// its start function.
+    ///
+    /// # Safety
+    ///
+    /// See [`InstanceHandle::finish_instantiation`].
+    pub(crate) unsafe fn finish_instantiation(
+        &self,
+        handle: &InstanceHandle,
+    ) -> Result<(), InstantiationError> {
+        self.artifact.finish_instantiation(handle)
+    }
+
+    /// Instantiates this module again in the slot of a `handle` that was
+    /// previously instantiated from it and then reset.
+    ///
+    /// # Safety
+    ///
+    /// See [`InstanceHandle::reuse`].
+    pub(crate) unsafe fn reinstantiate(
+        &self,
+        handle: &InstanceHandle,
+        resolver: &dyn Resolver,
+    ) -> Result<(), InstantiationError> {
+        self.artifact.reinstantiate(handle, resolver)?;
+        self.artifact.finish_instantiation(handle)
+    }
+
     /// Returns the name of the current module.
     ///
     /// This name is normally set in the WebAssembly bytecode by some
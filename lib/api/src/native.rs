@@ -17,7 +17,8 @@ use crate::{FromToNativeWasmType, Function, FunctionType, RuntimeError, Store, W
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use wasmer_types::NativeWasmType;
 use wasmer_vm::{
-    ExportFunction, VMContext, VMDynamicFunctionContext, VMFunctionBody, VMFunctionKind,
+    ExportFunction, HostFunctionEnv, VMContext, VMDynamicFunctionContext, VMFunctionBody,
+    VMFunctionKind,
 };
 
 /// A WebAssembly function that can be called natively
@@ -28,6 +29,7 @@ pub struct NativeFunc<'a, Args = (), Rets = ()> {
     address: *const VMFunctionBody,
     vmctx: *mut VMContext,
     arg_kind: VMFunctionKind,
+    host_env: Option<HostFunctionEnv>,
     // exported: ExportFunction,
     _phantom: PhantomData<(&'a (), Args, Rets)>,
 }
@@ -44,6 +46,7 @@ where
         address: *const VMFunctionBody,
         vmctx: *mut VMContext,
         arg_kind: VMFunctionKind,
+        host_env: Option<HostFunctionEnv>,
         definition: FunctionDefinition,
     ) -> Self {
         Self {
@@ -52,6 +55,7 @@ where
             address,
             vmctx,
             arg_kind,
+            host_env,
             _phantom: PhantomData,
         }
     }
@@ -70,6 +74,7 @@ where
             signature,
             kind: other.arg_kind,
             call_trampoline: None,
+            host_env: other.host_env.clone(),
         }
     }
 }
@@ -90,6 +95,7 @@ where
                 signature,
                 kind: other.arg_kind,
                 call_trampoline: None,
+                host_env: other.host_env,
             },
         }
     }
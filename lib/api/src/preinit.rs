@@ -0,0 +1,833 @@
+//! Pre-initialization of WebAssembly modules.
+//!
+//! A module is instantiated, a designated init export is called, and a new
+//! module is written whose data segments and global initializers capture
+//! the resulting memory and globals, so its instances start initialized.
+//!
+//! The tables and the passive segments are kept as in the original
+//! module, so pre-initialization fails if the start or the init function
+//! changes them: the module is instrumented to compare the tables before
+//! and after running them, and to check whether the passive segments
+//! were dropped.
+
+use crate::exports::ExportError;
+use crate::externals::Table;
+use crate::instance::Instance;
+use crate::module::Module;
+use crate::store::Store;
+use crate::types::Val;
+use crate::{InstantiationError, RuntimeError};
+use thiserror::Error;
+use wasmer_compiler::wasmparser::{
+    BinaryReader, DataKind, ElementKind, ExternalKind, ImportSectionEntryType, ModuleReader,
+    SectionCode, Type,
+};
+use wasmer_compiler::CompileError;
+use wasmer_engine::Resolver;
+
+/// The prefix of the exports added to read the state of the instance.
+const STATE_EXPORT_PREFIX: &str = "__wasmer_preinit";
+
+/// Zero gaps shorter than this are kept inside a data segment, as they are
+/// cheaper than the overhead of starting a new segment.
+const MIN_DATA_SEGMENT_GAP: usize = 8;
+
+/// The maximum number of data segments of the pre-initialized module.
+const MAX_DATA_SEGMENTS: usize = 10_000;
+
+/// An error while pre-initializing a module.
+#[derive(Error, Debug)]
+pub enum PreInitError {
+    /// The module could not be parsed.
+    #[error("Error while parsing the module: {0}")]
+    Parse(String),
+
+    /// The module uses something that can't be captured in a snapshot.
+    #[error("The module can't be pre-initialized: {0}")]
+    Unsupported(String),
+
+    /// The module could not be compiled.
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+
+    /// The module could not be instantiated.
+    #[error(transparent)]
+    Instantiation(Box<InstantiationError>),
+
+    /// The init function or the state of the instance could not be found.
+    #[error(transparent)]
+    Export(#[from] ExportError),
+
+    /// The init function trapped.
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+impl From<InstantiationError> for PreInitError {
+    fn from(error: InstantiationError) -> Self {
+        Self::Instantiation(Box::new(error))
+    }
+}
+
+/// Pre-initializes the module `wasm`: instantiates it with `resolver`,
+/// calls its `init_func` export and returns a new module whose memory and
+/// globals start in the state left by it.
+///
+/// The start function of the module runs before `init_func`, so it is
+/// removed from the new module, as well as the `init_func` export.
+/// Immutable globals keep their initializer, so the ones initialized from
+/// imported globals still get their value from the imports of each
+/// instance.
+///
+/// # Errors
+///
+/// Mutable globals initialized from imported globals can't be captured,
+/// as their value would be frozen to the one imported while
+/// pre-initializing.
+///
+/// Tables are not captured either: their elements are initialized like
+/// in the original module, so pre-initialization fails if the start or
+/// the init function changes a table, or drops a passive data or element
+/// segment.
+///
+/// ```
+/// # use wasmer::{imports, preinitialize, Instance, Module, Store, Val};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let wasm = wat::parse_str(r#"(module
+///     (global $ready (export "ready") (mut i32) (i32.const 0))
+///     (func (export "init") (global.set $ready (i32.const 1))))"#)?;
+/// let wasm = preinitialize(&store, &wasm, "init", &imports! {})?;
+///
+/// let module = Module::new(&store, &wasm)?;
+/// let instance = Instance::new(&module, &imports! {})?;
+/// assert_eq!(instance.exports.get_global("ready")?.get(), Val::I32(1));
+/// # Ok(())
+/// # }
+/// ```
+pub fn preinitialize(
+    store: &Store,
+    wasm: &[u8],
+    init_func: &str,
+    resolver: &dyn Resolver,
+) -> Result<Vec<u8>, PreInitError> {
+    preinitialize_with(store, wasm, init_func, resolver, |_| Ok(()))
+}
+
+/// Pre-initializes the module `wasm` like [`preinitialize`], calling
+/// `setup` with the instance before calling its `init_func` export.
+///
+/// This is needed by imports that have to be bound to the instance, such
+/// as giving WASI access to the memory of the module.
+pub fn preinitialize_with<F>(
+    store: &Store,
+    wasm: &[u8],
+    init_func: &str,
+    resolver: &dyn Resolver,
+    setup: F,
+) -> Result<Vec<u8>, PreInitError>
+where
+    F: FnOnce(&Instance) -> Result<(), PreInitError>,
+{
+    let mut sections = Sections::parse(wasm)?;
+    let info = ModuleState::parse(wasm)?;
+
+    // Export the memory, the tables and the globals of the module, so we
+    // can read their state once initialized, and the start function, so
+    // it runs once the tables are read.
+    let mut instrumented = sections.clone();
+    let mut exports = info.exports.clone();
+    for index in 0..info.tables.len() {
+        exports.push(Export {
+            name: format!("{}_table_{}", STATE_EXPORT_PREFIX, index),
+            kind: 1,
+            index: index as u32,
+        });
+    }
+    if let Some(start) = info.start {
+        exports.push(Export {
+            name: format!("{}_start", STATE_EXPORT_PREFIX),
+            kind: 0,
+            index: start,
+        });
+        instrumented.remove(START_SECTION);
+    }
+    for (index, _) in info.globals.iter().enumerate() {
+        exports.push(Export {
+            name: format!("{}_global_{}", STATE_EXPORT_PREFIX, index),
+            kind: 3,
+            index: (info.num_imported_globals + index) as u32,
+        });
+    }
+    if info.memory.is_some() {
+        exports.push(Export {
+            name: format!("{}_memory", STATE_EXPORT_PREFIX),
+            kind: 2,
+            index: 0,
+        });
+    }
+
+    // Add a function checking whether each passive segment was dropped,
+    // which traps if so.
+    let probes = info.segment_probes();
+    if !probes.is_empty() {
+        let mut probe_type = vec![];
+        leb128::write::unsigned(&mut probe_type, u64::from(info.num_types)).unwrap();
+        instrumented.append(TYPE_SECTION, &[vec![0x60, 0x00, 0x00]])?;
+        instrumented.append(FUNCTION_SECTION, &vec![probe_type; probes.len()])?;
+        let bodies = probes
+            .iter()
+            .map(|probe| {
+                let mut body = vec![];
+                leb128::write::unsigned(&mut body, probe.code.len() as u64 + 2).unwrap();
+                body.push(0x00);
+                body.extend_from_slice(&probe.code);
+                body.push(0x0b);
+                body
+            })
+            .collect::<Vec<_>>();
+        instrumented.append(CODE_SECTION, &bodies)?;
+        for (index, probe) in probes.iter().enumerate() {
+            exports.push(Export {
+                name: probe.name.clone(),
+                kind: 0,
+                index: info.num_functions + index as u32,
+            });
+        }
+    }
+    instrumented.set(EXPORT_SECTION, encode_exports(&exports));
+
+    let module = Module::new(store, instrumented.encode())?;
+    let instance = Instance::new(&module, resolver)?;
+    let table_name = |index| format!("{}_table_{}", STATE_EXPORT_PREFIX, index);
+    let tables = (0..info.tables.len())
+        .map(|index| {
+            Ok(table_elements(
+                instance.exports.get_table(&table_name(index))?,
+            ))
+        })
+        .collect::<Result<Vec<_>, PreInitError>>()?;
+    if info.start.is_some() {
+        instance
+            .exports
+            .get_function(&format!("{}_start", STATE_EXPORT_PREFIX))?
+            .call(&[])?;
+    }
+    setup(&instance)?;
+    instance.exports.get_function(init_func)?.call(&[])?;
+
+    // Check that the tables and the passive segments are left as they
+    // were initialized.
+    for (index, elements) in tables.iter().enumerate() {
+        let table = instance.exports.get_table(&table_name(index))?;
+        if !same_elements(&table_elements(table), elements) {
+            return Err(PreInitError::Unsupported(format!(
+                "the table {} was changed, and tables can't be captured",
+                index
+            )));
+        }
+    }
+    for probe in probes.iter() {
+        if instance
+            .exports
+            .get_function(&probe.name)?
+            .call(&[])
+            .is_err()
+        {
+            return Err(PreInitError::Unsupported(format!(
+                "the {} was dropped, and dropped segments can't be captured",
+                probe.segment
+            )));
+        }
+    }
+
+    // Capture the state of the globals.
+    let mut globals = Vec::new();
+    for (index, global) in info.globals.iter().enumerate() {
+        let name = format!("{}_global_{}", STATE_EXPORT_PREFIX, index);
+        let value = instance.exports.get_global(&name)?.get();
+        globals.push(encode_global(global, &value)?);
+    }
+    if !globals.is_empty() {
+        sections.set(GLOBAL_SECTION, encode_vec(&globals));
+    }
+
+    // Capture the state of the memory in new data segments, keeping the
+    // passive ones at their index.
+    if let Some(memory_type) = &info.memory {
+        let memory = instance
+            .exports
+            .get_memory(&format!("{}_memory", STATE_EXPORT_PREFIX))?;
+        let data = unsafe { memory.data_unchecked() };
+
+        let mut segments = Vec::new();
+        if info.has_passive_data {
+            for passive in info.data.iter() {
+                let mut segment = vec![0x01];
+                match passive {
+                    Some(bytes) => {
+                        leb128::write::unsigned(&mut segment, bytes.len() as u64).unwrap();
+                        segment.extend_from_slice(bytes);
+                    }
+                    None => {
+                        leb128::write::unsigned(&mut segment, 0).unwrap();
+                    }
+                }
+                segments.push(segment);
+            }
+        }
+        for (start, end) in nonzero_ranges(data) {
+            let mut segment = vec![0x00, 0x41];
+            leb128::write::signed(&mut segment, i64::from(start as i32)).unwrap();
+            segment.push(0x0b);
+            leb128::write::unsigned(&mut segment, (end - start) as u64).unwrap();
+            segment.extend_from_slice(&data[start..end]);
+            segments.push(segment);
+        }
+
+        let mut memory_section = Vec::new();
+        leb128::write::unsigned(&mut memory_section, 1).unwrap();
+        encode_memory_type(&mut memory_section, memory.size().0, memory_type);
+        sections.set(MEMORY_SECTION, memory_section);
+
+        if sections.get(DATA_COUNT_SECTION).is_some() {
+            let mut data_count = Vec::new();
+            leb128::write::unsigned(&mut data_count, segments.len() as u64).unwrap();
+            sections.set(DATA_COUNT_SECTION, data_count);
+        }
+        sections.set(DATA_SECTION, encode_vec(&segments));
+    }
+
+    // The module is initialized, don't let it be initialized again.
+    let exports = info
+        .exports
+        .into_iter()
+        .filter(|export| export.kind != 0 || export.name != init_func)
+        .collect::<Vec<_>>();
+    sections.set(EXPORT_SECTION, encode_exports(&exports));
+    sections.remove(START_SECTION);
+
+    Ok(sections.encode())
+}
+
+const TYPE_SECTION: u8 = 1;
+const FUNCTION_SECTION: u8 = 3;
+const MEMORY_SECTION: u8 = 5;
+const GLOBAL_SECTION: u8 = 6;
+const EXPORT_SECTION: u8 = 7;
+const START_SECTION: u8 = 8;
+const CODE_SECTION: u8 = 10;
+const DATA_SECTION: u8 = 11;
+const DATA_COUNT_SECTION: u8 = 12;
+
+/// The opcode of `global.get`.
+const GLOBAL_GET: u8 = 0x23;
+
+/// The raw sections of a module.
+#[derive(Clone)]
+struct Sections {
+    /// The id and the payload of each section, in order.
+    sections: Vec<(u8, Vec<u8>)>,
+}
+
+impl Sections {
+    fn parse(wasm: &[u8]) -> Result<Self, PreInitError> {
+        let mut reader = ModuleReader::new(wasm).map_err(to_parse_error)?;
+        let mut sections = Vec::new();
+        while !reader.eof() {
+            let section = reader.read().map_err(to_parse_error)?;
+            let range = section.range();
+            let body = &wasm[range.start..range.end];
+            let (id, payload) = match section.code {
+                SectionCode::Custom { name, .. } => {
+                    let mut payload = Vec::new();
+                    leb128::write::unsigned(&mut payload, name.len() as u64).unwrap();
+                    payload.extend_from_slice(name.as_bytes());
+                    payload.extend_from_slice(body);
+                    (0, payload)
+                }
+                SectionCode::Type => (TYPE_SECTION, body.to_vec()),
+                SectionCode::Import => (2, body.to_vec()),
+                SectionCode::Function => (FUNCTION_SECTION, body.to_vec()),
+                SectionCode::Table => (4, body.to_vec()),
+                SectionCode::Memory => (MEMORY_SECTION, body.to_vec()),
+                SectionCode::Global => (GLOBAL_SECTION, body.to_vec()),
+                SectionCode::Export => (EXPORT_SECTION, body.to_vec()),
+                SectionCode::Start => (START_SECTION, body.to_vec()),
+                SectionCode::Element => (9, body.to_vec()),
+                SectionCode::Code => (CODE_SECTION, body.to_vec()),
+                SectionCode::Data => (DATA_SECTION, body.to_vec()),
+                SectionCode::DataCount => (DATA_COUNT_SECTION, body.to_vec()),
+            };
+            sections.push((id, payload));
+        }
+        Ok(Self { sections })
+    }
+
+    fn get(&self, id: u8) -> Option<&[u8]> {
+        self.sections
+            .iter()
+            .find(|(section_id, _)| *section_id == id)
+            .map(|(_, payload)| &payload[..])
+    }
+
+    /// Replaces the payload of the section `id`, or inserts the section
+    /// where the binary format expects it.
+    fn set(&mut self, id: u8, payload: Vec<u8>) {
+        if let Some(section) = self.sections.iter_mut().find(|(i, _)| *i == id) {
+            section.1 = payload;
+            return;
+        }
+        let position = self
+            .sections
+            .iter()
+            .position(|(i, _)| *i != 0 && section_order(*i) > section_order(id))
+            .unwrap_or(self.sections.len());
+        self.sections.insert(position, (id, payload));
+    }
+
+    /// Appends `entries` to the vector making the section `id`.
+    fn append(&mut self, id: u8, entries: &[Vec<u8>]) -> Result<(), PreInitError> {
+        let (count, existing) = match self.get(id) {
+            Some(payload) => {
+                let mut reader = BinaryReader::new(payload);
+                let count = reader.read_var_u32().map_err(to_parse_error)?;
+                (count, &payload[reader.original_position()..])
+            }
+            None => (0, &[][..]),
+        };
+        let mut payload = vec![];
+        leb128::write::unsigned(&mut payload, u64::from(count) + entries.len() as u64).unwrap();
+        payload.extend_from_slice(existing);
+        for entry in entries {
+            payload.extend_from_slice(entry);
+        }
+        self.set(id, payload);
+        Ok(())
+    }
+
+    fn remove(&mut self, id: u8) {
+        self.sections.retain(|(i, _)| *i != id);
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        for (id, payload) in self.sections.iter() {
+            wasm.push(*id);
+            leb128::write::unsigned(&mut wasm, payload.len() as u64).unwrap();
+            wasm.extend_from_slice(payload);
+        }
+        wasm
+    }
+}
+
+/// The position of a section in a module, the data count section going
+/// before the code section.
+fn section_order(id: u8) -> u8 {
+    match id {
+        DATA_COUNT_SECTION => 10,
+        CODE_SECTION | DATA_SECTION => id + 1,
+        _ => id,
+    }
+}
+
+#[derive(Clone)]
+struct Export {
+    name: String,
+    kind: u8,
+    index: u32,
+}
+
+/// A global defined by the module.
+struct GlobalEntry {
+    ty: Type,
+    mutable: bool,
+    /// The raw init expression, kept for immutable globals.
+    init_expr: Vec<u8>,
+}
+
+/// A function checking whether a passive segment was dropped.
+struct SegmentProbe {
+    /// The name it's exported with.
+    name: String,
+    segment: String,
+    /// The instructions of its body.
+    code: Vec<u8>,
+}
+
+/// The parts of the module pre-initialization needs to know about.
+struct ModuleState {
+    num_types: u32,
+    /// The number of functions, imported ones included.
+    num_functions: u32,
+    num_imported_globals: usize,
+    globals: Vec<GlobalEntry>,
+    memory: Option<(Option<u32>, bool)>,
+    /// The element type of each table, imported ones included.
+    tables: Vec<Type>,
+    exports: Vec<Export>,
+    start: Option<u32>,
+    /// The element type of the non-empty passive element segments, by
+    /// element index.
+    elements: Vec<Option<Type>>,
+    /// The bytes of the passive data segments, by data index.
+    data: Vec<Option<Vec<u8>>>,
+    has_passive_data: bool,
+    has_data_count: bool,
+}
+
+impl ModuleState {
+    fn parse(wasm: &[u8]) -> Result<Self, PreInitError> {
+        let mut reader = ModuleReader::new(wasm).map_err(to_parse_error)?;
+        let mut state = Self {
+            num_types: 0,
+            num_functions: 0,
+            num_imported_globals: 0,
+            globals: Vec::new(),
+            memory: None,
+            tables: Vec::new(),
+            exports: Vec::new(),
+            start: None,
+            elements: Vec::new(),
+            data: Vec::new(),
+            has_passive_data: false,
+            has_data_count: false,
+        };
+        while !reader.eof() {
+            let section = reader.read().map_err(to_parse_error)?;
+            match section.code {
+                SectionCode::Type => {
+                    state.num_types = section
+                        .get_type_section_reader()
+                        .map_err(to_parse_error)?
+                        .get_count();
+                }
+                SectionCode::Import => {
+                    for import in section
+                        .get_import_section_reader()
+                        .map_err(to_parse_error)?
+                    {
+                        match import.map_err(to_parse_error)?.ty {
+                            ImportSectionEntryType::Function(_) => state.num_functions += 1,
+                            ImportSectionEntryType::Table(table) => {
+                                state.tables.push(table.element_type)
+                            }
+                            ImportSectionEntryType::Global(_) => state.num_imported_globals += 1,
+                            ImportSectionEntryType::Memory(_) => {
+                                return Err(PreInitError::Unsupported(
+                                    "imported memories can't be captured".to_string(),
+                                ))
+                            }
+                        }
+                    }
+                }
+                SectionCode::Function => {
+                    state.num_functions += section
+                        .get_function_section_reader()
+                        .map_err(to_parse_error)?
+                        .get_count();
+                }
+                SectionCode::Table => {
+                    for table in section.get_table_section_reader().map_err(to_parse_error)? {
+                        state
+                            .tables
+                            .push(table.map_err(to_parse_error)?.element_type);
+                    }
+                }
+                SectionCode::Memory => {
+                    for (index, memory) in section
+                        .get_memory_section_reader()
+                        .map_err(to_parse_error)?
+                        .into_iter()
+                        .enumerate()
+                    {
+                        if index > 0 {
+                            return Err(PreInitError::Unsupported(
+                                "multiple memories can't be captured".to_string(),
+                            ));
+                        }
+                        let memory = memory.map_err(to_parse_error)?;
+                        state.memory = Some((memory.limits.maximum, memory.shared));
+                    }
+                }
+                SectionCode::Global => {
+                    for global in section
+                        .get_global_section_reader()
+                        .map_err(to_parse_error)?
+                    {
+                        let global = global.map_err(to_parse_error)?;
+                        let mut reader = global.init_expr.get_binary_reader();
+                        let init_expr = reader
+                            .read_bytes(reader.bytes_remaining())
+                            .map_err(to_parse_error)?
+                            .to_vec();
+                        state.globals.push(GlobalEntry {
+                            ty: global.ty.content_type,
+                            mutable: global.ty.mutable,
+                            init_expr,
+                        });
+                    }
+                }
+                SectionCode::Export => {
+                    for export in section
+                        .get_export_section_reader()
+                        .map_err(to_parse_error)?
+                    {
+                        let export = export.map_err(to_parse_error)?;
+                        if export.field.starts_with(STATE_EXPORT_PREFIX) {
+                            return Err(PreInitError::Unsupported(format!(
+                                "the module already exports `{}`",
+                                export.field
+                            )));
+                        }
+                        state.exports.push(Export {
+                            name: export.field.to_string(),
+                            kind: match export.kind {
+                                ExternalKind::Function => 0,
+                                ExternalKind::Table => 1,
+                                ExternalKind::Memory => 2,
+                                ExternalKind::Global => 3,
+                            },
+                            index: export.index,
+                        });
+                    }
+                }
+                SectionCode::Start => {
+                    state.start = Some(
+                        section
+                            .get_start_section_content()
+                            .map_err(to_parse_error)?,
+                    );
+                }
+                SectionCode::Element => {
+                    for element in section
+                        .get_element_section_reader()
+                        .map_err(to_parse_error)?
+                    {
+                        let element = element.map_err(to_parse_error)?;
+                        let count = element
+                            .items
+                            .get_items_reader()
+                            .map_err(to_parse_error)?
+                            .get_count();
+                        state.elements.push(match element.kind {
+                            ElementKind::Passive if count > 0 => Some(element.ty),
+                            _ => None,
+                        });
+                    }
+                }
+                SectionCode::DataCount => state.has_data_count = true,
+                SectionCode::Data => {
+                    for data in section.get_data_section_reader().map_err(to_parse_error)? {
+                        let data = data.map_err(to_parse_error)?;
+                        state.data.push(match data.kind {
+                            DataKind::Passive => {
+                                state.has_passive_data = true;
+                                Some(data.data.to_vec())
+                            }
+                            DataKind::Active { .. } => None,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(state)
+    }
+}
+
+impl ModuleState {
+    /// Returns the functions checking whether
+    /// the passive segments were dropped, by initializing nothing from
+    /// their second byte: it traps once they are dropped, as they are
+    /// then empty. Empty segments behave the same whether they are
+    /// dropped or not, so they don't need to be checked.
+    fn segment_probes(&self) -> Vec<SegmentProbe> {
+        let mut probes = Vec::new();
+        for (index, ty) in self.elements.iter().enumerate() {
+            let table = ty.and_then(|ty| self.tables.iter().position(|table| *table == ty));
+            if let Some(table) = table {
+                // table.init $index $table (i32.const 0) (i32.const 1) (i32.const 0)
+                let mut code = vec![0x41, 0x00, 0x41, 0x01, 0x41, 0x00, 0xfc, 0x0c];
+                leb128::write::unsigned(&mut code, index as u64).unwrap();
+                leb128::write::unsigned(&mut code, table as u64).unwrap();
+                probes.push(SegmentProbe {
+                    name: format!("{}_element_{}", STATE_EXPORT_PREFIX, index),
+                    segment: format!("element segment {}", index),
+                    code,
+                });
+            }
+        }
+        // `memory.init` needs the data count section.
+        if self.memory.is_some() && self.has_data_count {
+            for (index, data) in self.data.iter().enumerate() {
+                if data.as_ref().map_or(false, |data| !data.is_empty()) {
+                    // memory.init $index (i32.const 0) (i32.const 1) (i32.const 0)
+                    let mut code = vec![0x41, 0x00, 0x41, 0x01, 0x41, 0x00, 0xfc, 0x08];
+                    leb128::write::unsigned(&mut code, index as u64).unwrap();
+                    code.push(0x00);
+                    probes.push(SegmentProbe {
+                        name: format!("{}_data_{}", STATE_EXPORT_PREFIX, index),
+                        segment: format!("data segment {}", index),
+                        code,
+                    });
+                }
+            }
+        }
+        probes
+    }
+}
+
+/// Returns the elements of `table`.
+fn table_elements(table: &Table) -> Vec<Option<Val>> {
+    (0..table.size()).map(|index| table.get(index)).collect()
+}
+
+/// Returns whether the elements of two tables are the same.
+fn same_elements(a: &[Option<Val>], b: &[Option<Val>]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| match (a, b) {
+            (Some(Val::FuncRef(a)), Some(Val::FuncRef(b))) => a.same(b),
+            (a, b) => a == b,
+        })
+}
+
+fn to_parse_error(error: impl ToString) -> PreInitError {
+    PreInitError::Parse(error.to_string())
+}
+
+fn encode_exports(exports: &[Export]) -> Vec<u8> {
+    let entries = exports
+        .iter()
+        .map(|export| {
+            let mut entry = Vec::new();
+            leb128::write::unsigned(&mut entry, export.name.len() as u64).unwrap();
+            entry.extend_from_slice(export.name.as_bytes());
+            entry.push(export.kind);
+            leb128::write::unsigned(&mut entry, u64::from(export.index)).unwrap();
+            entry
+        })
+        .collect::<Vec<_>>();
+    encode_vec(&entries)
+}
+
+fn encode_global(global: &GlobalEntry, value: &Val) -> Result<Vec<u8>, PreInitError> {
+    let mut entry = Vec::new();
+    let init_expr = match value {
+        // immutable globals can't be changed by the init function, and
+        // may be initialized from imported globals
+        _ if !global.mutable => {
+            entry.push(match global.ty {
+                Type::I32 => 0x7f,
+                Type::I64 => 0x7e,
+                Type::F32 => 0x7d,
+                Type::F64 => 0x7c,
+                Type::V128 => 0x7b,
+                Type::FuncRef => 0x70,
+                Type::ExternRef => 0x6f,
+                ty => {
+                    return Err(PreInitError::Unsupported(format!(
+                        "globals of type {:?} can't be captured",
+                        ty
+                    )))
+                }
+            });
+            global.init_expr.clone()
+        }
+        _ if global.init_expr.first() == Some(&GLOBAL_GET) => {
+            return Err(PreInitError::Unsupported(
+                "mutable globals initialized from imported globals can't be captured".to_string(),
+            ))
+        }
+        Val::I32(x) => {
+            entry.push(0x7f);
+            let mut init_expr = vec![0x41];
+            leb128::write::signed(&mut init_expr, i64::from(*x)).unwrap();
+            init_expr.push(0x0b);
+            init_expr
+        }
+        Val::I64(x) => {
+            entry.push(0x7e);
+            let mut init_expr = vec![0x42];
+            leb128::write::signed(&mut init_expr, *x).unwrap();
+            init_expr.push(0x0b);
+            init_expr
+        }
+        Val::F32(x) => {
+            entry.push(0x7d);
+            let mut init_expr = vec![0x43];
+            init_expr.extend_from_slice(&x.to_bits().to_le_bytes());
+            init_expr.push(0x0b);
+            init_expr
+        }
+        Val::F64(x) => {
+            entry.push(0x7c);
+            let mut init_expr = vec![0x44];
+            init_expr.extend_from_slice(&x.to_bits().to_le_bytes());
+            init_expr.push(0x0b);
+            init_expr
+        }
+        Val::V128(x) => {
+            entry.push(0x7b);
+            let mut init_expr = vec![0xfd, 0x0c];
+            init_expr.extend_from_slice(&x.to_le_bytes());
+            init_expr.push(0x0b);
+            init_expr
+        }
+        _ => {
+            return Err(PreInitError::Unsupported(
+                "mutable reference globals can't be captured".to_string(),
+            ))
+        }
+    };
+    entry.push(global.mutable as u8);
+    entry.extend(init_expr);
+    Ok(entry)
+}
+
+fn encode_memory_type(out: &mut Vec<u8>, pages: u32, memory_type: &(Option<u32>, bool)) {
+    let (maximum, shared) = *memory_type;
+    let flags = if shared { 0x02 } else { 0x00 } | if maximum.is_some() { 0x01 } else { 0x00 };
+    out.push(flags);
+    leb128::write::unsigned(out, u64::from(pages)).unwrap();
+    if let Some(maximum) = maximum {
+        leb128::write::unsigned(out, u64::from(maximum)).unwrap();
+    }
+}
+
+fn encode_vec(entries: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    leb128::write::unsigned(&mut out, entries.len() as u64).unwrap();
+    for entry in entries {
+        out.extend_from_slice(entry);
+    }
+    out
+}
+
+/// Returns the ranges of `data` to put in data segments: the non-zero
+/// bytes, merging ranges separated by short gaps.
+fn nonzero_ranges(data: &[u8]) -> Vec<(usize, usize)> {
+    let mut min_gap = MIN_DATA_SEGMENT_GAP;
+    loop {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut i = 0;
+        while i < data.len() {
+            if data[i] == 0 {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < data.len() && data[i] != 0 {
+                i += 1;
+            }
+            match ranges.last_mut() {
+                Some(last) if start - last.1 < min_gap => last.1 = i,
+                _ => ranges.push((start, i)),
+            }
+        }
+        if ranges.len() <= MAX_DATA_SEGMENTS {
+            return ranges;
+        }
+        min_gap *= 2;
+    }
+}
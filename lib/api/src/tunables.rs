@@ -1,11 +1,14 @@
 use crate::{MemoryType, Pages, TableType};
 use std::cmp::min;
-use std::sync::Arc;
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
 use target_lexicon::{OperatingSystem, PointerWidth};
 use wasmer_compiler::Target;
 use wasmer_engine::Tunables as BaseTunables;
-use wasmer_vm::MemoryError;
 use wasmer_vm::{LinearMemory, LinearTable, Memory, MemoryStyle, Table, TableStyle};
+use wasmer_vm::{MemoryError, Mmap, MmapImage, VMMemoryDefinition};
 
 /// Tunable parameters for WebAssembly compilation.
 #[derive(Clone)]
@@ -82,6 +85,16 @@ impl BaseTunables for Tunables {
         TableStyle::CallerChecksSignature
     }
 
+    /// The styles only depend on the bound and offset-guard sizes.
+    fn fingerprint(&self) -> String {
+        format!(
+            "static(bound: {}, guard: {}), dynamic(guard: {})",
+            self.static_memory_bound.0,
+            self.static_memory_offset_guard_size,
+            self.dynamic_memory_offset_guard_size
+        )
+    }
+
     /// Create a memory given a [`MemoryType`] and a [`MemoryStyle`].
     fn create_memory(
         &self,
@@ -96,3 +109,175 @@ impl BaseTunables for Tunables {
         Ok(Arc::new(LinearTable::new(&ty, &style)?))
     }
 }
+
+/// [`Tunables`] pre-reserving the memories of a number of instances, for
+/// hosts creating instances at a high rate, usually with an
+/// [`InstancePool`].
+///
+/// The address space of `slots` static memories is reserved up front.
+/// The static memories take one of these slots, which is reset with
+/// `madvise(MADV_DONTNEED)` and handed back when the memory is dropped,
+/// and creating one fails when all the slots are in use. The dynamic
+/// memories, whose maximum exceeds the static bound, are allocated like
+/// with [`Tunables`].
+///
+/// ```
+/// # use wasmer::{imports, InstancePool, Module, PoolingTunables, Store, Tunables};
+/// # fn main() -> anyhow::Result<()> {
+/// # let engine = Store::default().engine().clone();
+/// let tunables = PoolingTunables::new(Tunables::for_target(engine.target()), 16)?;
+/// let store = Store::new_with_tunables(&*engine, tunables);
+/// let module = Module::new(&store, "(module (memory 1 1))")?;
+/// let pool = InstancePool::new(&module, 16);
+/// pool.reserve(16, &imports! {})?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`InstancePool`]: crate::InstancePool
+#[derive(Clone)]
+pub struct PoolingTunables {
+    base: Tunables,
+    slots: Arc<MemorySlots>,
+}
+
+/// The mappings reserved for the static memories.
+struct MemorySlots {
+    free: Mutex<Vec<Mmap>>,
+    count: usize,
+    slot_size: usize,
+}
+
+impl PoolingTunables {
+    /// Creates `Tunables` reserving `slots` static memories of `base`.
+    pub fn new(base: Tunables, slots: usize) -> Result<Self, MemoryError> {
+        let slot_size =
+            base.static_memory_bound.bytes().0 + base.static_memory_offset_guard_size as usize;
+        let free = (0..slots)
+            .map(|_| Mmap::accessible_reserved(0, slot_size))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(MemoryError::Region)?;
+        Ok(Self {
+            base,
+            slots: Arc::new(MemorySlots {
+                free: Mutex::new(free),
+                count: slots,
+                slot_size,
+            }),
+        })
+    }
+
+    /// Returns the number of memory slots not in use.
+    pub fn free_slots(&self) -> usize {
+        self.slots.free.lock().unwrap().len()
+    }
+}
+
+impl BaseTunables for PoolingTunables {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn fingerprint(&self) -> String {
+        self.base.fingerprint()
+    }
+
+    /// Create a memory given a [`MemoryType`] and a [`MemoryStyle`], in
+    /// a free slot if it's static.
+    fn create_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        if let MemoryStyle::Dynamic { .. } = style {
+            return self.base.create_memory(ty, style);
+        }
+        let alloc = self.slots.free.lock().unwrap().pop().ok_or_else(|| {
+            MemoryError::Generic(format!(
+                "all the {} memory slots are in use",
+                self.slots.count
+            ))
+        })?;
+        match LinearMemory::new_in(ty, style, alloc) {
+            Ok(memory) => Ok(Arc::new(PooledMemory {
+                memory: ManuallyDrop::new(memory),
+                slots: self.slots.clone(),
+            })),
+            Err(e) => {
+                // replace the slot, which was consumed
+                if let Ok(alloc) = Mmap::accessible_reserved(0, self.slots.slot_size) {
+                    self.slots.free.lock().unwrap().push(alloc);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn create_table(&self, ty: &TableType, style: &TableStyle) -> Result<Arc<dyn Table>, String> {
+        self.base.create_table(ty, style)
+    }
+}
+
+/// A static memory living in a slot of [`PoolingTunables`].
+#[derive(Debug)]
+struct PooledMemory {
+    memory: ManuallyDrop<LinearMemory>,
+    slots: Arc<MemorySlots>,
+}
+
+impl fmt::Debug for MemorySlots {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemorySlots")
+            .field("count", &self.count)
+            .finish()
+    }
+}
+
+impl Memory for PooledMemory {
+    fn ty(&self) -> &MemoryType {
+        self.memory.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.memory.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.memory.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        self.memory.grow(delta)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.memory.vmmemory()
+    }
+
+    fn reset(&self) -> Result<(), MemoryError> {
+        self.memory.reset()
+    }
+
+    fn snapshot(&self) -> Result<MmapImage, MemoryError> {
+        self.memory.snapshot()
+    }
+
+    fn restore(&self, image: &MmapImage) -> Result<(), MemoryError> {
+        self.memory.restore(image)
+    }
+}
+
+impl Drop for PooledMemory {
+    fn drop(&mut self) {
+        let memory = unsafe { ManuallyDrop::take(&mut self.memory) };
+        let mut alloc = memory.into_mmap();
+        // slots which can't be reset are released
+        if alloc.reset(0).is_ok() {
+            self.slots.free.lock().unwrap().push(alloc);
+        }
+    }
+}
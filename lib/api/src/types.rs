@@ -41,7 +41,11 @@ pub trait ValFuncRef {
         store: &Store,
     ) -> Result<wasmer_vm::VMCallerCheckedAnyfunc, RuntimeError>;
 
-    fn from_checked_anyfunc(item: wasmer_vm::VMCallerCheckedAnyfunc, store: &Store) -> Self;
+    fn from_checked_anyfunc(
+        item: wasmer_vm::VMCallerCheckedAnyfunc,
+        host_env: Option<wasmer_vm::HostFunctionEnv>,
+        store: &Store,
+    ) -> Self;
 }
 
 impl ValFuncRef for Val {
@@ -63,7 +67,11 @@ impl ValFuncRef for Val {
         })
     }
 
-    fn from_checked_anyfunc(item: wasmer_vm::VMCallerCheckedAnyfunc, store: &Store) -> Self {
+    fn from_checked_anyfunc(
+        item: wasmer_vm::VMCallerCheckedAnyfunc,
+        host_env: Option<wasmer_vm::HostFunctionEnv>,
+        store: &Store,
+    ) -> Self {
         if item.type_index == wasmer_vm::VMSharedSignatureIndex::default() {
             return Self::ExternRef(ExternRef::Null);
         }
@@ -79,6 +87,7 @@ impl ValFuncRef for Val {
             kind: wasmer_vm::VMFunctionKind::Static,
             vmctx: item.vmctx,
             call_trampoline: None,
+            host_env,
         };
         let f = Function::from_export(store, export);
         Self::FuncRef(f)
@@ -1,4 +1,6 @@
 use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use wasmer::*;
 
 #[test]
@@ -83,19 +85,19 @@ fn table_new() -> Result<()> {
 }
 
 #[test]
-#[ignore]
 fn table_get() -> Result<()> {
     let store = Store::default();
     let table_type = TableType {
         ty: Type::FuncRef,
-        minimum: 0,
+        minimum: 1,
         maximum: Some(1),
     };
     let f = Function::new_native(&store, |num: i32| num + 1);
     let table = Table::new(&store, table_type, Value::FuncRef(f.clone()))?;
     assert_eq!(*table.ty(), table_type);
-    let _elem = table.get(0).unwrap();
-    // assert_eq!(elem.funcref().unwrap(), f);
+    let elem = table.get(0).unwrap();
+    assert!(elem.funcref().unwrap().same(&f));
+    assert!(table.get(1).is_none());
     Ok(())
 }
 
@@ -177,6 +179,273 @@ fn memory_grow() -> Result<()> {
     Ok(())
 }
 
+/// A memory buffer from an arena of `limit` bytes, which tracks how many
+/// bytes are allocated.
+#[derive(Debug)]
+struct ArenaBuffer {
+    data: Vec<u8>,
+    allocated: Arc<AtomicUsize>,
+    limit: usize,
+}
+
+unsafe impl vm::MemoryBuffer for ArenaBuffer {
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.data.as_mut_ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn resize(&mut self, new_len: usize) -> Result<(), MemoryError> {
+        let allocated = self.allocated.load(Ordering::SeqCst) - self.data.len();
+        if allocated + new_len > self.limit {
+            return Err(MemoryError::Generic("the arena is exhausted".to_string()));
+        }
+        self.data.resize(new_len, 0);
+        self.allocated.store(allocated + new_len, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Tunables creating the memories in an arena.
+struct ArenaTunables {
+    base: Tunables,
+    allocated: Arc<AtomicUsize>,
+    limit: usize,
+}
+
+impl ArenaTunables {
+    fn new(limit: Pages) -> Self {
+        Self {
+            base: Tunables::for_target(&Target::default()),
+            allocated: Arc::new(AtomicUsize::new(0)),
+            limit: limit.bytes().0,
+        }
+    }
+
+    fn buffer(&self) -> ArenaBuffer {
+        ArenaBuffer {
+            data: Vec::new(),
+            allocated: self.allocated.clone(),
+            limit: self.limit,
+        }
+    }
+}
+
+impl BaseTunables for ArenaTunables {
+    fn memory_style(&self, _memory: &MemoryType) -> vm::MemoryStyle {
+        vm::MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+        }
+    }
+
+    fn table_style(&self, table: &TableType) -> vm::TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_memory(
+        &self,
+        ty: &MemoryType,
+        _style: &vm::MemoryStyle,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        Ok(Arc::new(vm::BufferMemory::new(ty, self.buffer())?))
+    }
+
+    fn create_table(
+        &self,
+        ty: &TableType,
+        style: &vm::TableStyle,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_table(ty, style)
+    }
+}
+
+#[test]
+fn memory_custom_tunables() -> Result<()> {
+    let tunables = ArenaTunables::new(Pages(4));
+    let allocated = tunables.allocated.clone();
+    let store = Store::new_with_tunables(&**Store::default().engine(), tunables);
+    let module = Module::new(
+        &store,
+        r#"(module
+    (memory (export "memory") 1)
+    (func (export "grow") (param i32) (result i32)
+        (memory.grow (local.get 0)))
+    (func (export "store") (param i32 i32)
+        (i32.store (local.get 0) (local.get 1))))"#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(allocated.load(Ordering::SeqCst), Pages(1).bytes().0);
+
+    let grow = instance.exports.get_function("grow")?;
+    let store_i32 = instance.exports.get_function("store")?;
+    assert_eq!(grow.call(&[Value::I32(2)])?.to_vec(), vec![Value::I32(1)]);
+    assert_eq!(allocated.load(Ordering::SeqCst), Pages(3).bytes().0);
+    store_i32.call(&[Value::I32(0x2_0000), Value::I32(42)])?;
+
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.size(), Pages(3));
+    assert_eq!(memory.view::<u8>()[0x2_0000].get(), 42);
+
+    // The arena is exhausted, and the accesses are bounds checked.
+    assert_eq!(grow.call(&[Value::I32(2)])?.to_vec(), vec![Value::I32(-1)]);
+    assert!(store_i32
+        .call(&[Value::I32(0x3_0000), Value::I32(42)])
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn memory_custom_tunables_grown_by_host() -> Result<()> {
+    let tunables = ArenaTunables::new(Pages(4));
+    let store = Store::new_with_tunables(&**Store::default().engine(), tunables);
+    let module = Module::new(
+        &store,
+        r#"(module
+    (memory (export "memory") 1)
+    (func (export "load") (param i32) (result i32)
+        (i32.load (local.get 0)))
+    (func (export "store") (param i32 i32)
+        (i32.store (local.get 0) (local.get 1))))"#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let load = instance.exports.get_function("load")?;
+    let store_i32 = instance.exports.get_function("store")?;
+    store_i32.call(&[Value::I32(8), Value::I32(42)])?;
+
+    // Growing the buffer moves it, which the instance must see.
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.grow(Pages(2))?, Pages(1));
+    store_i32.call(&[Value::I32(0x2_0000), Value::I32(43)])?;
+    assert_eq!(memory.view::<u8>()[0x2_0000].get(), 43);
+    assert_eq!(load.call(&[Value::I32(8)])?.to_vec(), vec![Value::I32(42)]);
+    assert_eq!(
+        load.call(&[Value::I32(0x2_0000)])?.to_vec(),
+        vec![Value::I32(43)]
+    );
+
+    Ok(())
+}
+
+/// Tunables compiling static memories, but creating them as buffers.
+struct StaticBufferTunables {
+    base: Tunables,
+}
+
+impl BaseTunables for StaticBufferTunables {
+    fn memory_style(&self, memory: &MemoryType) -> vm::MemoryStyle {
+        self.base.memory_style(memory)
+    }
+
+    fn table_style(&self, table: &TableType) -> vm::TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_memory(
+        &self,
+        ty: &MemoryType,
+        _style: &vm::MemoryStyle,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        Ok(Arc::new(vm::BufferMemory::new(ty, Vec::new())?))
+    }
+
+    fn create_table(
+        &self,
+        ty: &TableType,
+        style: &vm::TableStyle,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_table(ty, style)
+    }
+}
+
+#[test]
+fn memory_custom_tunables_incompatible_style() -> Result<()> {
+    let tunables = StaticBufferTunables {
+        base: Tunables::for_target(&Target::default()),
+    };
+    let store = Store::new_with_tunables(&**Store::default().engine(), tunables);
+    // The memory is compiled as a static memory, without bounds checks,
+    // which the buffer can't honor.
+    let module = Module::new(&store, r#"(module (memory 1 1))"#)?;
+    let result = Instance::new(&module, &imports! {});
+    assert!(matches!(
+        result,
+        Err(InstantiationError::Link(LinkError::Resource(_)))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn memory_from_vm_memory() -> Result<()> {
+    let tunables = ArenaTunables::new(Pages(2));
+    let ty = MemoryType::new(Pages(1), None, false);
+    let buffer = vm::BufferMemory::new(&ty, tunables.buffer())?;
+    let store = Store::new_with_tunables(&**Store::default().engine(), tunables);
+    let memory = Memory::from_vm_memory(&store, Arc::new(buffer))?;
+    assert_eq!(memory.size(), Pages(1));
+
+    let module = Module::new(
+        &store,
+        r#"(module
+    (import "env" "memory" (memory 1))
+    (func (export "store") (param i32 i32)
+        (i32.store (local.get 0) (local.get 1))))"#,
+    )?;
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "memory" => memory.clone(),
+            },
+        },
+    )?;
+    let store_i32 = instance.exports.get_function("store")?;
+    store_i32.call(&[Value::I32(8), Value::I32(42)])?;
+    assert_eq!(memory.view::<u32>()[2].get(), 42);
+
+    memory.grow(Pages(1))?;
+    store_i32.call(&[Value::I32(0x1_0000), Value::I32(42)])?;
+    assert!(matches!(
+        memory.grow(Pages(1)),
+        Err(MemoryError::Generic(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn memory_from_vm_memory_incompatible_style() -> Result<()> {
+    let store = Store::default();
+    let ty = MemoryType::new(Pages(1), Some(Pages(1)), false);
+    let buffer = vm::BufferMemory::new(&ty, Vec::new())?;
+    let memory = Memory::from_vm_memory(&store, Arc::new(buffer))?;
+
+    // The default tunables compile the import as a static memory, without
+    // bounds checks, which the buffer can't honor.
+    let module = Module::new(&store, r#"(module (import "env" "memory" (memory 1 1)))"#)?;
+    let result = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "memory" => memory,
+            },
+        },
+    );
+    assert!(matches!(
+        result,
+        Err(InstantiationError::Link(LinkError::Import(
+            _,
+            _,
+            ImportError::IncompatibleMemoryStyle(..)
+        )))
+    ));
+
+    Ok(())
+}
+
 #[test]
 fn function_new() -> Result<()> {
     let store = Store::default();
@@ -317,6 +586,153 @@ fn function_new_dynamic_env() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn function_env_dropped_with_last_reference() -> Result<()> {
+    let store = Store::default();
+    struct MyEnv(Arc<AtomicUsize>);
+    impl Drop for MyEnv {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    fn host(_env: &mut MyEnv) {}
+
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let function = Function::new_native_with_env(&store, MyEnv(dropped.clone()), host);
+    let native_function: NativeFunc<(), ()> = function.native()?;
+    drop(function);
+    assert_eq!(dropped.load(Ordering::SeqCst), 0);
+    drop(native_function);
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+
+    let module = Module::new(
+        &store,
+        r#"(module
+    (import "host" "func" (func $func))
+    (func (export "call") (call $func)))"#,
+    )?;
+    let function_type = FunctionType::new(vec![], vec![]);
+    let function = Function::new_with_env(
+        &store,
+        &function_type,
+        MyEnv(dropped.clone()),
+        |_env: &mut MyEnv, _values: &[Value]| Ok(vec![]),
+    );
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "host" => {
+                "func" => function,
+            },
+        },
+    )?;
+    // the instance keeps the environment alive
+    instance.exports.get_function("call")?.call(&[])?;
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[test]
+fn function_env_kept_alive_by_tables() -> Result<()> {
+    let store = Store::default();
+    fn add_one(env: &mut i32, x: i32) -> i32 {
+        *env + x
+    }
+
+    let table_type = TableType {
+        ty: ValType::FuncRef,
+        minimum: 1,
+        maximum: None,
+    };
+    let table = Table::new(&store, table_type, Value::ExternRef(ExternRef::Null))?;
+    let function = Function::new_native_with_env(&store, 1, add_one);
+    table.set(0, Value::FuncRef(function))?;
+    // the table only holds the function, dropped here
+    match table.get(0) {
+        Some(Value::FuncRef(f)) => {
+            assert_eq!(f.native::<i32, i32>()?.call(41)?, 42);
+        }
+        _ => panic!("table.get(0) should be a funcref"),
+    }
+
+    // the tables exported by an instance outlive it
+    let module = Module::new(
+        &store,
+        r#"(module
+    (import "host" "add_one" (func $add_one (param i32) (result i32)))
+    (table (export "table") 1 anyfunc)
+    (elem (i32.const 0) $add_one))"#,
+    )?;
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "host" => {
+                "add_one" => Function::new_native_with_env(&store, 2, add_one),
+            },
+        },
+    )?;
+    let table = instance.exports.get_table("table")?.clone();
+    drop(instance);
+    match table.get(0) {
+        Some(Value::FuncRef(f)) => {
+            assert_eq!(f.native::<i32, i32>()?.call(40)?, 42);
+        }
+        _ => panic!("table.get(0) should be a funcref"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn function_env_dropped_with_table() -> Result<()> {
+    let store = Store::default();
+    struct MyEnv(Arc<AtomicUsize>);
+    impl Drop for MyEnv {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    fn host(_env: &mut MyEnv) {}
+
+    let table_type = TableType {
+        ty: ValType::FuncRef,
+        minimum: 2,
+        maximum: None,
+    };
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let table = Table::new(&store, table_type, Value::ExternRef(ExternRef::Null))?;
+    table.set(
+        0,
+        Value::FuncRef(Function::new_native_with_env(
+            &store,
+            MyEnv(dropped.clone()),
+            host,
+        )),
+    )?;
+    Table::copy(&table, 1, &table, 0, 1)?;
+    // overwriting the first element keeps the copy alive
+    table.set(0, Value::ExternRef(ExternRef::Null))?;
+    assert_eq!(dropped.load(Ordering::SeqCst), 0);
+    drop(table);
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+
+    let table = Table::new(&store, table_type, Value::ExternRef(ExternRef::Null))?;
+    table.grow(
+        1,
+        Value::FuncRef(Function::new_native_with_env(
+            &store,
+            MyEnv(dropped.clone()),
+            host,
+        )),
+    )?;
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+    drop(table);
+    assert_eq!(dropped.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
 #[test]
 fn native_function_works() -> Result<()> {
     let store = Store::default();
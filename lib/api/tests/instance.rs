@@ -0,0 +1,96 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use wasmer::*;
+
+#[test]
+fn instance_from_snapshot() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+    (import "host" "started" (func $started))
+    (memory (export "memory") 1)
+    (global $value (export "value") (mut i32) (i32.const 0))
+    (table 2 anyfunc)
+    (elem (i32.const 1) $seven)
+    (type $ret_i32 (func (result i32)))
+    (func $seven (type $ret_i32) (i32.const 7))
+    (func $start
+        (call $started)
+        (global.set $value (i32.const 42))
+        (i32.store8 (i32.const 0) (i32.const 1))
+        (drop (memory.grow (i32.const 1))))
+    (func (export "call_indirect") (param i32) (result i32)
+        (call_indirect (type $ret_i32) (local.get 0)))
+    (start $start)
+)"#;
+    let module = Module::new(&store, wat)?;
+    let started = Arc::new(AtomicUsize::new(0));
+    fn on_start(started: &mut Arc<AtomicUsize>) {
+        started.fetch_add(1, Ordering::SeqCst);
+    }
+    let imports = imports! {
+        "host" => {
+            "started" => Function::new_native_with_env(&store, started.clone(), on_start),
+        },
+    };
+
+    let instance = Instance::new(&module, &imports)?;
+    let snapshot = instance.snapshot()?;
+    assert_eq!(started.load(Ordering::SeqCst), 1);
+
+    for _ in 0..2 {
+        let fork = Instance::from_snapshot(&module, &snapshot, &imports)?;
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+        assert_eq!(fork.exports.get_global("value")?.get(), Val::I32(42));
+
+        let memory = fork.exports.get_memory("memory")?;
+        assert_eq!(memory.size(), Pages(2));
+        let view = memory.view::<u8>();
+        assert_eq!(view[0].get(), 1);
+        view[0].set(2);
+
+        let call_indirect = fork.exports.get_function("call_indirect")?;
+        assert_eq!(
+            call_indirect.call(&[Val::I32(1)])?.to_vec(),
+            vec![Val::I32(7)]
+        );
+        assert!(call_indirect.call(&[Val::I32(0)]).is_err());
+    }
+
+    let view = instance.exports.get_memory("memory")?.view::<u8>();
+    assert_eq!(view[0].get(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn instance_from_snapshot_of_another_module() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, "(module (memory 1))")?;
+    let other = Module::new(&store, "(module (memory 1))")?;
+    let snapshot = Instance::new(&module, &imports! {})?.snapshot()?;
+
+    assert!(matches!(
+        Instance::from_snapshot(&other, &snapshot, &imports! {}),
+        Err(InstantiationError::Link(LinkError::Resource(_)))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn instance_snapshot_with_foreign_table_element() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, "(module (table (export \"table\") 1 anyfunc))")?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let table = instance.exports.get_table("table")?;
+    let host_function = Function::new_native(&store, || {});
+    table.set(0, Val::FuncRef(host_function))?;
+
+    assert!(matches!(
+        instance.snapshot(),
+        Err(SnapshotError::ForeignTableElement { table: 0, index: 0 })
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use wasmer::*;
+
+#[test]
+fn instance_pool_resets_state() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+    (memory (export "memory") 1 4)
+    (global $counter (export "counter") (mut i32) (i32.const 0))
+    (data (i32.const 0) "\2a")
+    (func (export "bump") (result i32)
+        (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+        (i32.store8 (i32.const 1) (i32.const 7))
+        (drop (memory.grow (i32.const 1)))
+        (global.get $counter))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let pool = InstancePool::new(&module, 1);
+
+    for _ in 0..3 {
+        let instance = pool.instantiate(&imports! {})?;
+        let bump = instance.exports.get_function("bump")?;
+        assert_eq!(bump.call(&[])?.to_vec(), vec![Val::I32(1)]);
+
+        let memory = instance.exports.get_memory("memory")?;
+        assert_eq!(memory.size(), Pages(2));
+        let view = memory.view::<u8>();
+        assert_eq!(view[0].get(), 42);
+        assert_eq!(view[1].get(), 7);
+        assert_eq!(view[2].get(), 0);
+    }
+    assert_eq!(pool.allocated(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn instance_pool_capacity() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, "(module (memory 1) (table 1 anyfunc))")?;
+    let pool = InstancePool::new(&module, 2);
+
+    let first = pool.instantiate(&imports! {})?;
+    let second = pool.instantiate(&imports! {})?;
+    assert!(matches!(
+        pool.instantiate(&imports! {}),
+        Err(InstantiationError::Link(LinkError::Resource(_)))
+    ));
+
+    drop(first);
+    let _third = pool.instantiate(&imports! {})?;
+    drop(second);
+    assert_eq!(pool.allocated(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn instance_pool_retires_referenced_slots() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+    (memory (export "memory") 1)
+    (global $counter (export "counter") (mut i32) (i32.const 0))
+    (table (export "table") 1 anyfunc)
+    (elem (i32.const 0) $bump)
+    (func $bump (export "bump") (result i32)
+        (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+        (global.get $counter))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let pool = InstancePool::new(&module, 1);
+
+    // an export outliving the instance
+    let instance = pool.instantiate(&imports! {})?;
+    let memory = instance.exports.get_memory("memory")?.clone();
+    drop(instance);
+    assert_eq!(pool.allocated(), 0);
+    let instance = pool.instantiate(&imports! {})?;
+    memory.view::<u8>()[0].set(1);
+    let view = instance.exports.get_memory("memory")?.view::<u8>();
+    assert_eq!(view[0].get(), 0);
+    drop(instance);
+    assert_eq!(pool.allocated(), 1);
+
+    // a function read from a table
+    let instance = pool.instantiate(&imports! {})?;
+    let bump = match instance.exports.get_table("table")?.get(0) {
+        Some(Val::FuncRef(bump)) => bump.native::<(), i32>()?,
+        _ => panic!("table.get(0) should be a funcref"),
+    };
+    drop(instance);
+    let instance = pool.instantiate(&imports! {})?;
+    assert_eq!(bump.call()?, 1);
+    assert_eq!(instance.exports.get_global("counter")?.get(), Val::I32(0));
+    drop(instance);
+
+    // a clone of the instance
+    let instance = pool.instantiate(&imports! {})?;
+    let clone = Instance::clone(&instance);
+    drop(instance);
+    let instance = pool.instantiate(&imports! {})?;
+    clone.exports.get_function("bump")?.call(&[])?;
+    assert_eq!(instance.exports.get_global("counter")?.get(), Val::I32(0));
+
+    Ok(())
+}
+
+#[test]
+fn instance_pool_with_imported_tables() -> Result<()> {
+    let store = Store::default();
+    let table = Table::new(
+        &store,
+        TableType::new(ValType::FuncRef, 1, None),
+        Val::null(),
+    )?;
+    let imports = imports! {
+        "env" => {
+            "table" => table.clone(),
+        },
+    };
+
+    // the slots are reused while the imported table doesn't hold functions
+    // of the instances
+    let module = Module::new(
+        &store,
+        r#"(module (import "env" "table" (table 1 anyfunc)) (memory 1))"#,
+    )?;
+    let pool = InstancePool::new(&module, 1);
+    for _ in 0..3 {
+        drop(pool.instantiate(&imports)?);
+    }
+    assert_eq!(pool.allocated(), 1);
+
+    // a function stored in the imported table
+    let module = Module::new(
+        &store,
+        r#"(module
+    (import "env" "table" (table 1 anyfunc))
+    (global $counter (mut i32) (i32.const 0))
+    (elem (i32.const 0) $bump)
+    (func $bump (result i32)
+        (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+        (global.get $counter))
+)"#,
+    )?;
+    let pool = InstancePool::new(&module, 1);
+    drop(pool.instantiate(&imports)?);
+    assert_eq!(pool.allocated(), 0);
+    let instance = pool.instantiate(&imports)?;
+    let bump = match table.get(0) {
+        Some(Val::FuncRef(bump)) => bump.native::<(), i32>()?,
+        _ => panic!("table.get(0) should be a funcref"),
+    };
+    assert_eq!(bump.call()?, 1);
+    drop(bump);
+
+    // the slot is reused once the table doesn't hold the function anymore
+    table.set(0, Val::null())?;
+    drop(instance);
+    assert_eq!(pool.allocated(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn instance_pool_reserve() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+    (import "host" "answer" (func $answer (result i32)))
+    (memory 1)
+    (func (export "answer") (result i32) (call $answer)))"#,
+    )?;
+    let pool = InstancePool::new(&module, 2);
+    let answer = |value: i32| {
+        imports! {
+            "host" => {
+                "answer" => Function::new_native_with_env(&store, value, |value: &mut i32| *value),
+            },
+        }
+    };
+
+    pool.reserve(3, &answer(0))?;
+    assert_eq!(pool.allocated(), 2);
+
+    let first = pool.instantiate(&answer(1))?;
+    let second = pool.instantiate(&answer(2))?;
+    assert_eq!(pool.allocated(), 2);
+    let answer = first.exports.get_function("answer")?.native::<(), i32>()?;
+    assert_eq!(answer.call()?, 1);
+    let answer = second.exports.get_function("answer")?.native::<(), i32>()?;
+    assert_eq!(answer.call()?, 2);
+
+    Ok(())
+}
+
+#[test]
+fn pooling_tunables_reuse_memory_slots() -> Result<()> {
+    let engine = Store::default().engine().clone();
+    let tunables = PoolingTunables::new(Tunables::for_target(engine.target()), 1)?;
+    let store = Store::new_with_tunables(&*engine, tunables.clone());
+    let ty = MemoryType::new(1, Some(2), false);
+
+    let memory = Memory::new(&store, ty)?;
+    assert_eq!(tunables.free_slots(), 0);
+    memory.view::<u8>()[0].set(42);
+    assert!(Memory::new(&store, ty).is_err());
+    drop(memory);
+    assert_eq!(tunables.free_slots(), 1);
+
+    let memory = Memory::new(&store, ty)?;
+    assert_eq!(memory.size(), Pages(1));
+    assert_eq!(memory.view::<u8>()[0].get(), 0);
+
+    Ok(())
+}
@@ -24,6 +24,39 @@ fn module_set_name() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn module_share_with() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+    (func (export "answer") (result i32) i32.const 42))"#;
+    let module = Module::new(&store, wat)?;
+
+    let handles = (0..4)
+        .map(|_| {
+            let module = module.clone();
+            std::thread::spawn(move || {
+                let store = Store::new(&**module.store().engine());
+                let module = module.share_with(&store).unwrap();
+                let instance = Instance::new(&module, &imports! {}).unwrap();
+                let answer = instance
+                    .exports
+                    .get_native_function::<(), i32>("answer")
+                    .unwrap();
+                answer.call().unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 42);
+    }
+
+    // stores with another engine can't use the compiled code
+    let other_store = Store::default();
+    assert!(module.share_with(&other_store).is_none());
+
+    Ok(())
+}
+
 #[test]
 fn imports() -> Result<()> {
     let store = Store::default();
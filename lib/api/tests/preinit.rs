@@ -0,0 +1,179 @@
+use anyhow::Result;
+use wasmer::*;
+
+#[test]
+fn preinitialize_captures_memory_and_globals() -> Result<()> {
+    let store = Store::default();
+    let wasm = wat2wasm(
+        br#"(module
+    (memory (export "memory") 1)
+    (global $counter (export "counter") (mut i32) (i32.const 0))
+    (global $big (mut i64) (i64.const 0))
+    (data (i32.const 16) "hello")
+    (func $start (global.set $counter (i32.add (global.get $counter) (i32.const 1))))
+    (func (export "init")
+        (global.set $counter (i32.add (global.get $counter) (i32.const 10)))
+        (global.set $big (i64.const -5000000000))
+        (drop (memory.grow (i32.const 1)))
+        (i32.store (i32.const 70000) (i32.const 0xdeadbeef)))
+    (func (export "big") (result i64) (global.get $big))
+    (start $start)
+)"#,
+    )?;
+    let preinitialized = preinitialize(&store, &wasm, "init", &imports! {})?;
+
+    let module = Module::new(&store, &preinitialized)?;
+    assert!(module
+        .exports()
+        .all(|export| !export.name().starts_with("__wasmer") && export.name() != "init"));
+
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(instance.exports.get_global("counter")?.get(), Val::I32(11));
+    let big = instance.exports.get_function("big")?;
+    assert_eq!(big.call(&[])?.to_vec(), vec![Val::I64(-5000000000)]);
+
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.size(), Pages(2));
+    let data = unsafe { memory.data_unchecked() };
+    assert_eq!(&data[16..21], b"hello");
+    assert_eq!(&data[70000..70004], &0xdeadbeef_u32.to_le_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn preinitialize_keeps_passive_segments() -> Result<()> {
+    let store = Store::default();
+    let wasm = wat2wasm(
+        br#"(module
+    (memory (export "memory") 1)
+    (data (i32.const 0) "active")
+    (data $passive "passive")
+    (func (export "init") (i32.store8 (i32.const 0) (i32.const 65)))
+    (func (export "load") (memory.init $passive (i32.const 8) (i32.const 0) (i32.const 7)))
+)"#,
+    )?;
+    let preinitialized = preinitialize(&store, &wasm, "init", &imports! {})?;
+
+    let module = Module::new(&store, &preinitialized)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    instance.exports.get_function("load")?.call(&[])?;
+
+    let memory = instance.exports.get_memory("memory")?;
+    let data = unsafe { memory.data_unchecked() };
+    assert_eq!(&data[0..15], b"Active\0\0passive");
+
+    Ok(())
+}
+
+#[test]
+fn preinitialize_keeps_imported_global_initializers() -> Result<()> {
+    let store = Store::default();
+    let wasm = wat2wasm(
+        br#"(module
+    (import "env" "base" (global $base i32))
+    (global $offset (export "offset") i32 (global.get $base))
+    (global $counter (export "counter") (mut i32) (i32.const 0))
+    (func (export "init") (global.set $counter (global.get $offset)))
+)"#,
+    )?;
+    let imports = |base| {
+        imports! {
+            "env" => {
+                "base" => Global::new(&store, Val::I32(base)),
+            },
+        }
+    };
+    let preinitialized = preinitialize(&store, &wasm, "init", &imports(1))?;
+
+    let module = Module::new(&store, &preinitialized)?;
+    let instance = Instance::new(&module, &imports(2))?;
+    assert_eq!(instance.exports.get_global("offset")?.get(), Val::I32(2));
+    assert_eq!(instance.exports.get_global("counter")?.get(), Val::I32(1));
+
+    let wasm = wat2wasm(
+        br#"(module
+    (import "env" "base" (global $base i32))
+    (global $offset (mut i32) (global.get $base))
+    (func (export "init"))
+)"#,
+    )?;
+    match preinitialize(&store, &wasm, "init", &imports(1)) {
+        Err(PreInitError::Unsupported(_)) => {}
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn preinitialize_rejects_changed_tables() -> Result<()> {
+    let store = Store::default();
+    let wat = |init: &str| {
+        wat2wasm(
+            format!(
+                r#"(module
+    (table $table 2 funcref)
+    (elem (i32.const 0) $one)
+    (elem $two func $two)
+    (func $one (result i32) (i32.const 1))
+    (func $two (result i32) (i32.const 2))
+    (func (export "init") {})
+    (func (export "call") (param i32) (result i32)
+        (call_indirect (result i32) (local.get 0)))
+)"#,
+                init
+            )
+            .as_bytes(),
+        )
+        .map(|wasm| wasm.into_owned())
+    };
+
+    let preinitialized = preinitialize(&store, &wat("")?, "init", &imports! {})?;
+    let module = Module::new(&store, &preinitialized)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let call = instance.exports.get_function("call")?;
+    assert_eq!(call.call(&[Val::I32(0)])?.to_vec(), vec![Val::I32(1)]);
+
+    let wasm = wat("(table.init $two (i32.const 1) (i32.const 0) (i32.const 1))")?;
+    match preinitialize(&store, &wasm, "init", &imports! {}) {
+        Err(PreInitError::Unsupported(_)) => {}
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn preinitialize_rejects_dropped_segments() -> Result<()> {
+    let store = Store::default();
+    let wat = |init: &str| {
+        wat2wasm(
+            format!(
+                r#"(module
+    (memory 1)
+    (table 1 funcref)
+    (data $data "data")
+    (elem $elem func $f)
+    (func $f)
+    (func $start {})
+    (func (export "init"))
+    (start $start)
+)"#,
+                init
+            )
+            .as_bytes(),
+        )
+        .map(|wasm| wasm.into_owned())
+    };
+
+    preinitialize(&store, &wat("")?, "init", &imports! {})?;
+    for drop in &["(data.drop $data)", "(elem.drop $elem)"] {
+        match preinitialize(&store, &wat(drop)?, "init", &imports! {}) {
+            Err(PreInitError::Unsupported(_)) => {}
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    Ok(())
+}
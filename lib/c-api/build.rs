@@ -75,6 +75,11 @@ fn build_wasm_c_api_headers(crate_dir: &str, out_dir: &str) {
         r#"// The Wasmer C/C++ header file compatible with the `wasm-c-api` standard API.
 // This file is generated by lib/c-api/build.rs.
 
+// The environments given to `wasm_func_new_with_env` and
+// `wasmer_func_new_with_caller` must be usable from any thread: the
+// callbacks are called on the threads calling the functions, and the
+// finalizers on the thread releasing the functions last.
+
 #if !defined(WASMER_WASM_H_MACROS)
 
 #define WASMER_WASM_H_MACROS
@@ -364,6 +369,8 @@ fn exclude_items_from_wasm_c_api(builder: Builder) -> Builder {
         .exclude_item("wasi_config_env")
         .exclude_item("wasi_config_mapdir")
         .exclude_item("wasi_config_preopen_dir")
+        .exclude_item("wasi_config_stdin")
+        .exclude_item("wasi_config_virtual_file")
         .exclude_item("wasi_config_inherit_stderr")
         .exclude_item("wasi_config_inherit_stdin")
         .exclude_item("wasi_config_inherit_stdout")
@@ -371,21 +378,71 @@ fn exclude_items_from_wasm_c_api(builder: Builder) -> Builder {
         .exclude_item("wasi_config_t")
         .exclude_item("wasi_env_delete")
         .exclude_item("wasi_env_new")
+        .exclude_item("wasi_env_push_arg")
         .exclude_item("wasi_env_read_stderr")
         .exclude_item("wasi_env_read_stdout")
+        .exclude_item("wasi_env_set_env")
         .exclude_item("wasi_env_set_instance")
         .exclude_item("wasi_env_set_memory")
         .exclude_item("wasi_env_t")
+        .exclude_item("wasi_file_delete")
+        .exclude_item("wasi_file_finalizer_t")
+        .exclude_item("wasi_file_new")
+        .exclude_item("wasi_file_read_callback_t")
+        .exclude_item("wasi_file_seek_callback_t")
+        .exclude_item("wasi_file_t")
+        .exclude_item("wasi_file_write_callback_t")
         .exclude_item("wasi_get_imports")
         .exclude_item("wasi_get_imports_inner")
         .exclude_item("wasi_get_start_function")
         .exclude_item("wasi_get_wasi_version")
+        .exclude_item("wasi_seek_whence_t")
+        .exclude_item("wasi_trap_exit_code")
         .exclude_item("wasi_version_t")
+        .exclude_item("wasm_config_push_middleware")
         .exclude_item("wasm_config_set_compiler")
         .exclude_item("wasm_config_set_engine")
+        .exclude_item("wasm_config_set_features")
+        .exclude_item("wasm_config_set_opt_level")
+        .exclude_item("wasm_config_set_target")
         .exclude_item("wasm_module_name")
         .exclude_item("wasm_module_set_name")
         .exclude_item("wasmer_compiler_t")
+        .exclude_item("wasmer_caller_export")
+        .exclude_item("wasmer_caller_memory")
+        .exclude_item("wasmer_caller_t")
+        .exclude_item("wasmer_cpu_features_add")
+        .exclude_item("wasmer_cpu_features_delete")
+        .exclude_item("wasmer_cpu_features_new")
+        .exclude_item("wasmer_cpu_features_t")
         .exclude_item("wasmer_engine_t")
+        .exclude_item("wasmer_features_bulk_memory")
+        .exclude_item("wasmer_features_delete")
+        .exclude_item("wasmer_features_multi_value")
+        .exclude_item("wasmer_features_new")
+        .exclude_item("wasmer_features_reference_types")
+        .exclude_item("wasmer_features_simd")
+        .exclude_item("wasmer_features_t")
+        .exclude_item("wasmer_features_threads")
+        .exclude_item("wasmer_func_callback_with_caller_t")
+        .exclude_item("wasmer_func_new_with_caller")
+        .exclude_item("wasmer_metering_as_middleware")
+        .exclude_item("wasmer_metering_cost_function_t")
+        .exclude_item("wasmer_metering_delete")
+        .exclude_item("wasmer_metering_get_remaining_points")
+        .exclude_item("wasmer_metering_new")
+        .exclude_item("wasmer_metering_points_are_exhausted")
+        .exclude_item("wasmer_metering_set_remaining_points")
+        .exclude_item("wasmer_metering_t")
+        .exclude_item("wasmer_middleware_t")
+        .exclude_item("wasmer_operator_category_t")
+        .exclude_item("wasmer_opt_level_t")
+        .exclude_item("wasmer_target_delete")
+        .exclude_item("wasmer_target_new")
+        .exclude_item("wasmer_target_t")
+        .exclude_item("wasmer_triple_delete")
+        .exclude_item("wasmer_triple_new")
+        .exclude_item("wasmer_triple_new_from_host")
+        .exclude_item("wasmer_triple_t")
         .exclude_item("wat2wasm")
 }
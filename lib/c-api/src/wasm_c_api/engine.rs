@@ -1,6 +1,12 @@
+use super::features::wasmer_features_t;
+#[cfg(feature = "compiler")]
+use super::middlewares::wasmer_middleware_t;
+use super::target::wasmer_target_t;
 use cfg_if::cfg_if;
 use std::sync::Arc;
 use wasmer::Engine;
+#[cfg(feature = "compiler")]
+use wasmer::FunctionMiddlewareGenerator;
 #[cfg(feature = "jit")]
 use wasmer_engine_jit::JIT;
 #[cfg(feature = "native")]
@@ -58,13 +64,29 @@ impl Default for wasmer_engine_t {
     }
 }
 
+/// The optimization level of the compilers.
+///
+/// Singlepass doesn't optimize, and ignores it.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub enum wasmer_opt_level_t {
+    NONE = 0,
+    SPEED = 1,
+    SPEED_AND_SIZE = 2,
+}
+
 /// cbindgen:ignore
 /// this can be a wasmer-specific type with wasmer-specific functions for manipulating it
-#[derive(Debug, Default)]
-#[repr(C)]
+#[derive(Default)]
 pub struct wasm_config_t {
     compiler: wasmer_compiler_t,
     engine: wasmer_engine_t,
+    target: Option<Box<wasmer_target_t>>,
+    features: Option<Box<wasmer_features_t>>,
+    opt_level: Option<wasmer_opt_level_t>,
+    #[cfg(feature = "compiler")]
+    middlewares: Vec<Arc<dyn FunctionMiddlewareGenerator>>,
 }
 
 /// cbindgen:ignore
@@ -86,6 +108,43 @@ pub extern "C" fn wasm_config_set_engine(config: &mut wasm_config_t, engine: was
     config.engine = engine;
 }
 
+/// Sets the target the modules are compiled for, taking ownership of
+/// it. By default, the modules are compiled for the host.
+#[no_mangle]
+pub extern "C" fn wasm_config_set_target(config: &mut wasm_config_t, target: Box<wasmer_target_t>) {
+    config.target = Some(target);
+}
+
+/// Sets the WebAssembly features the modules may use, taking
+/// ownership of them.
+#[no_mangle]
+pub extern "C" fn wasm_config_set_features(
+    config: &mut wasm_config_t,
+    features: Box<wasmer_features_t>,
+) {
+    config.features = Some(features);
+}
+
+/// Sets the optimization level of the compiler.
+#[no_mangle]
+pub extern "C" fn wasm_config_set_opt_level(
+    config: &mut wasm_config_t,
+    opt_level: wasmer_opt_level_t,
+) {
+    config.opt_level = Some(opt_level);
+}
+
+/// Pushes a middleware onto the back of the middleware chain of the
+/// compiler, taking ownership of it.
+#[cfg(feature = "compiler")]
+#[no_mangle]
+pub extern "C" fn wasm_config_push_middleware(
+    config: &mut wasm_config_t,
+    middleware: Box<wasmer_middleware_t>,
+) {
+    config.middlewares.push(middleware.inner);
+}
+
 /// cbindgen:ignore
 #[allow(non_camel_case_types)]
 pub struct wasm_engine_t {
@@ -168,15 +227,51 @@ pub unsafe extern "C" fn wasm_engine_delete(_wasm_engine_address: Option<Box<was
 pub extern "C" fn wasm_engine_new_with_config(
     config: Box<wasm_config_t>,
 ) -> Option<Box<wasm_engine_t>> {
+    let wasm_config_t {
+        compiler,
+        engine,
+        target,
+        features,
+        opt_level,
+        #[cfg(feature = "compiler")]
+        middlewares,
+    } = *config;
+
+    // Applies the target and the features of the configuration to an
+    // engine builder, and builds the engine.
+    #[allow(unused_macros)]
+    macro_rules! build_engine {
+        ($builder:expr) => {{
+            let mut builder = $builder;
+            if let Some(target) = target {
+                builder = builder.target(target.inner);
+            }
+            if let Some(features) = features {
+                builder = builder.features(features.inner);
+            }
+            Arc::new(builder.engine())
+        }};
+    }
+
     // TODO: return useful error messages in failure branches
     cfg_if! {
         if #[cfg(feature = "compiler")] {
             #[allow(unused_mut)]
-            let mut compiler_config: Box<dyn CompilerConfig> = match config.compiler {
+            let mut compiler_config: Box<dyn CompilerConfig> = match compiler {
                 wasmer_compiler_t::CRANELIFT => {
                     cfg_if! {
                         if #[cfg(feature = "cranelift")] {
-                            Box::new(wasmer_compiler_cranelift::Cranelift::default())
+                            use wasmer_compiler_cranelift::CraneliftOptLevel;
+
+                            let mut compiler = wasmer_compiler_cranelift::Cranelift::default();
+                            if let Some(opt_level) = opt_level {
+                                compiler.opt_level(match opt_level {
+                                    wasmer_opt_level_t::NONE => CraneliftOptLevel::None,
+                                    wasmer_opt_level_t::SPEED => CraneliftOptLevel::Speed,
+                                    wasmer_opt_level_t::SPEED_AND_SIZE => CraneliftOptLevel::SpeedAndSize,
+                                });
+                            }
+                            Box::new(compiler)
                         } else {
                             return None;
                         }
@@ -185,7 +280,17 @@ pub extern "C" fn wasm_engine_new_with_config(
                 wasmer_compiler_t::LLVM => {
                     cfg_if! {
                         if #[cfg(feature = "llvm")] {
-                            Box::new(wasmer_compiler_llvm::LLVM::default())
+                            use wasmer_compiler_llvm::LLVMOptLevel;
+
+                            let mut compiler = wasmer_compiler_llvm::LLVM::default();
+                            if let Some(opt_level) = opt_level {
+                                compiler.opt_level(match opt_level {
+                                    wasmer_opt_level_t::NONE => LLVMOptLevel::None,
+                                    wasmer_opt_level_t::SPEED => LLVMOptLevel::Aggressive,
+                                    wasmer_opt_level_t::SPEED_AND_SIZE => LLVMOptLevel::Default,
+                                });
+                            }
+                            Box::new(compiler)
                         } else {
                             return None;
                         }
@@ -202,11 +307,15 @@ pub extern "C" fn wasm_engine_new_with_config(
                 },
             };
 
-            let inner: Arc<dyn Engine + Send + Sync> = match config.engine {
+            for middleware in middlewares {
+                compiler_config.push_middleware(middleware);
+            }
+
+            let inner: Arc<dyn Engine + Send + Sync> = match engine {
                 wasmer_engine_t::JIT => {
                     cfg_if! {
                         if #[cfg(feature = "jit")] {
-                            Arc::new(JIT::new(&*compiler_config).engine())
+                            build_engine!(JIT::new(&*compiler_config))
                         } else {
                             return None;
                         }
@@ -215,7 +324,7 @@ pub extern "C" fn wasm_engine_new_with_config(
                 wasmer_engine_t::NATIVE => {
                     cfg_if! {
                         if #[cfg(feature = "native")] {
-                            Arc::new(Native::new(&mut *compiler_config).engine())
+                            build_engine!(Native::new(&mut *compiler_config))
                         } else {
                             return None;
                         }
@@ -226,7 +335,7 @@ pub extern "C" fn wasm_engine_new_with_config(
                         // There are currently no uses of the object-file engine + compiler from the C API.
                         // So we run in headless mode.
                         if #[cfg(feature = "object-file")] {
-                            Arc::new(ObjectFile::headless().engine())
+                            build_engine!(ObjectFile::headless())
                         } else {
                             return None;
                         }
@@ -235,11 +344,13 @@ pub extern "C" fn wasm_engine_new_with_config(
             };
             Some(Box::new(wasm_engine_t { inner }))
         } else {
-            let inner: Arc<dyn Engine + Send + Sync> = match config.engine {
+            let _ = (compiler, opt_level);
+
+            let inner: Arc<dyn Engine + Send + Sync> = match engine {
                 wasmer_engine_t::JIT => {
                     cfg_if! {
                         if #[cfg(feature = "jit")] {
-                            Arc::new(JIT::headless().engine())
+                            build_engine!(JIT::headless())
                         } else {
                             return None;
                         }
@@ -248,7 +359,7 @@ pub extern "C" fn wasm_engine_new_with_config(
                 wasmer_engine_t::NATIVE => {
                     cfg_if! {
                         if #[cfg(feature = "native")] {
-                            Arc::new(Native::headless().engine())
+                            build_engine!(Native::headless())
                         } else {
                             return None;
                         }
@@ -257,7 +368,7 @@ pub extern "C" fn wasm_engine_new_with_config(
                 wasmer_engine_t::OBJECT_FILE => {
                     cfg_if! {
                         if #[cfg(feature = "object-file")] {
-                            Arc::new(ObjectFile::headless().engine())
+                            build_engine!(ObjectFile::headless())
                         } else {
                             return None;
                         }
@@ -1,17 +1,36 @@
+use super::super::reference::{ObjectKey, RefBase};
 use super::super::store::wasm_store_t;
 use super::super::trap::wasm_trap_t;
 use super::super::types::{wasm_functype_t, wasm_valkind_enum};
-use super::super::value::{wasm_val_inner, wasm_val_t, wasm_val_vec_t};
+use super::super::value::{wasm_val_inner, wasm_val_t, wasm_val_vec_delete_refs, wasm_val_vec_t};
+use super::super::wasmer::CallerFunction;
 use std::convert::TryInto;
 use std::ffi::c_void;
+use std::ptr;
 use std::sync::Arc;
-use wasmer::{Function, Instance, RuntimeError, Val};
+use wasmer::{Extern, Function, Instance, RuntimeError, Val};
 
 #[allow(non_camel_case_types)]
 pub struct wasm_func_t {
     pub(crate) inner: Function,
     // this is how we ensure the instance stays alive
     pub(crate) instance: Option<Arc<Instance>>,
+    pub(crate) base: RefBase,
+    // creates the function for each instance importing it, if it was
+    // created with `wasmer_func_new_with_caller`
+    pub(crate) caller: Option<Arc<CallerFunction>>,
+}
+
+impl wasm_func_t {
+    pub(crate) fn new(inner: Function, instance: Option<Arc<Instance>>) -> Box<Self> {
+        let base = RefBase::new(ObjectKey::of_extern(&Extern::Function(inner.clone())));
+        Box::new(Self {
+            inner,
+            instance,
+            base,
+            caller: None,
+        })
+    }
 }
 
 #[allow(non_camel_case_types)]
@@ -28,7 +47,89 @@ pub type wasm_func_callback_with_env_t = unsafe extern "C" fn(
 ) -> *mut wasm_trap_t;
 
 #[allow(non_camel_case_types)]
-pub type wasm_env_finalizer_t = unsafe extern "C" fn(c_void);
+pub type wasm_env_finalizer_t = Option<unsafe extern "C" fn(*mut c_void)>;
+
+/// The environment of a host function given by the C side, finalized
+/// once the function is dropped along with the instances importing it.
+pub(crate) struct FinalizedEnv {
+    pub(crate) env: *mut c_void,
+    pub(crate) finalizer: wasm_env_finalizer_t,
+}
+
+/// # Safety
+/// The C side is required to make the environment usable, and
+/// finalizable, from any thread, as documented by
+/// `wasm_func_new_with_env` and `wasmer_func_new_with_caller`.
+unsafe impl Send for FinalizedEnv {}
+
+impl Drop for FinalizedEnv {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer {
+            unsafe { finalizer(self.env) }
+        }
+    }
+}
+
+/// Calls a C callback with the arguments of a host function, turning
+/// the trap it may return into an error.
+pub(crate) unsafe fn call_callback<F>(
+    num_rets: usize,
+    args: &[Val],
+    callback: F,
+) -> Result<Vec<Val>, RuntimeError>
+where
+    F: FnOnce(&wasm_val_vec_t, &mut wasm_val_vec_t) -> *mut wasm_trap_t,
+{
+    let mut converted_args = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg.try_into() {
+            Ok(arg) => converted_args.push(arg),
+            Err(message) => {
+                let mut converted_args: wasm_val_vec_t = converted_args.into();
+                wasm_val_vec_delete_refs(&mut converted_args);
+                return Err(RuntimeError::new(format!(
+                    "Argument conversion failed: {}",
+                    message
+                )));
+            }
+        }
+    }
+    let mut processed_args: wasm_val_vec_t = converted_args.into();
+
+    let mut results: wasm_val_vec_t = vec![
+        wasm_val_t {
+            kind: wasm_valkind_enum::WASM_I64 as _,
+            of: wasm_val_inner { int64_t: 0 },
+        };
+        num_rets
+    ]
+    .into();
+
+    let trap = callback(&processed_args, &mut results);
+    wasm_val_vec_delete_refs(&mut processed_args);
+
+    if !trap.is_null() {
+        let trap: Box<wasm_trap_t> = Box::from_raw(trap);
+        wasm_val_vec_delete_refs(&mut results);
+        return Err(trap.inner);
+    }
+
+    let processed_results = results
+        .into_slice()
+        .ok_or_else(|| RuntimeError::new("Failed to convert `results` into a slice"))
+        .and_then(|results| {
+            results
+                .iter()
+                .map(TryInto::try_into)
+                .collect::<Result<Vec<Val>, _>>()
+                .map_err(|message| {
+                    RuntimeError::new(format!("Result conversion failed: {}", message))
+                })
+        });
+    wasm_val_vec_delete_refs(&mut results);
+
+    processed_results
+}
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_func_new(
@@ -40,47 +141,23 @@ pub unsafe extern "C" fn wasm_func_new(
     let func_sig = ft.sig();
     let num_rets = func_sig.results().len();
     let inner_callback = move |args: &[Val]| -> Result<Vec<Val>, RuntimeError> {
-        let processed_args: wasm_val_vec_t = args
-            .into_iter()
-            .map(TryInto::try_into)
-            .collect::<Result<Vec<wasm_val_t>, _>>()
-            .expect("Argument conversion failed")
-            .into();
-
-        let mut results: wasm_val_vec_t = vec![
-            wasm_val_t {
-                kind: wasm_valkind_enum::WASM_I64 as _,
-                of: wasm_val_inner { int64_t: 0 },
-            };
-            num_rets
-        ]
-        .into();
-
-        let trap = callback(&processed_args, &mut results);
-
-        if !trap.is_null() {
-            let trap: Box<wasm_trap_t> = Box::from_raw(trap);
-            RuntimeError::raise(Box::new(trap.inner));
-        }
-
-        let processed_results = results
-            .into_slice()
-            .expect("Failed to convert `results` into a slice")
-            .into_iter()
-            .map(TryInto::try_into)
-            .collect::<Result<Vec<Val>, _>>()
-            .expect("Result conversion failed");
-
-        Ok(processed_results)
+        call_callback(num_rets, args, |args, results| callback(args, results))
     };
     let function = Function::new(&store.inner, &func_sig, inner_callback);
 
-    Some(Box::new(wasm_func_t {
-        instance: None,
-        inner: function,
-    }))
+    Some(wasm_func_t::new(function, None))
 }
 
+/// Creates a host function with an environment, passed to each call
+/// of `callback`.
+///
+/// `finalizer`, if not null, is called with `env` once the function
+/// is deleted and no instance importing it can call it anymore. A
+/// function put in a table is only finalized with its store.
+///
+/// `env` must be usable from any thread: `callback` is called on the
+/// threads calling the function, and `finalizer` on the thread
+/// releasing the function last.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_func_new_with_env(
     store: &wasm_store_t,
@@ -93,48 +170,32 @@ pub unsafe extern "C" fn wasm_func_new_with_env(
     let func_sig = ft.sig();
     let num_rets = func_sig.results().len();
     let inner_callback =
-        move |env: &mut *mut c_void, args: &[Val]| -> Result<Vec<Val>, RuntimeError> {
-            let processed_args: wasm_val_vec_t = args
-                .into_iter()
-                .map(TryInto::try_into)
-                .collect::<Result<Vec<wasm_val_t>, _>>()
-                .expect("Argument conversion failed")
-                .into();
-
-            let mut results: wasm_val_vec_t = vec![
-                wasm_val_t {
-                    kind: wasm_valkind_enum::WASM_I64 as _,
-                    of: wasm_val_inner { int64_t: 0 },
-                };
-                num_rets
-            ]
-            .into();
-
-            let _traps = callback(*env, &processed_args, &mut results);
-            // TODO: do something with `traps`
-
-            let processed_results = results
-                .into_slice()
-                .expect("Failed to convert `results` into a slice")
-                .into_iter()
-                .map(TryInto::try_into)
-                .collect::<Result<Vec<Val>, _>>()
-                .expect("Result conversion failed");
-
-            Ok(processed_results)
+        move |env: &mut FinalizedEnv, args: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+            call_callback(num_rets, args, |args, results| {
+                callback(env.env, args, results)
+            })
         };
-
+    let env = FinalizedEnv { env, finalizer };
     let function = Function::new_with_env(&store.inner, &func_sig, env, inner_callback);
 
-    Some(Box::new(wasm_func_t {
-        instance: None,
-        inner: function,
-    }))
+    Some(wasm_func_t::new(function, None))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_func_delete(_func: Option<Box<wasm_func_t>>) {}
 
+#[no_mangle]
+pub unsafe extern "C" fn wasm_func_copy(func: &wasm_func_t) -> Box<wasm_func_t> {
+    let mut copy = wasm_func_t::new(func.inner.clone(), func.instance.clone());
+    copy.caller = func.caller.clone();
+    copy
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_func_same(func1: &wasm_func_t, func2: &wasm_func_t) -> bool {
+    func1.inner.same(&func2.inner)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_func_call(
     func: &wasm_func_t,
@@ -154,12 +215,18 @@ pub unsafe extern "C" fn wasm_func_call(
 
     match func.inner.call(&params) {
         Ok(wasm_results) => {
-            *results = wasm_results
+            let wasm_results = wasm_results
                 .into_iter()
                 .map(TryInto::try_into)
                 .collect::<Result<Vec<wasm_val_t>, _>>()
-                .expect("Argument conversion failed")
-                .into();
+                .expect("Argument conversion failed");
+
+            if !results.data.is_null() && results.size == wasm_results.len() {
+                // the results are written in the storage given by the caller
+                ptr::copy_nonoverlapping(wasm_results.as_ptr(), results.data, results.size);
+            } else {
+                *results = wasm_results.into();
+            }
 
             None
         }
@@ -1,13 +1,22 @@
+use super::super::reference::{ObjectKey, RefBase};
 use super::super::store::wasm_store_t;
 use super::super::types::wasm_globaltype_t;
 use super::super::value::wasm_val_t;
 use std::convert::TryInto;
-use wasmer::{Global, Val};
+use wasmer::{Extern, Global, Val};
 
 #[allow(non_camel_case_types)]
 pub struct wasm_global_t {
     // maybe needs to hold onto instance
     pub(crate) inner: Global,
+    pub(crate) base: RefBase,
+}
+
+impl wasm_global_t {
+    pub(crate) fn new(inner: Global) -> Box<Self> {
+        let base = RefBase::new(ObjectKey::of_extern(&Extern::Global(inner.clone())));
+        Box::new(Self { inner, base })
+    }
 }
 
 #[no_mangle]
@@ -25,7 +34,7 @@ pub unsafe extern "C" fn wasm_global_new(
         Global::new(store, wasm_val)
     };
 
-    Some(Box::new(wasm_global_t { inner: global }))
+    Some(wasm_global_t::new(global))
 }
 
 #[no_mangle]
@@ -35,9 +44,7 @@ pub unsafe extern "C" fn wasm_global_delete(_global: Option<Box<wasm_global_t>>)
 #[no_mangle]
 pub unsafe extern "C" fn wasm_global_copy(wasm_global: &wasm_global_t) -> Box<wasm_global_t> {
     // do shallow copy
-    Box::new(wasm_global_t {
-        inner: wasm_global.inner.clone(),
-    })
+    wasm_global_t::new(wasm_global.inner.clone())
 }
 
 #[no_mangle]
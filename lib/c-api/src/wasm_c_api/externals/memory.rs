@@ -1,12 +1,21 @@
+use super::super::reference::{ObjectKey, RefBase};
 use super::super::store::wasm_store_t;
 use super::super::types::wasm_memorytype_t;
 use std::mem;
-use wasmer::{Memory, Pages};
+use wasmer::{Extern, Memory, Pages};
 
 #[allow(non_camel_case_types)]
 pub struct wasm_memory_t {
     // maybe needs to hold onto instance
     pub(crate) inner: Memory,
+    pub(crate) base: RefBase,
+}
+
+impl wasm_memory_t {
+    pub(crate) fn new(inner: Memory) -> Box<Self> {
+        let base = RefBase::new(ObjectKey::of_extern(&Extern::Memory(inner.clone())));
+        Box::new(Self { inner, base })
+    }
 }
 
 #[no_mangle]
@@ -17,7 +26,7 @@ pub unsafe extern "C" fn wasm_memory_new(
     let md = mt.as_memorytype().clone();
     let memory = c_try!(Memory::new(&store.inner, md));
 
-    Some(Box::new(wasm_memory_t { inner: memory }))
+    Some(wasm_memory_t::new(memory))
 }
 
 #[no_mangle]
@@ -27,9 +36,7 @@ pub unsafe extern "C" fn wasm_memory_delete(_memory: Option<Box<wasm_memory_t>>)
 #[no_mangle]
 pub unsafe extern "C" fn wasm_memory_copy(memory: &wasm_memory_t) -> Box<wasm_memory_t> {
     // do shallow copy
-    Box::new(wasm_memory_t {
-        inner: memory.inner.clone(),
-    })
+    wasm_memory_t::new(memory.inner.clone())
 }
 
 #[no_mangle]
@@ -3,6 +3,8 @@ mod global;
 mod memory;
 mod table;
 
+use super::reference::{ObjectKey, RefBase};
+use super::wasmer::CallerFunction;
 pub use function::*;
 pub use global::*;
 pub use memory::*;
@@ -16,10 +18,40 @@ pub struct wasm_extern_t {
     // this is how we ensure the instance stays alive
     pub(crate) instance: Option<Arc<Instance>>,
     pub(crate) inner: Extern,
+    // see `wasm_func_t::caller`
+    pub(crate) caller: Option<Arc<CallerFunction>>,
+    pub(crate) base: RefBase,
+}
+
+impl wasm_extern_t {
+    pub(crate) fn new(inner: Extern, instance: Option<Arc<Instance>>) -> Box<Self> {
+        let base = RefBase::new(ObjectKey::of_extern(&inner));
+        Box::new(Self {
+            instance,
+            inner,
+            caller: None,
+            base,
+        })
+    }
 }
 
 wasm_declare_boxed_vec!(extern);
 
+#[no_mangle]
+pub unsafe extern "C" fn wasm_extern_copy(r#extern: &wasm_extern_t) -> Box<wasm_extern_t> {
+    let mut copy = wasm_extern_t::new(r#extern.inner.clone(), r#extern.instance.clone());
+    copy.caller = r#extern.caller.clone();
+    copy
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_extern_same(
+    extern1: &wasm_extern_t,
+    extern2: &wasm_extern_t,
+) -> bool {
+    extern1.base.same(&extern2.base)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_func_as_extern(
     func_ptr: Option<NonNull<wasm_func_t>>,
@@ -27,10 +59,10 @@ pub unsafe extern "C" fn wasm_func_as_extern(
     let func_ptr = func_ptr?;
     let func = func_ptr.as_ref();
 
-    Some(Box::new(wasm_extern_t {
-        instance: func.instance.clone(),
-        inner: Extern::Function(func.inner.clone()),
-    }))
+    let mut r#extern =
+        wasm_extern_t::new(Extern::Function(func.inner.clone()), func.instance.clone());
+    r#extern.caller = func.caller.clone();
+    Some(r#extern)
 }
 
 #[no_mangle]
@@ -40,11 +72,11 @@ pub unsafe extern "C" fn wasm_global_as_extern(
     let global_ptr = global_ptr?;
     let global = global_ptr.as_ref();
 
-    Some(Box::new(wasm_extern_t {
-        // update this if global does hold onto an `instance`
-        instance: None,
-        inner: Extern::Global(global.inner.clone()),
-    }))
+    // update this if global does hold onto an `instance`
+    Some(wasm_extern_t::new(
+        Extern::Global(global.inner.clone()),
+        None,
+    ))
 }
 
 #[no_mangle]
@@ -54,11 +86,11 @@ pub unsafe extern "C" fn wasm_memory_as_extern(
     let memory_ptr = memory_ptr?;
     let memory = memory_ptr.as_ref();
 
-    Some(Box::new(wasm_extern_t {
-        // update this if global does hold onto an `instance`
-        instance: None,
-        inner: Extern::Memory(memory.inner.clone()),
-    }))
+    // update this if memory does hold onto an `instance`
+    Some(wasm_extern_t::new(
+        Extern::Memory(memory.inner.clone()),
+        None,
+    ))
 }
 
 #[no_mangle]
@@ -68,11 +100,8 @@ pub unsafe extern "C" fn wasm_table_as_extern(
     let table_ptr = table_ptr?;
     let table = table_ptr.as_ref();
 
-    Some(Box::new(wasm_extern_t {
-        // update this if global does hold onto an `instance`
-        instance: None,
-        inner: Extern::Table(table.inner.clone()),
-    }))
+    // update this if table does hold onto an `instance`
+    Some(wasm_extern_t::new(Extern::Table(table.inner.clone()), None))
 }
 
 #[no_mangle]
@@ -82,10 +111,9 @@ pub unsafe extern "C" fn wasm_extern_as_func(
     let extern_ptr = extern_ptr?;
     let r#extern = extern_ptr.as_ref();
     if let Extern::Function(f) = &r#extern.inner {
-        Some(Box::new(wasm_func_t {
-            inner: f.clone(),
-            instance: r#extern.instance.clone(),
-        }))
+        let mut func = wasm_func_t::new(f.clone(), r#extern.instance.clone());
+        func.caller = r#extern.caller.clone();
+        Some(func)
     } else {
         None
     }
@@ -98,7 +126,7 @@ pub unsafe extern "C" fn wasm_extern_as_global(
     let extern_ptr = extern_ptr?;
     let r#extern = extern_ptr.as_ref();
     if let Extern::Global(g) = &r#extern.inner {
-        Some(Box::new(wasm_global_t { inner: g.clone() }))
+        Some(wasm_global_t::new(g.clone()))
     } else {
         None
     }
@@ -111,7 +139,7 @@ pub unsafe extern "C" fn wasm_extern_as_memory(
     let extern_ptr = extern_ptr?;
     let r#extern = extern_ptr.as_ref();
     if let Extern::Memory(m) = &r#extern.inner {
-        Some(Box::new(wasm_memory_t { inner: m.clone() }))
+        Some(wasm_memory_t::new(m.clone()))
     } else {
         None
     }
@@ -124,7 +152,7 @@ pub unsafe extern "C" fn wasm_extern_as_table(
     let extern_ptr = extern_ptr?;
     let r#extern = extern_ptr.as_ref();
     if let Extern::Table(t) = &r#extern.inner {
-        Some(Box::new(wasm_table_t { inner: t.clone() }))
+        Some(wasm_table_t::new(t.clone()))
     } else {
         None
     }
@@ -1,24 +1,33 @@
+use super::super::reference::{wasm_ref_t, ObjectKey, RefBase};
 use super::super::store::wasm_store_t;
-use super::super::types::{wasm_ref_t, wasm_table_size_t, wasm_tabletype_t};
-use wasmer::Table;
+use super::super::types::{wasm_table_size_t, wasm_tabletype_t};
+use wasmer::{Extern, Table};
 
 #[allow(non_camel_case_types)]
 pub struct wasm_table_t {
     // maybe needs to hold onto instance
     pub(crate) inner: Table,
+    pub(crate) base: RefBase,
+}
+
+impl wasm_table_t {
+    pub(crate) fn new(inner: Table) -> Box<Self> {
+        let base = RefBase::new(ObjectKey::of_extern(&Extern::Table(inner.clone())));
+        Box::new(Self { inner, base })
+    }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_table_new(
     store: &wasm_store_t,
     tt: &wasm_tabletype_t,
-    init: *const wasm_ref_t,
+    init: Option<&wasm_ref_t>,
 ) -> Option<Box<wasm_table_t>> {
     let tt = tt.as_tabletype().clone();
-    let init_val = todo!("get val from init somehow");
+    let init_val = wasm_ref_t::to_val(init);
     let table = c_try!(Table::new(&store.inner, tt, init_val));
 
-    Some(Box::new(wasm_table_t { inner: table }))
+    Some(wasm_table_t::new(table))
 }
 
 #[no_mangle]
@@ -27,9 +36,7 @@ pub unsafe extern "C" fn wasm_table_delete(_table: Option<Box<wasm_table_t>>) {}
 #[no_mangle]
 pub unsafe extern "C" fn wasm_table_copy(wasm_table: &wasm_table_t) -> Box<wasm_table_t> {
     // do shallow copy
-    Box::new(wasm_table_t {
-        inner: wasm_table.inner.clone(),
-    })
+    wasm_table_t::new(wasm_table.inner.clone())
 }
 
 #[no_mangle]
@@ -40,18 +47,38 @@ pub unsafe extern "C" fn wasm_table_same(
     wasm_table1.inner.same(&wasm_table2.inner)
 }
 
+/// Gets the element at `index`; null elements and out of bounds
+/// accesses both return a null reference.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_table_get(
+    wasm_table: &wasm_table_t,
+    index: wasm_table_size_t,
+) -> Option<Box<wasm_ref_t>> {
+    let val = wasm_table.inner.get(index)?;
+    wasm_ref_t::new(val)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_table_set(
+    wasm_table: &mut wasm_table_t,
+    index: wasm_table_size_t,
+    item: Option<&wasm_ref_t>,
+) -> bool {
+    let val = wasm_ref_t::to_val(item);
+    wasm_table.inner.set(index, val).is_ok()
+}
+
 #[no_mangle]
-pub unsafe extern "C" fn wasm_table_size(wasm_table: &wasm_table_t) -> usize {
-    wasm_table.inner.size() as _
+pub unsafe extern "C" fn wasm_table_size(wasm_table: &wasm_table_t) -> wasm_table_size_t {
+    wasm_table.inner.size()
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_table_grow(
-    _wasm_table: &mut wasm_table_t,
-    _delta: wasm_table_size_t,
-    _init: *mut wasm_ref_t,
+    wasm_table: &mut wasm_table_t,
+    delta: wasm_table_size_t,
+    init: Option<&wasm_ref_t>,
 ) -> bool {
-    // TODO: maybe need to look at result to return `true`; also maybe report error here
-    //wasm_table.inner.grow(delta, init).is_ok()
-    todo!("Blocked on transforming ExternRef into a val type")
+    let init_val = wasm_ref_t::to_val(init);
+    wasm_table.inner.grow(delta, init_val).is_ok()
 }
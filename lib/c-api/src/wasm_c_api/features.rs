@@ -0,0 +1,77 @@
+//! The WebAssembly proposals a module may use.
+//!
+//! A `wasmer_features_t` is given to a `wasm_config_t` with
+//! `wasm_config_set_features`.
+
+use wasmer::Features;
+
+/// The set of enabled WebAssembly features, starting with the
+/// defaults: bulk memory and multi-value are enabled, threads,
+/// reference types and SIMD are not.
+#[allow(non_camel_case_types)]
+pub struct wasmer_features_t {
+    pub(crate) inner: Features,
+}
+
+#[no_mangle]
+pub extern "C" fn wasmer_features_new() -> Box<wasmer_features_t> {
+    Box::new(wasmer_features_t {
+        inner: Features::new(),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wasmer_features_delete(_features: Option<Box<wasmer_features_t>>) {}
+
+/// Configures whether the threads proposal is enabled.
+#[no_mangle]
+pub extern "C" fn wasmer_features_threads(features: &mut wasmer_features_t, enable: bool) -> bool {
+    features.inner.threads(enable);
+
+    true
+}
+
+/// Configures whether the reference types proposal is enabled.
+///
+/// Enabling it also enables bulk memory.
+#[no_mangle]
+pub extern "C" fn wasmer_features_reference_types(
+    features: &mut wasmer_features_t,
+    enable: bool,
+) -> bool {
+    features.inner.reference_types(enable);
+
+    true
+}
+
+/// Configures whether the SIMD proposal is enabled.
+#[no_mangle]
+pub extern "C" fn wasmer_features_simd(features: &mut wasmer_features_t, enable: bool) -> bool {
+    features.inner.simd(enable);
+
+    true
+}
+
+/// Configures whether the bulk memory proposal is enabled.
+///
+/// Disabling it also disables reference types.
+#[no_mangle]
+pub extern "C" fn wasmer_features_bulk_memory(
+    features: &mut wasmer_features_t,
+    enable: bool,
+) -> bool {
+    features.inner.bulk_memory(enable);
+
+    true
+}
+
+/// Configures whether the multi-value proposal is enabled.
+#[no_mangle]
+pub extern "C" fn wasmer_features_multi_value(
+    features: &mut wasmer_features_t,
+    enable: bool,
+) -> bool {
+    features.inner.multi_value(enable);
+
+    true
+}
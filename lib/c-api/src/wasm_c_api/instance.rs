@@ -1,7 +1,9 @@
 use super::externals::{wasm_extern_t, wasm_extern_vec_t};
 use super::module::wasm_module_t;
+use super::reference::{ObjectKey, RefBase};
 use super::store::wasm_store_t;
 use super::trap::wasm_trap_t;
+use super::wasmer::wasmer_caller_t;
 use crate::ordered_resolver::OrderedResolver;
 use std::mem;
 use std::sync::Arc;
@@ -10,6 +12,14 @@ use wasmer::{Extern, Instance};
 #[allow(non_camel_case_types)]
 pub struct wasm_instance_t {
     pub(crate) inner: Arc<Instance>,
+    pub(crate) base: RefBase,
+}
+
+impl wasm_instance_t {
+    pub(crate) fn new(inner: Arc<Instance>) -> Box<Self> {
+        let base = RefBase::new(ObjectKey::of_arc(&inner));
+        Box::new(Self { inner, base })
+    }
 }
 
 #[no_mangle]
@@ -23,22 +33,49 @@ pub unsafe extern "C" fn wasm_instance_new(
     let wasm_module = &module.inner;
     let module_imports = wasm_module.imports();
     let module_import_count = module_imports.len();
-    let resolver: OrderedResolver = imports
+    let imports = imports
         .into_slice()
         .map(|imports| imports.iter())
         .unwrap_or_else(|| [].iter())
-        .map(|imp| &imp.inner)
-        .take(module_import_count)
-        .cloned()
+        .take(module_import_count);
+
+    // a function created with `wasmer_func_new_with_caller` is created
+    // anew for the instance, to receive it as its caller
+    let mut callers = vec![];
+    let resolver: OrderedResolver = imports
+        .map(|imp| match &imp.caller {
+            Some(caller_function) => {
+                let caller = Arc::new(wasmer_caller_t::default());
+                callers.push(caller.clone());
+                Extern::Function(caller_function.new_function(caller))
+            }
+            None => imp.inner.clone(),
+        })
         .collect();
 
     let instance = Arc::new(c_try!(Instance::new(wasm_module, &resolver)));
-    Some(Box::new(wasm_instance_t { inner: instance }))
+    for caller in callers {
+        caller.bind(&instance);
+    }
+    Some(wasm_instance_t::new(instance))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_instance_delete(_instance: Option<Box<wasm_instance_t>>) {}
 
+#[no_mangle]
+pub unsafe extern "C" fn wasm_instance_copy(instance: &wasm_instance_t) -> Box<wasm_instance_t> {
+    wasm_instance_t::new(instance.inner.clone())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_instance_same(
+    instance1: &wasm_instance_t,
+    instance2: &wasm_instance_t,
+) -> bool {
+    Arc::ptr_eq(&instance1.inner, &instance2.inner)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_instance_exports(
     instance: &wasm_instance_t,
@@ -55,10 +92,10 @@ pub unsafe extern "C" fn wasm_instance_exports(
             } else {
                 None
             };
-            Box::into_raw(Box::new(wasm_extern_t {
-                instance: Some(Arc::clone(instance)),
-                inner: r#extern.clone(),
-            }))
+            Box::into_raw(wasm_extern_t::new(
+                r#extern.clone(),
+                Some(Arc::clone(instance)),
+            ))
         })
         .collect::<Vec<*mut wasm_extern_t>>();
     extern_vec.shrink_to_fit();
@@ -181,21 +181,65 @@ macro_rules! wasm_declare_boxed_vec {
     };
 }
 
+/// Declares the functions of `WASM_DECLARE_REF`, besides `copy` and
+/// `same`, for a type implementing `RefObject`.
 #[doc(hidden)]
 #[macro_export]
-macro_rules! wasm_declare_ref_base {
+macro_rules! wasm_declare_ref {
     ($name:ident) => {
-        wasm_declare_own!($name);
-
         paste::item! {
             #[no_mangle]
-            pub extern "C" fn [<wasm_ $name _copy>](_arg: *const [<wasm_ $name _t>]) -> *mut [<wasm_ $name _t>] {
-                todo!("in generated declare ref base");
-                //ptr::null_mut()
+            pub unsafe extern "C" fn [<wasm_ $name _get_host_info>](
+                object: &[<wasm_ $name _t>],
+            ) -> *mut ::std::ffi::c_void {
+                $crate::wasm_c_api::reference::RefObject::ref_base(object).get_host_info()
             }
 
-            // TODO: finish this...
+            #[no_mangle]
+            pub unsafe extern "C" fn [<wasm_ $name _set_host_info>](
+                object: &[<wasm_ $name _t>],
+                info: *mut ::std::ffi::c_void,
+            ) {
+                [<wasm_ $name _set_host_info_with_finalizer>](object, info, None)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn [<wasm_ $name _set_host_info_with_finalizer>](
+                object: &[<wasm_ $name _t>],
+                info: *mut ::std::ffi::c_void,
+                finalizer: Option<$crate::wasm_c_api::reference::wasm_host_info_finalizer_t>,
+            ) {
+                $crate::wasm_c_api::reference::RefObject::ref_base(object)
+                    .set_host_info(info, finalizer)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn [<wasm_ $name _as_ref>](
+                object: &[<wasm_ $name _t>],
+            ) -> &$crate::wasm_c_api::reference::wasm_ref_t {
+                [<wasm_ $name _as_ref_const>](object)
+            }
 
+            #[no_mangle]
+            pub unsafe extern "C" fn [<wasm_ $name _as_ref_const>](
+                object: &[<wasm_ $name _t>],
+            ) -> &$crate::wasm_c_api::reference::wasm_ref_t {
+                $crate::wasm_c_api::reference::RefObject::ref_base(object).as_ref(object)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn [<wasm_ref_as_ $name>](
+                reference: &$crate::wasm_c_api::reference::wasm_ref_t,
+            ) -> Option<&[<wasm_ $name _t>]> {
+                [<wasm_ref_as_ $name _const>](reference)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn [<wasm_ref_as_ $name _const>](
+                reference: &$crate::wasm_c_api::reference::wasm_ref_t,
+            ) -> Option<&[<wasm_ $name _t>]> {
+                reference.view::<[<wasm_ $name _t>]>()
+            }
         }
     };
 }
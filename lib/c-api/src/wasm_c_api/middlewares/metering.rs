@@ -0,0 +1,123 @@
+//! The metering middleware, limiting the points an instance can
+//! spend executing operators.
+
+use super::super::instance::wasm_instance_t;
+use super::operator::wasmer_operator_category_t;
+use super::wasmer_middleware_t;
+use crate::error::update_last_error;
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer_middlewares::metering::{
+    get_remaining_points, set_remaining_points, Metering, MeteringPoints,
+};
+
+/// The cost function of a `wasmer_metering_t`, returning the points
+/// an operator of the given category costs.
+#[allow(non_camel_case_types)]
+pub type wasmer_metering_cost_function_t =
+    extern "C" fn(operator_category: wasmer_operator_category_t) -> u64;
+
+/// The cost function of a `wasmer_metering_t`, wrapped for the Rust
+/// `Metering` middleware.
+type CostFunction = Box<dyn Fn(&Operator) -> u64 + Send + Sync>;
+
+/// A metering middleware, charging the cost of each executed
+/// operator to the instance until its points are exhausted.
+#[allow(non_camel_case_types)]
+pub struct wasmer_metering_t {
+    pub(crate) inner: Arc<Metering<CostFunction>>,
+}
+
+/// Creates a metering middleware granting `initial_limit` points to
+/// the instances, and charging `cost_function` for each operator.
+#[no_mangle]
+pub extern "C" fn wasmer_metering_new(
+    initial_limit: u64,
+    cost_function: wasmer_metering_cost_function_t,
+) -> Box<wasmer_metering_t> {
+    let cost_function = move |operator: &Operator| -> u64 { cost_function(operator.into()) };
+
+    Box::new(wasmer_metering_t {
+        inner: Arc::new(Metering::new(initial_limit, Box::new(cost_function))),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wasmer_metering_delete(_metering: Option<Box<wasmer_metering_t>>) {}
+
+/// Transforms a `wasmer_metering_t` into a generic
+/// `wasmer_middleware_t`, taking ownership of the metering.
+#[no_mangle]
+pub extern "C" fn wasmer_metering_as_middleware(
+    metering: Option<Box<wasmer_metering_t>>,
+) -> Option<Box<wasmer_middleware_t>> {
+    let metering = metering?;
+
+    Some(Box::new(wasmer_middleware_t {
+        inner: metering.inner,
+    }))
+}
+
+/// Writes the points left to the instance in `remaining_points`, or 0
+/// if they are exhausted.
+///
+/// Returns false if the instance wasn't compiled with a metering
+/// middleware, in which case the error can be read with
+/// `wasmer_last_error_message`.
+#[no_mangle]
+pub extern "C" fn wasmer_metering_get_remaining_points(
+    instance: &wasm_instance_t,
+    remaining_points: &mut u64,
+) -> bool {
+    match get_remaining_points(&instance.inner) {
+        Ok(MeteringPoints::Remaining(points)) => {
+            *remaining_points = points;
+            true
+        }
+        Ok(MeteringPoints::Exhausted) => {
+            *remaining_points = 0;
+            true
+        }
+        Err(e) => {
+            update_last_error(e);
+            false
+        }
+    }
+}
+
+/// Returns whether the instance was trapped because it ran out of
+/// points.
+///
+/// Returns false if the instance wasn't compiled with a metering
+/// middleware, in which case the error can be read with
+/// `wasmer_last_error_message`.
+#[no_mangle]
+pub extern "C" fn wasmer_metering_points_are_exhausted(instance: &wasm_instance_t) -> bool {
+    match get_remaining_points(&instance.inner) {
+        Ok(points) => points == MeteringPoints::Exhausted,
+        Err(e) => {
+            update_last_error(e);
+            false
+        }
+    }
+}
+
+/// Sets the points left to the instance, which makes an exhausted
+/// instance runnable again.
+///
+/// Returns false if the instance wasn't compiled with a metering
+/// middleware, in which case the error can be read with
+/// `wasmer_last_error_message`.
+#[no_mangle]
+pub extern "C" fn wasmer_metering_set_remaining_points(
+    instance: &wasm_instance_t,
+    new_limit: u64,
+) -> bool {
+    match set_remaining_points(&instance.inner, new_limit) {
+        Ok(()) => true,
+        Err(e) => {
+            update_last_error(e);
+            false
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Middlewares transform the WebAssembly code of the functions
+//! before they are compiled, see `wasm_config_push_middleware`.
+
+#[cfg(feature = "compiler")]
+pub mod metering;
+pub mod operator;
+
+#[cfg(feature = "compiler")]
+use std::sync::Arc;
+#[cfg(feature = "compiler")]
+use wasmer::FunctionMiddlewareGenerator;
+
+/// An opaque middleware, to be pushed onto the middleware chain of a
+/// `wasm_config_t` with `wasm_config_push_middleware`.
+///
+/// Middlewares are created from concrete middlewares, for example
+/// with `wasmer_metering_as_middleware`.
+#[cfg(feature = "compiler")]
+#[allow(non_camel_case_types)]
+pub struct wasmer_middleware_t {
+    pub(crate) inner: Arc<dyn FunctionMiddlewareGenerator>,
+}
@@ -0,0 +1,136 @@
+//! The categories of the WebAssembly operators, as given to the C
+//! callbacks of the middlewares.
+
+#[cfg(feature = "compiler")]
+use wasmer::wasmparser::Operator;
+
+/// The category of an operator of the WebAssembly code.
+///
+/// It is given to the cost function of a `wasmer_metering_t`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub enum wasmer_operator_category_t {
+    /// The structured control and branch operators, such as `block`,
+    /// `br_if` or `return`.
+    CONTROL = 0,
+    /// The direct and indirect calls.
+    CALL = 1,
+    /// The parametric operators, `drop` and `select`.
+    PARAMETRIC = 2,
+    /// The accesses to locals and globals.
+    VARIABLE = 3,
+    /// The loads and stores of scalars, and the operators managing the
+    /// memories.
+    MEMORY = 4,
+    /// The operators accessing and managing the tables.
+    TABLE = 5,
+    /// The constants, including the vector ones.
+    CONST = 6,
+    /// The operators creating and testing references.
+    REFERENCE = 7,
+    /// The arithmetic, comparison and conversion operators on scalars.
+    NUMERIC = 8,
+    /// The atomic accesses to memories, and the fences.
+    ATOMIC = 9,
+    /// The operators on vectors, including their loads and stores.
+    SIMD = 10,
+}
+
+#[cfg(feature = "compiler")]
+impl<'a> From<&Operator<'a>> for wasmer_operator_category_t {
+    fn from(operator: &Operator<'a>) -> Self {
+        use Operator as O;
+
+        match operator {
+            O::Unreachable
+            | O::Nop
+            | O::Block { .. }
+            | O::Loop { .. }
+            | O::If { .. }
+            | O::Else
+            | O::End
+            | O::Br { .. }
+            | O::BrIf { .. }
+            | O::BrTable { .. }
+            | O::Return => Self::CONTROL,
+            O::Call { .. }
+            | O::CallIndirect { .. }
+            | O::ReturnCall { .. }
+            | O::ReturnCallIndirect { .. } => Self::CALL,
+            O::Drop | O::Select | O::TypedSelect { .. } => Self::PARAMETRIC,
+            O::LocalGet { .. }
+            | O::LocalSet { .. }
+            | O::LocalTee { .. }
+            | O::GlobalGet { .. }
+            | O::GlobalSet { .. } => Self::VARIABLE,
+            O::I32Load { .. }
+            | O::I64Load { .. }
+            | O::F32Load { .. }
+            | O::F64Load { .. }
+            | O::I32Load8S { .. }
+            | O::I32Load8U { .. }
+            | O::I32Load16S { .. }
+            | O::I32Load16U { .. }
+            | O::I64Load8S { .. }
+            | O::I64Load8U { .. }
+            | O::I64Load16S { .. }
+            | O::I64Load16U { .. }
+            | O::I64Load32S { .. }
+            | O::I64Load32U { .. }
+            | O::I32Store { .. }
+            | O::I64Store { .. }
+            | O::F32Store { .. }
+            | O::F64Store { .. }
+            | O::I32Store8 { .. }
+            | O::I32Store16 { .. }
+            | O::I64Store8 { .. }
+            | O::I64Store16 { .. }
+            | O::I64Store32 { .. }
+            | O::MemorySize { .. }
+            | O::MemoryGrow { .. }
+            | O::MemoryInit { .. }
+            | O::DataDrop { .. }
+            | O::MemoryCopy
+            | O::MemoryFill => Self::MEMORY,
+            O::TableInit { .. }
+            | O::ElemDrop { .. }
+            | O::TableCopy { .. }
+            | O::TableFill { .. }
+            | O::TableGet { .. }
+            | O::TableSet { .. }
+            | O::TableGrow { .. }
+            | O::TableSize { .. } => Self::TABLE,
+            O::I32Const { .. }
+            | O::I64Const { .. }
+            | O::F32Const { .. }
+            | O::F64Const { .. }
+            | O::V128Const { .. } => Self::CONST,
+            O::RefNull { .. } | O::RefIsNull { .. } | O::RefFunc { .. } => Self::REFERENCE,
+            // The remaining operators are too many to be listed, and
+            // their names tell their category: the atomic ones are
+            // named after `Atomic`, and the vector ones after their
+            // `v128` type or their lanes, such as `I32x4Add`.
+            _ => {
+                let name = format!("{:?}", operator);
+                let name = name.split(&[' ', '{'][..]).next().unwrap_or("");
+                if name.contains("Atomic") {
+                    Self::ATOMIC
+                } else if name.starts_with("V128") || is_named_after_lanes(name) {
+                    Self::SIMD
+                } else {
+                    Self::NUMERIC
+                }
+            }
+        }
+    }
+}
+
+/// Whether the name of an operator contains a lane shape, such as
+/// `8x16` or `32x4`.
+#[cfg(feature = "compiler")]
+fn is_named_after_lanes(name: &str) -> bool {
+    name.as_bytes()
+        .windows(2)
+        .any(|window| window[0].is_ascii_digit() && window[1] == b'x')
+}
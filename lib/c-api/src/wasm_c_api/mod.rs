@@ -8,15 +8,24 @@ pub mod engine;
 /// cbindgen:ignore
 pub mod externals;
 
+pub mod features;
+
 /// cbindgen:ignore
 pub mod instance;
 
+pub mod middlewares;
+
 /// cbindgen:ignore
 pub mod module;
 
+/// cbindgen:ignore
+pub mod reference;
+
 /// cbindgen:ignore
 pub mod store;
 
+pub mod target;
+
 /// cbindgen:ignore
 pub mod trap;
 
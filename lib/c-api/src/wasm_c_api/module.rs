@@ -1,3 +1,4 @@
+use super::reference::{ObjectKey, RefBase};
 use super::store::wasm_store_t;
 use super::types::{
     wasm_byte_vec_t, wasm_exporttype_t, wasm_exporttype_vec_t, wasm_importtype_t,
@@ -12,6 +13,14 @@ use wasmer::Module;
 #[allow(non_camel_case_types)]
 pub struct wasm_module_t {
     pub(crate) inner: Arc<Module>,
+    pub(crate) base: RefBase,
+}
+
+impl wasm_module_t {
+    pub(crate) fn new(inner: Arc<Module>) -> Box<Self> {
+        let base = RefBase::new(ObjectKey::of_arc(&inner));
+        Box::new(Self { inner, base })
+    }
 }
 
 #[no_mangle]
@@ -23,14 +32,25 @@ pub unsafe extern "C" fn wasm_module_new(
     let wasm_byte_slice: &[u8] = slice::from_raw_parts_mut(bytes.data, bytes.size);
     let module = c_try!(Module::from_binary(&store.inner, wasm_byte_slice));
 
-    Some(Box::new(wasm_module_t {
-        inner: Arc::new(module),
-    }))
+    Some(wasm_module_t::new(Arc::new(module)))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_module_delete(_module: Option<Box<wasm_module_t>>) {}
 
+#[no_mangle]
+pub unsafe extern "C" fn wasm_module_copy(module: &wasm_module_t) -> Box<wasm_module_t> {
+    wasm_module_t::new(module.inner.clone())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_module_same(
+    module1: &wasm_module_t,
+    module2: &wasm_module_t,
+) -> bool {
+    Arc::ptr_eq(&module1.inner, &module2.inner)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_module_validate(
     store: &wasm_store_t,
@@ -94,10 +114,8 @@ pub unsafe extern "C" fn wasm_module_deserialize(
 
     let module = c_try!(Module::deserialize(&store.inner, byte_slice));
 
-    Some(NonNull::new_unchecked(Box::into_raw(Box::new(
-        wasm_module_t {
-            inner: Arc::new(module),
-        },
+    Some(NonNull::new_unchecked(Box::into_raw(wasm_module_t::new(
+        Arc::new(module),
     ))))
 }
 
@@ -115,3 +133,37 @@ pub unsafe extern "C" fn wasm_module_serialize(
     };
     *out_ptr = byte_vec.into();
 }
+
+/// A module that can be sent to other threads, to be instantiated in
+/// other stores with `wasm_module_obtain`.
+///
+/// It shares the compiled code of the module it comes from.
+#[allow(non_camel_case_types)]
+pub struct wasm_shared_module_t {
+    inner: Module,
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_shared_module_delete(_module: Option<Box<wasm_shared_module_t>>) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_module_share(module: &wasm_module_t) -> Box<wasm_shared_module_t> {
+    Box::new(wasm_shared_module_t {
+        inner: module.inner.as_ref().clone(),
+    })
+}
+
+/// Creates a module in `store` from a shared module, without
+/// compiling it again.
+///
+/// Returns null if `store` doesn't use the engine that compiled the
+/// module.
+#[no_mangle]
+pub unsafe extern "C" fn wasm_module_obtain(
+    store: &wasm_store_t,
+    shared_module: &wasm_shared_module_t,
+) -> Option<Box<wasm_module_t>> {
+    let module = shared_module.inner.share_with(&store.inner)?;
+
+    Some(wasm_module_t::new(Arc::new(module)))
+}
@@ -0,0 +1,556 @@
+use super::externals::{wasm_extern_t, wasm_func_t, wasm_global_t, wasm_memory_t, wasm_table_t};
+use super::instance::wasm_instance_t;
+use super::module::wasm_module_t;
+use super::store::wasm_store_t;
+use super::trap::wasm_trap_t;
+use std::any::Any;
+use std::cell::{self, RefCell};
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::ptr;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use wasmer::{Export, Exportable, Extern, ExternRef, Instance, Module, RuntimeError, Val};
+
+#[allow(non_camel_case_types)]
+pub type wasm_host_info_finalizer_t = unsafe extern "C" fn(*mut c_void);
+
+/// The host info attached to an object by the embedder.
+///
+/// The finalizer, if any, is called with the info once it is
+/// replaced or once the last handle to the object goes away.
+struct HostInfo {
+    info: *mut c_void,
+    finalizer: Option<wasm_host_info_finalizer_t>,
+}
+
+impl HostInfo {
+    fn new(info: *mut c_void, finalizer: Option<wasm_host_info_finalizer_t>) -> Self {
+        Self { info, finalizer }
+    }
+}
+
+impl Drop for HostInfo {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer {
+            unsafe { finalizer(self.info) }
+        }
+    }
+}
+
+/// The identity of an object, under which its host info is kept.
+#[derive(Clone)]
+pub(crate) enum ObjectKey {
+    /// A function, by its body and context, like `Function::same`.
+    Func(usize, usize),
+    /// Any other object, by the address of the data its handles share.
+    Address(usize),
+    /// A trap, compared with `RuntimeError::same`.
+    Trap(RuntimeError),
+}
+
+impl ObjectKey {
+    pub(crate) fn of_extern(r#extern: &Extern) -> Self {
+        match r#extern.to_export() {
+            Export::Function(function) => {
+                Self::Func(function.address as usize, function.vmctx as usize)
+            }
+            Export::Global(global) => Self::of_arc(&global.from),
+            Export::Table(table) => Self::of_arc(&table.from),
+            Export::Memory(memory) => Self::of_arc(&memory.from),
+        }
+    }
+
+    pub(crate) fn of_arc<T: ?Sized>(arc: &Arc<T>) -> Self {
+        Self::Address(Arc::as_ptr(arc) as *const u8 as usize)
+    }
+}
+
+impl PartialEq for ObjectKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Func(address1, vmctx1), Self::Func(address2, vmctx2)) => {
+                address1 == address2 && vmctx1 == vmctx2
+            }
+            (Self::Address(address1), Self::Address(address2)) => address1 == address2,
+            (Self::Trap(error1), Self::Trap(error2)) => error1.same(error2),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ObjectKey {}
+
+impl Hash for ObjectKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Self::Func(address, vmctx) => (address, vmctx).hash(state),
+            Self::Address(address) => address.hash(state),
+            // traps only compare by identity
+            Self::Trap(_) => {}
+        }
+    }
+}
+
+thread_local! {
+    /// The host info of the objects with handles, by object.
+    static HOST_INFOS: RefCell<HashMap<ObjectKey, Weak<ObjectHostInfo>>> = Default::default();
+}
+
+/// The host info of an object, shared by all its handles: its copies
+/// and its views as a reference or an extern.
+pub(crate) struct ObjectHostInfo {
+    /// The key of the host info in `HOST_INFOS`, unless the object
+    /// holds its host info itself.
+    key: Option<ObjectKey>,
+    host_info: RefCell<Option<HostInfo>>,
+}
+
+impl ObjectHostInfo {
+    /// Gets the host info of the object `key`, which is finalized with
+    /// the last handle to the object.
+    pub(crate) fn of(key: ObjectKey) -> Rc<Self> {
+        HOST_INFOS.with(|host_infos| {
+            let mut host_infos = host_infos.borrow_mut();
+            if let Some(host_info) = host_infos.get(&key).and_then(Weak::upgrade) {
+                return host_info;
+            }
+            let host_info = Rc::new(Self {
+                key: Some(key.clone()),
+                host_info: Default::default(),
+            });
+            host_infos.insert(key, Rc::downgrade(&host_info));
+            host_info
+        })
+    }
+
+    /// Creates the host info of an object holding it itself, which is
+    /// finalized with the object.
+    fn new() -> Rc<Self> {
+        Rc::new(Self {
+            key: None,
+            host_info: Default::default(),
+        })
+    }
+
+    fn get(&self) -> *mut c_void {
+        self.host_info
+            .borrow()
+            .as_ref()
+            .map(|host_info| host_info.info)
+            .unwrap_or_else(ptr::null_mut)
+    }
+
+    fn set(&self, info: *mut c_void, finalizer: Option<wasm_host_info_finalizer_t>) {
+        // the previous info is finalized after the borrow is released, in
+        // case its finalizer reaches back into the object
+        let _previous = self.host_info.replace(Some(HostInfo::new(info, finalizer)));
+    }
+}
+
+impl Drop for ObjectHostInfo {
+    fn drop(&mut self) {
+        if let Some(key) = &self.key {
+            // the registry is gone if the thread is exiting
+            let _ = HOST_INFOS.try_with(|host_infos| {
+                let mut host_infos = host_infos.borrow_mut();
+                let is_gone = host_infos
+                    .get(key)
+                    .map_or(false, |host_info| host_info.upgrade().is_none());
+                if is_gone {
+                    host_infos.remove(key);
+                }
+            });
+        }
+    }
+}
+
+/// What the handles to an object with references hold, beside the
+/// object.
+pub(crate) struct RefBase {
+    host_info: Rc<ObjectHostInfo>,
+    // the borrowed view returned by `wasm_*_as_ref`
+    as_ref: RefCell<Option<Box<wasm_ref_t>>>,
+}
+
+impl RefBase {
+    pub(crate) fn new(key: ObjectKey) -> Self {
+        Self::with_host_info(ObjectHostInfo::of(key))
+    }
+
+    fn with_host_info(host_info: Rc<ObjectHostInfo>) -> Self {
+        Self {
+            host_info,
+            as_ref: Default::default(),
+        }
+    }
+
+    /// Returns whether or not the handles point to the same object.
+    pub(crate) fn same(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.host_info, &other.host_info)
+    }
+
+    pub(crate) fn get_host_info(&self) -> *mut c_void {
+        self.host_info.get()
+    }
+
+    pub(crate) fn set_host_info(
+        &self,
+        info: *mut c_void,
+        finalizer: Option<wasm_host_info_finalizer_t>,
+    ) {
+        self.host_info.set(info, finalizer)
+    }
+
+    /// Gets the view of `object`, whose base this is, as a reference.
+    pub(crate) unsafe fn as_ref<T: RefObject>(&self, object: &T) -> &wasm_ref_t {
+        let mut as_ref = self.as_ref.borrow_mut();
+        let reference = as_ref.get_or_insert_with(|| {
+            Box::new(wasm_ref_t {
+                inner: object.to_val(),
+                host_info: self.host_info.clone(),
+                views: Default::default(),
+            })
+        });
+        &*(reference.as_ref() as *const wasm_ref_t)
+    }
+}
+
+/// The objects which can be viewed as references, for which
+/// `wasm_declare_ref!` declares the functions of `WASM_DECLARE_REF`.
+pub(crate) trait RefObject: Sized + 'static {
+    fn ref_base(&self) -> &RefBase;
+
+    /// Creates a reference to the object, never null.
+    fn to_val(&self) -> Val;
+
+    /// Creates a handle to the object `reference` points to, if it is
+    /// an object of this type.
+    fn from_ref(reference: &wasm_ref_t) -> Option<Box<Self>>;
+}
+
+/// The data behind the `ExternRef` of a `wasm_foreign_t`.
+struct ForeignData {
+    host_info: Rc<ObjectHostInfo>,
+}
+
+/// The data behind the `ExternRef` of a reference to an object which
+/// isn't a function or a foreign object.
+#[derive(Clone)]
+enum ObjectData {
+    /// A global, a table or a memory.
+    Extern(Extern),
+    Instance(Arc<Instance>),
+    Module(Arc<Module>),
+    Trap(RuntimeError),
+}
+
+impl ObjectData {
+    fn key(&self) -> ObjectKey {
+        match self {
+            Self::Extern(r#extern) => ObjectKey::of_extern(r#extern),
+            Self::Instance(instance) => ObjectKey::of_arc(instance),
+            Self::Module(module) => ObjectKey::of_arc(module),
+            Self::Trap(trap) => ObjectKey::Trap(trap.clone()),
+        }
+    }
+
+    fn into_val(self) -> Val {
+        Val::ExternRef(ExternRef::new(Box::new(self)))
+    }
+}
+
+/// Returns the data of `val` if it is an `ExternRef` to a `T`.
+fn extern_ref_data<T: 'static>(val: &Val) -> Option<cell::Ref<T>> {
+    match val {
+        Val::ExternRef(r @ ExternRef::Other(_)) => {
+            let data = r.data();
+            if data.is::<T>() {
+                Some(cell::Ref::map(data, |data| {
+                    data.downcast_ref::<T>().unwrap()
+                }))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+#[allow(non_camel_case_types)]
+pub struct wasm_ref_t {
+    /// Either a `Val::FuncRef` or a non-null `Val::ExternRef`; null
+    /// references are represented by null pointers.
+    pub(crate) inner: Val,
+    host_info: Rc<ObjectHostInfo>,
+    // the borrowed views returned by `wasm_ref_as_*`
+    views: RefCell<Vec<Box<dyn Any>>>,
+}
+
+impl wasm_ref_t {
+    /// Creates a reference from a `Val`, returning `None` for null
+    /// and non-reference values.
+    pub(crate) fn new(val: Val) -> Option<Box<Self>> {
+        let host_info = match &val {
+            Val::FuncRef(function) => {
+                ObjectHostInfo::of(ObjectKey::of_extern(&Extern::Function(function.clone())))
+            }
+            Val::ExternRef(r @ ExternRef::Other(_)) => {
+                if let Some(data) = extern_ref_data::<ForeignData>(&val) {
+                    data.host_info.clone()
+                } else if let Some(data) = extern_ref_data::<ObjectData>(&val) {
+                    ObjectHostInfo::of(data.key())
+                } else {
+                    let data = &**r.data() as *const dyn Any as *const u8;
+                    ObjectHostInfo::of(ObjectKey::Address(data as usize))
+                }
+            }
+            _ => return None,
+        };
+
+        Some(Box::new(Self {
+            inner: val,
+            host_info,
+            views: Default::default(),
+        }))
+    }
+
+    /// Converts a (possibly null) reference into a `Val`.
+    pub(crate) fn to_val(reference: Option<&Self>) -> Val {
+        match reference {
+            Some(reference) => reference.inner.clone(),
+            None => Val::ExternRef(ExternRef::Null),
+        }
+    }
+
+    /// Gets the view of the reference as a `T`, if it points to one.
+    unsafe fn view<T: RefObject>(&self) -> Option<&T> {
+        let mut views = self.views.borrow_mut();
+        let view = match views.iter().find_map(|view| view.downcast_ref::<T>()) {
+            Some(view) => view,
+            None => {
+                views.push(T::from_ref(self)?);
+                views.last().unwrap().downcast_ref::<T>().unwrap()
+            }
+        };
+        Some(&*(view as *const T))
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_ref_delete(_reference: Option<Box<wasm_ref_t>>) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_ref_copy(reference: Option<&wasm_ref_t>) -> Option<Box<wasm_ref_t>> {
+    let reference = reference?;
+
+    Some(Box::new(wasm_ref_t {
+        inner: reference.inner.clone(),
+        host_info: reference.host_info.clone(),
+        views: Default::default(),
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_ref_same(
+    reference1: Option<&wasm_ref_t>,
+    reference2: Option<&wasm_ref_t>,
+) -> bool {
+    match (reference1, reference2) {
+        (None, None) => true,
+        (Some(reference1), Some(reference2)) => {
+            Rc::ptr_eq(&reference1.host_info, &reference2.host_info)
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_ref_get_host_info(reference: &wasm_ref_t) -> *mut c_void {
+    reference.host_info.get()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_ref_set_host_info(reference: &wasm_ref_t, info: *mut c_void) {
+    wasm_ref_set_host_info_with_finalizer(reference, info, None)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_ref_set_host_info_with_finalizer(
+    reference: &wasm_ref_t,
+    info: *mut c_void,
+    finalizer: Option<wasm_host_info_finalizer_t>,
+) {
+    reference.host_info.set(info, finalizer)
+}
+
+#[allow(non_camel_case_types)]
+pub struct wasm_foreign_t {
+    pub(crate) inner: ExternRef,
+    pub(crate) base: RefBase,
+}
+
+impl wasm_foreign_t {
+    fn new(inner: ExternRef, host_info: Rc<ObjectHostInfo>) -> Box<Self> {
+        Box::new(Self {
+            inner,
+            base: RefBase::with_host_info(host_info),
+        })
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_foreign_new(_store: &wasm_store_t) -> Box<wasm_foreign_t> {
+    // the host info is held by the object, to outlive the handles to
+    // a foreign object stored in a table or a global
+    let host_info = ObjectHostInfo::new();
+    let inner = ExternRef::new(Box::new(ForeignData {
+        host_info: host_info.clone(),
+    }));
+
+    wasm_foreign_t::new(inner, host_info)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_foreign_delete(_foreign: Option<Box<wasm_foreign_t>>) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_foreign_copy(foreign: &wasm_foreign_t) -> Box<wasm_foreign_t> {
+    wasm_foreign_t::new(foreign.inner.clone(), foreign.base.host_info.clone())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_foreign_same(
+    foreign1: &wasm_foreign_t,
+    foreign2: &wasm_foreign_t,
+) -> bool {
+    foreign1.inner.ptr_eq(&foreign2.inner)
+}
+
+impl RefObject for wasm_foreign_t {
+    fn ref_base(&self) -> &RefBase {
+        &self.base
+    }
+
+    fn to_val(&self) -> Val {
+        Val::ExternRef(self.inner.clone())
+    }
+
+    fn from_ref(reference: &wasm_ref_t) -> Option<Box<Self>> {
+        let host_info = extern_ref_data::<ForeignData>(&reference.inner)?
+            .host_info
+            .clone();
+        match &reference.inner {
+            Val::ExternRef(extern_ref) => Some(Self::new(extern_ref.clone(), host_info)),
+            _ => None,
+        }
+    }
+}
+
+impl RefObject for wasm_func_t {
+    fn ref_base(&self) -> &RefBase {
+        &self.base
+    }
+
+    fn to_val(&self) -> Val {
+        Val::FuncRef(self.inner.clone())
+    }
+
+    fn from_ref(reference: &wasm_ref_t) -> Option<Box<Self>> {
+        match &reference.inner {
+            Val::FuncRef(function) => Some(Self::new(function.clone(), None)),
+            _ => None,
+        }
+    }
+}
+
+impl RefObject for wasm_extern_t {
+    fn ref_base(&self) -> &RefBase {
+        &self.base
+    }
+
+    fn to_val(&self) -> Val {
+        match &self.inner {
+            Extern::Function(function) => Val::FuncRef(function.clone()),
+            r#extern => ObjectData::Extern(r#extern.clone()).into_val(),
+        }
+    }
+
+    fn from_ref(reference: &wasm_ref_t) -> Option<Box<Self>> {
+        let r#extern = match &reference.inner {
+            Val::FuncRef(function) => Extern::Function(function.clone()),
+            val => match &*extern_ref_data::<ObjectData>(val)? {
+                ObjectData::Extern(r#extern) => r#extern.clone(),
+                _ => return None,
+            },
+        };
+        Some(Self::new(r#extern, None))
+    }
+}
+
+/// Implements `RefObject` for the handles to an `ObjectData`.
+macro_rules! impl_ref_object {
+    ($type:ty, $object:ident => $data:expr, $pattern:pat => $handle:expr) => {
+        impl RefObject for $type {
+            fn ref_base(&self) -> &RefBase {
+                &self.base
+            }
+
+            fn to_val(&self) -> Val {
+                let $object = self;
+                $data.into_val()
+            }
+
+            fn from_ref(reference: &wasm_ref_t) -> Option<Box<Self>> {
+                match &*extern_ref_data::<ObjectData>(&reference.inner)? {
+                    $pattern => Some($handle),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_ref_object!(
+    wasm_global_t,
+    global => ObjectData::Extern(Extern::Global(global.inner.clone())),
+    ObjectData::Extern(Extern::Global(global)) => Self::new(global.clone())
+);
+impl_ref_object!(
+    wasm_table_t,
+    table => ObjectData::Extern(Extern::Table(table.inner.clone())),
+    ObjectData::Extern(Extern::Table(table)) => Self::new(table.clone())
+);
+impl_ref_object!(
+    wasm_memory_t,
+    memory => ObjectData::Extern(Extern::Memory(memory.inner.clone())),
+    ObjectData::Extern(Extern::Memory(memory)) => Self::new(memory.clone())
+);
+impl_ref_object!(
+    wasm_instance_t,
+    instance => ObjectData::Instance(instance.inner.clone()),
+    ObjectData::Instance(instance) => Self::new(instance.clone())
+);
+impl_ref_object!(
+    wasm_module_t,
+    module => ObjectData::Module(module.inner.clone()),
+    ObjectData::Module(module) => Self::new(module.clone())
+);
+impl_ref_object!(
+    wasm_trap_t,
+    trap => ObjectData::Trap(trap.inner.clone()),
+    ObjectData::Trap(trap) => Box::new(trap.clone().into())
+);
+
+wasm_declare_ref!(foreign);
+wasm_declare_ref!(func);
+wasm_declare_ref!(global);
+wasm_declare_ref!(table);
+wasm_declare_ref!(memory);
+wasm_declare_ref!(extern);
+wasm_declare_ref!(instance);
+wasm_declare_ref!(module);
+wasm_declare_ref!(trap);
@@ -0,0 +1,100 @@
+//! The target of the compilation, to cross-compile modules for
+//! another triple or other CPU features than the host's.
+//!
+//! A `wasmer_target_t` is given to a `wasm_config_t` with
+//! `wasm_config_set_target`.
+
+use super::types::wasm_name_t;
+use enumset::EnumSet;
+use std::str::{self, FromStr};
+use wasmer::{CpuFeature, Target, Triple};
+
+/// A target “triple”, e.g. `x86_64-unknown-linux-gnu`.
+#[allow(non_camel_case_types)]
+pub struct wasmer_triple_t {
+    inner: Triple,
+}
+
+/// Parses a target triple, returning null if it's invalid.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_triple_new(
+    triple: Option<&wasm_name_t>,
+) -> Option<Box<wasmer_triple_t>> {
+    let triple = triple?.into_slice()?;
+    let triple = Triple::from_str(str::from_utf8(triple).ok()?).ok()?;
+
+    Some(Box::new(wasmer_triple_t { inner: triple }))
+}
+
+/// Creates the triple of the host.
+#[no_mangle]
+pub extern "C" fn wasmer_triple_new_from_host() -> Box<wasmer_triple_t> {
+    Box::new(wasmer_triple_t {
+        inner: Triple::host(),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wasmer_triple_delete(_triple: Option<Box<wasmer_triple_t>>) {}
+
+/// A set of CPU features, e.g. `sse4.2` or `avx`.
+#[allow(non_camel_case_types)]
+pub struct wasmer_cpu_features_t {
+    inner: EnumSet<CpuFeature>,
+}
+
+/// Creates an empty set of CPU features.
+#[no_mangle]
+pub extern "C" fn wasmer_cpu_features_new() -> Box<wasmer_cpu_features_t> {
+    Box::new(wasmer_cpu_features_t {
+        inner: EnumSet::new(),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wasmer_cpu_features_delete(_cpu_features: Option<Box<wasmer_cpu_features_t>>) {}
+
+/// Adds a CPU feature by name, e.g. `sse4.2`, returning false if the
+/// feature is unknown.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_cpu_features_add(
+    cpu_features: &mut wasmer_cpu_features_t,
+    feature: Option<&wasm_name_t>,
+) -> bool {
+    let feature = match feature
+        .and_then(|feature| feature.into_slice())
+        .and_then(|feature| str::from_utf8(feature).ok())
+        .and_then(|feature| CpuFeature::from_str(feature).ok())
+    {
+        Some(feature) => feature,
+        None => return false,
+    };
+
+    cpu_features.inner.insert(feature);
+
+    true
+}
+
+/// A compilation target: a triple and the CPU features it supports.
+#[allow(non_camel_case_types)]
+pub struct wasmer_target_t {
+    pub(crate) inner: Target,
+}
+
+/// Creates a target, taking ownership of the triple and the CPU
+/// features.
+#[no_mangle]
+pub extern "C" fn wasmer_target_new(
+    triple: Option<Box<wasmer_triple_t>>,
+    cpu_features: Option<Box<wasmer_cpu_features_t>>,
+) -> Option<Box<wasmer_target_t>> {
+    let triple = triple?;
+    let cpu_features = cpu_features?;
+
+    Some(Box::new(wasmer_target_t {
+        inner: Target::new(triple.inner, cpu_features.inner),
+    }))
+}
+
+#[no_mangle]
+pub extern "C" fn wasmer_target_delete(_target: Option<Box<wasmer_target_t>>) {}
@@ -1,3 +1,4 @@
+use super::reference::{ObjectKey, RefBase};
 use super::store::wasm_store_t;
 use super::types::{wasm_byte_vec_t, wasm_frame_t, wasm_frame_vec_t, wasm_message_t};
 use wasmer::RuntimeError;
@@ -6,11 +7,13 @@ use wasmer::RuntimeError;
 #[allow(non_camel_case_types)]
 pub struct wasm_trap_t {
     pub(crate) inner: RuntimeError,
+    pub(crate) base: RefBase,
 }
 
 impl From<RuntimeError> for wasm_trap_t {
     fn from(other: RuntimeError) -> Self {
-        Self { inner: other }
+        let base = RefBase::new(ObjectKey::Trap(other.clone()));
+        Self { inner: other, base }
     }
 }
 
@@ -30,6 +33,16 @@ pub unsafe extern "C" fn wasm_trap_new(
 #[no_mangle]
 pub unsafe extern "C" fn wasm_trap_delete(_trap: Option<Box<wasm_trap_t>>) {}
 
+#[no_mangle]
+pub unsafe extern "C" fn wasm_trap_copy(trap: &wasm_trap_t) -> Box<wasm_trap_t> {
+    Box::new(trap.inner.clone().into())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_trap_same(trap1: &wasm_trap_t, trap2: &wasm_trap_t) -> bool {
+    trap1.inner.same(&trap2.inner)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasm_trap_message(trap: &wasm_trap_t, out_ptr: &mut wasm_byte_vec_t) {
     let message = trap.inner.message();
@@ -46,7 +59,11 @@ pub unsafe extern "C" fn wasm_trap_origin(trap: &wasm_trap_t) -> Option<Box<wasm
 #[no_mangle]
 pub unsafe extern "C" fn wasm_trap_trace(trap: &wasm_trap_t, out_ptr: &mut wasm_frame_vec_t) {
     let frames = trap.inner.trace();
-    let frame_vec: wasm_frame_vec_t = frames.into();
+    let frame_vec: wasm_frame_vec_t = frames
+        .iter()
+        .map(|frame| Box::new(frame.into()))
+        .collect::<Vec<Box<wasm_frame_t>>>()
+        .into();
 
     out_ptr.size = frame_vec.size;
     out_ptr.data = frame_vec.data;
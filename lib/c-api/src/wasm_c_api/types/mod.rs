@@ -28,9 +28,5 @@ wasm_declare_vec!(byte);
 #[allow(non_camel_case_types)]
 pub type wasm_name_t = wasm_byte_vec_t;
 
-// opaque type over `ExternRef`?
-#[allow(non_camel_case_types)]
-pub struct wasm_ref_t;
-
 #[allow(non_camel_case_types)]
 pub type wasm_message_t = wasm_byte_vec_t;
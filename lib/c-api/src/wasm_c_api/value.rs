@@ -1,7 +1,8 @@
-use super::types::{wasm_ref_t, wasm_valkind_enum};
+use super::reference::{wasm_ref_copy, wasm_ref_t};
+use super::types::wasm_valkind_enum;
 use std::convert::{TryFrom, TryInto};
-use std::ptr::NonNull;
-use wasmer::Val;
+use std::ptr;
+use wasmer::{ExternRef, Val};
 
 #[allow(non_camel_case_types)]
 pub type wasm_valkind_t = u8;
@@ -44,17 +45,39 @@ pub unsafe extern "C" fn wasm_val_copy(out_ptr: *mut wasm_val_t, val: &wasm_val_
             wasm_valkind_enum::WASM_I64 => wasm_val_inner { int64_t: val.of.int64_t },
             wasm_valkind_enum::WASM_F32 => wasm_val_inner { float32_t: val.of.float32_t },
             wasm_valkind_enum::WASM_F64 => wasm_val_inner { float64_t: val.of.float64_t },
-            wasm_valkind_enum::WASM_ANYREF => wasm_val_inner { wref: val.of.wref },
-            wasm_valkind_enum::WASM_FUNCREF => wasm_val_inner { wref: val.of.wref },
+            wasm_valkind_enum::WASM_ANYREF | wasm_valkind_enum::WASM_FUNCREF => wasm_val_inner {
+                wref: wasm_ref_copy(val.of.wref.as_ref()).map_or(ptr::null_mut(), Box::into_raw),
+            },
         };
 }
 
+/// Deletes the reference owned by the value, if any; the value
+/// itself is owned by the caller.
 #[no_mangle]
-pub unsafe extern "C" fn wasm_val_delete(val: Option<NonNull<wasm_val_t>>) {
-    if let Some(v_inner) = val {
-        // TODO: figure out where wasm_val is allocated first...
-        let _ = Box::from_raw(v_inner.as_ptr());
+pub unsafe extern "C" fn wasm_val_delete(val: Option<&mut wasm_val_t>) {
+    if let Some(val) = val {
+        if is_ref_kind(val.kind) && !val.of.wref.is_null() {
+            let _ = Box::from_raw(val.of.wref);
+            val.of.wref = ptr::null_mut();
+        }
+    }
+}
+
+/// Deletes the references owned by the values of `vec`.
+pub(crate) unsafe fn wasm_val_vec_delete_refs(vec: &mut wasm_val_vec_t) {
+    if vec.data.is_null() {
+        return;
     }
+    for val in std::slice::from_raw_parts_mut(vec.data, vec.size) {
+        wasm_val_delete(Some(val));
+    }
+}
+
+fn is_ref_kind(kind: wasm_valkind_t) -> bool {
+    matches!(
+        kind.try_into(),
+        Ok(wasm_valkind_enum::WASM_ANYREF) | Ok(wasm_valkind_enum::WASM_FUNCREF)
+    )
 }
 
 impl TryFrom<wasm_valkind_t> for wasm_valkind_enum {
@@ -90,8 +113,13 @@ impl TryFrom<&wasm_val_t> for Val {
             wasm_valkind_enum::WASM_I64 => Val::I64(unsafe { item.of.int64_t }),
             wasm_valkind_enum::WASM_F32 => Val::F32(unsafe { item.of.float32_t }),
             wasm_valkind_enum::WASM_F64 => Val::F64(unsafe { item.of.float64_t }),
-            wasm_valkind_enum::WASM_ANYREF => return Err("ANYREF not supported at this time"),
-            wasm_valkind_enum::WASM_FUNCREF => return Err("FUNCREF not supported at this time"),
+            wasm_valkind_enum::WASM_ANYREF => wasm_ref_t::to_val(unsafe { item.of.wref.as_ref() }),
+            wasm_valkind_enum::WASM_FUNCREF => {
+                match wasm_ref_t::to_val(unsafe { item.of.wref.as_ref() }) {
+                    val @ Val::FuncRef(_) | val @ Val::ExternRef(ExternRef::Null) => val,
+                    _ => return Err("FUNCREF value holds a non-function reference"),
+                }
+            }
         })
     }
 }
@@ -125,8 +153,19 @@ impl TryFrom<&Val> for wasm_val_t {
                 of: wasm_val_inner { float64_t: v },
                 kind: wasm_valkind_enum::WASM_F64 as _,
             },
+            Val::ExternRef(_) => wasm_val_t {
+                of: wasm_val_inner {
+                    wref: wasm_ref_t::new(item.clone()).map_or(ptr::null_mut(), Box::into_raw),
+                },
+                kind: wasm_valkind_enum::WASM_ANYREF as _,
+            },
+            Val::FuncRef(_) => wasm_val_t {
+                of: wasm_val_inner {
+                    wref: wasm_ref_t::new(item.clone()).map_or(ptr::null_mut(), Box::into_raw),
+                },
+                kind: wasm_valkind_enum::WASM_FUNCREF as _,
+            },
             Val::V128(_) => return Err("128bit SIMD types not yet supported in Wasm C API"),
-            _ => todo!("Handle these values in TryFrom<Val> for wasm_val_t"),
         })
     }
 }
@@ -0,0 +1,238 @@
+//! Files implemented by C callbacks, to supply the stdin of a WASI
+//! program or to add virtual files to its filesystem.
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::raw::{c_char, c_void};
+use wasmer_wasi::devices::{PollEvent, PollEventSet};
+use wasmer_wasi::{WasiFile, WasiFsError};
+
+/// Reads at most `buffer_len` bytes in `buffer`, returning the number
+/// of bytes read, 0 at the end of the file, or -1 on error.
+#[allow(non_camel_case_types)]
+pub type wasi_file_read_callback_t =
+    Option<unsafe extern "C" fn(env: *mut c_void, buffer: *mut c_char, buffer_len: usize) -> isize>;
+
+/// Writes at most `buffer_len` bytes from `buffer`, returning the
+/// number of bytes written, or -1 on error.
+#[allow(non_camel_case_types)]
+pub type wasi_file_write_callback_t = Option<
+    unsafe extern "C" fn(env: *mut c_void, buffer: *const c_char, buffer_len: usize) -> isize,
+>;
+
+/// Moves the cursor of the file, returning its new position from the
+/// start of the file, or -1 on error.
+#[allow(non_camel_case_types)]
+pub type wasi_file_seek_callback_t =
+    Option<unsafe extern "C" fn(env: *mut c_void, offset: i64, whence: wasi_seek_whence_t) -> i64>;
+
+/// Releases the environment of the callbacks once the file is
+/// dropped.
+#[allow(non_camel_case_types)]
+pub type wasi_file_finalizer_t = Option<unsafe extern "C" fn(env: *mut c_void)>;
+
+/// The position an offset given to a `wasi_file_seek_callback_t` is
+/// relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub enum wasi_seek_whence_t {
+    SeekStart = 0,
+    SeekCurrent = 1,
+    SeekEnd = 2,
+}
+
+/// The number of bytes reported as available to read from a file
+/// without a seek callback, e.g. to `poll_oneoff`.
+///
+/// Such a file is a stream whose remaining size can't be known without
+/// blocking in the read callback, so it's assumed to have data ready.
+/// The count is only a hint for the WASI program: reads still return
+/// what the callback provides, which may be less.
+pub(crate) const STREAM_BYTES_AVAILABLE: usize = 1024;
+
+/// A file whose content is read, written and sought by C callbacks.
+///
+/// It can't be serialized with the WASI state.
+pub struct CallbackFile {
+    env: *mut c_void,
+    read: wasi_file_read_callback_t,
+    write: wasi_file_write_callback_t,
+    seek: wasi_file_seek_callback_t,
+    finalizer: wasi_file_finalizer_t,
+}
+
+// The callbacks are required to be callable from any thread.
+unsafe impl Send for CallbackFile {}
+
+impl CallbackFile {
+    pub fn new(
+        env: *mut c_void,
+        read: wasi_file_read_callback_t,
+        write: wasi_file_write_callback_t,
+        seek: wasi_file_seek_callback_t,
+        finalizer: wasi_file_finalizer_t,
+    ) -> Self {
+        Self {
+            env,
+            read,
+            write,
+            seek,
+            finalizer,
+        }
+    }
+
+    fn seek_inner(&self, offset: i64, whence: wasi_seek_whence_t) -> io::Result<u64> {
+        let seek = self.seek.ok_or_else(|| unsupported("seek"))?;
+        let position = unsafe { seek(self.env, offset, whence) };
+
+        if position < 0 {
+            Err(io::Error::new(io::ErrorKind::Other, "seek callback failed"))
+        } else {
+            Ok(position as u64)
+        }
+    }
+
+    /// Returns the current position and the size of the file, if it
+    /// can be sought.
+    fn position_and_size(&self) -> Option<(u64, u64)> {
+        let position = self.seek_inner(0, wasi_seek_whence_t::SeekCurrent).ok()?;
+        let size = self.seek_inner(0, wasi_seek_whence_t::SeekEnd).ok()?;
+        self.seek_inner(position as i64, wasi_seek_whence_t::SeekStart)
+            .ok()?;
+
+        Some((position, size))
+    }
+}
+
+fn unsupported(operation: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!("the file has no {} callback", operation),
+    )
+}
+
+impl Drop for CallbackFile {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer {
+            unsafe { finalizer(self.env) }
+        }
+    }
+}
+
+impl fmt::Debug for CallbackFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CallbackFile")
+            .field("env", &self.env)
+            .field("read", &self.read.is_some())
+            .field("write", &self.write.is_some())
+            .field("seek", &self.seek.is_some())
+            .finish()
+    }
+}
+
+impl Serialize for CallbackFile {
+    fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("a callback file can't be serialized"))
+    }
+}
+
+impl<'de> Deserialize<'de> for CallbackFile {
+    fn deserialize<D: Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+        Err(de::Error::custom("a callback file can't be deserialized"))
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for CallbackFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        self.position_and_size().map(|(_, size)| size).unwrap_or(0)
+    }
+    fn set_len(&mut self, _len: u64) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        match self.position_and_size() {
+            Some((position, size)) => Ok(size.saturating_sub(position) as usize),
+            None => Ok(STREAM_BYTES_AVAILABLE),
+        }
+    }
+    fn poll_readiness(&self, events: PollEventSet) -> PollEventSet {
+        // the callbacks can't be asked whether they would block
+        let mut ready = 0;
+        if self.read.is_some() {
+            ready |= PollEvent::PollIn as PollEventSet;
+        }
+        if self.write.is_some() {
+            ready |= PollEvent::PollOut as PollEventSet;
+        }
+        events & ready
+    }
+}
+
+impl Read for CallbackFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.read.ok_or_else(|| unsupported("read"))?;
+        let num_bytes = unsafe { read(self.env, buf.as_mut_ptr() as *mut c_char, buf.len()) };
+
+        if num_bytes < 0 {
+            Err(io::Error::new(io::ErrorKind::Other, "read callback failed"))
+        } else if num_bytes as usize > buf.len() {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "read callback reported more bytes than the buffer holds",
+            ))
+        } else {
+            Ok(num_bytes as usize)
+        }
+    }
+}
+
+impl Seek for CallbackFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Start(offset) => {
+                self.seek_inner(offset as i64, wasi_seek_whence_t::SeekStart)
+            }
+            SeekFrom::Current(offset) => self.seek_inner(offset, wasi_seek_whence_t::SeekCurrent),
+            SeekFrom::End(offset) => self.seek_inner(offset, wasi_seek_whence_t::SeekEnd),
+        }
+    }
+}
+
+impl Write for CallbackFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let write = self.write.ok_or_else(|| unsupported("write"))?;
+        let num_bytes = unsafe { write(self.env, buf.as_ptr() as *const c_char, buf.len()) };
+
+        if num_bytes < 0 {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "write callback failed",
+            ))
+        } else if num_bytes as usize > buf.len() {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "write callback reported more bytes than the buffer holds",
+            ))
+        } else {
+            Ok(num_bytes as usize)
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! This API will be superseded by a standard WASI API when/if such a standard is created.
 
+mod callback_files;
 mod capture_files;
 
 use super::{
@@ -9,17 +10,22 @@ use super::{
     instance::wasm_instance_t,
     module::wasm_module_t,
     store::wasm_store_t,
+    trap::wasm_trap_t,
+};
+pub use callback_files::{
+    wasi_file_finalizer_t, wasi_file_read_callback_t, wasi_file_seek_callback_t,
+    wasi_file_write_callback_t, wasi_seek_whence_t,
 };
 // required due to really weird Rust resolution rules for macros
 // https://github.com/rust-lang/rust/issues/57966
 use crate::error::{update_last_error, CApiError};
 use std::convert::TryFrom;
 use std::ffi::CStr;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
 use std::slice;
 use wasmer::{Extern, NamedResolver};
 use wasmer_wasi::{
-    generate_import_object_from_env, get_wasi_version, WasiEnv, WasiFile, WasiState,
+    generate_import_object_from_env, get_wasi_version, WasiEnv, WasiError, WasiFile, WasiState,
     WasiStateBuilder, WasiVersion,
 };
 
@@ -145,6 +151,72 @@ pub extern "C" fn wasi_config_inherit_stdin(config: &mut wasi_config_t) {
     config.inherit_stdin = true;
 }
 
+/// A file implemented by C callbacks.
+///
+/// A null callback makes the corresponding operation fail. The
+/// callbacks may be called from any thread, and `finalizer`, if not
+/// null, is called with `env` once the file is dropped.
+///
+/// When polled, a file is ready to be read if it has a `read` callback
+/// and to be written if it has a `write` callback. A file without a
+/// `seek` callback is a stream, which always reports 1024 bytes
+/// available.
+#[allow(non_camel_case_types)]
+pub struct wasi_file_t {
+    inner: callback_files::CallbackFile,
+}
+
+#[no_mangle]
+pub extern "C" fn wasi_file_new(
+    env: *mut c_void,
+    read: wasi_file_read_callback_t,
+    write: wasi_file_write_callback_t,
+    seek: wasi_file_seek_callback_t,
+    finalizer: wasi_file_finalizer_t,
+) -> Box<wasi_file_t> {
+    Box::new(wasi_file_t {
+        inner: callback_files::CallbackFile::new(env, read, write, seek, finalizer),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wasi_file_delete(_file: Option<Box<wasi_file_t>>) {}
+
+/// Uses `file` as the stdin of the program, taking ownership of it.
+#[no_mangle]
+pub extern "C" fn wasi_config_stdin(config: &mut wasi_config_t, file: Box<wasi_file_t>) {
+    config.state_builder.stdin(Box::new(file.inner));
+}
+
+/// Adds `file` to the filesystem of the program at `path`, taking
+/// ownership of it.
+///
+/// The path is relative to the root, and the missing parent
+/// directories are created in memory.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_config_virtual_file(
+    config: &mut wasi_config_t,
+    path: *const c_char,
+    file: Box<wasi_file_t>,
+) -> bool {
+    debug_assert!(!path.is_null());
+
+    let path_cstr = CStr::from_ptr(path);
+    let path_str = match path_cstr.to_str() {
+        Ok(path_str) => path_str,
+        Err(e) => {
+            update_last_error(e);
+            return false;
+        }
+    };
+
+    config
+        .state_builder
+        .virtual_file(path_str, Box::new(file.inner));
+
+    true
+}
+
 #[allow(non_camel_case_types)]
 pub struct wasi_env_t {
     /// cbindgen:ignore
@@ -191,6 +263,56 @@ pub extern "C" fn wasi_env_set_memory(env: &mut wasi_env_t, memory: &wasm_memory
     env.inner.set_memory(memory.inner.clone());
 }
 
+/// Sets the environment variable `key` to `value`, replacing its
+/// previous value if any.
+///
+/// The program reads its environment when it starts, so this must be
+/// called before running it.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_env_set_env(
+    env: &mut wasi_env_t,
+    key: *const c_char,
+    value: *const c_char,
+) -> bool {
+    debug_assert!(!key.is_null());
+    debug_assert!(!value.is_null());
+
+    let key_bytes = CStr::from_ptr(key).to_bytes();
+    let value_bytes = CStr::from_ptr(value).to_bytes();
+
+    if key_bytes.contains(&b'=') {
+        update_last_error(CApiError {
+            msg: "an environment variable key can't contain `=`".to_string(),
+        });
+        return false;
+    }
+
+    let mut env_var = key_bytes.to_vec();
+    env_var.push(b'=');
+    env_var.extend_from_slice(value_bytes);
+
+    let mut state = env.inner.state_mut();
+    state.envs.retain(|existing| {
+        !(existing.starts_with(key_bytes) && existing.get(key_bytes.len()) == Some(&b'='))
+    });
+    state.envs.push(env_var);
+
+    true
+}
+
+/// Appends `arg` to the arguments of the program.
+///
+/// The program reads its arguments when it starts, so this must be
+/// called before running it.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_env_push_arg(env: &mut wasi_env_t, arg: *const c_char) {
+    debug_assert!(!arg.is_null());
+
+    let arg_bytes = CStr::from_ptr(arg).to_bytes();
+
+    env.inner.state_mut().args.push(arg_bytes.to_vec());
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wasi_env_read_stdout(
     env: &mut wasi_env_t,
@@ -254,6 +376,23 @@ fn read_inner(wasi_file: &mut Box<dyn WasiFile>, inner_buffer: &mut [u8]) -> isi
     }
 }
 
+/// Gets the exit code given to `proc_exit` if `trap` was raised by
+/// it, returning false otherwise.
+///
+/// A program which returns from its start function without calling
+/// `proc_exit` exits with the code 0.
+#[no_mangle]
+pub extern "C" fn wasi_trap_exit_code(trap: &wasm_trap_t, exit_code: &mut u32) -> bool {
+    match trap.inner.downcast_ref::<WasiError>() {
+        Some(WasiError::Exit(code)) => {
+            *exit_code = *code;
+
+            true
+        }
+        _ => false,
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 #[allow(non_camel_case_types)]
@@ -337,10 +476,7 @@ unsafe fn wasi_get_imports_inner(
                 }));
             let inner = Extern::from_export(store, export);
 
-            Some(Box::new(wasm_extern_t {
-                instance: None,
-                inner,
-            }))
+            Some(wasm_extern_t::new(inner, None))
         })
         .collect::<Option<Vec<_>>>()?
         .into();
@@ -353,10 +489,7 @@ pub unsafe extern "C" fn wasi_get_start_function(
     instance: &mut wasm_instance_t,
 ) -> Option<Box<wasm_func_t>> {
     let f = c_try!(instance.inner.exports.get_function("_start"));
-    Some(Box::new(wasm_func_t {
-        inner: f.clone(),
-        instance: Some(instance.inner.clone()),
-    }))
+    Some(wasm_func_t::new(f.clone(), Some(instance.inner.clone())))
 }
 
 /// Delete a `wasm_extern_t` allocated by the API.
@@ -1,9 +1,15 @@
 //! Wasmer-specific extensions to the Wasm C API.
 
+use super::externals::{call_callback, wasm_extern_t, wasm_func_t, wasm_memory_t, FinalizedEnv};
 use super::module::wasm_module_t;
-use super::types::wasm_name_t;
+use super::store::wasm_store_t;
+use super::trap::wasm_trap_t;
+use super::types::{wasm_functype_t, wasm_name_t};
+use super::value::wasm_val_vec_t;
+use std::ffi::c_void;
 use std::str;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, Weak};
+use wasmer::{Function, FunctionType, Instance, RuntimeError, Store, Val};
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_module_name(module: &wasm_module_t, out: &mut wasm_name_t) {
@@ -33,3 +39,125 @@ pub unsafe extern "C" fn wasm_module_set_name(
         None => false,
     }
 }
+
+/// The callback of a host function created with
+/// `wasmer_func_new_with_caller`, receiving the instance calling it
+/// along with its environment.
+#[allow(non_camel_case_types)]
+pub type wasmer_func_callback_with_caller_t = unsafe extern "C" fn(
+    caller: &wasmer_caller_t,
+    env: *mut c_void,
+    args: *const wasm_val_vec_t,
+    results: *mut wasm_val_vec_t,
+) -> *mut wasm_trap_t;
+
+/// The instance calling a host function created with
+/// `wasmer_func_new_with_caller`, giving access to its exports.
+///
+/// The function is created anew for each instance importing it, with
+/// its own `wasmer_caller_t` bound to that instance, so several live
+/// instances can import it. When the function is called directly, or
+/// by the start function of the instance, the caller has no exports.
+#[allow(non_camel_case_types)]
+#[derive(Default)]
+pub struct wasmer_caller_t {
+    instance: Mutex<Weak<Instance>>,
+}
+
+impl wasmer_caller_t {
+    /// Binds the caller to the instance importing the function.
+    pub(crate) fn bind(&self, instance: &Arc<Instance>) {
+        *self.instance.lock().unwrap() = Arc::downgrade(instance);
+    }
+
+    fn instance(&self) -> Option<Arc<Instance>> {
+        self.instance.lock().unwrap().upgrade()
+    }
+}
+
+/// A host function created with `wasmer_func_new_with_caller`, from
+/// which a function is created for each instance importing it.
+pub(crate) struct CallerFunction {
+    store: Store,
+    ty: FunctionType,
+    callback: wasmer_func_callback_with_caller_t,
+    // shared by the functions created, finalized with the last of them
+    env: Arc<FinalizedEnv>,
+}
+
+/// The environment of a function created by a `CallerFunction`.
+struct CallerEnv {
+    env: Arc<FinalizedEnv>,
+    caller: Arc<wasmer_caller_t>,
+}
+
+impl CallerFunction {
+    /// Creates a function passing `caller` to each call of the callback.
+    pub(crate) unsafe fn new_function(&self, caller: Arc<wasmer_caller_t>) -> Function {
+        let num_rets = self.ty.results().len();
+        let callback = self.callback;
+        let inner_callback =
+            move |env: &mut CallerEnv, args: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+                call_callback(num_rets, args, |args, results| {
+                    callback(&env.caller, env.env.env, args, results)
+                })
+            };
+        let env = CallerEnv {
+            env: self.env.clone(),
+            caller,
+        };
+        Function::new_with_env(&self.store, &self.ty, env, inner_callback)
+    }
+}
+
+/// Creates a host function like `wasm_func_new_with_env`, whose
+/// callback also receives the instance calling it.
+///
+/// Like with `wasm_func_new_with_env`, `env` must be usable from any
+/// thread.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_func_new_with_caller(
+    store: &wasm_store_t,
+    ft: &wasm_functype_t,
+    callback: wasmer_func_callback_with_caller_t,
+    env: *mut c_void,
+    finalizer: Option<unsafe extern "C" fn(*mut c_void)>,
+) -> Option<Box<wasm_func_t>> {
+    let caller_function = Arc::new(CallerFunction {
+        store: store.inner.clone(),
+        ty: ft.sig().clone(),
+        callback,
+        env: Arc::new(FinalizedEnv { env, finalizer }),
+    });
+    let function = caller_function.new_function(Arc::new(wasmer_caller_t::default()));
+
+    let mut func = wasm_func_t::new(function, None);
+    func.caller = Some(caller_function);
+    Some(func)
+}
+
+/// Gets the export named `name` of the instance calling a host
+/// function, or null if there's none.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_caller_export(
+    caller: &wasmer_caller_t,
+    name: &wasm_name_t,
+) -> Option<Box<wasm_extern_t>> {
+    let instance = caller.instance()?;
+    let name = str::from_utf8(name.into_slice()?).ok()?;
+    let inner = instance.exports.get_extern(name)?.clone();
+
+    Some(wasm_extern_t::new(inner, Some(instance)))
+}
+
+/// Gets the first memory exported by the instance calling a host
+/// function, or null if there's none.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_caller_memory(
+    caller: &wasmer_caller_t,
+) -> Option<Box<wasm_memory_t>> {
+    let instance = caller.instance()?;
+    let (_, memory) = instance.exports.iter().memories().next()?;
+
+    Some(wasm_memory_t::new(memory.clone()))
+}
@@ -0,0 +1,179 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <inttypes.h>
+
+#include "wasmer_wasm.h"
+
+#define own
+
+void check(bool success, const char* message) {
+  if (!success) {
+    printf("> Error: %s\n", message);
+    exit(1);
+  }
+}
+
+own wasm_byte_vec_t* compile_wat(const char* wat_string) {
+  wasm_byte_vec_t wat;
+  wasm_byte_vec_new(&wat, strlen(wat_string), wat_string);
+  wasm_byte_vec_t* wasm = wat2wasm(&wat);
+  wasm_byte_vec_delete(&wat);
+  check(wasm != NULL, "wat2wasm failed");
+  return wasm;
+}
+
+bool module_compiles(wasm_engine_t* engine, wasm_byte_vec_t* wasm) {
+  wasm_store_t* store = wasm_store_new(engine);
+  own wasm_module_t* module = wasm_module_new(store, wasm);
+  bool compiled = module != NULL;
+  if (module) wasm_module_delete(module);
+  wasm_store_delete(store);
+  return compiled;
+}
+
+uint64_t cost_function(wasmer_operator_category_t operator_category) {
+  switch (operator_category) {
+    case VARIABLE:
+    case CONST:
+      return 1;
+    case NUMERIC:
+      return 2;
+    default:
+      return 0;
+  }
+}
+
+int main(int argc, const char* argv[]) {
+  printf("Creating targets...\n");
+  wasm_name_t triple_name;
+  wasm_name_new_from_string(&triple_name, "x86_64-apple-darwin");
+  own wasmer_triple_t* triple = wasmer_triple_new(&triple_name);
+  wasm_name_delete(&triple_name);
+  check(triple != NULL, "valid triple rejected");
+  wasmer_triple_delete(triple);
+
+  wasm_name_new_from_string(&triple_name, "not-a-triple");
+  check(wasmer_triple_new(&triple_name) == NULL, "invalid triple accepted");
+  wasm_name_delete(&triple_name);
+
+  own wasmer_cpu_features_t* cpu_features = wasmer_cpu_features_new();
+  wasm_name_t feature;
+  wasm_name_new_from_string(&feature, "sse2");
+  check(wasmer_cpu_features_add(cpu_features, &feature), "known CPU feature rejected");
+  wasm_name_delete(&feature);
+  wasm_name_new_from_string(&feature, "not-a-feature");
+  check(!wasmer_cpu_features_add(cpu_features, &feature), "unknown CPU feature accepted");
+  wasm_name_delete(&feature);
+
+  own wasmer_target_t* target = wasmer_target_new(wasmer_triple_new_from_host(), cpu_features);
+  check(target != NULL, "target creation failed");
+
+  printf("Configuring features...\n");
+  own wasm_byte_vec_t* two_tables = compile_wat("(module (table 1 funcref) (table 1 funcref))");
+
+  own wasm_engine_t* engine = wasm_engine_new_with_config(wasm_config_new());
+  check(!module_compiles(engine, two_tables), "reference types should be disabled by default");
+  wasm_engine_delete(engine);
+
+  own wasmer_features_t* features = wasmer_features_new();
+  check(wasmer_features_reference_types(features, true), "enabling reference types failed");
+
+  own wasm_config_t* config = wasm_config_new();
+  wasm_config_set_target(config, target);
+  wasm_config_set_features(config, features);
+  wasm_config_set_opt_level(config, NONE);
+  engine = wasm_engine_new_with_config(config);
+  check(engine != NULL, "engine creation failed");
+  check(module_compiles(engine, two_tables), "reference types should be enabled");
+  wasm_engine_delete(engine);
+  wasm_byte_vec_delete(two_tables);
+
+  printf("Metering...\n");
+  own wasmer_metering_t* metering = wasmer_metering_new(7, cost_function);
+  config = wasm_config_new();
+  wasm_config_push_middleware(config, wasmer_metering_as_middleware(metering));
+  engine = wasm_engine_new_with_config(config);
+  check(engine != NULL, "engine creation failed");
+
+  wasm_store_t* store = wasm_store_new(engine);
+  own wasm_byte_vec_t* wasm = compile_wat(
+    "(module\n"
+    "  (func (export \"add_one\") (param i32) (result i32)\n"
+    "    local.get 0\n"
+    "    i32.const 1\n"
+    "    i32.add))");
+  own wasm_module_t* module = wasm_module_new(store, wasm);
+  check(module != NULL, "module compilation failed");
+  own wasm_module_t* other_module = wasm_module_new(store, wasm);
+  check(other_module != NULL, "second module compilation failed");
+  wasm_byte_vec_delete(wasm);
+
+  wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+  own wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+  check(instance != NULL, "instantiation failed");
+  uint64_t points = 0;
+  check(wasmer_metering_get_remaining_points(instance, &points), "getting points failed");
+  check(points == 7, "unexpected initial points");
+
+  wasm_extern_vec_t exports;
+  wasm_instance_exports(instance, &exports);
+  wasm_func_t* add_one = NULL;
+  for (size_t i = 0; i < exports.size; ++i) {
+    if (wasm_extern_kind(exports.data[i]) == WASM_EXTERN_FUNC) {
+      add_one = wasm_extern_as_func(exports.data[i]);
+    }
+  }
+  check(add_one != NULL, "add_one not exported");
+
+  wasm_val_t args_val[1] = { WASM_I32_VAL(1) };
+  wasm_val_t results_val[1] = { WASM_INIT_VAL };
+  wasm_val_vec_t args = WASM_ARRAY_VEC(args_val);
+  wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+
+  check(wasm_func_call(add_one, &args, &results) == NULL, "call failed");
+  check(results_val[0].of.i32 == 2, "unexpected result");
+  check(wasmer_metering_get_remaining_points(instance, &points), "getting points failed");
+  check(points == 3, "points not charged");
+  check(!wasmer_metering_points_are_exhausted(instance), "points exhausted too early");
+
+  own wasm_trap_t* trap = wasm_func_call(add_one, &args, &results);
+  check(trap != NULL, "call beyond the limit should trap");
+  wasm_trap_delete(trap);
+  check(wasmer_metering_points_are_exhausted(instance), "points should be exhausted");
+
+  check(wasmer_metering_set_remaining_points(instance, 4), "setting points failed");
+  check(!wasmer_metering_points_are_exhausted(instance), "points should be reset");
+  check(wasm_func_call(add_one, &args, &results) == NULL, "call failed after reset");
+  check(wasmer_metering_get_remaining_points(instance, &points), "getting points failed");
+  check(points == 0, "points not charged after reset");
+
+  own wasm_engine_t* unmetered_engine = wasm_engine_new();
+  own wasm_store_t* unmetered_store = wasm_store_new(unmetered_engine);
+  wasm = compile_wat("(module)");
+  own wasm_module_t* unmetered_module = wasm_module_new(unmetered_store, wasm);
+  wasm_byte_vec_delete(wasm);
+  own wasm_instance_t* unmetered_instance =
+    wasm_instance_new(unmetered_store, unmetered_module, &imports, NULL);
+  check(unmetered_instance != NULL, "instantiation failed");
+  check(!wasmer_metering_get_remaining_points(unmetered_instance, &points),
+        "an instance without metering has no points");
+  check(wasmer_last_error_length() > 0, "the error should be reported");
+  check(!wasmer_metering_set_remaining_points(unmetered_instance, 4),
+        "an instance without metering has no points");
+  wasm_instance_delete(unmetered_instance);
+  wasm_module_delete(unmetered_module);
+  wasm_store_delete(unmetered_store);
+  wasm_engine_delete(unmetered_engine);
+
+  printf("Shutting down...\n");
+  wasm_extern_vec_delete(&exports);
+  wasm_instance_delete(instance);
+  wasm_module_delete(other_module);
+  wasm_module_delete(module);
+  wasm_store_delete(store);
+  wasm_engine_delete(engine);
+
+  printf("Done.\n");
+  return 0;
+}
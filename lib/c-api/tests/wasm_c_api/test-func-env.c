@@ -0,0 +1,211 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "wasmer_wasm.h"
+
+#define own
+
+void check(bool success, const char* message) {
+  if (!success) {
+    printf("> Error: %s\n", message);
+    exit(1);
+  }
+}
+
+typedef struct {
+  wasm_store_t* store;
+  char message[32];
+  bool finalized;
+} host_env;
+
+void host_env_finalize(void* env) {
+  ((host_env*) env)->finalized = true;
+}
+
+// Copies the string given by the caller from its memory.
+own wasm_trap_t* print_callback(
+  const wasmer_caller_t* caller, void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results
+) {
+  host_env* data = (host_env*) env;
+  int32_t offset = args->data[0].of.i32;
+  int32_t length = args->data[1].of.i32;
+
+  own wasm_memory_t* memory = wasmer_caller_memory(caller);
+  check(memory != NULL, "the caller should export a memory");
+  check(offset + length <= (int32_t) wasm_memory_data_size(memory), "out of bounds string");
+  check(length < (int32_t) sizeof(data->message), "string too long");
+
+  memcpy(data->message, wasm_memory_data(memory) + offset, length);
+  data->message[length] = '\0';
+  wasm_memory_delete(memory);
+
+  wasm_name_t name;
+  wasm_name_new_from_string(&name, "run");
+  own wasm_extern_t* run = wasmer_caller_export(caller, &name);
+  wasm_name_delete(&name);
+  check(run != NULL && wasm_extern_kind(run) == WASM_EXTERN_FUNC, "the caller should export `run`");
+  wasm_extern_delete(run);
+
+  return NULL;
+}
+
+own wasm_trap_t* fail_callback(void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+  host_env* data = (host_env*) env;
+  wasm_message_t message;
+  wasm_name_new_from_string(&message, "host failure");
+  own wasm_trap_t* trap = wasm_trap_new(data->store, &message);
+  wasm_name_delete(&message);
+
+  return trap;
+}
+
+// Creates the imports of the module, with a new `print` function.
+void new_imports(wasm_store_t* store, host_env* env, wasm_extern_vec_t* imports) {
+  own wasm_functype_t* print_type = wasm_functype_new_2_0(wasm_valtype_new_i32(), wasm_valtype_new_i32());
+  own wasm_functype_t* fail_type = wasm_functype_new_0_0();
+  own wasm_func_t* print_func = wasmer_func_new_with_caller(store, print_type, print_callback, env, NULL);
+  own wasm_func_t* fail_func = wasm_func_new_with_env(store, fail_type, fail_callback, env, NULL);
+  wasm_extern_vec_new_uninitialized(imports, 2);
+  imports->data[0] = wasm_func_as_extern(print_func);
+  imports->data[1] = wasm_func_as_extern(fail_func);
+  wasm_func_delete(print_func);
+  wasm_func_delete(fail_func);
+  wasm_functype_delete(print_type);
+  wasm_functype_delete(fail_type);
+}
+
+// Calls the `run` export of `instance`.
+void call_run(const wasm_instance_t* instance) {
+  wasm_extern_vec_t exports;
+  wasm_instance_exports(instance, &exports);
+  wasm_val_vec_t args = WASM_EMPTY_VEC;
+  wasm_val_vec_t results = WASM_EMPTY_VEC;
+  own wasm_func_t* run_func = wasm_extern_as_func(exports.data[1]);
+  check(wasm_func_call(run_func, &args, &results) == NULL, "calling `run` failed");
+  wasm_func_delete(run_func);
+  wasm_extern_vec_delete(&exports);
+}
+
+// A function receiving its caller can be imported by several live
+// instances, each call receiving the instance making it.
+void test_two_instances(wasm_store_t* store, const wasm_module_t* module) {
+  host_env env = { store, "", false };
+  wasm_extern_vec_t imports;
+  new_imports(store, &env, &imports);
+
+  own wasm_instance_t* first = wasm_instance_new(store, module, &imports, NULL);
+  check(first != NULL, "instantiation failed");
+  own wasm_instance_t* second = wasm_instance_new(store, module, &imports, NULL);
+  check(second != NULL, "a function receiving its caller should be importable by two instances");
+
+  // The second instance prints another message.
+  wasm_extern_vec_t exports;
+  wasm_instance_exports(second, &exports);
+  own wasm_memory_t* memory = wasm_extern_as_memory(exports.data[0]);
+  memcpy(wasm_memory_data(memory) + 16, "Hello, second!", 14);
+  wasm_memory_delete(memory);
+  wasm_extern_vec_delete(&exports);
+
+  call_run(first);
+  check(strcmp(env.message, "Hello, caller!") == 0, "unexpected message");
+  call_run(second);
+  check(strcmp(env.message, "Hello, second!") == 0, "unexpected message");
+  call_run(first);
+  check(strcmp(env.message, "Hello, caller!") == 0, "unexpected message");
+
+  wasm_instance_delete(first);
+  wasm_instance_delete(second);
+  wasm_extern_vec_delete(&imports);
+}
+
+int main(int argc, const char* argv[]) {
+  printf("Initializing...\n");
+  own wasm_engine_t* engine = wasm_engine_new();
+  own wasm_store_t* store = wasm_store_new(engine);
+
+  printf("Compiling module...\n");
+  const char* wat_string =
+    "(module\n"
+    "  (import \"host\" \"print\" (func $print (param i32 i32)))\n"
+    "  (import \"host\" \"fail\" (func $fail))\n"
+    "  (memory (export \"memory\") 1)\n"
+    "  (data (i32.const 16) \"Hello, caller!\")\n"
+    "  (func (export \"run\") (call $print (i32.const 16) (i32.const 14)))\n"
+    "  (func (export \"fail\") (call $fail)))";
+  wasm_byte_vec_t wat;
+  wasm_byte_vec_new(&wat, strlen(wat_string), wat_string);
+  own wasm_byte_vec_t* wasm = wat2wasm(&wat);
+  wasm_byte_vec_delete(&wat);
+  check(wasm != NULL, "wat2wasm failed");
+
+  own wasm_module_t* module = wasm_module_new(store, wasm);
+  wasm_byte_vec_delete(wasm);
+  check(module != NULL, "module compilation failed");
+
+  printf("Creating host functions...\n");
+  host_env unused_env = { store, "", false };
+  own wasm_functype_t* fail_type = wasm_functype_new_0_0();
+  wasm_func_delete(wasm_func_new_with_env(store, fail_type, fail_callback, &unused_env, host_env_finalize));
+  check(unused_env.finalized, "the env of a deleted function should be finalized");
+
+  host_env print_env = { store, "", false };
+  own wasm_functype_t* print_type = wasm_functype_new_2_0(wasm_valtype_new_i32(), wasm_valtype_new_i32());
+  own wasm_func_t* print_func =
+    wasmer_func_new_with_caller(store, print_type, print_callback, &print_env, host_env_finalize);
+  wasm_functype_delete(print_type);
+
+  host_env fail_env = { store, "", false };
+  own wasm_func_t* fail_func = wasm_func_new_with_env(store, fail_type, fail_callback, &fail_env, host_env_finalize);
+  wasm_functype_delete(fail_type);
+
+  printf("Instantiating module...\n");
+  wasm_extern_vec_t imports;
+  wasm_extern_vec_new_uninitialized(&imports, 2);
+  imports.data[0] = wasm_func_as_extern(print_func);
+  imports.data[1] = wasm_func_as_extern(fail_func);
+  own wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+  check(instance != NULL, "instantiation failed");
+
+  // The instance keeps the functions it imports alive.
+  wasm_extern_vec_delete(&imports);
+  wasm_func_delete(print_func);
+  wasm_func_delete(fail_func);
+  check(!print_env.finalized && !fail_env.finalized, "the env of an imported function shouldn't be finalized");
+
+  wasm_extern_vec_t exports;
+  wasm_instance_exports(instance, &exports);
+  check(exports.size == 3, "unexpected number of exports");
+  const wasm_func_t* run_func = wasm_extern_as_func(exports.data[1]);
+  const wasm_func_t* fail_export = wasm_extern_as_func(exports.data[2]);
+
+  printf("Calling `run`...\n");
+  wasm_val_vec_t args = WASM_EMPTY_VEC;
+  wasm_val_vec_t results = WASM_EMPTY_VEC;
+  check(wasm_func_call(run_func, &args, &results) == NULL, "calling `run` failed");
+  printf("> %s\n", print_env.message);
+  check(strcmp(print_env.message, "Hello, caller!") == 0, "unexpected message");
+
+  printf("Calling `fail`...\n");
+  own wasm_trap_t* trap = wasm_func_call(fail_export, &args, &results);
+  check(trap != NULL, "calling `fail` should trap");
+  wasm_message_t message;
+  wasm_trap_message(trap, &message);
+  check(message.size == strlen("host failure") && strncmp(message.data, "host failure", message.size) == 0,
+        "unexpected trap message");
+  wasm_byte_vec_delete(&message);
+  wasm_trap_delete(trap);
+
+  printf("Instantiating the module twice...\n");
+  test_two_instances(store, module);
+
+  printf("Shutting down...\n");
+  wasm_extern_vec_delete(&exports);
+  wasm_instance_delete(instance);
+  wasm_module_delete(module);
+  wasm_store_delete(store);
+  wasm_engine_delete(engine);
+
+  printf("Done.\n");
+  return 0;
+}
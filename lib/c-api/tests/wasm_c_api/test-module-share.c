@@ -0,0 +1,130 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <pthread.h>
+
+#include "wasmer_wasm.h"
+
+#define own
+
+#define N_THREADS 8
+#define N_REPS 4
+
+void check(bool success, const char* message) {
+  if (!success) {
+    printf("> Error: %s\n", message);
+    exit(1);
+  }
+}
+
+typedef struct {
+  wasm_engine_t* engine;
+  wasm_shared_module_t* module;
+  int32_t id;
+  bool success;
+} thread_args;
+
+void* run(void* args_abs) {
+  thread_args* args = (thread_args*)args_abs;
+
+  // Each thread has its own store, sharing the engine of the module.
+  own wasm_store_t* store = wasm_store_new(args->engine);
+  own wasm_module_t* module = wasm_module_obtain(store, args->module);
+  if (!module) {
+    wasm_store_delete(store);
+    return NULL;
+  }
+
+  bool success = true;
+
+  for (int i = 0; i < N_REPS && success; ++i) {
+    wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+    own wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+    if (!instance) {
+      success = false;
+      break;
+    }
+
+    wasm_extern_vec_t exports;
+    wasm_instance_exports(instance, &exports);
+    const wasm_func_t* double_it = wasm_extern_as_func(exports.data[0]);
+
+    wasm_val_t args_val[1] = { WASM_I32_VAL(args->id) };
+    wasm_val_t results_val[1] = { WASM_INIT_VAL };
+    wasm_val_vec_t call_args = WASM_ARRAY_VEC(args_val);
+    wasm_val_vec_t call_results = WASM_ARRAY_VEC(results_val);
+
+    own wasm_trap_t* trap = wasm_func_call(double_it, &call_args, &call_results);
+    if (trap) {
+      wasm_trap_delete(trap);
+      success = false;
+    } else if (results_val[0].of.i32 != args->id * 2) {
+      success = false;
+    }
+
+    wasm_extern_vec_delete(&exports);
+    wasm_instance_delete(instance);
+  }
+
+  wasm_module_delete(module);
+  wasm_store_delete(store);
+  args->success = success;
+
+  return NULL;
+}
+
+int main(int argc, const char* argv[]) {
+  printf("Initializing...\n");
+  own wasm_engine_t* engine = wasm_engine_new();
+  own wasm_store_t* store = wasm_store_new(engine);
+
+  printf("Compiling module...\n");
+  wasm_byte_vec_t wat;
+  wasm_byte_vec_new(
+    &wat,
+    strlen("(module (func (export \"double\") (param i32) (result i32) local.get 0 i32.const 2 i32.mul))"),
+    "(module (func (export \"double\") (param i32) (result i32) local.get 0 i32.const 2 i32.mul))");
+  own wasm_byte_vec_t* wasm = wat2wasm(&wat);
+  wasm_byte_vec_delete(&wat);
+  check(wasm != NULL, "wat2wasm failed");
+
+  own wasm_module_t* module = wasm_module_new(store, wasm);
+  wasm_byte_vec_delete(wasm);
+  check(module != NULL, "module compilation failed");
+
+  own wasm_shared_module_t* shared = wasm_module_share(module);
+  wasm_module_delete(module);
+
+  printf("Instantiating from %d threads...\n", N_THREADS);
+  pthread_t threads[N_THREADS];
+  thread_args args[N_THREADS];
+
+  for (int i = 0; i < N_THREADS; ++i) {
+    args[i].engine = engine;
+    args[i].module = shared;
+    args[i].id = i + 1;
+    args[i].success = false;
+    check(pthread_create(&threads[i], NULL, &run, &args[i]) == 0, "thread creation failed");
+  }
+
+  for (int i = 0; i < N_THREADS; ++i) {
+    pthread_join(threads[i], NULL);
+    check(args[i].success, "thread failed");
+  }
+
+  printf("Obtaining from another engine...\n");
+  own wasm_engine_t* other_engine = wasm_engine_new();
+  own wasm_store_t* other_store = wasm_store_new(other_engine);
+  check(wasm_module_obtain(other_store, shared) == NULL,
+        "a module compiled by another engine shouldn't be obtained");
+  wasm_store_delete(other_store);
+  wasm_engine_delete(other_engine);
+
+  printf("Shutting down...\n");
+  wasm_shared_module_delete(shared);
+  wasm_store_delete(store);
+  wasm_engine_delete(engine);
+
+  printf("Done.\n");
+  return 0;
+}
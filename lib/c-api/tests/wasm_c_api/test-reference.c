@@ -0,0 +1,219 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <inttypes.h>
+
+#include "wasmer_wasm.h"
+
+#define own
+
+static int finalized = 0;
+
+void finalizer(void* info) {
+  finalized += *(int*)info;
+}
+
+own wasm_trap_t* seven_callback(const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+  results->data[0].kind = WASM_I32;
+  results->data[0].of.i32 = 7;
+  return NULL;
+}
+
+void check(bool success, const char* message) {
+  if (!success) {
+    printf("> Error: %s\n", message);
+    exit(1);
+  }
+}
+
+int32_t call_indirect(wasm_func_t* call, int32_t index, bool* trapped) {
+  wasm_val_t args_val[1] = { WASM_I32_VAL(index) };
+  wasm_val_t results_val[1] = { WASM_INIT_VAL };
+  wasm_val_vec_t args = WASM_ARRAY_VEC(args_val);
+  wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+  own wasm_trap_t* trap = wasm_func_call(call, &args, &results);
+  *trapped = trap != NULL;
+  if (trap) wasm_trap_delete(trap);
+  return results_val[0].of.i32;
+}
+
+int main(int argc, const char* argv[]) {
+  printf("Initializing...\n");
+  wasm_engine_t* engine = wasm_engine_new();
+  wasm_store_t* store = wasm_store_new(engine);
+
+  printf("Compiling module...\n");
+  const char* wat_string =
+    "(module\n"
+    "  (type $t (func (result i32)))\n"
+    "  (table (export \"table\") 2 funcref)\n"
+    "  (func (export \"f\") (type $t) i32.const 42)\n"
+    "  (func (export \"call\") (param i32) (result i32)\n"
+    "    local.get 0\n"
+    "    call_indirect (type $t)))";
+  wasm_byte_vec_t wat;
+  wasm_byte_vec_new(&wat, strlen(wat_string), wat_string);
+  wasm_byte_vec_t* wasm = wat2wasm(&wat);
+  wasm_byte_vec_delete(&wat);
+  check(wasm != NULL, "wat2wasm failed");
+
+  own wasm_module_t* module = wasm_module_new(store, wasm);
+  wasm_byte_vec_delete(wasm);
+  check(module != NULL, "module compilation failed");
+
+  wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+  own wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+  check(instance != NULL, "instantiation failed");
+
+  wasm_extern_vec_t exports;
+  wasm_instance_exports(instance, &exports);
+  check(exports.size == 3, "unexpected number of exports");
+  wasm_table_t* table = wasm_extern_as_table(exports.data[0]);
+  wasm_func_t* f = wasm_extern_as_func(exports.data[1]);
+  wasm_func_t* call = wasm_extern_as_func(exports.data[2]);
+
+  printf("Setting the instance table...\n");
+  bool trapped;
+  check(wasm_table_get(table, 0) == NULL, "table elements start as null");
+  call_indirect(call, 0, &trapped);
+  check(trapped, "calling a null element should trap");
+  check(wasm_table_set(table, 0, wasm_func_as_ref(f)), "table set failed");
+  check(call_indirect(call, 0, &trapped) == 42 && !trapped, "unexpected call_indirect result");
+  check(!wasm_table_set(table, 2, NULL), "out of bounds table set should fail");
+
+  own wasm_ref_t* element = wasm_table_get(table, 0);
+  check(element != NULL, "table get failed");
+  check(wasm_ref_same(element, wasm_func_as_ref(f)), "table element is not the function set");
+  check(wasm_ref_as_func(element) != NULL, "funcref is not a function");
+  check(wasm_ref_as_foreign(element) == NULL, "funcref is a foreign object");
+  wasm_ref_delete(element);
+
+  printf("Creating a stand-alone table...\n");
+  own wasm_functype_t* seven_type = wasm_functype_new_0_1(wasm_valtype_new_i32());
+  own wasm_func_t* seven = wasm_func_new(store, seven_type, seven_callback);
+  wasm_functype_delete(seven_type);
+
+  wasm_limits_t limits = { 2, 4 };
+  own wasm_tabletype_t* table_type = wasm_tabletype_new(wasm_valtype_new_funcref(), &limits);
+  own wasm_table_t* host_table = wasm_table_new(store, table_type, wasm_func_as_ref(seven));
+  wasm_tabletype_delete(table_type);
+  check(host_table != NULL, "table creation failed");
+  check(wasm_table_size(host_table) == 2, "unexpected table size");
+
+  element = wasm_table_get(host_table, 1);
+  check(wasm_ref_same(element, wasm_func_as_ref(seven)), "table not initialized");
+  wasm_ref_delete(element);
+
+  check(wasm_table_grow(host_table, 2, NULL), "table grow failed");
+  check(wasm_table_size(host_table) == 4, "unexpected grown table size");
+  check(wasm_table_get(host_table, 3) == NULL, "grown elements should be null");
+  check(!wasm_table_grow(host_table, 1, NULL), "table grow beyond maximum should fail");
+  check(wasm_table_get(host_table, 4) == NULL, "out of bounds table get should be null");
+
+  printf("Copying references in values...\n");
+  wasm_val_t val = WASM_REF_VAL(wasm_ref_copy(wasm_func_as_ref(f)));
+  val.kind = WASM_FUNCREF;
+  wasm_val_t copy;
+  wasm_val_copy(&copy, &val);
+  check(copy.of.ref != val.of.ref, "references should be deep copied");
+  check(wasm_ref_same(copy.of.ref, val.of.ref), "copied reference differs");
+  wasm_val_delete(&val);
+  wasm_val_delete(&copy);
+
+  printf("Creating foreign objects...\n");
+  int info = 1;
+  own wasm_foreign_t* foreign = wasm_foreign_new(store);
+  wasm_foreign_set_host_info_with_finalizer(foreign, &info, finalizer);
+  own wasm_foreign_t* foreign_copy = wasm_foreign_copy(foreign);
+  check(wasm_foreign_same(foreign, foreign_copy), "foreign copy is not the same object");
+  check(wasm_foreign_get_host_info(foreign_copy) == &info, "host info not shared");
+
+  own wasm_ref_t* foreign_ref = wasm_ref_copy(wasm_foreign_as_ref(foreign));
+  check(wasm_ref_as_func(foreign_ref) == NULL, "foreign object is a function");
+  check(wasm_foreign_same(wasm_ref_as_foreign(foreign_ref), foreign), "foreign ref differs");
+  check(!wasm_ref_same(foreign_ref, wasm_func_as_ref(f)), "foreign ref is the same as a funcref");
+
+  wasm_foreign_delete(foreign);
+  wasm_foreign_delete(foreign_copy);
+  check(finalized == 0, "host info finalized while still referenced");
+  wasm_ref_delete(foreign_ref);
+  check(finalized == 1, "host info not finalized");
+
+  printf("Attaching host info to objects...\n");
+  int func_info = 0;
+  wasm_func_set_host_info(f, &func_info);
+  element = wasm_table_get(table, 0);
+  check(wasm_ref_get_host_info(element) == &func_info, "host info not shared by table elements");
+  check(wasm_extern_get_host_info(exports.data[1]) == &func_info, "host info not shared by externs");
+  wasm_ref_delete(element);
+
+  own wasm_globaltype_t* global_type = wasm_globaltype_new(wasm_valtype_new_i32(), WASM_CONST);
+  wasm_val_t zero = WASM_I32_VAL(0);
+  own wasm_global_t* global = wasm_global_new(store, global_type, &zero);
+  wasm_globaltype_delete(global_type);
+  int global_info = 2;
+  wasm_global_set_host_info_with_finalizer(global, &global_info, finalizer);
+  own wasm_global_t* global_copy = wasm_global_copy(global);
+  check(wasm_global_get_host_info(global_copy) == &global_info, "host info not shared by copies");
+  own wasm_extern_t* global_extern = wasm_global_as_extern(global_copy);
+  check(wasm_extern_get_host_info(global_extern) == &global_info, "host info not shared by externs");
+
+  own wasm_ref_t* global_ref = wasm_ref_copy(wasm_global_as_ref(global));
+  check(wasm_ref_get_host_info(global_ref) == &global_info, "host info not shared by references");
+  check(wasm_ref_same(global_ref, wasm_extern_as_ref(global_extern)), "global ref differs");
+  check(wasm_global_same(wasm_ref_as_global(global_ref), global), "global ref is not the global");
+  check(wasm_extern_same(wasm_ref_as_extern(global_ref), global_extern), "global ref is not the extern");
+  check(wasm_ref_as_func(global_ref) == NULL, "global ref is a function");
+  check(wasm_ref_as_table(global_ref) == NULL, "global ref is a table");
+
+  wasm_global_delete(global);
+  wasm_global_delete(global_copy);
+  wasm_extern_delete(global_extern);
+  check(finalized == 1, "host info finalized while still referenced");
+  wasm_ref_delete(global_ref);
+  check(finalized == 3, "host info not finalized");
+
+  int instance_info = 4;
+  wasm_instance_set_host_info_with_finalizer(instance, &instance_info, finalizer);
+  own wasm_instance_t* instance_copy = wasm_instance_copy(instance);
+  check(wasm_instance_same(instance, instance_copy), "instance copy is not the same object");
+  check(wasm_instance_get_host_info(instance_copy) == &instance_info, "host info not shared by copies");
+  const wasm_ref_t* instance_ref = wasm_instance_as_ref_const(instance_copy);
+  check(wasm_instance_same(wasm_ref_as_instance_const(instance_ref), instance), "instance ref differs");
+  check(wasm_ref_as_module_const(instance_ref) == NULL, "instance ref is a module");
+  wasm_instance_delete(instance_copy);
+
+  int module_info = 8;
+  wasm_module_set_host_info(module, &module_info);
+  own wasm_module_t* module_copy = wasm_module_copy(module);
+  check(wasm_module_same(module, module_copy), "module copy is not the same object");
+  check(wasm_ref_get_host_info(wasm_module_as_ref(module_copy)) == &module_info, "host info not shared by references");
+  wasm_module_delete(module_copy);
+
+  wasm_name_t message;
+  wasm_name_new_from_string_nt(&message, "oops");
+  own wasm_trap_t* trap = wasm_trap_new(store, &message);
+  wasm_name_delete(&message);
+  int trap_info = 16;
+  wasm_trap_set_host_info_with_finalizer(trap, &trap_info, finalizer);
+  own wasm_trap_t* trap_copy = wasm_trap_copy(trap);
+  check(wasm_trap_same(trap, trap_copy), "trap copy is not the same object");
+  check(wasm_trap_get_host_info(trap_copy) == &trap_info, "host info not shared by copies");
+  check(wasm_trap_same(wasm_ref_as_trap(wasm_trap_as_ref(trap_copy)), trap), "trap ref differs");
+  wasm_trap_delete(trap);
+  wasm_trap_delete(trap_copy);
+  check(finalized == 19, "host info not finalized");
+
+  printf("Shutting down...\n");
+  wasm_table_delete(host_table);
+  wasm_func_delete(seven);
+  wasm_extern_vec_delete(&exports);
+  wasm_instance_delete(instance);
+  check(finalized == 23, "host info not finalized");
+  wasm_module_delete(module);
+  wasm_store_delete(store);
+  wasm_engine_delete(engine);
+
+  printf("Done.\n");
+  return 0;
+}
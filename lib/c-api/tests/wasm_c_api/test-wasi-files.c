@@ -0,0 +1,285 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <inttypes.h>
+
+#include "wasmer_wasm.h"
+
+#define BUF_SIZE 128
+#define own
+
+void check(bool success, const char* message) {
+  if (!success) {
+    printf("> Error: %s\n", message);
+    exit(1);
+  }
+}
+
+// A read-only in-memory file, read and sought by the callbacks below.
+typedef struct {
+  const char* data;
+  size_t size;
+  size_t position;
+  bool finalized;
+} memory_file;
+
+intptr_t memory_file_read(void* env, char* buffer, uintptr_t buffer_len) {
+  memory_file* file = (memory_file*) env;
+  size_t remaining = file->size - file->position;
+  size_t num_bytes = buffer_len < remaining ? buffer_len : remaining;
+
+  memcpy(buffer, file->data + file->position, num_bytes);
+  file->position += num_bytes;
+
+  return num_bytes;
+}
+
+int64_t memory_file_seek(void* env, int64_t offset, wasi_seek_whence_t whence) {
+  memory_file* file = (memory_file*) env;
+  int64_t base = 0;
+
+  switch (whence) {
+    case SeekStart:
+      base = 0;
+      break;
+    case SeekCurrent:
+      base = file->position;
+      break;
+    case SeekEnd:
+      base = file->size;
+      break;
+  }
+
+  if (base + offset < 0 || base + offset > (int64_t) file->size) {
+    return -1;
+  }
+
+  file->position = base + offset;
+
+  return file->position;
+}
+
+void memory_file_finalize(void* env) {
+  ((memory_file*) env)->finalized = true;
+}
+
+// Polls stdin for reading and for writing, and stores the events from
+// offset 96 and their number at offset 160.
+const char* poll_wat =
+  "(module"
+  "  (import \"wasi_snapshot_preview1\" \"poll_oneoff\""
+  "    (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))"
+  "  (memory (export \"memory\") 1)"
+  "  (data (i32.const 8) \"\\01\")"
+  "  (data (i32.const 56) \"\\02\")"
+  "  (func (export \"poll\") (result i32)"
+  "    (call $poll_oneoff (i32.const 0) (i32.const 96) (i32.const 2) (i32.const 160))))";
+
+// Instantiates `wat` with `stdin_stream` as standard input, and calls its
+// exported function returning an `i32`. The first `data_len` bytes of
+// its memory are copied into `data` afterwards.
+int32_t call_with_stdin(wasm_engine_t* engine, const char* wat_string, wasi_file_t* stdin_stream, byte_t* data, size_t data_len) {
+  wasm_store_t* store = wasm_store_new(engine);
+
+  wasm_byte_vec_t wat;
+  wasm_byte_vec_new(&wat, strlen(wat_string), wat_string);
+  own wasm_byte_vec_t* binary = wat2wasm(&wat);
+  wasm_byte_vec_delete(&wat);
+  check(binary != NULL, "wat2wasm failed");
+  own wasm_module_t* module = wasm_module_new(store, binary);
+  wasm_byte_vec_delete(binary);
+  check(module != NULL, "compiling the module failed");
+
+  wasi_config_t* config = wasi_config_new("example_program");
+  wasi_config_stdin(config, stdin_stream);
+  wasi_env_t* wasi_env = wasi_env_new(config);
+  check(wasi_env != NULL, "building the WASI env failed");
+
+  wasm_importtype_vec_t import_types;
+  wasm_module_imports(module, &import_types);
+  wasm_extern_vec_t imports;
+  wasm_extern_vec_new_uninitialized(&imports, import_types.size);
+  wasm_importtype_vec_delete(&import_types);
+  check(wasi_get_imports(store, module, wasi_env, &imports), "getting the WASI imports failed");
+
+  own wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+  check(instance != NULL, "instantiating the module failed");
+  wasi_env_set_instance(wasi_env, instance);
+
+  wasm_extern_vec_t exports;
+  wasm_instance_exports(instance, &exports);
+  wasm_memory_t* memory = wasm_extern_as_memory(exports.data[0]);
+  const wasm_func_t* func = wasm_extern_as_func(exports.data[1]);
+
+  wasm_val_t results_val[1] = { WASM_INIT_VAL };
+  wasm_val_vec_t args = WASM_EMPTY_VEC;
+  wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+  check(wasm_func_call(func, &args, &results) == NULL, "calling the function failed");
+  memcpy(data, wasm_memory_data(memory), data_len);
+
+  wasm_extern_vec_delete(&exports);
+  wasm_extern_vec_delete(&imports);
+  wasm_instance_delete(instance);
+  wasm_module_delete(module);
+  wasi_env_delete(wasi_env);
+  wasm_store_delete(store);
+
+  return results_val[0].of.i32;
+}
+
+void test_poll_stream(wasm_engine_t* engine) {
+  memory_file stdin_file = { "Hello", 5, 0, false };
+  wasi_file_t* stream = wasi_file_new(&stdin_file, memory_file_read, NULL, NULL, memory_file_finalize);
+  byte_t data[164];
+  check(call_with_stdin(engine, poll_wat, stream, data, sizeof(data)) == 0, "poll_oneoff failed");
+
+  uint16_t read_error, write_error;
+  uint64_t read_bytes;
+  memcpy(&read_error, data + 96 + 8, sizeof(read_error));
+  memcpy(&read_bytes, data + 96 + 16, sizeof(read_bytes));
+  memcpy(&write_error, data + 128 + 8, sizeof(write_error));
+  check(*(uint32_t*) (data + 160) == 2, "unexpected number of events");
+  check(read_error == 0, "a stream with a read callback should be readable");
+  check(read_bytes == 1024, "a stream should report 1024 bytes available");
+  check(write_error == 6, "a stream without a write callback shouldn't be writable");
+}
+
+// A read callback claiming to read more bytes than the buffer holds.
+intptr_t over_reporting_read(void* env, char* buffer, uintptr_t buffer_len) {
+  memset(buffer, 'x', buffer_len);
+  return buffer_len + 1;
+}
+
+// Reads 4 bytes of stdin at offset 16, and stores the number of bytes
+// read at offset 8.
+const char* read_wat =
+  "(module"
+  "  (import \"wasi_snapshot_preview1\" \"fd_read\""
+  "    (func $fd_read (param i32 i32 i32 i32) (result i32)))"
+  "  (memory (export \"memory\") 1)"
+  "  (data (i32.const 0) \"\\10\\00\\00\\00\\04\")"
+  "  (func (export \"read\") (result i32)"
+  "    (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8))))";
+
+void test_over_reporting_read(wasm_engine_t* engine) {
+  wasi_file_t* stream = wasi_file_new(NULL, over_reporting_read, NULL, NULL, NULL);
+  byte_t data[20];
+  check(call_with_stdin(engine, read_wat, stream, data, sizeof(data)) != 0, "reading more bytes than requested should fail");
+}
+
+int main(int argc, const char* argv[]) {
+  printf("Initializing...\n");
+  wasm_engine_t* engine = wasm_engine_new();
+  wasm_store_t* store = wasm_store_new(engine);
+
+  printf("Loading binary...\n");
+  FILE* file = fopen("assets/qjs.wasm", "r");
+  check(file != NULL, "loading the module failed");
+  fseek(file, 0L, SEEK_END);
+  size_t file_size = ftell(file);
+  fseek(file, 0L, SEEK_SET);
+  wasm_byte_vec_t binary;
+  wasm_byte_vec_new_uninitialized(&binary, file_size);
+  check(fread(binary.data, file_size, 1, file) == 1, "loading the module failed");
+  fclose(file);
+
+  printf("Compiling module...\n");
+  own wasm_module_t* module = wasm_module_new(store, &binary);
+  wasm_byte_vec_delete(&binary);
+  check(module != NULL, "compiling the module failed");
+
+  printf("Setting up WASI...\n");
+  memory_file stdin_file = { "Hello", 5, 0, false };
+  memory_file greeting_file = { "virtual", 7, 0, false };
+
+  wasi_config_t* config = wasi_config_new("example_program");
+  wasi_config_inherit_stdout(config);
+  wasi_config_stdin(config, wasi_file_new(&stdin_file, memory_file_read, NULL, NULL, memory_file_finalize));
+  check(
+    wasi_config_virtual_file(
+      config,
+      "/data/greeting.txt",
+      wasi_file_new(&greeting_file, memory_file_read, NULL, memory_file_seek, memory_file_finalize)),
+    "adding a virtual file failed");
+
+  wasi_env_t* wasi_env = wasi_env_new(config);
+  check(wasi_env != NULL, "building the WASI env failed");
+
+  const char* js_string =
+    "var file = std.open('/data/greeting.txt', 'r');"
+    "file.seek(0, std.SEEK_END);"
+    "var size = file.tell();"
+    "file.close();"
+    "file = std.open('/data/greeting.txt', 'r');"
+    "file.seek(0, std.SEEK_SET);"
+    "print(std.in.readAsString() + ', ' + file.readAsString() + ' ' + std.getenv('NAME') + '! (' + size + ')');"
+    "std.exit(3);";
+  wasi_env_push_arg(wasi_env, "--std");
+  wasi_env_push_arg(wasi_env, "--eval");
+  wasi_env_push_arg(wasi_env, js_string);
+  check(wasi_env_set_env(wasi_env, "NAME", "someone"), "setting an env var failed");
+  check(wasi_env_set_env(wasi_env, "NAME", "world"), "replacing an env var failed");
+  check(!wasi_env_set_env(wasi_env, "NA=ME", "world"), "an invalid env var key was accepted");
+
+  printf("Instantiating module...\n");
+  wasm_importtype_vec_t import_types;
+  wasm_module_imports(module, &import_types);
+  wasm_extern_vec_t imports;
+  wasm_extern_vec_new_uninitialized(&imports, import_types.size);
+  wasm_importtype_vec_delete(&import_types);
+
+  check(wasi_get_imports(store, module, wasi_env, &imports), "getting the WASI imports failed");
+
+  own wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+  check(instance != NULL, "instantiating the module failed");
+
+  wasi_env_set_instance(wasi_env, instance);
+  wasm_func_t* run_func = wasi_get_start_function(instance);
+  check(run_func != NULL, "getting the start function failed");
+
+  printf("Calling export...\n");
+  wasm_val_vec_t args = WASM_EMPTY_VEC;
+  wasm_val_vec_t res = WASM_EMPTY_VEC;
+  own wasm_trap_t* trap = wasm_func_call(run_func, &args, &res);
+  check(trap != NULL, "the program should exit with proc_exit");
+
+  uint32_t exit_code = 0;
+  check(wasi_trap_exit_code(trap, &exit_code), "the trap should come from proc_exit");
+  check(exit_code == 3, "unexpected exit code");
+  wasm_trap_delete(trap);
+
+  char buffer[BUF_SIZE] = { 0 };
+  intptr_t num_bytes = wasi_env_read_stdout(wasi_env, buffer, BUF_SIZE - 1);
+  check(num_bytes > 0, "reading stdout failed");
+  printf("%s", buffer);
+  check(strcmp(buffer, "Hello, virtual world! (7)\n") == 0, "unexpected output");
+
+  printf("Shutting down...\n");
+  wasm_func_delete(run_func);
+  wasm_extern_vec_delete(&imports);
+  wasm_instance_delete(instance);
+  wasm_module_delete(module);
+  wasi_env_delete(wasi_env);
+  wasm_store_delete(store);
+
+  printf("Polling a stream...\n");
+  test_poll_stream(engine);
+
+  printf("Reading from a callback reporting too many bytes...\n");
+  test_over_reporting_read(engine);
+  wasm_engine_delete(engine);
+
+  printf("Finalizing files...\n");
+  memory_file unused_file = { "", 0, 0, false };
+  wasi_file_delete(wasi_file_new(&unused_file, memory_file_read, NULL, NULL, memory_file_finalize));
+  check(unused_file.finalized, "a deleted file should be finalized");
+
+  memory_file env_file = { "", 0, 0, false };
+  config = wasi_config_new("example_program");
+  wasi_config_stdin(config, wasi_file_new(&env_file, memory_file_read, NULL, NULL, memory_file_finalize));
+  wasi_env_delete(wasi_env_new(config));
+  check(env_file.finalized, "the files of a deleted env should be finalized");
+
+  printf("Done.\n");
+  return 0;
+}
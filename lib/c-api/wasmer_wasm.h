@@ -1,6 +1,11 @@
 // The Wasmer C/C++ header file compatible with the `wasm-c-api` standard API.
 // This file is generated by lib/c-api/build.rs.
 
+// The environments given to `wasm_func_new_with_env` and
+// `wasmer_func_new_with_caller` must be usable from any thread: the
+// callbacks are called on the threads calling the functions, and the
+// finalizers on the thread releasing the functions last.
+
 #if !defined(WASMER_WASM_H_MACROS)
 
 #define WASMER_WASM_H_MACROS
@@ -55,6 +60,24 @@
 #include <stdlib.h>
 #include "wasm.h"
 
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * The position an offset given to a `wasi_file_seek_callback_t` is
+ * relative to.
+ */
+typedef enum {
+#if defined(WASMER_WASI_ENABLED)
+  SeekStart = 0,
+#endif
+#if defined(WASMER_WASI_ENABLED)
+  SeekCurrent = 1,
+#endif
+#if defined(WASMER_WASI_ENABLED)
+  SeekEnd = 2,
+#endif
+} wasi_seek_whence_t;
+#endif
+
 /**
  * this can be a wasmer-specific type with wasmer-specific functions for manipulating it
  */
@@ -70,6 +93,71 @@ typedef enum {
   OBJECT_FILE = 2,
 } wasmer_engine_t;
 
+/**
+ * The category of an operator of the WebAssembly code.
+ *
+ * It is given to the cost function of a `wasmer_metering_t`.
+ */
+typedef enum {
+  /**
+   * The structured control and branch operators, such as `block`,
+   * `br_if` or `return`.
+   */
+  CONTROL = 0,
+  /**
+   * The direct and indirect calls.
+   */
+  CALL = 1,
+  /**
+   * The parametric operators, `drop` and `select`.
+   */
+  PARAMETRIC = 2,
+  /**
+   * The accesses to locals and globals.
+   */
+  VARIABLE = 3,
+  /**
+   * The loads and stores of scalars, and the operators managing the
+   * memories.
+   */
+  MEMORY = 4,
+  /**
+   * The operators accessing and managing the tables.
+   */
+  TABLE = 5,
+  /**
+   * The constants, including the vector ones.
+   */
+  CONST = 6,
+  /**
+   * The operators creating and testing references.
+   */
+  REFERENCE = 7,
+  /**
+   * The arithmetic, comparison and conversion operators on scalars.
+   */
+  NUMERIC = 8,
+  /**
+   * The atomic accesses to memories, and the fences.
+   */
+  ATOMIC = 9,
+  /**
+   * The operators on vectors, including their loads and stores.
+   */
+  SIMD = 10,
+} wasmer_operator_category_t;
+
+/**
+ * The optimization level of the compilers.
+ *
+ * Singlepass doesn't optimize, and ignores it.
+ */
+typedef enum {
+  NONE = 0,
+  SPEED = 1,
+  SPEED_AND_SIZE = 2,
+} wasmer_opt_level_t;
+
 #if defined(WASMER_WASI_ENABLED)
 typedef struct wasi_config_t wasi_config_t;
 #endif
@@ -78,10 +166,125 @@ typedef struct wasi_config_t wasi_config_t;
 typedef struct wasi_env_t wasi_env_t;
 #endif
 
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * A file implemented by C callbacks.
+ *
+ * A null callback makes the corresponding operation fail. The
+ * callbacks may be called from any thread, and `finalizer`, if not
+ * null, is called with `env` once the file is dropped.
+ *
+ * When polled, a file is ready to be read if it has a `read` callback
+ * and to be written if it has a `write` callback. A file without a
+ * `seek` callback is a stream, which always reports 1024 bytes
+ * available.
+ */
+typedef struct wasi_file_t wasi_file_t;
+#endif
+
 #if defined(WASMER_WASI_ENABLED)
 typedef struct wasi_version_t wasi_version_t;
 #endif
 
+/**
+ * The instance calling a host function created with
+ * `wasmer_func_new_with_caller`, giving access to its exports.
+ *
+ * The function is created anew for each instance importing it, with
+ * its own `wasmer_caller_t` bound to that instance, so several live
+ * instances can import it. When the function is called directly, or
+ * by the start function of the instance, the caller has no exports.
+ */
+typedef struct wasmer_caller_t wasmer_caller_t;
+
+/**
+ * A set of CPU features, e.g. `sse4.2` or `avx`.
+ */
+typedef struct wasmer_cpu_features_t wasmer_cpu_features_t;
+
+/**
+ * The set of enabled WebAssembly features, starting with the
+ * defaults: bulk memory and multi-value are enabled, threads,
+ * reference types and SIMD are not.
+ */
+typedef struct wasmer_features_t wasmer_features_t;
+
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * A metering middleware, charging the cost of each executed
+ * operator to the instance until its points are exhausted.
+ */
+typedef struct wasmer_metering_t wasmer_metering_t;
+#endif
+
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * An opaque middleware, to be pushed onto the middleware chain of a
+ * `wasm_config_t` with `wasm_config_push_middleware`.
+ *
+ * Middlewares are created from concrete middlewares, for example
+ * with `wasmer_metering_as_middleware`.
+ */
+typedef struct wasmer_middleware_t wasmer_middleware_t;
+#endif
+
+/**
+ * A compilation target: a triple and the CPU features it supports.
+ */
+typedef struct wasmer_target_t wasmer_target_t;
+
+/**
+ * A target “triple”, e.g. `x86_64-unknown-linux-gnu`.
+ */
+typedef struct wasmer_triple_t wasmer_triple_t;
+
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Reads at most `buffer_len` bytes in `buffer`, returning the number
+ * of bytes read, 0 at the end of the file, or -1 on error.
+ */
+typedef intptr_t (*wasi_file_read_callback_t)(void *env, char *buffer, uintptr_t buffer_len);
+#endif
+
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Writes at most `buffer_len` bytes from `buffer`, returning the
+ * number of bytes written, or -1 on error.
+ */
+typedef intptr_t (*wasi_file_write_callback_t)(void *env, const char *buffer, uintptr_t buffer_len);
+#endif
+
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Moves the cursor of the file, returning its new position from the
+ * start of the file, or -1 on error.
+ */
+typedef int64_t (*wasi_file_seek_callback_t)(void *env, int64_t offset, wasi_seek_whence_t whence);
+#endif
+
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Releases the environment of the callbacks once the file is
+ * dropped.
+ */
+typedef void (*wasi_file_finalizer_t)(void *env);
+#endif
+
+/**
+ * The callback of a host function created with
+ * `wasmer_func_new_with_caller`, receiving the instance calling it
+ * along with its environment.
+ */
+typedef wasm_trap_t *(*wasmer_func_callback_with_caller_t)(const wasmer_caller_t *caller, void *env, const wasm_val_vec_t *args, wasm_val_vec_t *results);
+
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * The cost function of a `wasmer_metering_t`, returning the points
+ * an operator of the given category costs.
+ */
+typedef uint64_t (*wasmer_metering_cost_function_t)(wasmer_operator_category_t operator_category);
+#endif
+
 #if defined(WASMER_WASI_ENABLED)
 void wasi_config_arg(wasi_config_t *config, const char *arg);
 #endif
@@ -114,6 +317,24 @@ wasi_config_t *wasi_config_new(const char *program_name);
 bool wasi_config_preopen_dir(wasi_config_t *config, const char *dir);
 #endif
 
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Uses `file` as the stdin of the program, taking ownership of it.
+ */
+void wasi_config_stdin(wasi_config_t *config, wasi_file_t *file);
+#endif
+
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Adds `file` to the filesystem of the program at `path`, taking
+ * ownership of it.
+ *
+ * The path is relative to the root, and the missing parent
+ * directories are created in memory.
+ */
+bool wasi_config_virtual_file(wasi_config_t *config, const char *path, wasi_file_t *file);
+#endif
+
 #if defined(WASMER_WASI_ENABLED)
 void wasi_env_delete(wasi_env_t *_state);
 #endif
@@ -125,6 +346,16 @@ void wasi_env_delete(wasi_env_t *_state);
 wasi_env_t *wasi_env_new(wasi_config_t *config);
 #endif
 
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Appends `arg` to the arguments of the program.
+ *
+ * The program reads its arguments when it starts, so this must be
+ * called before running it.
+ */
+void wasi_env_push_arg(wasi_env_t *env, const char *arg);
+#endif
+
 #if defined(WASMER_WASI_ENABLED)
 intptr_t wasi_env_read_stderr(wasi_env_t *env, char *buffer, uintptr_t buffer_len);
 #endif
@@ -133,6 +364,17 @@ intptr_t wasi_env_read_stderr(wasi_env_t *env, char *buffer, uintptr_t buffer_le
 intptr_t wasi_env_read_stdout(wasi_env_t *env, char *buffer, uintptr_t buffer_len);
 #endif
 
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Sets the environment variable `key` to `value`, replacing its
+ * previous value if any.
+ *
+ * The program reads its environment when it starts, so this must be
+ * called before running it.
+ */
+bool wasi_env_set_env(wasi_env_t *env, const char *key, const char *value);
+#endif
+
 #if defined(WASMER_WASI_ENABLED)
 bool wasi_env_set_instance(wasi_env_t *env, const wasm_instance_t *instance);
 #endif
@@ -141,6 +383,18 @@ bool wasi_env_set_instance(wasi_env_t *env, const wasm_instance_t *instance);
 void wasi_env_set_memory(wasi_env_t *env, const wasm_memory_t *memory);
 #endif
 
+#if defined(WASMER_WASI_ENABLED)
+void wasi_file_delete(wasi_file_t *_file);
+#endif
+
+#if defined(WASMER_WASI_ENABLED)
+wasi_file_t *wasi_file_new(void *env,
+                           wasi_file_read_callback_t read,
+                           wasi_file_write_callback_t write,
+                           wasi_file_seek_callback_t seek,
+                           wasi_file_finalizer_t finalizer);
+#endif
+
 #if defined(WASMER_WASI_ENABLED)
 /**
  * Takes ownership of `wasi_env_t`.
@@ -159,14 +413,121 @@ wasm_func_t *wasi_get_start_function(wasm_instance_t *instance);
 wasi_version_t wasi_get_wasi_version(const wasm_module_t *module);
 #endif
 
+#if defined(WASMER_WASI_ENABLED)
+/**
+ * Gets the exit code given to `proc_exit` if `trap` was raised by
+ * it, returning false otherwise.
+ *
+ * A program which returns from its start function without calling
+ * `proc_exit` exits with the code 0.
+ */
+bool wasi_trap_exit_code(const wasm_trap_t *trap, uint32_t *exit_code);
+#endif
+
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * Pushes a middleware onto the back of the middleware chain of the
+ * compiler, taking ownership of it.
+ */
+void wasm_config_push_middleware(wasm_config_t *config, wasmer_middleware_t *middleware);
+#endif
+
 void wasm_config_set_compiler(wasm_config_t *config, wasmer_compiler_t compiler);
 
 void wasm_config_set_engine(wasm_config_t *config, wasmer_engine_t engine);
 
+/**
+ * Sets the WebAssembly features the modules may use, taking
+ * ownership of them.
+ */
+void wasm_config_set_features(wasm_config_t *config, wasmer_features_t *features);
+
+/**
+ * Sets the optimization level of the compiler.
+ */
+void wasm_config_set_opt_level(wasm_config_t *config, wasmer_opt_level_t opt_level);
+
+/**
+ * Sets the target the modules are compiled for, taking ownership of
+ * it. By default, the modules are compiled for the host.
+ */
+void wasm_config_set_target(wasm_config_t *config, wasmer_target_t *target);
+
 void wasm_module_name(const wasm_module_t *module, wasm_name_t *out);
 
 bool wasm_module_set_name(wasm_module_t *module, const wasm_name_t *name);
 
+/**
+ * Gets the export named `name` of the instance calling a host
+ * function, or null if there's none.
+ */
+wasm_extern_t *wasmer_caller_export(const wasmer_caller_t *caller, const wasm_name_t *name);
+
+/**
+ * Gets the first memory exported by the instance calling a host
+ * function, or null if there's none.
+ */
+wasm_memory_t *wasmer_caller_memory(const wasmer_caller_t *caller);
+
+/**
+ * Adds a CPU feature by name, e.g. `sse4.2`, returning false if the
+ * feature is unknown.
+ */
+bool wasmer_cpu_features_add(wasmer_cpu_features_t *cpu_features, const wasm_name_t *feature);
+
+void wasmer_cpu_features_delete(wasmer_cpu_features_t *_cpu_features);
+
+/**
+ * Creates an empty set of CPU features.
+ */
+wasmer_cpu_features_t *wasmer_cpu_features_new(void);
+
+/**
+ * Configures whether the bulk memory proposal is enabled.
+ *
+ * Disabling it also disables reference types.
+ */
+bool wasmer_features_bulk_memory(wasmer_features_t *features, bool enable);
+
+void wasmer_features_delete(wasmer_features_t *_features);
+
+/**
+ * Configures whether the multi-value proposal is enabled.
+ */
+bool wasmer_features_multi_value(wasmer_features_t *features, bool enable);
+
+wasmer_features_t *wasmer_features_new(void);
+
+/**
+ * Configures whether the reference types proposal is enabled.
+ *
+ * Enabling it also enables bulk memory.
+ */
+bool wasmer_features_reference_types(wasmer_features_t *features, bool enable);
+
+/**
+ * Configures whether the SIMD proposal is enabled.
+ */
+bool wasmer_features_simd(wasmer_features_t *features, bool enable);
+
+/**
+ * Configures whether the threads proposal is enabled.
+ */
+bool wasmer_features_threads(wasmer_features_t *features, bool enable);
+
+/**
+ * Creates a host function like `wasm_func_new_with_env`, whose
+ * callback also receives the instance calling it.
+ *
+ * Like with `wasm_func_new_with_env`, `env` must be usable from any
+ * thread.
+ */
+wasm_func_t *wasmer_func_new_with_caller(const wasm_store_t *store,
+                                         const wasm_functype_t *ft,
+                                         wasmer_func_callback_with_caller_t callback,
+                                         void *env,
+                                         void (*finalizer)(void*));
+
 /**
  * Gets the length in bytes of the last error if any.
  *
@@ -209,6 +570,84 @@ int wasmer_last_error_length(void);
  */
 int wasmer_last_error_message(char *buffer, int length);
 
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * Transforms a `wasmer_metering_t` into a generic
+ * `wasmer_middleware_t`, taking ownership of the metering.
+ */
+wasmer_middleware_t *wasmer_metering_as_middleware(wasmer_metering_t *metering);
+#endif
+
+#if defined(WASMER_COMPILER_ENABLED)
+void wasmer_metering_delete(wasmer_metering_t *_metering);
+#endif
+
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * Writes the points left to the instance in `remaining_points`, or 0
+ * if they are exhausted.
+ *
+ * Returns false if the instance wasn't compiled with a metering
+ * middleware, in which case the error can be read with
+ * `wasmer_last_error_message`.
+ */
+bool wasmer_metering_get_remaining_points(const wasm_instance_t *instance,
+                                          uint64_t *remaining_points);
+#endif
+
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * Creates a metering middleware granting `initial_limit` points to
+ * the instances, and charging `cost_function` for each operator.
+ */
+wasmer_metering_t *wasmer_metering_new(uint64_t initial_limit,
+                                       wasmer_metering_cost_function_t cost_function);
+#endif
+
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * Returns whether the instance was trapped because it ran out of
+ * points.
+ *
+ * Returns false if the instance wasn't compiled with a metering
+ * middleware, in which case the error can be read with
+ * `wasmer_last_error_message`.
+ */
+bool wasmer_metering_points_are_exhausted(const wasm_instance_t *instance);
+#endif
+
+#if defined(WASMER_COMPILER_ENABLED)
+/**
+ * Sets the points left to the instance, which makes an exhausted
+ * instance runnable again.
+ *
+ * Returns false if the instance wasn't compiled with a metering
+ * middleware, in which case the error can be read with
+ * `wasmer_last_error_message`.
+ */
+bool wasmer_metering_set_remaining_points(const wasm_instance_t *instance, uint64_t new_limit);
+#endif
+
+void wasmer_target_delete(wasmer_target_t *_target);
+
+/**
+ * Creates a target, taking ownership of the triple and the CPU
+ * features.
+ */
+wasmer_target_t *wasmer_target_new(wasmer_triple_t *triple, wasmer_cpu_features_t *cpu_features);
+
+void wasmer_triple_delete(wasmer_triple_t *_triple);
+
+/**
+ * Parses a target triple, returning null if it's invalid.
+ */
+wasmer_triple_t *wasmer_triple_new(const wasm_name_t *triple);
+
+/**
+ * Creates the triple of the host.
+ */
+wasmer_triple_t *wasmer_triple_new_from_host(void);
+
 /**
  * Parses in-memory bytes as either the WAT format, or a binary Wasm
  * module. This is wasmer-specific.
@@ -22,3 +22,12 @@ pub trait Cache {
     /// Store a [`Module`] into the cache with the given [`Hash`].
     fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError>;
 }
+
+/// Statistics on the lookups of a cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of modules found in the cache.
+    pub hits: u64,
+    /// The number of modules not found in the cache.
+    pub misses: u64,
+}
@@ -1,15 +1,33 @@
 use crate::cache::Cache;
 use crate::hash::Hash;
-use std::fs::{create_dir_all, File};
+use filetime::FileTime;
+use fs2::FileExt;
+use std::fs::{self, create_dir_all, File, OpenOptions};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
 use wasmer::{DeserializeError, Module, SerializeError, Store};
 
+/// The name of the file locked by the processes using the cache.
+const LOCK_FILE: &str = ".lock";
+
+/// The prefix of the temporary files entries are written to.
+const TEMP_PREFIX: &str = ".tmp";
+
 /// Representation of a directory that contains compiled wasm artifacts.
 ///
 /// The `FileSystemCache` type implements the [`Cache`] trait, which allows it to be used
 /// generically when some sort of cache is required.
 ///
+/// The size of the cache can be bounded with [`FileSystemCache::set_max_size`]
+/// and [`FileSystemCache::set_max_entries`]: when storing a module exceeds
+/// the budget, the least recently loaded or stored entries are removed.
+///
+/// Entries are written to a temporary file which is then renamed, and the
+/// directory is locked while entries are read, written or removed, so the
+/// same cache can be used by several processes at once.
+///
 /// # Usage
 ///
 /// ```
@@ -21,11 +39,14 @@ use wasmer::{DeserializeError, Module, SerializeError, Store};
 ///     // Create a new file system cache.
 ///     let mut fs_cache = FileSystemCache::new("some/directory/goes/here")?;
 ///
+///     // Keep at most 512 MiB of compiled modules.
+///     fs_cache.set_max_size(Some(512 * 1024 * 1024));
+///
 ///     // Compute a key for a given WebAssembly binary
 ///     let hash = Hash::generate(bytes);
 ///
 ///     // Store a module into the cache given a key
-///     fs_cache.store(key, module.clone())?;
+///     fs_cache.store(hash, module)?;
 ///
 ///     Ok(())
 /// }
@@ -33,6 +54,21 @@ use wasmer::{DeserializeError, Module, SerializeError, Store};
 pub struct FileSystemCache {
     path: PathBuf,
     ext: Option<String>,
+    max_size: Option<u64>,
+    max_entries: Option<usize>,
+}
+
+/// An entry of a [`FileSystemCache`].
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// The key of the entry.
+    pub key: Hash,
+    /// The path of the file holding the entry.
+    pub path: PathBuf,
+    /// The size of the entry in bytes.
+    pub size: u64,
+    /// The last time the entry was loaded or stored.
+    pub accessed: SystemTime,
 }
 
 impl FileSystemCache {
@@ -43,7 +79,7 @@ impl FileSystemCache {
             let metadata = path.metadata()?;
             if metadata.is_dir() {
                 if !metadata.permissions().readonly() {
-                    Ok(Self { path, ext: None })
+                    Ok(Self::with_path(path))
                 } else {
                     // This directory is readonly.
                     Err(io::Error::new(
@@ -64,10 +100,24 @@ impl FileSystemCache {
         } else {
             // Create the directory and any parent directories if they don't yet exist.
             create_dir_all(&path)?;
-            Ok(Self { path, ext: None })
+            Ok(Self::with_path(path))
+        }
+    }
+
+    fn with_path(path: PathBuf) -> Self {
+        Self {
+            path,
+            ext: None,
+            max_size: None,
+            max_entries: None,
         }
     }
 
+    /// Returns the directory of the cache.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Set the extension for this cached file.
     ///
     /// This is needed for loading native files from Windows, as otherwise
@@ -75,34 +125,308 @@ impl FileSystemCache {
     pub fn set_cache_extension(&mut self, ext: Option<impl ToString>) {
         self.ext = ext.map(|ext| ext.to_string());
     }
-}
 
-impl Cache for FileSystemCache {
-    type DeserializeError = DeserializeError;
-    type SerializeError = SerializeError;
+    /// Set the maximum size in bytes of the entries of the cache.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
 
-    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+    /// Returns the maximum size in bytes of the entries of the cache.
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    /// Set the maximum number of entries of the cache.
+    pub fn set_max_entries(&mut self, max_entries: Option<usize>) {
+        self.max_entries = max_entries;
+    }
+
+    /// Returns the maximum number of entries of the cache.
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// Returns the entries of the cache.
+    pub fn entries(&self) -> io::Result<Vec<CacheEntry>> {
+        let _lock = self.lock(false)?;
+        self.read_entries()
+    }
+
+    /// Returns the entry with the given key, if any.
+    pub fn entry(&self, key: Hash) -> io::Result<Option<CacheEntry>> {
+        Ok(self.entries()?.into_iter().find(|entry| entry.key == key))
+    }
+
+    /// Remove the entry with the given key, returning whether it existed.
+    pub fn remove(&self, key: Hash) -> io::Result<bool> {
+        let _lock = self.lock(true)?;
+        match fs::remove_file(self.entry_path(key)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Remove the least recently used entries until the cache fits in its
+    /// budget, returning the removed entries.
+    ///
+    /// This also removes the temporary files left by interrupted writes.
+    pub fn prune(&self) -> io::Result<Vec<CacheEntry>> {
+        let _lock = self.lock(true)?;
+
+        // No other process is writing an entry while we hold the lock.
+        for dir_entry in fs::read_dir(&self.path)? {
+            let dir_entry = dir_entry?;
+            if dir_entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(TEMP_PREFIX)
+            {
+                remove_file_if_exists(&dir_entry.path())?;
+            }
+        }
+
+        let mut entries = self.read_entries()?;
+        entries.sort_by_key(|entry| entry.accessed);
+        let mut size: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut count = entries.len();
+        let mut removed = Vec::new();
+        for entry in entries {
+            let over_size = matches!(self.max_size, Some(max_size) if size > max_size);
+            let over_count = matches!(self.max_entries, Some(max_entries) if count > max_entries);
+            if !over_size && !over_count {
+                break;
+            }
+            remove_file_if_exists(&entry.path)?;
+            size -= entry.size;
+            count -= 1;
+            removed.push(entry);
+        }
+        Ok(removed)
+    }
+
+    fn entry_path(&self, key: Hash) -> PathBuf {
         let filename = if let Some(ref ext) = self.ext {
             format!("{}.{}", key.to_string(), ext)
         } else {
             key.to_string()
         };
-        let path = self.path.join(filename);
-        Module::deserialize_from_file(&store, path)
+        self.path.join(filename)
     }
 
-    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key.to_string(), ext)
+    /// Lock the cache directory, for all the processes using it.
+    ///
+    /// Loading and storing entries takes a shared lock, while removing
+    /// them takes an exclusive lock.
+    fn lock(&self, exclusive: bool) -> io::Result<CacheLock> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.path.join(LOCK_FILE))?;
+        if exclusive {
+            file.lock_exclusive()?;
         } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
-        let mut file = File::create(path)?;
+            file.lock_shared()?;
+        }
+        Ok(CacheLock(file))
+    }
 
+    fn read_entries(&self) -> io::Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.path)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            let key = match path
+                .file_stem()
+                .and_then(|stem| Hash::from_str(&stem.to_string_lossy()).ok())
+            {
+                Some(key) => key,
+                // Not an entry of the cache.
+                None => continue,
+            };
+            let metadata = match dir_entry.metadata() {
+                Ok(metadata) => metadata,
+                // The entry was removed in the meantime.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            let accessed = FileTime::from_last_access_time(&metadata)
+                .max(FileTime::from_last_modification_time(&metadata));
+            entries.push(CacheEntry {
+                key,
+                path,
+                size: metadata.len(),
+                accessed: to_system_time(accessed),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+impl Cache for FileSystemCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        let path = self.entry_path(key);
+        let _lock = self.lock(false)?;
+        let module = Module::deserialize_from_file(store, &path)?;
+        // Mark the entry as used. Failing to do so only makes it more
+        // likely to be evicted.
+        let _ = filetime::set_file_atime(&path, FileTime::now());
+        Ok(module)
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        let path = self.entry_path(key);
         let buffer = module.serialize()?;
-        file.write_all(&buffer)?;
+        {
+            let _lock = self.lock(false)?;
+            let mut file = tempfile::Builder::new()
+                .prefix(TEMP_PREFIX)
+                .tempfile_in(&self.path)?;
+            file.write_all(&buffer)?;
+            file.persist(&path).map_err(|e| e.error)?;
+        }
+
+        if self.max_size.is_some() || self.max_entries.is_some() {
+            self.prune()?;
+        }
 
         Ok(())
     }
 }
+
+/// A lock on the directory of a [`FileSystemCache`], released on drop.
+struct CacheLock(File);
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = self.0.unlock();
+    }
+}
+
+fn remove_file_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn to_system_time(time: FileTime) -> SystemTime {
+    let since_epoch = std::time::Duration::new(time.unix_seconds() as u64, time.nanoseconds());
+    SystemTime::UNIX_EPOCH + since_epoch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_entry(cache: &FileSystemCache, byte: u8, size: usize, accessed: i64) -> Hash {
+        let key = Hash::new([byte; 32]);
+        let path = cache.entry_path(key);
+        fs::write(&path, vec![0; size]).unwrap();
+        let time = FileTime::from_unix_time(accessed, 0);
+        filetime::set_file_times(&path, time, time).unwrap();
+        key
+    }
+
+    #[test]
+    fn prune_removes_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let oldest = write_entry(&cache, 1, 100, 1_000);
+        let newest = write_entry(&cache, 2, 100, 3_000);
+        let middle = write_entry(&cache, 3, 100, 2_000);
+        fs::write(dir.path().join(".tmp1234"), b"partial").unwrap();
+
+        assert!(cache.prune().unwrap().is_empty());
+        assert_eq!(cache.entries().unwrap().len(), 3);
+        assert!(!dir.path().join(".tmp1234").exists());
+
+        cache.set_max_size(Some(250));
+        let removed = cache.prune().unwrap();
+        assert_eq!(
+            removed.iter().map(|entry| entry.key).collect::<Vec<_>>(),
+            vec![oldest]
+        );
+
+        cache.set_max_entries(Some(1));
+        let removed = cache.prune().unwrap();
+        assert_eq!(
+            removed.iter().map(|entry| entry.key).collect::<Vec<_>>(),
+            vec![middle]
+        );
+        assert!(cache.entry(newest).unwrap().is_some());
+        assert!(cache.remove(newest).unwrap());
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn prune_removes_temporary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileSystemCache::new(dir.path()).unwrap();
+        let key = write_entry(&cache, 1, 100, 1_000);
+        fs::write(dir.path().join(".tmp1234"), b"partial").unwrap();
+        fs::write(dir.path().join(".tmpabcd"), b"partial").unwrap();
+
+        assert!(cache.prune().unwrap().is_empty());
+        assert!(!dir.path().join(".tmp1234").exists());
+        assert!(!dir.path().join(".tmpabcd").exists());
+        assert!(cache.entry(key).unwrap().is_some());
+    }
+
+    #[test]
+    fn max_entries_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let oldest = write_entry(&cache, 1, 100, 1_000);
+        let newest = write_entry(&cache, 2, 100, 3_000);
+        let middle = write_entry(&cache, 3, 100, 2_000);
+
+        cache.set_max_entries(Some(3));
+        assert!(cache.prune().unwrap().is_empty());
+
+        cache.set_max_entries(Some(1));
+        let removed = cache.prune().unwrap();
+        assert_eq!(
+            removed.iter().map(|entry| entry.key).collect::<Vec<_>>(),
+            vec![oldest, middle]
+        );
+        let remaining = cache.entries().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].key, newest);
+    }
+
+    #[test]
+    fn store_enforces_the_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let old = write_entry(&cache, 1, 100, 1_000);
+
+        let store = Store::default();
+        let module = Module::new(&store, "(module)").unwrap();
+        let key = Hash::new([2; 32]);
+
+        cache.set_max_entries(Some(1));
+        cache.store(key, &module).unwrap();
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, key);
+        assert!(cache.entry(old).unwrap().is_none());
+
+        // Loading the stored entry still works after pruning.
+        unsafe { cache.load(&store, key) }.unwrap();
+
+        // An entry larger than the whole budget is removed right away.
+        cache.set_max_entries(None);
+        cache.set_max_size(Some(1));
+        cache.store(Hash::new([3; 32]), &module).unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+    }
+}
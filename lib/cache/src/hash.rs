@@ -1,6 +1,7 @@
 use crate::DeserializeError;
 use std::str::FromStr;
 use std::string::ToString;
+use wasmer::{Features, Store};
 
 /// A hash used as a key when loading and storing modules in a
 /// [`Cache`].
@@ -20,6 +21,12 @@ impl Hash {
         Self::new(hash.into())
     }
 
+    /// Creates a new [`HashBuilder`] from a slice of bytes, to combine
+    /// them with the setup compiling the module.
+    pub fn builder(bytes: &[u8]) -> HashBuilder {
+        HashBuilder::new(bytes)
+    }
+
     pub(crate) fn into_array(self) -> [u8; 32] {
         let mut total = [0u8; 32];
         total[0..32].copy_from_slice(&self.0);
@@ -27,6 +34,93 @@ impl Hash {
     }
 }
 
+/// A builder of a [`Hash`] which combines the bytes of a module with
+/// everything affecting the code generated for it: the version of Wasmer,
+/// the engine, the compiler configuration, the target, the tunables and
+/// the enabled features.
+///
+/// Keying a cache with such hashes keeps modules compiled with another
+/// setup from being loaded.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{CompilerConfig, Features, Store};
+/// use wasmer_cache::Hash;
+///
+/// fn module_key(store: &Store, config: &dyn CompilerConfig, bytes: &[u8]) -> Option<Hash> {
+///     // a configuration which can't be fingerprinted isn't cached
+///     let fingerprint = config.fingerprint()?;
+///     Some(
+///         Hash::builder(bytes)
+///             .engine("jit")
+///             .compiler(&fingerprint)
+///             .store(store)
+///             .features(&Features::default())
+///             .finish(),
+///     )
+/// }
+/// ```
+pub struct HashBuilder {
+    hasher: blake3::Hasher,
+}
+
+impl HashBuilder {
+    fn new(bytes: &[u8]) -> Self {
+        let mut builder = Self {
+            hasher: blake3::Hasher::new(),
+        };
+        builder.update("module", bytes);
+        builder.update("wasmer", env!("CARGO_PKG_VERSION").as_bytes());
+        builder
+    }
+
+    /// Adds a labelled part to the hash. Parts are prefixed with their
+    /// length so that different parts never hash the same.
+    fn update(&mut self, label: &str, bytes: &[u8]) {
+        for part in &[label.as_bytes(), bytes] {
+            self.hasher.update(&(part.len() as u64).to_le_bytes());
+            self.hasher.update(part);
+        }
+    }
+
+    /// Combines the name of the engine, such as `jit` or `native`.
+    pub fn engine(mut self, name: &str) -> Self {
+        self.update("engine", name.as_bytes());
+        self
+    }
+
+    /// Combines the fingerprint of the compiler configuration, as returned
+    /// by `CompilerConfig::fingerprint`.
+    pub fn compiler(mut self, fingerprint: &str) -> Self {
+        self.update("compiler", fingerprint.as_bytes());
+        self
+    }
+
+    /// Combines the target and the fingerprint of the tunables of `store`,
+    /// as returned by `Tunables::fingerprint`.
+    pub fn store(mut self, store: &Store) -> Self {
+        let target = store.engine().target();
+        self.update(
+            "target",
+            format!("{} {:?}", target.triple(), target.cpu_features()).as_bytes(),
+        );
+        self.update("tunables", store.tunables().fingerprint().as_bytes());
+        self
+    }
+
+    /// Combines the enabled WebAssembly features.
+    pub fn features(mut self, features: &Features) -> Self {
+        self.update("features", format!("{:?}", features).as_bytes());
+        self
+    }
+
+    /// Returns the resulting hash.
+    pub fn finish(&self) -> Hash {
+        Hash::new(self.hasher.finalize().into())
+    }
+}
+
 impl ToString for Hash {
     /// Create the hexadecimal representation of the
     /// stored hash.
@@ -56,3 +150,46 @@ impl FromStr for Hash {
         })?))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer::{Pages, Tunables};
+
+    #[test]
+    fn builder_combines_the_setup() {
+        let store = Store::default();
+        let key = |features: &Features, compiler: &str| {
+            Hash::builder(b"module")
+                .engine("jit")
+                .compiler(compiler)
+                .store(&store)
+                .features(features)
+                .finish()
+        };
+        let features = Features::default();
+        let mut simd = Features::default();
+        simd.simd(true);
+
+        assert_eq!(key(&features, "a"), key(&features, "a"));
+        assert_ne!(key(&features, "a"), key(&features, "b"));
+        assert_ne!(key(&features, "a"), key(&simd, "a"));
+        assert_ne!(key(&features, "a"), Hash::generate(b"module"));
+    }
+
+    #[test]
+    fn builder_combines_the_tunables() {
+        let store = Store::default();
+        let engine = store.engine().clone();
+        let key = |store: &Store| Hash::builder(b"module").store(store).finish();
+        let tunables = Tunables::for_target(engine.target());
+        let mut small = tunables.clone();
+        small.static_memory_bound = Pages(1);
+
+        assert_eq!(
+            key(&store),
+            key(&Store::new_with_tunables(&*engine, tunables))
+        );
+        assert_ne!(key(&store), key(&Store::new_with_tunables(&*engine, small)));
+    }
+}
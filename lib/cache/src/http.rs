@@ -0,0 +1,435 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use std::convert::TryFrom;
+use std::io::{self, Read};
+use std::time::Duration;
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// A cache of compiled modules stored on an HTTP server.
+///
+/// Modules are downloaded with `GET /<hash>.<ext>` requests and uploaded
+/// with `PUT /<hash>.<ext>` requests, relative to the URL of the cache.
+/// The hash combines the key with the target of the engine, the version
+/// of Wasmer and an identity set with [`HttpCache::set_identity`], so
+/// hosts with different setups don't share incompatible artifacts.
+///
+/// It's usually the back tier of a [`TieredCache`], so modules are only
+/// downloaded once per host.
+///
+/// # Trust model
+///
+/// The cached modules are native code, which is run as is by the hosts
+/// loading them. Whoever can change the modules served, or the responses
+/// on their way, can run arbitrary code on those hosts.
+///
+/// By default, the server is trusted: only HTTPS URLs are accepted, so
+/// the modules can't be changed on their way, but they aren't checked.
+///
+/// With a signing key set with [`HttpCache::set_signing_key`], uploaded
+/// modules are signed with a keyed hash, which is checked when they are
+/// downloaded. The signature covers the hash the module is stored under,
+/// so a module can't be served in place of another. Only the hosts
+/// sharing the key can then produce modules which are loaded, so neither
+/// the server nor the network have to be trusted, and plain HTTP URLs are
+/// accepted. The key must be kept secret, and never be stored on the
+/// server.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{DeserializeError, Module, Store};
+/// use wasmer_cache::{Cache, FileSystemCache, Hash, HttpCache, TieredCache};
+///
+/// fn load_module(store: &Store, bytes: &[u8]) -> Result<Module, DeserializeError> {
+///     let mut http_cache = HttpCache::new("https://cache.example.com/modules");
+///     http_cache.set_identity("cranelift-jit");
+///     let fs_cache = FileSystemCache::new("some/directory/goes/here")?;
+///     let cache = TieredCache::new(fs_cache, http_cache);
+///
+///     unsafe { cache.load(store, Hash::generate(bytes)) }
+/// }
+/// ```
+///
+/// [`TieredCache`]: crate::TieredCache
+pub struct HttpCache {
+    url: String,
+    ext: Option<String>,
+    identity: String,
+    timeout: Duration,
+    max_size: Option<u64>,
+    signing_key: Option<[u8; blake3::KEY_LEN]>,
+    agent: ureq::Agent,
+}
+
+impl HttpCache {
+    /// Construct a new `HttpCache` around the specified URL.
+    pub fn new(url: impl Into<String>) -> Self {
+        let url = url.into().trim_end_matches('/').to_string();
+        Self {
+            url,
+            ext: None,
+            identity: String::new(),
+            timeout: Duration::from_secs(30),
+            max_size: None,
+            signing_key: None,
+            agent: ureq::agent(),
+        }
+    }
+
+    /// Set the extension of the cached modules.
+    pub fn set_cache_extension(&mut self, ext: Option<impl ToString>) {
+        self.ext = ext.map(|ext| ext.to_string());
+    }
+
+    /// Set the identity of the setup compiling the modules, such as the
+    /// compiler and the engine, which is part of the keys.
+    pub fn set_identity(&mut self, identity: impl Into<String>) {
+        self.identity = identity.into();
+    }
+
+    /// Set the timeout of the requests, 30 seconds by default.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Set the maximum size in bytes of the modules downloaded or
+    /// uploaded. Larger modules are not cached.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    /// Set the secret key signing the modules, derived from `key`.
+    ///
+    /// The modules downloaded which weren't signed with the same key are
+    /// refused. See the [trust model](#trust-model).
+    pub fn set_signing_key(&mut self, key: Option<&[u8]>) {
+        self.signing_key = key.map(|key| {
+            let mut signing_key = [0; blake3::KEY_LEN];
+            blake3::derive_key(
+                "wasmer-cache 2020 HttpCache signing key",
+                key,
+                &mut signing_key,
+            );
+            signing_key
+        });
+    }
+
+    /// Check that the modules can be exchanged with the server, without
+    /// trusting unauthenticated code.
+    fn check_url(&self) -> io::Result<()> {
+        if self.signing_key.is_none() && !self.url.starts_with("https://") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "refusing to use the insecure URL {} without a signing key",
+                    self.url
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the signature of the module `bytes` stored under `hash`,
+    /// if modules are signed.
+    fn sign(&self, hash: Hash, bytes: &[u8]) -> Option<blake3::Hash> {
+        self.signing_key.as_ref().map(|key| {
+            blake3::Hasher::new_keyed(key)
+                .update(&hash.into_array())
+                .update(bytes)
+                .finalize()
+        })
+    }
+
+    /// Returns the hash the module with the given key is stored under,
+    /// for `store`.
+    fn module_hash(&self, store: &Store, key: Hash) -> Hash {
+        let target = store.engine().target();
+        Hash::generate(
+            format!(
+                "{}:{}:{}:{}:{:?}",
+                key.to_string(),
+                env!("CARGO_PKG_VERSION"),
+                self.identity,
+                target.triple(),
+                target.cpu_features(),
+            )
+            .as_bytes(),
+        )
+    }
+
+    /// Returns the URL of the module stored under `hash`.
+    fn module_url(&self, hash: Hash) -> String {
+        match &self.ext {
+            Some(ext) => format!("{}/{}.{}", self.url, hash.to_string(), ext),
+            None => format!("{}/{}", self.url, hash.to_string()),
+        }
+    }
+
+    /// Check that a module of `size` bytes can be cached.
+    fn check_size(&self, size: u64) -> io::Result<()> {
+        match self.max_size {
+            Some(max_size) if size > max_size => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the module is {} bytes, which is more than the maximum of {} bytes",
+                    size, max_size
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Convert the status of a response to an error.
+fn response_error(response: &ureq::Response) -> io::Error {
+    if let Some(error) = response.synthetic_error() {
+        let kind = match error {
+            ureq::Error::Io(e) => e.kind(),
+            _ => io::ErrorKind::Other,
+        };
+        return io::Error::new(kind, error.to_string());
+    }
+    let kind = match response.status() {
+        404 => io::ErrorKind::NotFound,
+        401 | 403 => io::ErrorKind::PermissionDenied,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(
+        kind,
+        format!(
+            "{} {} for {}",
+            response.status(),
+            response.status_text(),
+            response.get_url()
+        ),
+    )
+}
+
+/// The error of a downloaded module without a valid signature.
+fn invalid_signature() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "the module isn't signed with the signing key",
+    )
+}
+
+impl Cache for HttpCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        self.check_url()?;
+        let hash = self.module_hash(store, key);
+        let response = self
+            .agent
+            .get(&self.module_url(hash))
+            .timeout(self.timeout)
+            .call();
+        if !response.ok() {
+            return Err(response_error(&response).into());
+        }
+        // Signed modules are prefixed with their signature.
+        let signature_len = if self.signing_key.is_some() {
+            blake3::OUT_LEN as u64
+        } else {
+            0
+        };
+        if let Some(length) = response
+            .header("Content-Length")
+            .and_then(|length| length.parse::<u64>().ok())
+        {
+            self.check_size(length.saturating_sub(signature_len))?;
+        }
+
+        let mut bytes = Vec::new();
+        // Read one more byte than allowed, to detect larger bodies without
+        // a `Content-Length`.
+        let limit = self
+            .max_size
+            .map_or(u64::MAX, |max| max + signature_len + 1);
+        response.into_reader().take(limit).read_to_end(&mut bytes)?;
+
+        if self.signing_key.is_some() {
+            if bytes.len() < blake3::OUT_LEN {
+                return Err(invalid_signature().into());
+            }
+            let module = bytes.split_off(blake3::OUT_LEN);
+            let signature = <[u8; blake3::OUT_LEN]>::try_from(&bytes[..]).unwrap();
+            // `blake3::Hash` compares in constant time.
+            if self.sign(hash, &module) != Some(blake3::Hash::from(signature)) {
+                return Err(invalid_signature().into());
+            }
+            bytes = module;
+        }
+        self.check_size(bytes.len() as u64)?;
+
+        Module::deserialize(store, &bytes)
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        self.check_url()?;
+        let mut bytes = module.serialize()?;
+        self.check_size(bytes.len() as u64)?;
+        let hash = self.module_hash(module.store(), key);
+        if let Some(signature) = self.sign(hash, &bytes) {
+            bytes.splice(0..0, signature.as_bytes().iter().cloned());
+        }
+        let response = self
+            .agent
+            .put(&self.module_url(hash))
+            .timeout(self.timeout)
+            .set("Content-Type", "application/octet-stream")
+            .send_bytes(&bytes);
+        if !response.ok() {
+            return Err(response_error(&response).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::thread;
+
+    /// Serve a key/value store over HTTP, returning its URL.
+    fn serve() -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/modules/", server.server_addr());
+        thread::spawn(move || {
+            let mut modules = HashMap::new();
+            for mut request in server.incoming_requests() {
+                let response = match request.method() {
+                    tiny_http::Method::Put => {
+                        let mut body = Vec::new();
+                        request.as_reader().read_to_end(&mut body).unwrap();
+                        modules.insert(request.url().to_string(), body);
+                        tiny_http::Response::from_data(vec![])
+                    }
+                    _ => match modules.get(request.url()) {
+                        Some(body) => tiny_http::Response::from_data(body.clone()),
+                        None => tiny_http::Response::from_data(vec![]).with_status_code(404),
+                    },
+                };
+                request.respond(response).unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn http_cache_roundtrip() {
+        let url = serve();
+        let store = Store::default();
+        let module = Module::new(&store, "(module (func (export \"f\")))").unwrap();
+        let key = Hash::generate(b"module");
+
+        let mut cache = HttpCache::new(url.clone());
+        cache.set_identity("test");
+        cache.set_cache_extension(Some("wjit"));
+        cache.set_signing_key(Some(b"secret"));
+        cache.store(key, &module).unwrap();
+        let loaded = unsafe { cache.load(&store, key).unwrap() };
+        assert_eq!(loaded.exports().count(), 1);
+
+        // Modules compiled by another setup are not shared.
+        let mut other = HttpCache::new(url);
+        other.set_identity("other");
+        other.set_cache_extension(Some("wjit"));
+        other.set_signing_key(Some(b"secret"));
+        match unsafe { other.load(&store, key) } {
+            Err(DeserializeError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        cache.set_max_size(Some(16));
+        assert!(cache.store(key, &module).is_err());
+        assert!(unsafe { cache.load(&store, key) }.is_err());
+    }
+
+    #[test]
+    fn http_cache_checks_signatures() {
+        let url = serve();
+        let store = Store::default();
+        let module = Module::new(&store, "(module (func (export \"f\")))").unwrap();
+        let key = Hash::generate(b"module");
+        let error_kind = |result: Result<Module, DeserializeError>| match result {
+            Err(DeserializeError::Io(e)) => e.kind(),
+            result => panic!("unexpected result: {:?}", result),
+        };
+
+        // Unsigned modules are only exchanged over HTTPS.
+        let mut cache = HttpCache::new(url.clone());
+        assert!(cache.store(key, &module).is_err());
+        assert_eq!(
+            error_kind(unsafe { cache.load(&store, key) }),
+            io::ErrorKind::PermissionDenied
+        );
+
+        cache.set_signing_key(Some(b"secret"));
+        cache.store(key, &module).unwrap();
+        let module_url = cache.module_url(cache.module_hash(&store, key));
+
+        // Modules signed with another key are refused.
+        let mut other = HttpCache::new(url);
+        other.set_signing_key(Some(b"another secret"));
+        assert_eq!(
+            error_kind(unsafe { other.load(&store, key) }),
+            io::ErrorKind::InvalidData
+        );
+
+        // So are the modules changed on the server.
+        let mut bytes = Vec::new();
+        ureq::get(&module_url)
+            .call()
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(ureq::put(&module_url).send_bytes(&bytes).ok());
+        assert_eq!(
+            error_kind(unsafe { cache.load(&store, key) }),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn http_cache_refuses_swapped_modules() {
+        let url = serve();
+        let store = Store::default();
+        let module_f = Module::new(&store, "(module (func (export \"f\")))").unwrap();
+        let module_g = Module::new(&store, "(module (func (export \"g\")))").unwrap();
+        let key_f = Hash::generate(b"module f");
+        let key_g = Hash::generate(b"module g");
+
+        let mut cache = HttpCache::new(url);
+        cache.set_signing_key(Some(b"secret"));
+        cache.store(key_f, &module_f).unwrap();
+        cache.store(key_g, &module_g).unwrap();
+
+        // The server swaps the validly signed modules.
+        let url_f = cache.module_url(cache.module_hash(&store, key_f));
+        let url_g = cache.module_url(cache.module_hash(&store, key_g));
+        let download = |url: &str| {
+            let mut bytes = Vec::new();
+            ureq::get(url)
+                .call()
+                .into_reader()
+                .read_to_end(&mut bytes)
+                .unwrap();
+            bytes
+        };
+        let (bytes_f, bytes_g) = (download(&url_f), download(&url_g));
+        assert!(ureq::put(&url_f).send_bytes(&bytes_g).ok());
+        assert!(ureq::put(&url_g).send_bytes(&bytes_f).ok());
+
+        for key in &[key_f, key_g] {
+            match unsafe { cache.load(&store, *key) } {
+                Err(DeserializeError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+    }
+}
@@ -22,10 +22,18 @@
 mod cache;
 mod filesystem;
 mod hash;
+#[cfg(feature = "http")]
+mod http;
+mod memory;
+mod tiered;
 
-pub use crate::cache::Cache;
-pub use crate::filesystem::FileSystemCache;
-pub use crate::hash::Hash;
+pub use crate::cache::{Cache, CacheStats};
+pub use crate::filesystem::{CacheEntry, FileSystemCache};
+pub use crate::hash::{Hash, HashBuilder};
+#[cfg(feature = "http")]
+pub use crate::http::HttpCache;
+pub use crate::memory::MemoryCache;
+pub use crate::tiered::{TieredCache, TieredCacheStats};
 
 // We re-export those for convinience of users
 pub use wasmer::{DeserializeError, SerializeError};
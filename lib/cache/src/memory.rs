@@ -0,0 +1,197 @@
+use crate::cache::{Cache, CacheStats};
+use crate::hash::Hash;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// An in-process cache of compiled modules.
+///
+/// Loading a module from a `MemoryCache` shares its compiled artifact
+/// instead of deserializing it again, which makes it a good first tier of
+/// a [`TieredCache`].
+///
+/// Modules can only be loaded with a [`Store`] using the engine they were
+/// compiled with (or one of its clones); the loaded module belongs to
+/// that store.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{Module, SerializeError, Store};
+/// use wasmer_cache::{Cache, Hash, MemoryCache};
+///
+/// fn store_module(module: &Module, bytes: &[u8]) -> Result<(), SerializeError> {
+///     let mut cache = MemoryCache::new();
+///
+///     // Keep at most the 16 most recently used modules.
+///     cache.set_max_entries(Some(16));
+///
+///     cache.store(Hash::generate(bytes), module)?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// [`TieredCache`]: crate::TieredCache
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    inner: Mutex<MemoryCacheInner>,
+    max_entries: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryCacheInner {
+    // The modules, with the last time they were used.
+    modules: HashMap<Hash, (Module, u64)>,
+    // A logical clock, incremented on every use.
+    now: u64,
+    stats: CacheStats,
+}
+
+impl MemoryCache {
+    /// Construct a new, empty, `MemoryCache`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of modules of the cache.
+    ///
+    /// When storing a module exceeds it, the least recently used modules
+    /// are removed.
+    pub fn set_max_entries(&mut self, max_entries: Option<usize>) {
+        self.max_entries = max_entries;
+        self.inner.get_mut().unwrap().evict(max_entries);
+    }
+
+    /// Returns the maximum number of modules of the cache.
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// Returns the number of modules in the cache.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().modules.len()
+    }
+
+    /// Returns `true` if the cache holds no module.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove the module with the given key, returning whether it existed.
+    pub fn remove(&mut self, key: Hash) -> bool {
+        self.inner.get_mut().unwrap().modules.remove(&key).is_some()
+    }
+
+    /// Remove all the modules.
+    pub fn clear(&mut self) {
+        self.inner.get_mut().unwrap().modules.clear();
+    }
+
+    /// Returns the statistics on the lookups of the cache.
+    pub fn stats(&self) -> CacheStats {
+        self.inner.lock().unwrap().stats
+    }
+}
+
+impl MemoryCacheInner {
+    fn evict(&mut self, max_entries: Option<usize>) {
+        let max_entries = match max_entries {
+            Some(max_entries) => max_entries,
+            None => return,
+        };
+        while self.modules.len() > max_entries {
+            let oldest = self
+                .modules
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| *key)
+                .unwrap();
+            self.modules.remove(&oldest);
+        }
+    }
+}
+
+impl Cache for MemoryCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.now += 1;
+        let now = inner.now;
+        let result = match inner.modules.get_mut(&key) {
+            Some((module, used)) => {
+                *used = now;
+                // rebind the module to `store`, sharing its compiled code
+                module.share_with(store).ok_or_else(|| {
+                    DeserializeError::Incompatible(
+                        "the module was compiled with another engine".to_string(),
+                    )
+                })
+            }
+            None => Err(DeserializeError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the module is not in the cache",
+            ))),
+        };
+        if result.is_ok() {
+            inner.stats.hits += 1;
+        } else {
+            inner.stats.misses += 1;
+        }
+        result
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        let inner = self.inner.get_mut().unwrap();
+        inner.now += 1;
+        inner.modules.insert(key, (module.clone(), inner.now));
+        inner.evict(self.max_entries);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_cache_evicts_least_recently_used() {
+        let store = Store::default();
+        let module = Module::new(&store, "(module)").unwrap();
+        let keys = [1, 2, 3]
+            .iter()
+            .map(|i| Hash::new([*i; 32]))
+            .collect::<Vec<_>>();
+        let mut cache = MemoryCache::new();
+        cache.set_max_entries(Some(2));
+
+        cache.store(keys[0], &module).unwrap();
+        cache.store(keys[1], &module).unwrap();
+        unsafe { cache.load(&store, keys[0]).unwrap() };
+        cache.store(keys[2], &module).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        unsafe {
+            assert!(cache.load(&store, keys[0]).is_ok());
+            assert!(cache.load(&store, keys[1]).is_err());
+            assert!(cache.load(&store, keys[2]).is_ok());
+        }
+    }
+
+    #[test]
+    fn memory_cache_loads_into_the_given_store() {
+        let store = Store::default();
+        let other_store = Store::new(&**store.engine());
+        let module = Module::new(&store, "(module)").unwrap();
+        let key = Hash::new([1; 32]);
+        let mut cache = MemoryCache::new();
+
+        cache.store(key, &module).unwrap();
+        let loaded = unsafe { cache.load(&other_store, key).unwrap() };
+
+        assert!(Store::same(loaded.store(), &other_store));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use std::sync::{Mutex, MutexGuard};
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// A cache combining a fast `front` cache with a slower `back` cache.
+///
+/// Modules are looked up in the front cache first, and then in the back
+/// cache, in which case they're stored in the front cache as well.
+/// Stored modules go to both caches.
+///
+/// Tiered caches can be nested, to check the memory first, then the disk,
+/// then any other cache.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{DeserializeError, Module, Store};
+/// use wasmer_cache::{Cache, FileSystemCache, Hash, MemoryCache, TieredCache};
+///
+/// fn load_module(store: &Store, bytes: &[u8]) -> Result<Module, DeserializeError> {
+///     let fs_cache = FileSystemCache::new("some/directory/goes/here")?;
+///     let cache = TieredCache::new(MemoryCache::new(), fs_cache);
+///
+///     let module = unsafe { cache.load(store, Hash::generate(bytes))? };
+///
+///     // The module was found on disk.
+///     assert_eq!(cache.stats().back_hits, 1);
+///
+///     Ok(module)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TieredCache<F, B> {
+    front: Mutex<F>,
+    back: B,
+    stats: Mutex<TieredCacheStats>,
+}
+
+/// Statistics on the lookups of a [`TieredCache`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TieredCacheStats {
+    /// The number of modules found in the front cache.
+    pub front_hits: u64,
+    /// The number of modules found in the back cache only.
+    pub back_hits: u64,
+    /// The number of modules found in neither cache.
+    pub misses: u64,
+}
+
+impl<F: Cache, B: Cache> TieredCache<F, B> {
+    /// Construct a new `TieredCache` checking `front` before `back`.
+    pub fn new(front: F, back: B) -> Self {
+        Self {
+            front: Mutex::new(front),
+            back,
+            stats: Mutex::new(TieredCacheStats::default()),
+        }
+    }
+
+    /// Returns the front cache.
+    pub fn front(&self) -> MutexGuard<'_, F> {
+        self.front.lock().unwrap()
+    }
+
+    /// Returns the back cache.
+    pub fn back(&self) -> &B {
+        &self.back
+    }
+
+    /// Returns the front and back caches.
+    pub fn into_inner(self) -> (F, B) {
+        (self.front.into_inner().unwrap(), self.back)
+    }
+
+    /// Returns the statistics on the lookups of the cache.
+    pub fn stats(&self) -> TieredCacheStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+impl<F, B> Cache for TieredCache<F, B>
+where
+    F: Cache,
+    B: Cache,
+    B::DeserializeError: Into<DeserializeError>,
+    F::SerializeError: Into<SerializeError>,
+    B::SerializeError: Into<SerializeError>,
+{
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        // Any error of the front cache, such as a corrupted entry, is
+        // handled as a miss.
+        if let Ok(module) = self.front.lock().unwrap().load(store, key) {
+            self.stats.lock().unwrap().front_hits += 1;
+            return Ok(module);
+        }
+
+        match self.back.load(store, key) {
+            Ok(module) => {
+                self.stats.lock().unwrap().back_hits += 1;
+                // Failing to promote the module only makes the next
+                // lookup slower.
+                let _ = self.front.lock().unwrap().store(key, &module);
+                Ok(module)
+            }
+            Err(e) => {
+                self.stats.lock().unwrap().misses += 1;
+                Err(e.into())
+            }
+        }
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        // The module is stored in the front even if the back fails, which
+        // keeps an unreachable back cache from slowing down every lookup.
+        let back = self.back.store(key, module);
+        self.front
+            .get_mut()
+            .unwrap()
+            .store(key, module)
+            .map_err(Into::into)?;
+        back.map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CacheStats, MemoryCache};
+
+    #[test]
+    fn tiered_cache_promotes_modules() {
+        let store = Store::default();
+        let module = Module::new(&store, "(module)").unwrap();
+        let key = Hash::generate(b"(module)");
+        let mut back = MemoryCache::new();
+        back.store(key, &module).unwrap();
+        let cache = TieredCache::new(MemoryCache::new(), back);
+
+        unsafe {
+            assert!(cache.load(&store, Hash::generate(b"other")).is_err());
+            cache.load(&store, key).unwrap();
+            cache.load(&store, key).unwrap();
+        }
+        assert_eq!(
+            cache.stats(),
+            TieredCacheStats {
+                front_hits: 1,
+                back_hits: 1,
+                misses: 1,
+            }
+        );
+        assert_eq!(cache.front().len(), 1);
+        assert_eq!(cache.front().stats(), CacheStats { hits: 1, misses: 2 });
+    }
+}
@@ -3,7 +3,7 @@ use anyhow::Result;
 use wasmer_cli::commands::CreateExe;
 #[cfg(feature = "wast")]
 use wasmer_cli::commands::Wast;
-use wasmer_cli::commands::{Cache, Compile, Config, Inspect, Run, SelfUpdate, Validate};
+use wasmer_cli::commands::{Cache, Compile, Config, Inspect, Repl, Run, SelfUpdate, Validate};
 use wasmer_cli::error::PrettyError;
 
 use structopt::{clap::ErrorKind, StructOpt};
@@ -46,6 +46,10 @@ enum WasmerCLIOptions {
     #[structopt(name = "inspect")]
     Inspect(Inspect),
 
+    /// Explore a WebAssembly file interactively
+    #[structopt(name = "repl")]
+    Repl(Repl),
+
     /// Run spec testsuite
     #[cfg(feature = "wast")]
     #[structopt(name = "wast")]
@@ -64,6 +68,7 @@ impl WasmerCLIOptions {
             Self::CreateExe(create_exe) => create_exe.execute(),
             Self::Config(config) => config.execute(),
             Self::Inspect(inspect) => inspect.execute(),
+            Self::Repl(repl) => repl.execute(),
             #[cfg(feature = "wast")]
             Self::Wast(wast) => wast.execute(),
         }
@@ -82,7 +87,7 @@ fn main() {
     let args = std::env::args().collect::<Vec<_>>();
     let command = args.get(1);
     let options = match command.unwrap_or(&"".to_string()).as_ref() {
-        "cache" | "compile" | "config" | "create-exe" | "help" | "inspect" | "run"
+        "cache" | "compile" | "config" | "create-exe" | "help" | "inspect" | "repl" | "run"
         | "self-update" | "validate" | "wast" => WasmerCLIOptions::from_args(),
         _ => {
             WasmerCLIOptions::from_iter_safe(args.iter()).unwrap_or_else(|e| {
@@ -5,6 +5,7 @@ mod config;
 #[cfg(all(feature = "object-file", feature = "compiler"))]
 mod create_exe;
 mod inspect;
+mod repl;
 mod run;
 mod self_update;
 mod validate;
@@ -15,4 +16,6 @@ mod wast;
 pub use create_exe::*;
 #[cfg(feature = "wast")]
 pub use wast::*;
-pub use {cache::*, compile::*, config::*, inspect::*, run::*, self_update::*, validate::*};
+pub use {
+    cache::*, compile::*, config::*, inspect::*, repl::*, run::*, self_update::*, validate::*,
+};
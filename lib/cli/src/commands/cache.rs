@@ -1,7 +1,19 @@
+#[cfg(feature = "cache")]
+use crate::common::get_cache_budget;
 use crate::common::get_cache_dir;
+#[cfg(feature = "cache")]
+use crate::utils::parse_size;
+#[cfg(feature = "cache")]
+use anyhow::bail;
 use anyhow::{Context, Result};
+#[cfg(feature = "cache")]
+use bytesize::ByteSize;
 use std::fs;
+#[cfg(feature = "cache")]
+use std::time::SystemTime;
 use structopt::StructOpt;
+#[cfg(feature = "cache")]
+use wasmer_cache::{CacheEntry, FileSystemCache};
 
 #[derive(Debug, StructOpt)]
 /// The options for the `wasmer cache` subcommand
@@ -13,6 +25,41 @@ pub enum Cache {
     /// Display the location of the cache
     #[structopt(name = "dir")]
     Dir,
+
+    /// List the cached modules, least recently used first
+    #[cfg(feature = "cache")]
+    #[structopt(name = "list")]
+    List,
+
+    /// Display the details of a cached module
+    #[cfg(feature = "cache")]
+    #[structopt(name = "inspect")]
+    Inspect {
+        /// The key of the module, or a prefix of it
+        #[structopt(name = "KEY")]
+        key: String,
+    },
+
+    /// Remove the least recently used modules until each compiler cache
+    /// fits in the budget
+    #[cfg(feature = "cache")]
+    #[structopt(name = "prune")]
+    Prune {
+        /// The maximum size of each compiler cache, such as `512M`.
+        /// Defaults to `WASMER_CACHE_MAX_SIZE`
+        #[structopt(long = "max-size", parse(try_from_str = parse_size))]
+        max_size: Option<u64>,
+
+        /// The maximum number of modules of each compiler cache.
+        /// Defaults to `WASMER_CACHE_MAX_ENTRIES`
+        #[structopt(long = "max-entries")]
+        max_entries: Option<usize>,
+    },
+
+    /// Display the disk usage of the cache
+    #[cfg(feature = "cache")]
+    #[structopt(name = "usage")]
+    Usage,
 }
 
 impl Cache {
@@ -25,6 +72,28 @@ impl Cache {
             Cache::Dir => {
                 self.dir()?;
             }
+            #[cfg(feature = "cache")]
+            Cache::List => {
+                self.list().context("failed to list wasmer cache.")?;
+            }
+            #[cfg(feature = "cache")]
+            Cache::Inspect { key } => {
+                self.inspect(key)
+                    .with_context(|| format!("failed to inspect `{}`", key))?;
+            }
+            #[cfg(feature = "cache")]
+            Cache::Prune {
+                max_size,
+                max_entries,
+            } => {
+                self.prune(*max_size, *max_entries)
+                    .context("failed to prune wasmer cache.")?;
+            }
+            #[cfg(feature = "cache")]
+            Cache::Usage => {
+                self.usage()
+                    .context("failed to compute wasmer cache usage.")?;
+            }
         }
         Ok(())
     }
@@ -41,4 +110,130 @@ impl Cache {
         println!("{}", get_cache_dir().to_string_lossy());
         Ok(())
     }
+    #[cfg(feature = "cache")]
+    fn list(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        for (compiler, cache) in Self::compiler_caches()? {
+            for entry in cache.entries()? {
+                entries.push((compiler.clone(), entry));
+            }
+        }
+        entries.sort_by_key(|(_, entry)| entry.accessed);
+        for (compiler, entry) in entries {
+            println!(
+                "{}  {:<10}  {:>10}  {}",
+                entry.key.to_string(),
+                compiler,
+                ByteSize(entry.size).to_string(),
+                format_accessed(&entry),
+            );
+        }
+        Ok(())
+    }
+    #[cfg(feature = "cache")]
+    fn inspect(&self, key: &str) -> Result<()> {
+        let mut matches = Vec::new();
+        for (compiler, cache) in Self::compiler_caches()? {
+            for entry in cache.entries()? {
+                if entry.key.to_string().starts_with(key) {
+                    matches.push((compiler.clone(), entry));
+                }
+            }
+        }
+        let (compiler, entry) = match matches.len() {
+            0 => bail!("no cached module has this key"),
+            1 => matches.remove(0),
+            _ => bail!("several cached modules have a key starting with this prefix"),
+        };
+        println!("Key: {}", entry.key.to_string());
+        println!("Compiler: {}", compiler);
+        println!("Path: {}", entry.path.display());
+        println!("Size: {}", ByteSize(entry.size));
+        println!("Last used: {}", format_accessed(&entry));
+        Ok(())
+    }
+    #[cfg(feature = "cache")]
+    fn prune(&self, max_size: Option<u64>, max_entries: Option<usize>) -> Result<()> {
+        let (default_max_size, default_max_entries) = get_cache_budget()?;
+        let mut removed_entries = 0;
+        let mut removed_size = 0;
+        for (_, mut cache) in Self::compiler_caches()? {
+            cache.set_max_size(max_size.or(default_max_size));
+            cache.set_max_entries(max_entries.or(default_max_entries));
+            for entry in cache.prune()? {
+                removed_entries += 1;
+                removed_size += entry.size;
+            }
+        }
+        eprintln!(
+            "Removed {} cached modules ({}).",
+            removed_entries,
+            ByteSize(removed_size)
+        );
+        Ok(())
+    }
+    #[cfg(feature = "cache")]
+    fn usage(&self) -> Result<()> {
+        let (max_size, max_entries) = get_cache_budget()?;
+        let mut total_entries = 0;
+        let mut total_size = 0;
+        for (compiler, cache) in Self::compiler_caches()? {
+            let entries = cache.entries()?;
+            let size = entries.iter().map(|entry| entry.size).sum();
+            println!(
+                "{:<10}  {:>6} modules  {:>10}",
+                compiler,
+                entries.len(),
+                ByteSize(size).to_string()
+            );
+            total_entries += entries.len();
+            total_size += size;
+        }
+        println!(
+            "{:<10}  {:>6} modules  {:>10}",
+            "total",
+            total_entries,
+            ByteSize(total_size).to_string()
+        );
+        if let Some(max_size) = max_size {
+            println!("Maximum size per compiler: {}", ByteSize(max_size));
+        }
+        if let Some(max_entries) = max_entries {
+            println!("Maximum modules per compiler: {}", max_entries);
+        }
+        Ok(())
+    }
+    /// Get the cache of each compiler, which live in their own directory.
+    #[cfg(feature = "cache")]
+    fn compiler_caches() -> Result<Vec<(String, FileSystemCache)>> {
+        let cache_dir = get_cache_dir();
+        let mut caches = Vec::new();
+        if !cache_dir.exists() {
+            return Ok(caches);
+        }
+        for dir_entry in fs::read_dir(cache_dir)? {
+            let dir_entry = dir_entry?;
+            if dir_entry.file_type()?.is_dir() {
+                let compiler = dir_entry.file_name().to_string_lossy().to_string();
+                caches.push((compiler, FileSystemCache::new(dir_entry.path())?));
+            }
+        }
+        caches.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(caches)
+    }
+}
+
+/// Format the last time an entry was used, relative to now.
+#[cfg(feature = "cache")]
+fn format_accessed(entry: &CacheEntry) -> String {
+    let elapsed = match SystemTime::now().duration_since(entry.accessed) {
+        Ok(elapsed) => elapsed.as_secs(),
+        Err(_) => 0,
+    };
+    match elapsed {
+        0..=59 => format!("{}s ago", elapsed),
+        60..=3599 => format!("{}m ago", elapsed / 60),
+        3600..=86399 => format!("{}h ago", elapsed / 3600),
+        _ => format!("{}d ago", elapsed / 86400),
+    }
 }
@@ -1,6 +1,8 @@
 use crate::store::{EngineType, StoreOptions};
 use crate::warning;
 use anyhow::{Context, Result};
+#[cfg(feature = "compiler")]
+use std::path::Path;
 use std::path::PathBuf;
 use structopt::StructOpt;
 use wasmer::*;
@@ -29,6 +31,26 @@ pub struct Compile {
 
     #[structopt(short = "m", multiple = true)]
     cpu_features: Vec<CpuFeature>,
+
+    /// Pre-initialize the module by calling this export before compiling it
+    #[cfg(feature = "compiler")]
+    #[structopt(long = "pre-init", name = "INIT FUNCTION")]
+    pre_init: Option<String>,
+
+    /// Output path for the pre-initialized Wasm module
+    #[cfg(feature = "compiler")]
+    #[structopt(
+        long = "pre-init-output",
+        name = "PRE-INIT PATH",
+        requires = "INIT FUNCTION",
+        parse(from_os_str)
+    )]
+    pre_init_output: Option<PathBuf>,
+
+    /// Output path for the disassembly of the compiled functions
+    #[cfg(feature = "compiler")]
+    #[structopt(long = "emit-asm", name = "ASM PATH", parse(from_os_str))]
+    emit_asm: Option<PathBuf>,
 }
 
 impl Compile {
@@ -96,6 +118,22 @@ impl Compile {
         println!("Compiler: {}", compiler_type.to_string());
         println!("Target: {}", target.triple());
 
+        #[cfg(feature = "compiler")]
+        let module = match &self.pre_init {
+            Some(init_func) => {
+                let wasm = self.pre_initialize(init_func)?;
+                if let Some(pre_init_output) = &self.pre_init_output {
+                    std::fs::write(pre_init_output, &wasm)?;
+                    eprintln!(
+                        "✔ Pre-initialized module written to `{}`.",
+                        pre_init_output.display(),
+                    );
+                }
+                Module::new(&store, &wasm)?
+            }
+            None => Module::from_file(&store, &self.path)?,
+        };
+        #[cfg(not(feature = "compiler"))]
         let module = Module::from_file(&store, &self.path)?;
         let _ = module.serialize_to_file(&self.output)?;
         eprintln!(
@@ -103,6 +141,11 @@ impl Compile {
             self.output.display(),
         );
 
+        #[cfg(feature = "compiler")]
+        if let Some(asm_path) = &self.emit_asm {
+            Self::emit_asm(&target, &module, engine_type, asm_path)?;
+        }
+
         #[cfg(feature = "object-file")]
         if engine_type == EngineType::ObjectFile {
             let artifact: &wasmer_engine_object_file::ObjectFileArtifact =
@@ -142,4 +185,67 @@ impl Compile {
         }
         Ok(())
     }
+
+    /// Writes the disassembly of the functions of the compiled `module`
+    /// to `asm_path`.
+    #[cfg(feature = "compiler")]
+    fn emit_asm(
+        target: &Target,
+        module: &Module,
+        engine_type: EngineType,
+        asm_path: &Path,
+    ) -> Result<()> {
+        use wasmer_object::Disassembler;
+
+        if module.artifact().compilation().is_none() {
+            bail!(
+                "the {} engine doesn't keep the compiled functions, use `--jit` to disassemble them",
+                engine_type.to_string()
+            );
+        }
+        let listing =
+            Disassembler::new(target.triple())?.disassemble_artifact(module.artifact().as_ref())?;
+        std::fs::write(asm_path, listing)?;
+        eprintln!(
+            "✔ Disassembly written successfully to `{}`.",
+            asm_path.display(),
+        );
+        Ok(())
+    }
+
+    /// Runs the `init_func` export of the module and returns a module
+    /// with the resulting state as its initial state.
+    ///
+    /// The module is always run on the host, whatever the target is.
+    #[cfg(feature = "compiler")]
+    fn pre_initialize(&self, init_func: &str) -> Result<Vec<u8>> {
+        let (store, _engine_type, _compiler_type) = self.store.get_store()?;
+        let contents = std::fs::read(&self.path)?;
+        #[cfg(feature = "wat")]
+        let contents = wat2wasm(&contents)?.into_owned();
+
+        #[cfg(feature = "wasi")]
+        {
+            use wasmer_wasi::{get_wasi_version, WasiState};
+
+            let module = Module::new(&store, &contents)?;
+            if get_wasi_version(&module, true).is_some() {
+                let program_name = self
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let mut wasi_env = WasiState::new(program_name).finalize()?;
+                let import_object = wasi_env.import_object(&module)?;
+                let wasm =
+                    preinitialize_with(&store, &contents, init_func, &import_object, |instance| {
+                        wasi_env.set_memory(instance.exports.get_memory("memory")?.clone());
+                        Ok(())
+                    })?;
+                return Ok(wasm);
+            }
+        }
+
+        Ok(preinitialize(&store, &contents, init_func, &imports! {})?)
+    }
 }
@@ -1,3 +1,4 @@
+use crate::config::WasmerConfig;
 use crate::VERSION;
 use anyhow::{Context, Result};
 use std::env;
@@ -35,6 +36,24 @@ pub struct Config {
     /// and linking a program to Wasmer, using the `pkg-config` format.
     #[structopt(long)]
     pkg_config: bool,
+
+    /// Print the effective configuration loaded from the `wasmer-cli.toml` files.
+    #[structopt(long, conflicts_with = "pkg_config")]
+    show: bool,
+
+    /// The profile of the `wasmer-cli.toml` files to apply with `--show`.
+    #[structopt(long, name = "PROFILE", requires = "show")]
+    profile: Option<String>,
+
+    /// The configuration file to use with `--show` instead of the
+    /// `wasmer-cli.toml` file found in the current directory or its parents.
+    #[structopt(
+        long = "config",
+        name = "CONFIG",
+        requires = "show",
+        parse(from_os_str)
+    )]
+    config_file: Option<PathBuf>,
 }
 
 impl Config {
@@ -44,6 +63,10 @@ impl Config {
             .context("failed to retrieve the wasmer config".to_string())
     }
     fn inner_execute(&self) -> Result<()> {
+        if self.show {
+            return self.show();
+        }
+
         let key = "WASMER_DIR";
         let wasmer_dir = env::var(key).context(format!(
             "failed to retrieve the {} environment variables",
@@ -93,4 +116,27 @@ impl Config {
         }
         Ok(())
     }
+
+    /// Prints the files the configuration is loaded from and the
+    /// resulting settings, in the `wasmer-cli.toml` format.
+    fn show(&self) -> Result<()> {
+        let config = WasmerConfig::load(self.profile.as_deref(), self.config_file.as_deref())?;
+        if config.files.is_empty() {
+            println!("# No configuration file found");
+        }
+        for file in &config.files {
+            println!("# Loaded from `{}`", file.display());
+        }
+        if let Some(profile) = &config.profile {
+            println!("# With the `{}` profile", profile);
+        }
+        for file in &config.ignored_capabilities {
+            println!(
+                "# Ignoring the capabilities granted by `{}`, without a profile",
+                file.display()
+            );
+        }
+        print!("{}", toml::to_string(&config.settings)?);
+        Ok(())
+    }
 }
@@ -1,74 +1,652 @@
 use crate::store::StoreOptions;
 use anyhow::{Context, Result};
 use bytesize::ByteSize;
+use serde::Serialize;
 use std::path::PathBuf;
 use structopt::StructOpt;
+use wasmer::wasmparser::{
+    DataKind, ElementKind, ModuleReader, OperatorValidatorConfig, SectionCode,
+    ValidatingParserConfig,
+};
 use wasmer::*;
+#[cfg(any(feature = "jit", feature = "native"))]
+use wasmer_engine::ArtifactSummary;
+#[cfg(feature = "jit")]
+use wasmer_engine_jit::JITArtifact;
+#[cfg(feature = "native")]
+use wasmer_engine_native::NativeArtifact;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::LocalFunctionIndex;
+use wasmer_vm::ModuleInfo;
+
+/// The number of functions listed by their size.
+const LARGEST_FUNCTIONS: usize = 10;
 
 #[derive(Debug, StructOpt)]
-/// The options for the `wasmer validate` subcommand
+/// The options for the `wasmer inspect` subcommand
 pub struct Inspect {
-    /// File to validate as WebAssembly
+    /// File to inspect
     #[structopt(name = "FILE", parse(from_os_str))]
     path: PathBuf,
 
+    /// Output the report as JSON
+    #[structopt(long = "json")]
+    json: bool,
+
     #[structopt(flatten)]
     store: StoreOptions,
 }
 
+/// What `wasmer inspect` reports about a module.
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    size: u64,
+    name: Option<String>,
+    wasi_version: Option<&'static str>,
+    features: Vec<&'static str>,
+    start: Option<FunctionReport>,
+    sections: Vec<SectionReport>,
+    functions: FunctionsReport,
+    data: DataReport,
+    elements: ElementsReport,
+    imports: Vec<ImportReport>,
+    exports: Vec<ExportReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct SectionReport {
+    name: String,
+    custom: bool,
+    offset: usize,
+    size: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct FunctionsReport {
+    imported: usize,
+    defined: usize,
+    named: usize,
+    code_size: u64,
+    largest: Vec<FunctionReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct FunctionReport {
+    index: u32,
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct DataReport {
+    active: usize,
+    passive: usize,
+    bytes: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ElementsReport {
+    active: usize,
+    passive: usize,
+    declared: usize,
+    items: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportReport {
+    module: String,
+    name: String,
+    kind: &'static str,
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportReport {
+    name: String,
+    kind: &'static str,
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+/// What `wasmer inspect` reports about a compiled artifact.
+#[cfg(any(feature = "jit", feature = "native"))]
+#[derive(Debug, Serialize)]
+struct ArtifactReport {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    engine: &'static str,
+    size: u64,
+    target: String,
+    name: Option<String>,
+    features: Vec<&'static str>,
+    memory_styles: Vec<String>,
+    table_styles: Vec<String>,
+    functions: FunctionsReport,
+    function_call_trampolines: usize,
+    dynamic_function_trampolines: usize,
+    relocations: usize,
+    custom_sections: Vec<CustomSectionReport>,
+    frame_info: bool,
+    unwind_info: bool,
+    data_initializers: usize,
+    imports: Vec<ImportReport>,
+    exports: Vec<ExportReport>,
+}
+
+#[cfg(any(feature = "jit", feature = "native"))]
+#[derive(Debug, Serialize)]
+struct CustomSectionReport {
+    name: String,
+    size: u64,
+}
+
 impl Inspect {
-    /// Runs logic for the `validate` subcommand
+    /// Runs logic for the `inspect` subcommand
     pub fn execute(&self) -> Result<()> {
         self.inner_execute()
             .context(format!("failed to inspect `{}`", self.path.display()))
     }
     fn inner_execute(&self) -> Result<()> {
-        let (store, _engine_type, _compiler_type) = self.store.get_store()?;
         let module_contents = std::fs::read(&self.path)?;
+        #[cfg(feature = "jit")]
+        {
+            if JITArtifact::is_deserializable(&module_contents) {
+                let summary = JITArtifact::summarize(&module_contents)?;
+                return self.print(&ArtifactReport::new(summary, module_contents.len() as _));
+            }
+        }
+        #[cfg(feature = "native")]
+        {
+            if NativeArtifact::is_deserializable(&module_contents) {
+                let summary = NativeArtifact::summarize(&module_contents)?;
+                return self.print(&ArtifactReport::new(summary, module_contents.len() as _));
+            }
+        }
+        let (store, _engine_type, _compiler_type) = self.store.get_store()?;
         let module = Module::new(&store, &module_contents)?;
-        println!(
-            "Type: {}",
-            if !is_wasm(&module_contents) {
-                "wat"
-            } else {
-                "wasm"
+        #[cfg(feature = "wat")]
+        let wasm = wat2wasm(&module_contents)?;
+        #[cfg(not(feature = "wat"))]
+        let wasm = std::borrow::Cow::Borrowed(&module_contents[..]);
+        let mut report = Report::new(&wasm, &module)?;
+        report.kind = if !is_wasm(&module_contents) {
+            "wat"
+        } else {
+            "wasm"
+        };
+        report.size = module_contents.len() as _;
+        self.print(&report)
+    }
+
+    fn print<R: Serialize + PrintReport>(&self, report: &R) -> Result<()> {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(report)?);
+        } else {
+            report.print();
+        }
+        Ok(())
+    }
+}
+
+/// A report printed as text.
+trait PrintReport {
+    fn print(&self);
+}
+
+impl Report {
+    /// Build the report of `module`, compiled from the binary `wasm`. The
+    /// type and the size of the file are filled by the caller.
+    fn new(wasm: &[u8], module: &Module) -> Result<Self> {
+        let info = module.info();
+        let mut report = Self {
+            name: module.name().map(str::to_string),
+            features: features_needed(wasm),
+            start: info.start_function.map(|index| FunctionReport {
+                index: index.as_u32(),
+                name: info.function_names.get(&index).cloned(),
+                size: None,
+            }),
+            ..Self::default()
+        };
+        #[cfg(feature = "wasi")]
+        {
+            report.wasi_version =
+                wasmer_wasi::get_wasi_version(module, false).map(|version| match version {
+                    wasmer_wasi::WasiVersion::Snapshot0 => "wasi_unstable",
+                    _ => "wasi_snapshot_preview1",
+                });
+        }
+
+        let mut bodies = Vec::new();
+        let mut reader = ModuleReader::new(wasm)?;
+        while !reader.eof() {
+            let section = reader.read()?;
+            let range = section.range();
+            let (name, custom) = match section.code {
+                SectionCode::Custom { name, .. } => (name.to_string(), true),
+                code => (section_name(&code).to_string(), false),
+            };
+            report.sections.push(SectionReport {
+                name,
+                custom,
+                offset: range.start,
+                size: range.end - range.start,
+            });
+            match section.code {
+                SectionCode::Code => {
+                    for body in section.get_code_section_reader()? {
+                        let range = body?.range();
+                        bodies.push((range.end - range.start) as u64);
+                    }
+                }
+                SectionCode::Data => {
+                    for data in section.get_data_section_reader()? {
+                        let data = data?;
+                        match data.kind {
+                            DataKind::Passive => report.data.passive += 1,
+                            DataKind::Active { .. } => report.data.active += 1,
+                        }
+                        report.data.bytes += data.data.len();
+                    }
+                }
+                SectionCode::Element => {
+                    for element in section.get_element_section_reader()? {
+                        let element = element?;
+                        match element.kind {
+                            ElementKind::Passive => report.elements.passive += 1,
+                            ElementKind::Active { .. } => report.elements.active += 1,
+                            ElementKind::Declared => report.elements.declared += 1,
+                        }
+                        report.elements.items +=
+                            element.items.get_items_reader()?.get_count() as usize;
+                    }
+                }
+                _ => {}
             }
+        }
+
+        report.functions = functions_report(info, bodies.into_iter().collect());
+        let (imports, exports) = externs_report(info);
+        report.imports = imports;
+        report.exports = exports;
+        Ok(report)
+    }
+}
+
+impl PrintReport for Report {
+    fn print(&self) {
+        println!("Type: {}", self.kind);
+        println!("Size: {}", ByteSize(self.size));
+        if let Some(name) = &self.name {
+            println!("Name: {}", name);
+        }
+        if let Some(wasi_version) = self.wasi_version {
+            println!("WASI version: {}", wasi_version);
+        }
+        if self.features.is_empty() {
+            println!("Features: none");
+        } else {
+            println!("Features: {}", self.features.join(", "));
+        }
+        if let Some(start) = &self.start {
+            println!("Start function: {}", start);
+        }
+        println!("Sections:");
+        for section in &self.sections {
+            let name = if section.custom {
+                format!("custom \"{}\"", section.name)
+            } else {
+                section.name.clone()
+            };
+            println!(
+                "  {:<20} {:>10} at 0x{:x}",
+                name,
+                ByteSize(section.size as _).to_string(),
+                section.offset
+            );
+        }
+        print_functions(&self.functions);
+        println!(
+            "Data segments: {} active, {} passive, {}",
+            self.data.active,
+            self.data.passive,
+            ByteSize(self.data.bytes as _)
         );
-        println!("Size: {}", ByteSize(module_contents.len() as _));
-        println!("Imports:");
-        println!("  Functions:");
-        for f in module.imports().functions() {
-            println!("    \"{}\".\"{}\": {}", f.module(), f.name(), f.ty());
+        println!(
+            "Element segments: {} active, {} passive, {} declared, {} items",
+            self.elements.active,
+            self.elements.passive,
+            self.elements.declared,
+            self.elements.items
+        );
+        print_externs(&self.imports, &self.exports);
+    }
+}
+
+/// The titles of the kinds of imports and exports, in printing order.
+const KINDS: &[(&str, &str)] = &[
+    ("Functions", "function"),
+    ("Memories", "memory"),
+    ("Tables", "table"),
+    ("Globals", "global"),
+];
+
+#[cfg(any(feature = "jit", feature = "native"))]
+impl ArtifactReport {
+    /// Build the report of an artifact of `size` bytes.
+    fn new(summary: ArtifactSummary, size: u64) -> Self {
+        let info = &summary.compile_info.module;
+        let (imports, exports) = externs_report(info);
+        Self {
+            kind: "artifact",
+            engine: summary.engine,
+            size,
+            target: summary.target,
+            name: info.name.clone(),
+            features: enabled_features(&summary.compile_info.features),
+            memory_styles: summary
+                .compile_info
+                .memory_styles
+                .values()
+                .map(|style| format!("{:?}", style))
+                .collect(),
+            table_styles: summary
+                .compile_info
+                .table_styles
+                .values()
+                .map(|style| format!("{:?}", style))
+                .collect(),
+            functions: functions_report(info, summary.function_sizes.values().cloned().collect()),
+            function_call_trampolines: summary.function_call_trampolines,
+            dynamic_function_trampolines: summary.dynamic_function_trampolines,
+            relocations: summary.relocations,
+            custom_sections: summary
+                .custom_sections
+                .into_iter()
+                .map(|(name, size)| CustomSectionReport { name, size })
+                .collect(),
+            frame_info: summary.frame_info,
+            unwind_info: summary.unwind_info,
+            data_initializers: summary.data_initializers,
+            imports,
+            exports,
         }
-        println!("  Memories:");
-        for f in module.imports().memories() {
-            println!("    \"{}\".\"{}\": {}", f.module(), f.name(), f.ty());
+    }
+}
+
+#[cfg(any(feature = "jit", feature = "native"))]
+impl PrintReport for ArtifactReport {
+    fn print(&self) {
+        println!("Type: {} artifact", self.engine);
+        println!("Size: {}", ByteSize(self.size));
+        println!("Target: {}", self.target);
+        if let Some(name) = &self.name {
+            println!("Name: {}", name);
         }
-        println!("  Tables:");
-        for f in module.imports().tables() {
-            println!("    \"{}\".\"{}\": {}", f.module(), f.name(), f.ty());
+        if self.features.is_empty() {
+            println!("Features: none");
+        } else {
+            println!("Features: {}", self.features.join(", "));
         }
-        println!("  Globals:");
-        for f in module.imports().globals() {
-            println!("    \"{}\".\"{}\": {}", f.module(), f.name(), f.ty());
+        println!("Memory styles:");
+        for (index, style) in self.memory_styles.iter().enumerate() {
+            println!("  {}: {}", index, style);
         }
-        println!("Exports:");
-        println!("  Functions:");
-        for f in module.exports().functions() {
-            println!("    \"{}\": {}", f.name(), f.ty());
+        println!("Table styles:");
+        for (index, style) in self.table_styles.iter().enumerate() {
+            println!("  {}: {}", index, style);
         }
-        println!("  Memories:");
-        for f in module.exports().memories() {
-            println!("    \"{}\": {}", f.name(), f.ty());
+        print_functions(&self.functions);
+        println!(
+            "Trampolines: {} function call, {} dynamic function",
+            self.function_call_trampolines, self.dynamic_function_trampolines
+        );
+        println!("Relocations: {}", self.relocations);
+        println!("Custom sections:");
+        for section in &self.custom_sections {
+            println!("  {}: {}", section.name, ByteSize(section.size));
         }
-        println!("  Tables:");
-        for f in module.exports().tables() {
-            println!("    \"{}\": {}", f.name(), f.ty());
+        println!("Frame info: {}", if self.frame_info { "yes" } else { "no" });
+        println!(
+            "Unwind info: {}",
+            if self.unwind_info { "yes" } else { "no" }
+        );
+        println!("Data initializers: {}", self.data_initializers);
+        print_externs(&self.imports, &self.exports);
+    }
+}
+
+/// The report of the functions defined by a module, given the size of
+/// the code of each one.
+fn functions_report(info: &ModuleInfo, sizes: Vec<u64>) -> FunctionsReport {
+    let mut largest = sizes
+        .iter()
+        .enumerate()
+        .map(|(index, size)| (info.func_index(LocalFunctionIndex::new(index)), *size))
+        .collect::<Vec<_>>();
+    largest.sort_by(|(a_index, a_size), (b_index, b_size)| {
+        b_size.cmp(a_size).then(a_index.cmp(b_index))
+    });
+    FunctionsReport {
+        imported: info.num_imported_functions,
+        defined: sizes.len(),
+        named: info.function_names.len(),
+        code_size: sizes.iter().sum(),
+        largest: largest
+            .into_iter()
+            .take(LARGEST_FUNCTIONS)
+            .map(|(index, size)| FunctionReport {
+                index: index.as_u32(),
+                name: info.function_names.get(&index).cloned(),
+                size: Some(size),
+            })
+            .collect(),
+    }
+}
+
+/// The report of the imports and the exports of a module.
+fn externs_report(info: &ModuleInfo) -> (Vec<ImportReport>, Vec<ExportReport>) {
+    let imports = info
+        .imports()
+        .map(|import| {
+            let (kind, ty) = extern_type(import.ty());
+            ImportReport {
+                module: import.module().to_string(),
+                name: import.name().to_string(),
+                kind,
+                ty,
+            }
+        })
+        .collect();
+    let exports = info
+        .exports()
+        .map(|export| {
+            let (kind, ty) = extern_type(export.ty());
+            ExportReport {
+                name: export.name().to_string(),
+                kind,
+                ty,
+            }
+        })
+        .collect();
+    (imports, exports)
+}
+
+fn print_functions(functions: &FunctionsReport) {
+    println!("Functions:");
+    println!("  Imported: {}", functions.imported);
+    println!("  Defined: {}", functions.defined);
+    println!("  Named: {}", functions.named);
+    println!("  Code size: {}", ByteSize(functions.code_size));
+    println!("  Largest:");
+    for function in &functions.largest {
+        println!(
+            "    {}: {}",
+            function,
+            ByteSize(function.size.unwrap_or_default())
+        );
+    }
+}
+
+fn print_externs(imports: &[ImportReport], exports: &[ExportReport]) {
+    println!("Imports:");
+    for (title, kind) in KINDS {
+        println!("  {}:", title);
+        for import in imports.iter().filter(|import| import.kind == *kind) {
+            println!(
+                "    \"{}\".\"{}\": {}",
+                import.module, import.name, import.ty
+            );
         }
-        println!("  Globals:");
-        for f in module.exports().globals() {
-            println!("    \"{}\": {}", f.name(), f.ty());
+    }
+    println!("Exports:");
+    for (title, kind) in KINDS {
+        println!("  {}:", title);
+        for export in exports.iter().filter(|export| export.kind == *kind) {
+            println!("    \"{}\": {}", export.name, export.ty);
         }
-        Ok(())
+    }
+}
+
+/// The names of the WebAssembly proposals enabled in `features`.
+#[cfg(any(feature = "jit", feature = "native"))]
+fn enabled_features(features: &Features) -> Vec<&'static str> {
+    let mut enabled = vec![];
+    if features.threads {
+        enabled.push("threads");
+    }
+    if features.reference_types {
+        enabled.push("reference-types");
+    }
+    if features.simd {
+        enabled.push("simd");
+    }
+    if features.bulk_memory {
+        enabled.push("bulk-memory");
+    }
+    if features.multi_value {
+        enabled.push("multi-value");
+    }
+    enabled
+}
+
+impl std::fmt::Display for FunctionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} (${})", self.index, name),
+            None => write!(f, "{}", self.index),
+        }
+    }
+}
+
+/// The kind and the type of an import or an export.
+fn extern_type(ty: &ExternType) -> (&'static str, String) {
+    match ty {
+        ExternType::Function(ty) => ("function", ty.to_string()),
+        ExternType::Memory(ty) => ("memory", ty.to_string()),
+        ExternType::Table(ty) => ("table", ty.to_string()),
+        ExternType::Global(ty) => ("global", ty.to_string()),
+    }
+}
+
+/// The name of a standard section.
+fn section_name(code: &SectionCode) -> &'static str {
+    match code {
+        SectionCode::Custom { .. } => "custom",
+        SectionCode::Type => "type",
+        SectionCode::Import => "import",
+        SectionCode::Function => "function",
+        SectionCode::Table => "table",
+        SectionCode::Memory => "memory",
+        SectionCode::Global => "global",
+        SectionCode::Export => "export",
+        SectionCode::Start => "start",
+        SectionCode::Element => "element",
+        SectionCode::Code => "code",
+        SectionCode::Data => "data",
+        SectionCode::DataCount => "datacount",
+    }
+}
+
+/// The WebAssembly proposals a module needs, which are the ones it
+/// doesn't validate without.
+fn features_needed(wasm: &[u8]) -> Vec<&'static str> {
+    let all = OperatorValidatorConfig {
+        enable_threads: true,
+        enable_reference_types: true,
+        enable_simd: true,
+        enable_bulk_memory: true,
+        enable_multi_value: true,
+        enable_tail_call: false,
+    };
+    let validates = |operator_config| {
+        wasmparser::validate(wasm, Some(ValidatingParserConfig { operator_config })).is_ok()
+    };
+    let without: [(&str, OperatorValidatorConfig); 5] = [
+        (
+            "threads",
+            OperatorValidatorConfig {
+                enable_threads: false,
+                ..all
+            },
+        ),
+        (
+            "reference-types",
+            OperatorValidatorConfig {
+                enable_reference_types: false,
+                ..all
+            },
+        ),
+        (
+            "simd",
+            OperatorValidatorConfig {
+                enable_simd: false,
+                ..all
+            },
+        ),
+        (
+            "bulk-memory",
+            OperatorValidatorConfig {
+                enable_bulk_memory: false,
+                ..all
+            },
+        ),
+        (
+            "multi-value",
+            OperatorValidatorConfig {
+                enable_multi_value: false,
+                ..all
+            },
+        ),
+    ];
+    if !validates(all) {
+        return vec![];
+    }
+    without
+        .iter()
+        .filter(|(_, config)| !validates(*config))
+        .map(|(feature, _)| *feature)
+        .collect()
+}
+
+#[cfg(all(test, feature = "wat"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features_needed_by_modules() {
+        let wasm = wat2wasm(b"(module (func (result i32 i32) i32.const 0 i32.const 1))").unwrap();
+        assert_eq!(features_needed(&wasm), vec!["multi-value"]);
+        let wasm = wat2wasm(
+            b"(module (memory 1) (func (param v128) (v128.store (i32.const 0) (local.get 0))))",
+        )
+        .unwrap();
+        assert_eq!(features_needed(&wasm), vec!["simd"]);
+        let wasm = wat2wasm(b"(module (func))").unwrap();
+        assert!(features_needed(&wasm).is_empty());
     }
 }
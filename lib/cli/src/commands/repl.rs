@@ -0,0 +1,410 @@
+use super::run::invoke::{format_results, parse_value, OutputFormat};
+#[cfg(feature = "wasi")]
+use super::run::Wasi;
+use crate::store::StoreOptions;
+use anyhow::{anyhow, bail, Context, Result};
+use colored::*;
+use std::convert::TryFrom;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use wasmer::*;
+
+/// The commands of the REPL.
+const HELP: &str = "\
+Commands:
+  exports                     List the exports of the instance
+  call FUNCTION [ARGS...]     Call an exported function
+  get GLOBAL                  Read an exported global
+  set GLOBAL VALUE            Write an exported mutable global
+  read MEMORY OFFSET LENGTH   Dump a range of an exported memory
+  write MEMORY OFFSET HEX     Write hexadecimal bytes to an exported memory
+  grow MEMORY DELTA           Grow an exported memory by DELTA pages
+  grow TABLE DELTA            Grow an exported table by DELTA null elements
+  trace                       Print the stack trace of the last error
+  help                        Print this message
+  quit                        Exit the REPL";
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer repl` subcommand
+pub struct Repl {
+    /// File to explore
+    #[structopt(name = "FILE", parse(from_os_str))]
+    path: PathBuf,
+
+    #[structopt(flatten)]
+    store: StoreOptions,
+
+    #[cfg(feature = "wasi")]
+    #[structopt(flatten)]
+    wasi: Wasi,
+}
+
+impl Repl {
+    /// Runs logic for the `repl` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute()
+            .context(format!("failed to explore `{}`", self.path.display()))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let (store, _engine_type, _compiler_type) = self.store.get_store()?;
+        let contents = std::fs::read(&self.path)?;
+        let mut module = Module::new(&store, &contents)?;
+        module.set_name(&self.path.file_name().unwrap_or_default().to_string_lossy());
+        let mut session = Session {
+            instance: self.instantiate(&module)?,
+            last_error: None,
+        };
+
+        eprintln!(
+            "Exploring `{}`. Type `help` for the list of commands.",
+            self.path.display()
+        );
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            eprint!("> ");
+            io::stderr().flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+            match session.eval(&line) {
+                Ok(Some(output)) => {
+                    if !output.is_empty() {
+                        println!("{}", output);
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => eprintln!("{}: {:#}", "error".red().bold(), err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Instantiates the module, with the WASI imports if it needs them.
+    fn instantiate(&self, module: &Module) -> Result<Instance> {
+        #[cfg(feature = "wasi")]
+        {
+            if Wasi::get_version(module).is_some() {
+                let program_name = self
+                    .path
+                    .file_name()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let wasi = self.wasi.with_config(&self.store.config()?.settings)?;
+                return wasi.instantiate(module, program_name, vec![]);
+            }
+        }
+        Ok(Instance::new(module, &imports! {})?)
+    }
+}
+
+/// The state of a REPL session.
+struct Session {
+    instance: Instance,
+    last_error: Option<RuntimeError>,
+}
+
+impl Session {
+    /// Evaluates a line, returning its output, or `None` to quit.
+    fn eval(&mut self, line: &str) -> Result<Option<String>> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let output = match words.as_slice() {
+            [] => String::new(),
+            ["help"] => HELP.to_string(),
+            ["quit"] | ["exit"] => return Ok(None),
+            ["exports"] => self.exports(),
+            ["call", name, args @ ..] => self.call(name, args)?,
+            ["get", name] => {
+                let global = self.instance.exports.get_global(name)?;
+                format_results(&[global.get()], OutputFormat::Text)
+            }
+            ["set", name, value] => {
+                let global = self.instance.exports.get_global(name)?;
+                let value = parse_value(&self.instance, value, &global.ty().ty)?;
+                global.set(value)?;
+                String::new()
+            }
+            ["read", name, offset, length] => {
+                let memory = self.instance.exports.get_memory(name)?;
+                let offset = parse_number(offset)?;
+                let length = parse_number(length)?;
+                let range = memory_range(memory, offset, length)?;
+                let bytes = memory.view::<u8>()[range]
+                    .iter()
+                    .map(|byte| byte.get())
+                    .collect::<Vec<_>>();
+                hexdump(offset, &bytes)
+            }
+            ["write", name, offset, hex] => {
+                let memory = self.instance.exports.get_memory(name)?;
+                let bytes = parse_hex(hex)?;
+                let range = memory_range(memory, parse_number(offset)?, bytes.len() as u64)?;
+                for (cell, byte) in memory.view::<u8>()[range].iter().zip(bytes) {
+                    cell.set(byte);
+                }
+                String::new()
+            }
+            ["grow", name, delta] => {
+                let delta = u32::try_from(parse_number(delta)?)
+                    .with_context(|| format!("The delta `{}` doesn't fit in 32 bits", delta))?;
+                match self.instance.exports.get_extern(name) {
+                    Some(Extern::Memory(memory)) => {
+                        let previous = memory.grow(Pages(delta))?;
+                        format!("{} -> {} pages", previous.0, memory.size().0)
+                    }
+                    Some(Extern::Table(table)) => {
+                        let previous = table.grow(delta, Val::ExternRef(ExternRef::Null))?;
+                        format!("{} -> {} elements", previous, table.size())
+                    }
+                    _ => bail!("No memory or table `{}` is exported", name),
+                }
+            }
+            ["trace"] => match &self.last_error {
+                Some(error) => format_trace(error),
+                None => "No call failed yet.".to_string(),
+            },
+            [command, ..] => bail!(
+                "Unknown command or arguments `{}`, type `help` for the list of commands",
+                command
+            ),
+        };
+        Ok(Some(output))
+    }
+
+    /// Lists the exports of the instance, sorted by name.
+    fn exports(&self) -> String {
+        let mut exports = self
+            .instance
+            .exports
+            .iter()
+            .map(|(name, export)| match export {
+                Extern::Function(f) => format!("func {}: {}", name, f.ty()),
+                Extern::Global(g) => format!("global {}: {}", name, g.ty()),
+                Extern::Memory(m) => format!("memory {}: {}", name, m.ty()),
+                Extern::Table(t) => format!("table {}: {}", name, t.ty()),
+            })
+            .collect::<Vec<_>>();
+        exports.sort();
+        exports.join("\n")
+    }
+
+    /// Calls an exported function, remembering the error if it fails.
+    fn call(&mut self, name: &str, args: &[&str]) -> Result<String> {
+        let func = self.instance.exports.get_function(name)?;
+        let params = func.ty().params();
+        if params.len() != args.len() {
+            bail!(
+                "Function expected {} arguments, but received {}",
+                params.len(),
+                args.len()
+            );
+        }
+        let args = args
+            .iter()
+            .zip(params.iter())
+            .map(|(arg, ty)| parse_value(&self.instance, arg, ty))
+            .collect::<Result<Vec<_>>>()?;
+        match func.call(&args) {
+            Ok(results) => Ok(format_results(&results, OutputFormat::Text)),
+            Err(error) => {
+                let message = error.message();
+                self.last_error = Some(error);
+                Err(anyhow!("{} (type `trace` for the stack trace)", message))
+            }
+        }
+    }
+}
+
+/// Parse a decimal number, or a hexadecimal one prefixed with `0x`.
+fn parse_number(arg: &str) -> Result<u64> {
+    match arg.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => arg.parse(),
+    }
+    .with_context(|| format!("Can't convert `{}` into a number", arg))
+}
+
+/// Parse bytes written in hexadecimal, such as `0061736d`.
+fn parse_hex(hex: &str) -> Result<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    hex.as_bytes()
+        .chunks(2)
+        .map(|digits| {
+            std::str::from_utf8(digits)
+                .ok()
+                .filter(|digits| digits.len() == 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .with_context(|| format!("`{}` is not a list of hexadecimal bytes", hex))
+        })
+        .collect()
+}
+
+/// Returns the range of `length` bytes at `offset`, if it's in `memory`.
+fn memory_range(memory: &Memory, offset: u64, length: u64) -> Result<std::ops::Range<usize>> {
+    match offset.checked_add(length) {
+        Some(end) if end <= memory.data_size() => Ok(offset as usize..end as usize),
+        _ => bail!(
+            "The range is out of the bounds of the memory, which has {} bytes",
+            memory.data_size()
+        ),
+    }
+}
+
+/// Formats bytes read at `offset` as lines of 16 hexadecimal bytes
+/// followed by their ASCII characters.
+fn hexdump(offset: u64, bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            format!("{:08x}  {:<47}  |{}|", offset + i as u64 * 16, hex, ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the message and the WebAssembly frames of an error.
+fn format_trace(error: &RuntimeError) -> String {
+    let mut lines = vec![error.message()];
+    for (i, frame) in error.trace().iter().enumerate() {
+        let function = match frame.function_name() {
+            Some(name) => name.to_string(),
+            None => format!("<func {}>", frame.func_index()),
+        };
+        lines.push(format!(
+            "  {}: {}!{} @ 0x{:x}",
+            i,
+            frame.module_name(),
+            function,
+            frame.module_offset()
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hexdump_lines() {
+        assert_eq!(
+            hexdump(0x10, b"\0asm\x01\0\0\0hello, world!"),
+            "00000010  00 61 73 6d 01 00 00 00 68 65 6c 6c 6f 2c 20 77  |.asm....hello, w|\n\
+             00000020  6f 72 6c 64 21                                   |orld!|"
+        );
+    }
+
+    #[test]
+    fn parse_hex_bytes() {
+        assert_eq!(parse_hex("0061736d").unwrap(), b"\0asm");
+        assert_eq!(parse_hex("0xff").unwrap(), [0xff]);
+        assert!(parse_hex("abc").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+
+    /// The tests evaluating commands, which need a compiler.
+    #[cfg(feature = "compiler")]
+    mod eval {
+        use super::*;
+
+        /// Starts a session on an instance of `wat`.
+        fn session(wat: &str) -> Session {
+            let (store, _, _) = StoreOptions::from_iter(&["repl"]).get_store().unwrap();
+            let module = Module::new(&store, wat).unwrap();
+            Session {
+                instance: Instance::new(&module, &imports! {}).unwrap(),
+                last_error: None,
+            }
+        }
+
+        const MODULE: &str = r#"
+            (module
+              (memory (export "mem") 1 3)
+              (global (export "counter") (mut i32) (i32.const 0))
+              (global (export "constant") i32 (i32.const 7))
+              (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+              (func $fail (export "fail")
+                unreachable))
+        "#;
+
+        /// Evaluates a line which doesn't fail nor quit.
+        fn eval(session: &mut Session, line: &str) -> String {
+            session.eval(line).unwrap().unwrap()
+        }
+
+        #[test]
+        fn eval_calls() {
+            let mut session = session(MODULE);
+            assert_eq!(eval(&mut session, "call add 1 0x2"), "3");
+            assert!(session.eval("call add 1").is_err());
+            assert!(session.eval("call nope").is_err());
+            assert_eq!(eval(&mut session, "trace"), "No call failed yet.");
+
+            let error = session.eval("call fail").unwrap_err().to_string();
+            assert!(error.contains("type `trace`"), "{}", error);
+            let trace = eval(&mut session, "trace");
+            assert!(trace.starts_with("unreachable"), "{}", trace);
+            assert!(trace.contains("!fail @ 0x"), "{}", trace);
+        }
+
+        #[test]
+        fn eval_globals() {
+            let mut session = session(MODULE);
+            assert_eq!(eval(&mut session, "get counter"), "0");
+            assert_eq!(eval(&mut session, "set counter -5"), "");
+            assert_eq!(eval(&mut session, "get counter"), "-5");
+            assert!(session.eval("set constant 1").is_err());
+            assert!(session.eval("set counter 1.5").is_err());
+            assert_eq!(eval(&mut session, "get constant"), "7");
+        }
+
+        #[test]
+        fn eval_memory() {
+            let mut session = session(MODULE);
+            assert_eq!(eval(&mut session, "write mem 0x10 68656c6c6f"), "");
+            assert_eq!(
+                eval(&mut session, "read mem 16 5"),
+                "00000010  68 65 6c 6c 6f                                   |hello|"
+            );
+            assert!(session.eval("read mem 65534 4").is_err());
+            assert!(session.eval("write mem 65535 0000").is_err());
+
+            assert_eq!(eval(&mut session, "grow mem 1"), "1 -> 2 pages");
+            assert!(session.eval("grow mem 0x100000001").is_err());
+            assert!(session.eval("grow mem 2").is_err());
+            assert_eq!(eval(&mut session, "grow mem 0"), "2 -> 2 pages");
+            assert!(session.eval("grow add 1").is_err());
+            assert_eq!(
+                eval(&mut session, "read mem 65534 4"),
+                "0000fffe  00 00 00 00                                      |....|"
+            );
+        }
+
+        #[test]
+        fn eval_quit() {
+            let mut session = session(MODULE);
+            assert!(session.eval("quit").unwrap().is_none());
+            assert!(session.eval("frobnicate").is_err());
+        }
+    }
+}
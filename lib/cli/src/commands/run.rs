@@ -1,4 +1,4 @@
-use crate::common::get_cache_dir;
+use crate::common::{get_cache_budget, get_cache_dir};
 #[cfg(feature = "debug")]
 use crate::logging;
 use crate::store::{CompilerType, EngineType, StoreOptions};
@@ -9,15 +9,18 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use wasmer::*;
 #[cfg(feature = "cache")]
-use wasmer_cache::{Cache, FileSystemCache, Hash};
+use wasmer_cache::{Cache, FileSystemCache, Hash, HttpCache, TieredCache};
 
 use structopt::StructOpt;
 
+pub(crate) mod invoke;
 #[cfg(feature = "wasi")]
 mod wasi;
 
+use invoke::{format_results, parse_script, parse_value, Invocation, OutputFormat};
+
 #[cfg(feature = "wasi")]
-use wasi::Wasi;
+pub(crate) use wasi::Wasi;
 
 #[derive(Debug, StructOpt, Clone)]
 /// The options for the `wasmer run` subcommand
@@ -34,6 +37,20 @@ pub struct Run {
     #[structopt(long = "invoke", short = "i")]
     invoke: Option<String>,
 
+    /// Invoke the functions listed in a script, with one `function arg1 arg2 ...`
+    /// invocation per line, all in the same instance
+    #[structopt(
+        long = "invoke-script",
+        name = "SCRIPT",
+        parse(from_os_str),
+        conflicts_with = "invoke"
+    )]
+    invoke_script: Option<PathBuf>,
+
+    /// The format of the results of the invoked functions: `text` or `json`
+    #[structopt(long = "output", name = "FORMAT", default_value = "text")]
+    output: OutputFormat,
+
     /// The command name is a string that will override the first argument passed
     /// to the wasm program. This is used in wapm to provide nicer output in
     /// help commands and error messages of the running wasm program
@@ -46,6 +63,14 @@ pub struct Run {
     #[structopt(long = "cache-key", hidden = true)]
     cache_key: Option<String>,
 
+    /// URL of a remote HTTP cache of compiled modules, checked after the
+    /// local cache. The native code it serves is run as is, so the server
+    /// is trusted and must be reached over HTTPS, unless the modules are
+    /// signed with the secret key of the WASMER_REMOTE_CACHE_KEY env var
+    #[cfg(feature = "cache")]
+    #[structopt(long = "remote-cache", name = "URL")]
+    remote_cache: Option<String>,
+
     #[structopt(flatten)]
     store: StoreOptions,
 
@@ -91,29 +116,24 @@ impl Run {
 
     fn inner_execute(&self) -> Result<()> {
         let module = self.get_module()?;
-        // Do we want to invoke a function?
-        if let Some(ref invoke) = self.invoke {
-            let imports = imports! {};
-            let instance = Instance::new(&module, &imports)?;
-            let result = self.invoke_function(&instance, &invoke, &self.args)?;
-            println!(
-                "{}",
-                result
-                    .iter()
-                    .map(|val| val.to_string())
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            );
-            return Ok(());
-        }
+        // Do we want to invoke functions?
+        let invocations = self.get_invocations()?;
         #[cfg(feature = "emscripten")]
         {
+            use std::ffi::c_void;
             use wasmer_emscripten::{
-                generate_emscripten_env, is_emscripten_module, run_emscripten_instance, EmEnv,
-                EmscriptenGlobals,
+                generate_emscripten_env, is_emscripten_module, run_emscripten_instance,
+                set_up_emscripten, EmEnv, EmscriptenData, EmscriptenGlobals,
             };
             // TODO: refactor this
             if is_emscripten_module(&module) {
+                #[cfg(feature = "wasi")]
+                let mapped_dirs = self
+                    .wasi
+                    .with_config(&self.store.config()?.settings)?
+                    .mapped_dirs();
+                #[cfg(not(feature = "wasi"))]
+                let mapped_dirs = Vec::new();
                 let mut emscripten_globals = EmscriptenGlobals::new(module.store(), &module)
                     .map_err(|e| anyhow!("{}", e))?;
                 let mut em_env = EmEnv::new();
@@ -122,6 +142,18 @@ impl Run {
                 let mut instance = Instance::new(&module, &import_object)
                     .with_context(|| "Can't instantiate emscripten module")?;
 
+                if let Some(invocations) = invocations {
+                    let mut data = EmscriptenData::new(
+                        &mut instance,
+                        &emscripten_globals.data,
+                        mapped_dirs.into_iter().collect(),
+                    );
+                    em_env.set_memory(emscripten_globals.memory.clone());
+                    em_env.set_data(&mut data as *mut _ as *mut c_void);
+                    set_up_emscripten(&mut instance)?;
+                    return self.invoke_functions(&instance, &invocations);
+                }
+
                 run_emscripten_instance(
                     &mut instance,
                     &mut em_env,
@@ -132,8 +164,8 @@ impl Run {
                         self.path.to_str().unwrap()
                     },
                     self.args.iter().map(|arg| arg.as_str()).collect(),
-                    None,   //run.em_entrypoint.clone(),
-                    vec![], //mapped_dirs,
+                    None, //run.em_entrypoint.clone(),
+                    mapped_dirs,
                 )?;
                 return Ok(());
             }
@@ -144,6 +176,7 @@ impl Run {
         {
             let wasi_version = Wasi::get_version(&module);
             if wasi_version.is_some() {
+                let wasi = self.wasi.with_config(&self.store.config()?.settings)?;
                 let program_name = self
                     .command_name
                     .clone()
@@ -153,8 +186,11 @@ impl Run {
                             .map(|f| f.to_string_lossy().to_string())
                     })
                     .unwrap_or_default();
-                return self
-                    .wasi
+                if let Some(invocations) = invocations {
+                    let instance = wasi.instantiate(&module, program_name, vec![])?;
+                    return self.invoke_functions(&instance, &invocations);
+                }
+                return wasi
                     .execute(module, program_name, self.args.clone())
                     .with_context(|| "WASI execution failed");
             }
@@ -163,12 +199,33 @@ impl Run {
         // Try to instantiate the wasm file, with no provided imports
         let imports = imports! {};
         let instance = Instance::new(&module, &imports)?;
+        if let Some(invocations) = invocations {
+            return self.invoke_functions(&instance, &invocations);
+        }
         let start: Function = self.try_find_function(&instance, "_start", &[])?;
         start.call(&[])?;
 
         Ok(())
     }
 
+    /// Get the functions to invoke with `--invoke` or `--invoke-script`, if any
+    fn get_invocations(&self) -> Result<Option<Vec<Invocation>>> {
+        if let Some(name) = &self.invoke {
+            return Ok(Some(vec![Invocation {
+                name: name.clone(),
+                args: self.args.clone(),
+            }]));
+        }
+        match &self.invoke_script {
+            Some(path) => {
+                let script = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read `{}`", path.display()))?;
+                Ok(Some(parse_script(&script)))
+            }
+            None => Ok(None),
+        }
+    }
+
     fn get_module(&self) -> Result<Module> {
         let contents = std::fs::read(self.path.clone())?;
         #[cfg(feature = "native")]
@@ -226,12 +283,20 @@ impl Run {
         // as it takes space and the speedup is minimal.
         let mut cache = self.get_cache(engine_type, compiler_type)?;
         // Try to get the hash from the provided `--cache-key`, otherwise
-        // generate one from the provided file `.wasm` contents.
-        let hash = self
+        // generate one from the provided file `.wasm` contents and the
+        // options the module is compiled with.
+        let hash = match self
             .cache_key
             .as_ref()
             .and_then(|key| Hash::from_str(&key).ok())
-            .unwrap_or_else(|| Hash::generate(&contents));
+        {
+            Some(hash) => hash,
+            None => match self.store.get_module_hash(store, *engine_type, contents)? {
+                Some(hash) => hash,
+                // the module can't be keyed, so it isn't cached
+                None => return Ok(Module::new(&store, &contents)?),
+            },
+        };
         match unsafe { cache.load(&store, hash) } {
             Ok(module) => Ok(module),
             Err(e) => {
@@ -245,19 +310,22 @@ impl Run {
                 }
                 let module = Module::new(&store, &contents)?;
                 // Store the compiled Module in cache
-                cache.store(hash, &module)?;
+                if let Err(err) = cache.store(hash, &module) {
+                    warning!("failed to cache the module: {}", err);
+                }
                 Ok(module)
             }
         }
     }
 
     #[cfg(feature = "cache")]
-    /// Get the Compiler Filesystem cache
+    /// Get the Compiler Filesystem cache, backed by the remote cache if any
     fn get_cache(
         &self,
         engine_type: &EngineType,
         compiler_type: &CompilerType,
-    ) -> Result<FileSystemCache> {
+    ) -> Result<Box<dyn Cache<DeserializeError = DeserializeError, SerializeError = SerializeError>>>
+    {
         let mut cache_dir_root = get_cache_dir();
         cache_dir_root.push(compiler_type.to_string());
         let mut cache = FileSystemCache::new(cache_dir_root)?;
@@ -278,8 +346,31 @@ impl Run {
             // We use the compiler type as the default extension
             _ => compiler_type.to_string(),
         };
-        cache.set_cache_extension(Some(extension));
-        Ok(cache)
+        cache.set_cache_extension(Some(&extension));
+        let (max_size, max_entries) = get_cache_budget()?;
+        cache.set_max_size(max_size);
+        cache.set_max_entries(max_entries);
+        Ok(match &self.remote_cache {
+            Some(url) => {
+                let mut remote_cache = HttpCache::new(url.as_str());
+                remote_cache.set_cache_extension(Some(&extension));
+                let signing_key = std::env::var("WASMER_REMOTE_CACHE_KEY").ok();
+                if signing_key.is_none() && !url.starts_with("https://") {
+                    bail!(
+                        "the remote cache {} must use HTTPS, unless WASMER_REMOTE_CACHE_KEY is set",
+                        url
+                    );
+                }
+                remote_cache.set_signing_key(signing_key.as_ref().map(|key| key.as_bytes()));
+                remote_cache.set_identity(format!(
+                    "{}-{}",
+                    compiler_type.to_string(),
+                    engine_type.to_string()
+                ));
+                Box::new(TieredCache::new(cache, remote_cache))
+            }
+            None => Box::new(cache),
+        })
     }
 
     fn try_find_function(
@@ -342,40 +433,28 @@ impl Run {
                 "Function expected {} arguments, but received {}: \"{}\"",
                 required_arguments,
                 provided_arguments,
-                self.args.join(" ")
+                args.join(" ")
             );
         }
         let invoke_args = args
             .iter()
             .zip(func_ty.params().iter())
-            .map(|(arg, param_type)| match param_type {
-                ValType::I32 => {
-                    Ok(Val::I32(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a i32", arg)
-                    })?))
-                }
-                ValType::I64 => {
-                    Ok(Val::I64(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a i64", arg)
-                    })?))
-                }
-                ValType::F32 => {
-                    Ok(Val::F32(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a f32", arg)
-                    })?))
-                }
-                ValType::F64 => {
-                    Ok(Val::F64(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a f64", arg)
-                    })?))
-                }
-                _ => Err(anyhow!(
-                    "Don't know how to convert {} into {:?}",
-                    arg,
-                    param_type
-                )),
-            })
+            .map(|(arg, param_type)| parse_value(instance, arg, param_type))
             .collect::<Result<Vec<_>>>()?;
-        Ok(func.call(&invoke_args)?)
+        let result = func.call(&invoke_args);
+        #[cfg(feature = "wasi")]
+        let result = Wasi::handle_exit(result);
+        Ok(result?)
+    }
+
+    /// Invoke the functions in order, printing their results
+    fn invoke_functions(&self, instance: &Instance, invocations: &[Invocation]) -> Result<()> {
+        for invocation in invocations {
+            let result = self
+                .invoke_function(instance, &invocation.name, &invocation.args)
+                .with_context(|| format!("failed to invoke `{}`", invocation.name))?;
+            println!("{}", format_results(&result, self.output));
+        }
+        Ok(())
     }
 }
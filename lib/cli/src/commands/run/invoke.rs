@@ -0,0 +1,265 @@
+//! Parsing of the arguments and formatting of the results of the
+//! functions called with `wasmer run --invoke`.
+
+use anyhow::{bail, Context, Result};
+use std::str::FromStr;
+use wasmer::{ExternRef, Instance, Val, ValType};
+
+/// The format of the results of the invoked functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The results separated by spaces
+    Text,
+    /// A JSON array of `{"type": ..., "value": ...}` objects
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            format => bail!("The `{}` output format does not exist.", format),
+        }
+    }
+}
+
+/// An invocation of an exported function
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    /// The name of the function
+    pub name: String,
+    /// The arguments, parsed according to the signature of the function
+    pub args: Vec<String>,
+}
+
+/// Parse a script of invocations, with one `function arg1 arg2 ...`
+/// invocation per line. Empty lines and lines starting with `#` are
+/// ignored.
+pub fn parse_script(script: &str) -> Vec<Invocation> {
+    script
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut words = line.split_whitespace().map(str::to_string);
+            Invocation {
+                name: words.next().unwrap(),
+                args: words.collect(),
+            }
+        })
+        .collect()
+}
+
+/// Parse an argument of type `ty`.
+///
+/// `funcref` arguments are the names of functions exported by `instance`,
+/// and `externref` arguments can only be `null`.
+pub fn parse_value(instance: &Instance, arg: &str, ty: &ValType) -> Result<Val> {
+    Ok(match ty {
+        ValType::I32 => Val::I32(parse_int(arg, 32)? as i32),
+        ValType::I64 => Val::I64(parse_int(arg, 64)? as i64),
+        ValType::F32 => Val::F32(
+            arg.parse()
+                .with_context(|| format!("Can't convert `{}` into a f32", arg))?,
+        ),
+        ValType::F64 => Val::F64(
+            arg.parse()
+                .with_context(|| format!("Can't convert `{}` into a f64", arg))?,
+        ),
+        ValType::V128 => Val::V128(parse_v128(arg)?),
+        ValType::ExternRef if arg == "null" => Val::ExternRef(ExternRef::Null),
+        ValType::ExternRef => bail!("Only `null` can be passed as an externref"),
+        ValType::FuncRef => Val::FuncRef(
+            instance
+                .exports
+                .get_function(arg)
+                .with_context(|| format!("Can't convert `{}` into a funcref", arg))?
+                .clone(),
+        ),
+    })
+}
+
+/// Parse an integer of `bits` bits, either signed, unsigned or
+/// hexadecimal prefixed with `0x`, returning its bits.
+fn parse_int(arg: &str, bits: u32) -> Result<u64> {
+    let max = u64::MAX >> (64 - bits);
+    let value = if let Some(hex) = arg.strip_prefix("0x") {
+        u64::from_str_radix(&hex.replace('_', ""), 16)
+            .ok()
+            .filter(|value| *value <= max)
+    } else if let Some(negative) = arg.strip_prefix('-') {
+        negative
+            .parse::<u64>()
+            .ok()
+            .filter(|value| *value <= max / 2 + 1)
+            .map(|value| value.wrapping_neg() & max)
+    } else {
+        arg.parse::<u64>().ok().filter(|value| *value <= max)
+    };
+    value.with_context(|| format!("Can't convert `{}` into a i{}", arg, bits))
+}
+
+/// Parse a `v128`, either as a hexadecimal number prefixed with `0x`, or
+/// as lanes such as `i32x4:1,2,3,4`, lane 0 first.
+pub fn parse_v128(arg: &str) -> Result<u128> {
+    if let Some(hex) = arg.strip_prefix("0x") {
+        return u128::from_str_radix(&hex.replace('_', ""), 16)
+            .with_context(|| format!("Can't convert `{}` into a v128", arg));
+    }
+    let (shape, lanes) = match arg.find(':') {
+        Some(index) => (&arg[..index], &arg[index + 1..]),
+        None => bail!(
+            "Can't convert `{}` into a v128, expected a hexadecimal number or lanes such as `i32x4:1,2,3,4`",
+            arg
+        ),
+    };
+    let lanes = lanes.split(',').map(str::trim).collect::<Vec<_>>();
+    let (count, bits): (usize, u32) = match shape {
+        "i8x16" => (16, 8),
+        "i16x8" => (8, 16),
+        "i32x4" | "f32x4" => (4, 32),
+        "i64x2" | "f64x2" => (2, 64),
+        shape => bail!("Unknown v128 shape `{}`", shape),
+    };
+    if lanes.len() != count {
+        bail!(
+            "A {} v128 has {} lanes, but {} were given",
+            shape,
+            count,
+            lanes.len()
+        );
+    }
+    let mut value = 0u128;
+    for (index, lane) in lanes.iter().enumerate() {
+        let bits_of_lane: u128 = match shape {
+            "i8x16" | "i16x8" | "i32x4" | "i64x2" => parse_int(lane, bits)? as u128,
+            "f32x4" => lane
+                .parse::<f32>()
+                .with_context(|| format!("Can't convert `{}` into a f32", lane))?
+                .to_bits() as u128,
+            _ => lane
+                .parse::<f64>()
+                .with_context(|| format!("Can't convert `{}` into a f64", lane))?
+                .to_bits() as u128,
+        };
+        value |= bits_of_lane << (index as u32 * bits);
+    }
+    Ok(value)
+}
+
+/// Format the results of an invocation.
+pub fn format_results(results: &[Val], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text => results
+            .iter()
+            .map(|val| match val {
+                Val::V128(value) => format!("0x{:032x}", value),
+                val => val.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+        OutputFormat::Json => format!(
+            "[{}]",
+            results
+                .iter()
+                .map(|val| format!(
+                    "{{\"type\": \"{}\", \"value\": {}}}",
+                    type_name(&val.ty()),
+                    json_value(val)
+                ))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// The name of a type in the WebAssembly text format.
+fn type_name(ty: &ValType) -> &'static str {
+    match ty {
+        ValType::I32 => "i32",
+        ValType::I64 => "i64",
+        ValType::F32 => "f32",
+        ValType::F64 => "f64",
+        ValType::V128 => "v128",
+        ValType::ExternRef => "externref",
+        ValType::FuncRef => "funcref",
+    }
+}
+
+/// The JSON representation of a value. Floats which aren't finite, and
+/// `v128`s which don't fit in a JSON number, are strings.
+fn json_value(val: &Val) -> String {
+    fn float(value: f64, text: String) -> String {
+        if value.is_finite() {
+            text
+        } else {
+            format!("\"{}\"", text)
+        }
+    }
+    match val {
+        Val::I32(value) => value.to_string(),
+        Val::I64(value) => value.to_string(),
+        Val::F32(value) => float(*value as f64, value.to_string()),
+        Val::F64(value) => float(*value, value.to_string()),
+        Val::V128(value) => format!("\"0x{:032x}\"", value),
+        Val::ExternRef(ExternRef::Null) => "null".to_string(),
+        Val::ExternRef(_) => "\"externref\"".to_string(),
+        Val::FuncRef(_) => "\"funcref\"".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_v128_lanes() {
+        assert_eq!(parse_v128("0x1").unwrap(), 1);
+        assert_eq!(
+            parse_v128("i32x4:1,2,3,-1").unwrap(),
+            0xffffffff_00000003_00000002_00000001
+        );
+        assert_eq!(
+            parse_v128("i8x16:1,0,0,0,0,0,0,0,0,0,0,0,0,0,0,255").unwrap(),
+            0xff00_0000_0000_0000_0000_0000_0000_0001
+        );
+        assert_eq!(parse_v128("f64x2:1.0,0").unwrap(), 1.0f64.to_bits() as u128);
+        assert!(parse_v128("i32x4:1,2,3").is_err());
+        assert!(parse_v128("i32x4:1,2,3,4294967296").is_err());
+        assert!(parse_v128("42").is_err());
+    }
+
+    #[test]
+    fn parse_script_lines() {
+        let script = "# setup\nset 1 2\n\n  get 1\n";
+        assert_eq!(
+            parse_script(script),
+            vec![
+                Invocation {
+                    name: "set".to_string(),
+                    args: vec!["1".to_string(), "2".to_string()],
+                },
+                Invocation {
+                    name: "get".to_string(),
+                    args: vec!["1".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn format_json_results() {
+        let results = [Val::I32(-1), Val::F32(f32::NAN), Val::V128(1)];
+        assert_eq!(
+            format_results(&results, OutputFormat::Json),
+            "[{\"type\": \"i32\", \"value\": -1}, {\"type\": \"f32\", \"value\": \"NaN\"}, \
+             {\"type\": \"v128\", \"value\": \"0x00000000000000000000000000000001\"}]"
+        );
+        assert_eq!(
+            format_results(&results, OutputFormat::Text),
+            "-1 NaN 0x00000000000000000000000000000001"
+        );
+    }
+}
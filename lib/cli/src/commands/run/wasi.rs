@@ -1,7 +1,8 @@
-use crate::utils::{parse_envvar, parse_mapdir};
+use crate::config::Settings;
+use crate::utils::{parse_device, parse_envvar, parse_mapdir};
 use anyhow::{Context, Result};
 use std::path::PathBuf;
-use wasmer::{Instance, Module};
+use wasmer::{Instance, Module, RuntimeError};
 use wasmer_wasi::{get_wasi_version, WasiError, WasiState, WasiVersion};
 
 use structopt::StructOpt;
@@ -9,11 +10,12 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt, Clone)]
 /// WASI Options
 pub struct Wasi {
-    /// WASI pre-opened directory
+    /// WASI pre-opened directory, also accessible to Emscripten modules
     #[structopt(long = "dir", name = "DIR", multiple = true, group = "wasi")]
     pre_opened_directories: Vec<PathBuf>,
 
-    /// Map a host directory to a different location for the wasm module
+    /// Map a host directory to a different location for the wasm module,
+    /// WASI or Emscripten
     #[structopt(long = "mapdir", name = "GUEST_DIR:HOST_DIR", multiple = true, parse(try_from_str = parse_mapdir))]
     mapped_dirs: Vec<(String, PathBuf)>,
 
@@ -21,6 +23,11 @@ pub struct Wasi {
     #[structopt(long = "env", name = "KEY=VALUE", multiple = true, parse(try_from_str = parse_envvar))]
     env_vars: Vec<(String, String)>,
 
+    /// Mount a device implemented by the host (`null`, `zero`, `urandom`
+    /// or `tty`) at its default path in `/dev`, or at GUEST_PATH
+    #[structopt(long = "device", name = "NAME[:GUEST_PATH]", multiple = true, parse(try_from_str = parse_device))]
+    devices: Vec<(String, Option<PathBuf>)>,
+
     /// Enable experimental IO devices
     #[cfg(feature = "experimental-io-devices")]
     #[structopt(long = "enable-experimental-io-devices")]
@@ -28,6 +35,66 @@ pub struct Wasi {
 }
 
 impl Wasi {
+    /// Gets these options, with the directories and environment
+    /// variables of the configuration files added to the ones passed
+    /// in the command line.
+    ///
+    /// The environment variables of the command line override the ones
+    /// with the same name in the configuration.
+    pub fn with_config(&self, settings: &Settings) -> Result<Self> {
+        let mut wasi = self.clone();
+        let mut mapped_dirs = vec![];
+        if let Some(dirs) = &settings.dir {
+            let mut pre_opened_directories = vec![];
+            for dir in dirs {
+                match &settings.dir_base {
+                    // The module sees the directory at the given path.
+                    Some(base) if dir.is_relative() => {
+                        mapped_dirs.push((dir.to_string_lossy().into_owned(), base.join(dir)))
+                    }
+                    _ => pre_opened_directories.push(dir.clone()),
+                }
+            }
+            pre_opened_directories.extend(self.pre_opened_directories.iter().cloned());
+            wasi.pre_opened_directories = pre_opened_directories;
+        }
+        if let Some(mapdirs) = &settings.mapdir {
+            for mapdir in mapdirs {
+                mapped_dirs.push(parse_mapdir(mapdir)?);
+            }
+        }
+        mapped_dirs.extend(self.mapped_dirs.iter().cloned());
+        wasi.mapped_dirs = mapped_dirs;
+        if let Some(env_vars) = &settings.env {
+            wasi.env_vars = env_vars
+                .iter()
+                .map(|env_var| parse_envvar(env_var))
+                .collect::<Result<Vec<_>>>()?;
+            wasi.env_vars
+                .retain(|(key, _)| self.env_vars.iter().all(|(other, _)| other != key));
+            wasi.env_vars.extend(self.env_vars.iter().cloned());
+        }
+        if let Some(devices) = &settings.device {
+            wasi.devices = devices
+                .iter()
+                .map(|device| parse_device(device))
+                .collect::<Result<Vec<_>>>()?;
+            wasi.devices.extend(self.devices.iter().cloned());
+        }
+        Ok(wasi)
+    }
+
+    /// Gets the host directories the module can access, with the path
+    /// the module sees each of them at. Pre-opened directories keep
+    /// their own path.
+    pub fn mapped_dirs(&self) -> Vec<(String, PathBuf)> {
+        self.pre_opened_directories
+            .iter()
+            .map(|dir| (dir.to_string_lossy().into_owned(), dir.clone()))
+            .chain(self.mapped_dirs.iter().cloned())
+            .collect()
+    }
+
     /// Gets the WASI version (if any) for the provided module
     pub fn get_version(module: &Module) -> Option<WasiVersion> {
         // Get the wasi version on strict mode, so no other imports are
@@ -35,8 +102,13 @@ impl Wasi {
         get_wasi_version(&module, true)
     }
 
-    /// Helper function for executing Wasi from the `Run` command.
-    pub fn execute(&self, module: Module, program_name: String, args: Vec<String>) -> Result<()> {
+    /// Instantiates the module with the WASI imports.
+    pub fn instantiate(
+        &self,
+        module: &Module,
+        program_name: String,
+        args: Vec<String>,
+    ) -> Result<Instance> {
         let args = args.iter().cloned().map(|arg| arg.into_bytes());
 
         let mut wasi_state_builder = WasiState::new(program_name);
@@ -45,6 +117,12 @@ impl Wasi {
             .envs(self.env_vars.clone())
             .preopen_dirs(self.pre_opened_directories.clone())?
             .map_dirs(self.mapped_dirs.clone())?;
+        for (name, path) in &self.devices {
+            match path {
+                Some(path) => wasi_state_builder.device_at(name, path),
+                None => wasi_state_builder.device(name),
+            };
+        }
 
         #[cfg(feature = "experimental-io-devices")]
         {
@@ -55,28 +133,31 @@ impl Wasi {
         }
 
         let mut wasi_env = wasi_state_builder.finalize()?;
-        let import_object = wasi_env.import_object(&module)?;
-        let instance = Instance::new(&module, &import_object)?;
+        let import_object = wasi_env.import_object(module)?;
+        let instance = Instance::new(module, &import_object)?;
 
         wasi_env.set_memory(instance.exports.get_memory("memory")?.clone());
+        Ok(instance)
+    }
 
+    /// Helper function for executing Wasi from the `Run` command.
+    pub fn execute(&self, module: Module, program_name: String, args: Vec<String>) -> Result<()> {
+        let instance = self.instantiate(&module, program_name, args)?;
         let start = instance.exports.get_function("_start")?;
-        let result = start.call(&[]);
-
-        match result {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                let err: anyhow::Error = match err.downcast::<WasiError>() {
-                    Ok(WasiError::Exit(exit_code)) => {
-                        // We should exit with the provided exit code
-                        std::process::exit(exit_code as _);
-                    }
-                    Ok(err) => err.into(),
-                    Err(err) => err.into(),
-                };
-                Err(err)
+        Self::handle_exit(start.call(&[]))
+            .map(|_| ())
+            .with_context(|| "failed to run WASI `_start` function")
+    }
+
+    /// Exits with the code passed to `proc_exit`, if the call ended with it.
+    pub fn handle_exit<T>(result: Result<T, RuntimeError>) -> Result<T> {
+        result.map_err(|err| match err.downcast::<WasiError>() {
+            Ok(WasiError::Exit(exit_code)) => {
+                // We should exit with the provided exit code
+                std::process::exit(exit_code as _);
             }
-        }
-        .with_context(|| "failed to run WASI `_start` function")
+            Ok(err) => err.into(),
+            Err(err) => err.into(),
+        })
     }
 }
@@ -1,6 +1,8 @@
 //! Common module with common used structures across different
 //! commands.
+use crate::utils::parse_size;
 use crate::VERSION;
+use anyhow::{Context, Result};
 use std::env;
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -34,6 +36,25 @@ pub struct WasmFeatures {
     pub all: bool,
 }
 
+impl WasmFeatures {
+    /// Enables the proposals named in a configuration file, such as
+    /// `simd` or `bulk-memory`.
+    pub fn enable(&mut self, names: &[String]) -> Result<()> {
+        for name in names {
+            match name.as_str() {
+                "simd" => self.simd = true,
+                "threads" => self.threads = true,
+                "reference-types" => self.reference_types = true,
+                "multi-value" => self.multi_value = true,
+                "bulk-memory" => self.bulk_memory = true,
+                "all" => self.all = true,
+                name => bail!("Unknown WebAssembly feature `{}`", name),
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Get the cache dir
 pub fn get_cache_dir() -> PathBuf {
     match env::var("WASMER_CACHE_DIR") {
@@ -51,3 +72,21 @@ pub fn get_cache_dir() -> PathBuf {
         }
     }
 }
+
+/// Get the budget of each compiler cache, in bytes and in entries, from
+/// the `WASMER_CACHE_MAX_SIZE` and `WASMER_CACHE_MAX_ENTRIES` env vars
+pub fn get_cache_budget() -> Result<(Option<u64>, Option<usize>)> {
+    let max_size = match env::var("WASMER_CACHE_MAX_SIZE") {
+        Ok(size) => Some(parse_size(&size).context("invalid WASMER_CACHE_MAX_SIZE")?),
+        Err(_) => None,
+    };
+    let max_entries = match env::var("WASMER_CACHE_MAX_ENTRIES") {
+        Ok(entries) => Some(
+            entries
+                .parse()
+                .context("invalid WASMER_CACHE_MAX_ENTRIES")?,
+        ),
+        Err(_) => None,
+    };
+    Ok((max_size, max_entries))
+}
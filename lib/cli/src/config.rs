@@ -0,0 +1,414 @@
+//! Defaults for the command line options, loaded from `wasmer-cli.toml`
+//! configuration files.
+//!
+//! The settings are read from the user-global file (in `$WASMER_DIR`,
+//! or `~/.wasmer`) and then from the project-local one (the first
+//! `wasmer-cli.toml` found in the current directory or its parents, or
+//! the file given with `--config`), the latter overriding the former. A
+//! named profile can then be selected with `--profile`, which overrides
+//! both:
+//!
+//! ```toml
+//! compiler = "cranelift"
+//! features = ["simd", "bulk-memory"]
+//! dir = ["."]
+//!
+//! [profile.sandbox]
+//! dir = []
+//! mapdir = ["/data:./sandbox"]
+//! env = ["MODE=sandbox"]
+//! device = ["null", "urandom:/dev/random"]
+//! ```
+//!
+//! The relative paths of `dir` and `mapdir` are relative to the directory
+//! of the file setting them. The module still sees a relative `dir` at
+//! the path it's given with.
+//!
+//! Any directory the CLI runs in may contain a project-local file, so
+//! the capabilities granted to the module by a file found that way
+//! (`dir`, `mapdir`, `env` and `device`) are ignored unless a profile is
+//! selected with `--profile`, or the file is given with `--config`.
+//!
+//! Options passed in the command line always take precedence over
+//! the configuration files.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// The name of the configuration files.
+///
+/// It's distinct from `wasmer.toml`, which other tools use for their own
+/// manifests, so that their files aren't mistaken for configurations.
+pub const CONFIG_FILE_NAME: &str = "wasmer-cli.toml";
+
+/// The settings that can be set in a configuration file or in one of
+/// its profiles.
+///
+/// Every setting is optional; a setting that is present overrides the
+/// same setting in the files (or profiles) loaded before.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    /// The compiler: `singlepass`, `cranelift` or `llvm`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compiler: Option<String>,
+
+    /// The engine: `jit`, `native` or `object-file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine: Option<String>,
+
+    /// Enable the compiler internal verification.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_verifier: Option<bool>,
+
+    /// The WebAssembly proposals to enable, such as `simd` or `all`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+
+    /// The WASI pre-opened directories.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<Vec<PathBuf>>,
+
+    /// The directory the relative paths of `dir` are relative to, the
+    /// one of the file setting them.
+    #[serde(skip)]
+    pub dir_base: Option<PathBuf>,
+
+    /// The WASI mapped directories, as `GUEST_DIR:HOST_DIR`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mapdir: Option<Vec<String>>,
+
+    /// The WASI environment variables, as `KEY=VALUE`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<Vec<String>>,
+
+    /// The WASI devices to mount, as `NAME[:GUEST_PATH]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<Vec<String>>,
+}
+
+impl Settings {
+    /// Overrides these settings with the ones present in `other`.
+    pub fn merge(&mut self, other: &Self) {
+        fn merge_one<T: Clone>(setting: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                *setting = other.clone();
+            }
+        }
+        merge_one(&mut self.compiler, &other.compiler);
+        merge_one(&mut self.engine, &other.engine);
+        merge_one(&mut self.enable_verifier, &other.enable_verifier);
+        merge_one(&mut self.features, &other.features);
+        if other.dir.is_some() {
+            self.dir = other.dir.clone();
+            self.dir_base = other.dir_base.clone();
+        }
+        merge_one(&mut self.mapdir, &other.mapdir);
+        merge_one(&mut self.env, &other.env);
+        merge_one(&mut self.device, &other.device);
+    }
+
+    /// Removes the settings granting capabilities to the module, and
+    /// returns whether any was set.
+    fn remove_capabilities(&mut self) -> bool {
+        let had_capabilities = self.dir.is_some()
+            || self.mapdir.is_some()
+            || self.env.is_some()
+            || self.device.is_some();
+        self.dir = None;
+        self.dir_base = None;
+        self.mapdir = None;
+        self.env = None;
+        self.device = None;
+        had_capabilities
+    }
+
+    /// Makes the paths of these settings, read from a file in `base`,
+    /// relative to `base`.
+    fn resolve_paths(&mut self, base: &Path) {
+        if self.dir.is_some() {
+            self.dir_base = Some(base.to_path_buf());
+        }
+        if let Some(mapdirs) = &mut self.mapdir {
+            for mapdir in mapdirs {
+                *mapdir = resolve_mapdir(mapdir, base);
+            }
+        }
+    }
+}
+
+/// Makes the host directory of a `GUEST_DIR:HOST_DIR` mapping relative
+/// to `base`. Invalid mappings are left as is, to be reported when
+/// they are used.
+fn resolve_mapdir(mapdir: &str, base: &Path) -> String {
+    let (guest_dir, host_dir) = match mapdir.find("::") {
+        Some(i) => (&mapdir[..i], &mapdir[i + 2..]),
+        None => match mapdir.find(':') {
+            Some(i) => (&mapdir[..i], &mapdir[i + 1..]),
+            None => return mapdir.to_string(),
+        },
+    };
+    if Path::new(host_dir).is_absolute() {
+        return mapdir.to_string();
+    }
+    format!("{}::{}", guest_dir, base.join(host_dir).display())
+}
+
+/// The contents of a `wasmer-cli.toml` configuration file.
+#[derive(Debug, Default)]
+struct ConfigFile {
+    settings: Settings,
+    profile: BTreeMap<String, Settings>,
+    /// Whether the file was found in the current directory or its
+    /// parents, rather than given explicitly.
+    discovered: bool,
+}
+
+impl ConfigFile {
+    fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        let mut file = Self::parse(&contents)
+            .with_context(|| format!("invalid configuration file `{}`", path.display()))?;
+        let base = match path.parent() {
+            Some(base) if base != Path::new("") => base.to_path_buf(),
+            _ => env::current_dir()?,
+        };
+        file.settings.resolve_paths(&base);
+        for settings in file.profile.values_mut() {
+            settings.resolve_paths(&base);
+        }
+        Ok(file)
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        // The profiles are taken apart so that unknown settings are
+        // still rejected at the top level.
+        let mut table: toml::value::Table = toml::from_str(contents)?;
+        let profile = match table.remove("profile") {
+            Some(profile) => profile.try_into()?,
+            None => BTreeMap::new(),
+        };
+        let settings = toml::Value::Table(table).try_into()?;
+        Ok(Self {
+            settings,
+            profile,
+            discovered: false,
+        })
+    }
+}
+
+/// The effective configuration of the CLI.
+#[derive(Debug, Default)]
+pub struct WasmerConfig {
+    /// The configuration files that were loaded, in order.
+    pub files: Vec<PathBuf>,
+
+    /// The selected profile, if any.
+    pub profile: Option<String>,
+
+    /// The discovered files whose capabilities were ignored, as no
+    /// profile was selected.
+    pub ignored_capabilities: Vec<PathBuf>,
+
+    /// The resulting settings.
+    pub settings: Settings,
+}
+
+impl WasmerConfig {
+    /// Loads the configuration files, applying the given profile.
+    ///
+    /// The `config_file` replaces the project-local file found in the
+    /// current directory or its parents.
+    pub fn load(profile: Option<&str>, config_file: Option<&Path>) -> Result<Self> {
+        let global = global_config_path().filter(|path| path.is_file());
+        let local = match config_file {
+            Some(path) => Some((path.to_path_buf(), false)),
+            None => local_config_path().map(|path| (path, true)),
+        };
+        let mut paths = Vec::new();
+        let mut files = Vec::new();
+        if let Some(path) = global {
+            files.push(ConfigFile::from_file(&path)?);
+            paths.push(path);
+        }
+        if let Some((path, discovered)) = local {
+            if paths.last() != Some(&path) {
+                let mut file = ConfigFile::from_file(&path)?;
+                file.discovered = discovered;
+                files.push(file);
+                paths.push(path);
+            }
+        }
+        Self::from_files(paths, files, profile)
+    }
+
+    fn from_files(
+        paths: Vec<PathBuf>,
+        mut files: Vec<ConfigFile>,
+        profile: Option<&str>,
+    ) -> Result<Self> {
+        let mut settings = Settings::default();
+        let mut ignored_capabilities = Vec::new();
+        for (path, file) in paths.iter().zip(files.iter_mut()) {
+            if file.discovered && profile.is_none() && file.settings.remove_capabilities() {
+                ignored_capabilities.push(path.clone());
+            }
+            settings.merge(&file.settings);
+        }
+        if let Some(profile) = profile {
+            let profiles = files
+                .iter()
+                .filter_map(|file| file.profile.get(profile))
+                .collect::<Vec<_>>();
+            if profiles.is_empty() {
+                bail!(
+                    "the profile `{}` is not defined in any configuration file",
+                    profile
+                );
+            }
+            for profile in profiles {
+                settings.merge(profile);
+            }
+        }
+        Ok(Self {
+            files: paths,
+            profile: profile.map(str::to_string),
+            ignored_capabilities,
+            settings,
+        })
+    }
+}
+
+/// Gets the path of the user-global configuration file.
+fn global_config_path() -> Option<PathBuf> {
+    let wasmer_dir = match env::var_os("WASMER_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir()?.join(".wasmer"),
+    };
+    Some(wasmer_dir.join(CONFIG_FILE_NAME))
+}
+
+/// Gets the path of the project-local configuration file, looking for
+/// it in the current directory and its parents.
+fn local_config_path() -> Option<PathBuf> {
+    let current_dir = env::current_dir().ok()?;
+    current_dir
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLOBAL: &str = r#"
+compiler = "cranelift"
+features = ["simd"]
+dir = ["."]
+
+[profile.sandbox]
+dir = []
+env = ["MODE=sandbox"]
+"#;
+
+    const LOCAL: &str = r#"
+engine = "native"
+features = ["bulk-memory"]
+
+[profile.sandbox]
+mapdir = ["/data:./sandbox"]
+"#;
+
+    fn load(profile: Option<&str>) -> Result<WasmerConfig> {
+        let files = vec![ConfigFile::parse(GLOBAL)?, ConfigFile::parse(LOCAL)?];
+        WasmerConfig::from_files(vec![PathBuf::new(); 2], files, profile)
+    }
+
+    #[test]
+    fn local_overrides_global() -> Result<()> {
+        let config = load(None)?;
+        assert_eq!(
+            config.settings,
+            Settings {
+                compiler: Some("cranelift".to_string()),
+                engine: Some("native".to_string()),
+                features: Some(vec!["bulk-memory".to_string()]),
+                dir: Some(vec![PathBuf::from(".")]),
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn profile_overrides_files() -> Result<()> {
+        let config = load(Some("sandbox"))?;
+        assert_eq!(config.settings.dir, Some(vec![]));
+        assert_eq!(
+            config.settings.mapdir,
+            Some(vec!["/data:./sandbox".to_string()])
+        );
+        assert_eq!(config.settings.env, Some(vec!["MODE=sandbox".to_string()]));
+        assert_eq!(config.settings.engine, Some("native".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn discovered_files_need_a_profile_to_grant_capabilities() -> Result<()> {
+        let paths = vec![PathBuf::from("global"), PathBuf::from("local")];
+        let files = || -> Result<Vec<ConfigFile>> {
+            let mut local = ConfigFile::parse(GLOBAL)?;
+            local.discovered = true;
+            Ok(vec![ConfigFile::parse(LOCAL)?, local])
+        };
+
+        let config = WasmerConfig::from_files(paths.clone(), files()?, None)?;
+        assert_eq!(config.settings.dir, None);
+        assert_eq!(config.settings.compiler, Some("cranelift".to_string()));
+        assert_eq!(config.ignored_capabilities, vec![PathBuf::from("local")]);
+
+        let config = WasmerConfig::from_files(paths, files()?, Some("sandbox"))?;
+        assert_eq!(config.settings.dir, Some(vec![]));
+        assert_eq!(config.settings.env, Some(vec!["MODE=sandbox".to_string()]));
+        assert!(config.ignored_capabilities.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn paths_relative_to_the_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, GLOBAL)?;
+        std::fs::write(dir.path().join("other.toml"), LOCAL)?;
+
+        let file = ConfigFile::from_file(&path)?;
+        assert_eq!(file.settings.dir, Some(vec![PathBuf::from(".")]));
+        assert_eq!(file.settings.dir_base, Some(dir.path().to_path_buf()));
+        assert_eq!(
+            file.profile["sandbox"].dir_base,
+            Some(dir.path().to_path_buf())
+        );
+
+        let file = ConfigFile::from_file(&dir.path().join("other.toml"))?;
+        assert_eq!(
+            file.profile["sandbox"].mapdir,
+            Some(vec![format!(
+                "/data::{}",
+                dir.path().join("./sandbox").display()
+            )])
+        );
+        assert_eq!(resolve_mapdir("/data:/srv", dir.path()), "/data:/srv");
+        assert_eq!(resolve_mapdir("invalid", dir.path()), "invalid");
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_profile_and_settings() {
+        assert!(load(Some("missing")).is_err());
+        assert!(ConfigFile::parse("compilr = \"llvm\"").is_err());
+    }
+}
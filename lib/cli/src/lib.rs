@@ -18,6 +18,7 @@ extern crate anyhow;
 
 pub mod commands;
 pub mod common;
+pub mod config;
 #[macro_use]
 pub mod error;
 pub mod c_gen;
@@ -2,12 +2,14 @@
 //! commands.
 
 use crate::common::WasmFeatures;
+use crate::config::{Settings, WasmerConfig};
+use crate::warning;
 use anyhow::{Error, Result};
+use std::fmt;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::string::ToString;
-#[allow(unused_imports)]
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use structopt::StructOpt;
 use wasmer::*;
 #[cfg(feature = "compiler")]
@@ -30,6 +32,19 @@ pub struct StoreOptions {
     /// Use ObjectFile Engine.
     #[structopt(long, conflicts_with_all = &["jit", "native"])]
     object_file: bool,
+
+    /// Use the defaults of this profile of the `wasmer-cli.toml` files.
+    #[structopt(long, name = "PROFILE")]
+    profile: Option<String>,
+
+    /// Use this configuration file instead of the `wasmer-cli.toml` file
+    /// found in the current directory or its parents.
+    #[structopt(long = "config", name = "CONFIG", parse(from_os_str))]
+    config_file: Option<PathBuf>,
+
+    /// The configuration, once loaded.
+    #[structopt(skip)]
+    config: Arc<Mutex<Option<Arc<WasmerConfig>>>>,
 }
 
 #[derive(Debug, Clone, StructOpt)]
@@ -63,6 +78,34 @@ pub struct CompilerOptions {
     features: WasmFeatures,
 }
 
+#[cfg(feature = "engine")]
+impl CompilerOptions {
+    /// Takes the compiler, verifier and features from the configuration,
+    /// unless they are set in the command line.
+    fn apply_settings(&mut self, settings: &Settings) -> Result<()> {
+        let compiler_is_set =
+            self.singlepass || self.cranelift || self.llvm || self.backend.is_some();
+        if let (false, Some(compiler)) = (compiler_is_set, &settings.compiler) {
+            match compiler.as_str() {
+                "singlepass" => self.singlepass = true,
+                "cranelift" => self.cranelift = true,
+                "llvm" => self.llvm = true,
+                compiler => bail!(
+                    "The `{}` compiler is not valid in the configuration",
+                    compiler
+                ),
+            }
+        }
+        if settings.enable_verifier == Some(true) {
+            self.enable_verifier = true;
+        }
+        if let Some(features) = &settings.features {
+            self.features.enable(features)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(feature = "compiler")]
 impl CompilerOptions {
     fn get_compiler(&self) -> Result<CompilerType> {
@@ -336,14 +379,14 @@ impl CompilerType {
     }
 }
 
-impl ToString for CompilerType {
-    fn to_string(&self) -> String {
-        match self {
-            Self::Singlepass => "singlepass".to_string(),
-            Self::Cranelift => "cranelift".to_string(),
-            Self::LLVM => "llvm".to_string(),
-            Self::Headless => "headless".to_string(),
-        }
+impl fmt::Display for CompilerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Singlepass => "singlepass",
+            Self::Cranelift => "cranelift",
+            Self::LLVM => "llvm",
+            Self::Headless => "headless",
+        })
     }
 }
 
@@ -371,13 +414,13 @@ pub enum EngineType {
     ObjectFile,
 }
 
-impl ToString for EngineType {
-    fn to_string(&self) -> String {
-        match self {
-            Self::JIT => "jit".to_string(),
-            Self::Native => "native".to_string(),
-            Self::ObjectFile => "objectfile".to_string(),
-        }
+impl fmt::Display for EngineType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::JIT => "jit",
+            Self::Native => "native",
+            Self::ObjectFile => "objectfile",
+        })
     }
 }
 
@@ -394,12 +437,42 @@ impl StoreOptions {
         &self,
         target: Target,
     ) -> Result<(Store, EngineType, CompilerType)> {
-        let (compiler_config, compiler_type) = self.compiler.get_compiler_config()?;
-        let (engine, engine_type) = self.get_engine_with_compiler(target, compiler_config)?;
+        let options = self.with_config()?;
+        let (compiler_config, compiler_type) = options.compiler.get_compiler_config()?;
+        let (engine, engine_type) = options.get_engine_with_compiler(target, compiler_config)?;
         let store = Store::new(&*engine);
         Ok((store, engine_type, compiler_type))
     }
 
+    /// Gets the key of `contents` in the cache of modules compiled by
+    /// `store`, which was created from these options, or `None` if the
+    /// compiler configuration can't be fingerprinted.
+    #[cfg(feature = "cache")]
+    pub fn get_module_hash(
+        &self,
+        store: &Store,
+        engine_type: EngineType,
+        contents: &[u8],
+    ) -> Result<Option<wasmer_cache::Hash>> {
+        let options = self.with_config()?;
+        let (compiler_config, _) = options.compiler.get_compiler_config()?;
+        let fingerprint = match compiler_config.fingerprint() {
+            Some(fingerprint) => fingerprint,
+            None => return Ok(None),
+        };
+        let features = options
+            .compiler
+            .get_features(compiler_config.default_features_for_target(store.engine().target()))?;
+        Ok(Some(
+            wasmer_cache::Hash::builder(contents)
+                .engine(&engine_type.to_string())
+                .compiler(&fingerprint)
+                .store(store)
+                .features(&features)
+                .finish(),
+        ))
+    }
+
     fn get_engine_with_compiler(
         &self,
         target: Target,
@@ -414,8 +487,58 @@ impl StoreOptions {
     }
 }
 
+impl StoreOptions {
+    /// Loads the `wasmer-cli.toml` configuration files, with the selected profile.
+    ///
+    /// The files are only read the first time, when the ones in effect
+    /// are logged (with `--debug`); `wasmer config --show` lists them too.
+    pub fn config(&self) -> Result<Arc<WasmerConfig>> {
+        let mut config = self.config.lock().unwrap();
+        if let Some(config) = &*config {
+            return Ok(config.clone());
+        }
+        let loaded = Arc::new(WasmerConfig::load(
+            self.profile.as_deref(),
+            self.config_file.as_deref(),
+        )?);
+        #[cfg(feature = "debug")]
+        for file in &loaded.files {
+            log::debug!("Using the configuration file `{}`", file.display());
+        }
+        for file in &loaded.ignored_capabilities {
+            warning!(
+                "ignoring the `dir`, `mapdir`, `env` and `device` settings of `{}`, pass `--profile` or `--config` to apply them",
+                file.display()
+            );
+        }
+        *config = Some(loaded.clone());
+        Ok(loaded)
+    }
+}
+
 #[cfg(feature = "engine")]
 impl StoreOptions {
+    /// Gets these options, with the ones not set in the command line
+    /// taken from the configuration files.
+    fn with_config(&self) -> Result<Self> {
+        let config = self.config()?;
+        let settings = &config.settings;
+        let mut options = self.clone();
+        if let (false, Some(engine)) = (
+            self.jit || self.native || self.object_file,
+            &settings.engine,
+        ) {
+            match engine.as_str() {
+                "jit" => options.jit = true,
+                "native" => options.native = true,
+                "object-file" => options.object_file = true,
+                engine => bail!("The `{}` engine is not valid in the configuration", engine),
+            }
+        }
+        options.compiler.apply_settings(settings)?;
+        Ok(options)
+    }
+
     fn get_engine(&self) -> Result<EngineType> {
         if self.jit {
             Ok(EngineType::JIT)
@@ -463,7 +586,7 @@ impl StoreOptions {
 
     /// Get the store (headless engine)
     pub fn get_store(&self) -> Result<(Store, EngineType, CompilerType)> {
-        let (engine, engine_type) = self.get_engine_headless()?;
+        let (engine, engine_type) = self.with_config()?.get_engine_headless()?;
         let store = Store::new(&*engine);
         Ok((store, engine_type, CompilerType::Headless))
     }
@@ -475,6 +598,23 @@ impl StoreOptions {
     ) -> Result<(Store, EngineType, CompilerType)> {
         bail!("You need compilers to retrieve a store for a specific target");
     }
+
+    /// Gets the key of `contents` in the cache of modules loaded by
+    /// `store`, which was created from these options.
+    #[cfg(feature = "cache")]
+    pub fn get_module_hash(
+        &self,
+        store: &Store,
+        engine_type: EngineType,
+        contents: &[u8],
+    ) -> Result<Option<wasmer_cache::Hash>> {
+        Ok(Some(
+            wasmer_cache::Hash::builder(contents)
+                .engine(&engine_type.to_string())
+                .store(store)
+                .finish(),
+        ))
+    }
 }
 
 // If we don't have any engine enabled
@@ -492,4 +632,15 @@ impl StoreOptions {
     ) -> Result<(Store, EngineType, CompilerType)> {
         bail!("No engines are enabled");
     }
+
+    /// Gets the key of a module in the cache
+    #[cfg(feature = "cache")]
+    pub fn get_module_hash(
+        &self,
+        _store: &Store,
+        _engine_type: EngineType,
+        _contents: &[u8],
+    ) -> Result<Option<wasmer_cache::Hash>> {
+        bail!("No engines are enabled");
+    }
 }
@@ -40,6 +40,21 @@ pub fn parse_mapdir(entry: &str) -> Result<(String, PathBuf)> {
     }
 }
 
+/// Parses a device to mount, as `NAME` or `NAME:GUEST_PATH`
+pub fn parse_device(entry: &str) -> Result<(String, Option<PathBuf>)> {
+    let (name, path) = match entry.find(':') {
+        Some(i) => (&entry[..i], Some(PathBuf::from(&entry[i + 1..]))),
+        None => (entry, None),
+    };
+    if name.is_empty() {
+        bail!(
+            "Devices must be of the form <name>[:<guest_path>]. Found {}",
+            &entry
+        );
+    }
+    Ok((name.to_string(), path))
+}
+
 /// Parses a mapdir from an env var
 pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
     if let [env_var, value] = entry.split('=').collect::<Vec<&str>>()[..] {
@@ -51,3 +66,65 @@ pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
         );
     }
 }
+
+/// Parses a size in bytes, with an optional `K`, `M`, `G` or `T` suffix
+/// for powers of 1024, such as `512M` or `2GiB`
+pub fn parse_size(entry: &str) -> Result<u64> {
+    let entry = entry.trim();
+    let digits = entry
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(entry.len());
+    let (number, unit) = entry.split_at(digits);
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => bail!(
+            "Sizes must be a number of bytes with an optional K, M, G or T suffix. Found {}",
+            entry
+        ),
+    };
+    match number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+    {
+        Some(size) => Ok(size),
+        None => bail!("Invalid size {}", entry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("10").unwrap(), 10);
+        assert_eq!(parse_size("10B").unwrap(), 10);
+        assert_eq!(parse_size(" 1k ").unwrap(), 1 << 10);
+        assert_eq!(parse_size("512M").unwrap(), 512 << 20);
+        assert_eq!(parse_size("3 MB").unwrap(), 3 << 20);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 << 30);
+        assert_eq!(parse_size("4t").unwrap(), 4 << 40);
+    }
+
+    #[test]
+    fn parse_size_rejects_invalid_sizes() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("1X").is_err());
+        assert!(parse_size("-1K").is_err());
+        assert!(parse_size("1.5G").is_err());
+    }
+
+    #[test]
+    fn parse_size_rejects_overflows() {
+        assert_eq!(parse_size("16777215T").unwrap(), 16_777_215 << 40);
+        assert!(parse_size("16777216T").is_err());
+        assert!(parse_size("18446744073709551615").is_ok());
+        assert!(parse_size("18446744073709551616").is_err());
+    }
+}
@@ -202,6 +202,23 @@ impl CompilerConfig for Cranelift {
     fn push_middleware(&mut self, middleware: Arc<dyn FunctionMiddlewareGenerator>) {
         self.middlewares.push(middleware);
     }
+
+    fn fingerprint(&self) -> Option<String> {
+        let middlewares = self
+            .middlewares
+            .iter()
+            .map(|middleware| middleware.fingerprint())
+            .collect::<Option<Vec<_>>>()?;
+        Some(format!(
+            "cranelift {} nan_canonicalization={} simd={} pic={} opt_level={:?} middlewares=[{}]",
+            env!("CARGO_PKG_VERSION"),
+            self.enable_nan_canonicalization,
+            self.enable_simd,
+            self.enable_pic,
+            self.opt_level,
+            middlewares.join(", ")
+        ))
+    }
 }
 
 impl Default for Cranelift {
@@ -20,9 +20,10 @@ use tracing::info;
 use wasmer_compiler::wasmparser;
 use wasmer_compiler::{
     to_wasm_error, wasm_unsupported, GenerateMiddlewareChain, MiddlewareBinaryReader,
-    ModuleTranslationState, WasmResult,
+    ModuleTranslationState, WasmError, WasmResult,
 };
 use wasmer_types::LocalFunctionIndex;
+use wasmer_vm::ModuleInfo;
 
 /// WebAssembly to Cranelift IR function translator.
 ///
@@ -61,8 +62,10 @@ impl FuncTranslator {
     /// regarded as WebAssembly local variables. Any signature arguments marked as
     /// `ArgumentPurpose::Normal` are made accessible as WebAssembly local variables.
     ///
+    #[allow(clippy::too_many_arguments)]
     pub fn translate<FE: FuncEnvironment + ?Sized>(
         &mut self,
+        module: &ModuleInfo,
         module_translation_state: &ModuleTranslationState,
         code: &[u8],
         code_offset: usize,
@@ -75,7 +78,8 @@ impl FuncTranslator {
         reader.set_middleware_chain(
             config
                 .middlewares
-                .generate_middleware_chain(local_function_index),
+                .generate_middleware_chain(module, local_function_index)
+                .map_err(|error| WasmError::Generic(error.to_string()))?,
         );
         self.translate_from_reader(module_translation_state, reader, func, environ)
     }
@@ -242,7 +246,7 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
     // Keep going until the final `End` operator which pops the outermost block.
     while !state.control_stack.is_empty() {
         builder.set_srcloc(cur_srcloc(&reader));
-        let op = reader.read_operator().map_err(to_wasm_error)?;
+        let op = reader.read_operator()?;
         environ.before_translate_operator(&op, builder, state)?;
         translate_operator(module_translation_state, &op, builder, state, environ)?;
         environ.after_translate_operator(&op, builder, state)?;
@@ -215,6 +215,22 @@ impl CompilerConfig for LLVM {
     fn push_middleware(&mut self, middleware: Arc<dyn FunctionMiddlewareGenerator>) {
         self.middlewares.push(middleware);
     }
+
+    fn fingerprint(&self) -> Option<String> {
+        let middlewares = self
+            .middlewares
+            .iter()
+            .map(|middleware| middleware.fingerprint())
+            .collect::<Option<Vec<_>>>()?;
+        Some(format!(
+            "llvm {} nan_canonicalization={} pic={} opt_level={:?} middlewares=[{}]",
+            env!("CARGO_PKG_VERSION"),
+            self.enable_nan_canonicalization,
+            self.is_pic,
+            self.opt_level,
+            middlewares.join(", ")
+        ))
+    }
 }
 
 impl Default for LLVM {
@@ -69,6 +69,21 @@ impl CompilerConfig for Singlepass {
     fn push_middleware(&mut self, middleware: Arc<dyn FunctionMiddlewareGenerator>) {
         self.middlewares.push(middleware);
     }
+
+    fn fingerprint(&self) -> Option<String> {
+        let middlewares = self
+            .middlewares
+            .iter()
+            .map(|middleware| middleware.fingerprint())
+            .collect::<Option<Vec<_>>>()?;
+        Some(format!(
+            "singlepass {} nan_canonicalization={} stack_check={} middlewares=[{}]",
+            env!("CARGO_PKG_VERSION"),
+            self.enable_nan_canonicalization,
+            self.enable_stack_check,
+            middlewares.join(", ")
+        ))
+    }
 }
 
 impl Default for Singlepass {
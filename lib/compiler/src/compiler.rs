@@ -4,6 +4,7 @@
 use crate::error::CompileError;
 use crate::function::Compilation;
 use crate::lib::std::boxed::Box;
+use crate::lib::std::string::String;
 use crate::lib::std::sync::Arc;
 use crate::module::CompileModuleInfo;
 use crate::target::Target;
@@ -46,6 +47,17 @@ pub trait CompilerConfig {
 
     /// Pushes a middleware onto the back of the middleware chain.
     fn push_middleware(&mut self, middleware: Arc<dyn FunctionMiddlewareGenerator>);
+
+    /// Returns a fingerprint of the configuration, which changes whenever
+    /// the generated code may change, such as when enabling NaN
+    /// canonicalization or pushing a middleware.
+    ///
+    /// It's used to key the caches of compiled modules. By default, it's
+    /// `None`: the configuration can't be fingerprinted, and the modules
+    /// compiled with it aren't cached.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
 }
 
 /// An implementation of a Compiler from parsed WebAssembly module to Compiled native code.
@@ -71,6 +83,14 @@ pub trait Compiler {
         validate(data, Some(config)).map_err(|e| CompileError::Validate(format!("{}", e)))
     }
 
+    /// Returns the middlewares the functions are compiled with.
+    ///
+    /// The engines apply their `ModuleInfo` transformations before
+    /// compiling the module.
+    fn get_middlewares(&self) -> &[Arc<dyn FunctionMiddlewareGenerator>] {
+        &[]
+    }
+
     /// Compiles a parsed module.
     ///
     /// It returns the [`Compilation`] or a [`CompileError`].
@@ -69,11 +69,37 @@ pub enum WasmError {
     #[cfg_attr(feature = "std", error("Implementation limit exceeded"))]
     ImplLimitExceeded,
 
+    /// A middleware failed to transform the code.
+    #[cfg_attr(feature = "std", error("{0}"))]
+    Middleware(#[cfg_attr(feature = "std", from)] MiddlewareError),
+
     /// A generic error.
     #[cfg_attr(feature = "std", error("{0}"))]
     Generic(String),
 }
 
+/// An error raised by a middleware while transforming the code of a
+/// function.
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[cfg_attr(feature = "std", error("Error in middleware {name}: {message}"))]
+pub struct MiddlewareError {
+    /// The name of the middleware.
+    pub name: String,
+    /// A string describing the error.
+    pub message: String,
+}
+
+impl MiddlewareError {
+    /// Creates a new `MiddlewareError` raised by the middleware `name`.
+    pub fn new<A: Into<String>, B: Into<String>>(name: A, message: B) -> Self {
+        Self {
+            name: name.into(),
+            message: message.into(),
+        }
+    }
+}
+
 /// The error that can happen while parsing a `str`
 /// to retrieve a [`CpuFeature`].
 #[derive(Debug)]
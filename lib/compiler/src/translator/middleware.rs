@@ -1,17 +1,42 @@
 //! The middleware parses the function binary bytecodes and transform them
 //! with the chosen functions.
 
+use super::error::to_wasm_error;
+use crate::{CompileError, MiddlewareError, WasmResult};
 use smallvec::SmallVec;
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::ops::Deref;
 use wasmer_types::LocalFunctionIndex;
+use wasmer_vm::ModuleInfo;
 use wasmparser::{BinaryReader, Operator, Result as WpResult, Type};
 
 /// A shared builder for function middlewares.
 pub trait FunctionMiddlewareGenerator: Debug + Send + Sync {
-    /// Generates a `FunctionMiddleware` for a given function.
-    fn generate(&self, local_function_index: LocalFunctionIndex) -> Box<dyn FunctionMiddleware>;
+    /// Generates a `FunctionMiddleware` for a given function of the
+    /// module described by `module_info`, once transformed.
+    fn generate_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Result<Box<dyn FunctionMiddleware>, CompileError>;
+
+    /// Returns a fingerprint of the generator, which changes whenever the
+    /// generated middlewares may transform functions differently.
+    ///
+    /// By default, it's `None`: the generator can't be fingerprinted, and
+    /// the modules compiled with it aren't cached.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+
+    /// Transforms the `ModuleInfo` before the functions are compiled,
+    /// for example to declare the globals the generated middlewares use.
+    ///
+    /// By default, the module is left untouched.
+    fn transform_module_info(&self, _module_info: &mut ModuleInfo) -> Result<(), CompileError> {
+        Ok(())
+    }
 }
 
 /// A function middleware specialized for a single function.
@@ -21,7 +46,7 @@ pub trait FunctionMiddleware: Debug {
         &mut self,
         operator: Operator<'a>,
         state: &mut MiddlewareReaderState<'a>,
-    ) -> WpResult<()> {
+    ) -> Result<(), MiddlewareError> {
         state.push_operator(operator);
         Ok(())
     }
@@ -52,20 +77,33 @@ pub trait GenerateMiddlewareChain {
     /// Generates a middleware chain.
     fn generate_middleware_chain(
         &self,
+        module_info: &ModuleInfo,
         local_function_index: LocalFunctionIndex,
-    ) -> Vec<Box<dyn FunctionMiddleware>>;
+    ) -> Result<Vec<Box<dyn FunctionMiddleware>>, CompileError>;
+
+    /// Applies the `ModuleInfo` transformations of the chain, in order.
+    fn apply_on_module_info(&self, module_info: &mut ModuleInfo) -> Result<(), CompileError>;
 }
 
 impl<T: Deref<Target = dyn FunctionMiddlewareGenerator>> GenerateMiddlewareChain for [T] {
     /// Generates a middleware chain.
     fn generate_middleware_chain(
         &self,
+        module_info: &ModuleInfo,
         local_function_index: LocalFunctionIndex,
-    ) -> Vec<Box<dyn FunctionMiddleware>> {
+    ) -> Result<Vec<Box<dyn FunctionMiddleware>>, CompileError> {
         self.iter()
-            .map(|x| x.generate(local_function_index))
+            .map(|x| x.generate_for_module(module_info, local_function_index))
             .collect()
     }
+
+    /// Applies the `ModuleInfo` transformations of the chain, in order.
+    fn apply_on_module_info(&self, module_info: &mut ModuleInfo) -> Result<(), CompileError> {
+        for middleware in self {
+            middleware.transform_module_info(module_info)?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a> MiddlewareReaderState<'a> {
@@ -104,10 +142,10 @@ impl<'a> MiddlewareBinaryReader<'a> {
     }
 
     /// Reads the next available `Operator`.
-    pub fn read_operator(&mut self) -> WpResult<Operator<'a>> {
+    pub fn read_operator(&mut self) -> WasmResult<Operator<'a>> {
         // Try to fill the `self.pending_operations` buffer, until it is non-empty.
         while self.state.pending_operations.is_empty() {
-            let raw_op = self.state.inner.read_operator()?;
+            let raw_op = self.state.inner.read_operator().map_err(to_wasm_error)?;
 
             // Fill the initial raw operator into pending buffer.
             self.state.pending_operations.push_back(raw_op);
@@ -1,9 +1,12 @@
 #![allow(non_snake_case)]
 
 use crate::env::get_emscripten_data;
+use crate::exception::CppException;
+use crate::jmp::LongJumpRet;
 use crate::EmEnv;
 #[cfg(target_os = "linux")]
 use libc::getdtablesize;
+use wasmer::RuntimeError;
 
 pub fn asm_const_i(_ctx: &mut EmEnv, _val: i32) -> i32 {
     debug!("emscripten::asm_const_i: {}", _val);
@@ -50,20 +53,6 @@ pub fn __Unwind_GetIPInfo(_ctx: &mut EmEnv, _a: i32, _b: i32) -> i32 {
     debug!("emscripten::__Unwind_GetIPInfo");
     0
 }
-pub fn ___cxa_find_matching_catch_2(_ctx: &mut EmEnv) -> i32 {
-    debug!("emscripten::___cxa_find_matching_catch_2");
-    0
-}
-pub fn ___cxa_find_matching_catch_3(_ctx: &mut EmEnv, _a: i32) -> i32 {
-    debug!("emscripten::___cxa_find_matching_catch_3");
-    0
-}
-pub fn ___cxa_free_exception(_ctx: &mut EmEnv, _a: i32) {
-    debug!("emscripten::___cxa_free_exception");
-}
-pub fn ___resumeException(_ctx: &mut EmEnv, _a: i32) {
-    debug!("emscripten::___resumeException");
-}
 pub fn _dladdr(_ctx: &mut EmEnv, _a: i32, _b: i32) -> i32 {
     debug!("emscripten::_dladdr");
     0
@@ -143,10 +132,12 @@ macro_rules! invoke {
         let result = get_emscripten_data($ctx).$name.as_ref().expect(concat!("Dynamic call is None: ", stringify!($name))).call($($arg),*);
         match result {
             Ok(v) => v,
-            Err(_e) => {
+            Err(e) => {
                 get_emscripten_data($ctx).stack_restore.as_ref().expect("stack_restore is None").call(sp).expect("stack_restore call failed");
-                // TODO: We should check if _e != "longjmp" and if that's the case, re-throw the error
                 // JS version is: if (e !== e+0 && e !== 'longjmp') throw e;
+                if !e.is::<CppException>() && !e.is::<LongJumpRet>() {
+                    RuntimeError::raise(Box::new(e));
+                }
                 get_emscripten_data($ctx).set_threw.as_ref().expect("set_threw is None").call(1, 0).expect("set_threw call failed");
                 0 as _
             }
@@ -159,10 +150,12 @@ macro_rules! invoke_no_return {
         let result = get_emscripten_data($ctx).$name.as_ref().expect(concat!("Dynamic call is None: ", stringify!($name))).call($($arg),*);
         match result {
             Ok(v) => v,
-            Err(_e) => {
+            Err(e) => {
                 get_emscripten_data($ctx).stack_restore.as_ref().expect("stack_restore is None").call(sp).expect("stack_restore call failed");
-                // TODO: We should check if _e != "longjmp" and if that's the case, re-throw the error
                 // JS version is: if (e !== e+0 && e !== 'longjmp') throw e;
+                if !e.is::<CppException>() && !e.is::<LongJumpRet>() {
+                    RuntimeError::raise(Box::new(e));
+                }
                 get_emscripten_data($ctx).set_threw.as_ref().expect("set_threw is None").call(1, 0).expect("set_threw call failed");
             }
         }
@@ -1,5 +1,98 @@
 // use std::collections::HashMap;
 use crate::EmEnv;
+use std::io::Error;
+use std::os::raw::c_int;
+
+// The errno values seen by the module, as defined by Emscripten.
+pub const ENOENT: c_int = 2;
+pub const EACCES: c_int = 13;
+pub const EFAULT: c_int = 14;
+pub const ENOTDIR: c_int = 20;
+pub const EINVAL: c_int = 22;
+pub const ENAMETOOLONG: c_int = 36;
+#[cfg(windows)]
+pub const ENOSYS: c_int = 38;
+pub const EMSGSIZE: c_int = 90;
+pub const EOPNOTSUPP: c_int = 95;
+
+/// Gets the errno of the last failed call to the host, as the module
+/// expects it.
+pub fn last_errno() -> c_int {
+    let error = Error::last_os_error();
+    debug!("=> last os error: {}", error);
+    translate_errno(&error)
+}
+
+/// Linux and Emscripten share the same values.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn translate_errno(error: &Error) -> c_int {
+    error.raw_os_error().unwrap_or(0)
+}
+
+/// The values up to `ERANGE` are the same as the Linux ones on BSD
+/// systems, the ones after it aren't.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn translate_errno(error: &Error) -> c_int {
+    match error.raw_os_error().unwrap_or(0) {
+        libc::EAGAIN => 11,
+        libc::EDEADLK => 35,
+        libc::ENAMETOOLONG => 36,
+        libc::ENOLCK => 37,
+        libc::ENOSYS => 38,
+        libc::ENOTEMPTY => 39,
+        libc::ELOOP => 40,
+        libc::EOVERFLOW => 75,
+        libc::EILSEQ => 84,
+        libc::ENOTSOCK => 88,
+        libc::EDESTADDRREQ => 89,
+        libc::EMSGSIZE => 90,
+        libc::EPROTOTYPE => 91,
+        libc::ENOPROTOOPT => 92,
+        libc::EPROTONOSUPPORT => 93,
+        libc::ESOCKTNOSUPPORT => 94,
+        libc::EOPNOTSUPP => 95,
+        libc::EPFNOSUPPORT => 96,
+        libc::EAFNOSUPPORT => 97,
+        libc::EADDRINUSE => 98,
+        libc::EADDRNOTAVAIL => 99,
+        libc::ENETDOWN => 100,
+        libc::ENETUNREACH => 101,
+        libc::ENETRESET => 102,
+        libc::ECONNABORTED => 103,
+        libc::ECONNRESET => 104,
+        libc::ENOBUFS => 105,
+        libc::EISCONN => 106,
+        libc::ENOTCONN => 107,
+        libc::ESHUTDOWN => 108,
+        libc::ETOOMANYREFS => 109,
+        libc::ETIMEDOUT => 110,
+        libc::ECONNREFUSED => 111,
+        libc::EHOSTDOWN => 112,
+        libc::EHOSTUNREACH => 113,
+        libc::EALREADY => 114,
+        libc::EINPROGRESS => 115,
+        libc::ESTALE => 116,
+        libc::EDQUOT => 122,
+        libc::ECANCELED => 125,
+        otherwise => otherwise,
+    }
+}
+
+/// The C runtime doesn't report its errno through `GetLastError`, so
+/// only the kind of the error is kept.
+#[cfg(windows)]
+fn translate_errno(error: &Error) -> c_int {
+    use std::io::ErrorKind;
+    match error.kind() {
+        ErrorKind::NotFound => ENOENT,
+        ErrorKind::PermissionDenied => EACCES,
+        ErrorKind::Interrupted => 4,
+        ErrorKind::WouldBlock => 11,
+        ErrorKind::BrokenPipe => 32,
+        ErrorKind::InvalidInput => EINVAL,
+        _ => 5,
+    }
+}
 
 pub fn ___seterrno(_ctx: &mut EmEnv, _value: i32) {
     debug!("emscripten::___seterrno {}", _value);
@@ -1,6 +1,179 @@
-use super::env;
-use super::process::_abort;
+//! C++ exceptions, following the `EXCEPTIONS` object of Emscripten's
+//! `library_exceptions.js`.
+//!
+//! Throwing an exception unwinds the module with a `RuntimeError`
+//! whose payload is a [`CppException`], up to the closest `invoke_*`
+//! function, which flags the exception to the module with `setThrew`
+//! so that its landing pad runs.
+
+use super::env::{self, get_emscripten_data};
 use crate::EmEnv;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use wasmer::RuntimeError;
+
+/// The payload of the `RuntimeError` unwinding a module that threw a
+/// C++ exception, holding the address of the exception object.
+///
+/// It is returned to the host when the exception isn't caught by the
+/// module.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CppException(pub u32);
+
+impl fmt::Display for CppException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "uncaught C++ exception (object at {})", self.0)
+    }
+}
+
+impl Error for CppException {}
+
+/// The thrown exceptions of a module.
+#[derive(Debug, Default)]
+pub(crate) struct Exceptions {
+    /// The thrown exceptions, by the address of their object.
+    infos: HashMap<u32, ExceptionInfo>,
+    /// The exceptions being caught, the innermost last.
+    caught: Vec<u32>,
+    /// The last thrown exception.
+    last: u32,
+    /// The number of thrown exceptions not caught yet.
+    uncaught: u32,
+    /// The memory through which `___cxa_can_catch` adjusts the
+    /// address of an exception object, allocated on the first catch.
+    buffer: u32,
+}
+
+#[derive(Debug)]
+struct ExceptionInfo {
+    /// The address of the `type_info` of the exception.
+    ty: u32,
+    /// The table index of the destructor of the exception object, or 0.
+    destructor: u32,
+    refcount: u32,
+    caught: bool,
+    rethrown: bool,
+    /// The addresses the object was caught at, as base classes.
+    adjusted: Vec<u32>,
+}
+
+impl Exceptions {
+    /// Gets the address of the exception object caught at `adjusted`.
+    fn de_adjust(&self, adjusted: u32) -> u32 {
+        if adjusted == 0 || self.infos.contains_key(&adjusted) {
+            return adjusted;
+        }
+        self.infos
+            .iter()
+            .find(|(_, info)| info.adjusted.contains(&adjusted))
+            .map_or(adjusted, |(&ptr, _)| ptr)
+    }
+
+    fn add_ref(&mut self, ptr: u32) {
+        if let Some(info) = self.infos.get_mut(&ptr) {
+            info.refcount += 1;
+        }
+    }
+}
+
+/// Unwinds the module with the exception at `ptr`.
+fn throw(ctx: &mut EmEnv, ptr: u32) -> ! {
+    let exceptions = &mut get_emscripten_data(ctx).exceptions;
+    if exceptions.last == 0 {
+        exceptions.last = ptr;
+    }
+    RuntimeError::raise(Box::new(CppException(ptr)))
+}
+
+/// Drops a reference to the exception at `ptr`, destroying and freeing
+/// its object once there are none left.
+///
+/// The error of a failing destructor is returned, the object isn't
+/// freed then.
+fn dec_ref(ctx: &mut EmEnv, ptr: u32) -> Result<(), RuntimeError> {
+    let data = get_emscripten_data(ctx);
+    let destructor = match data.exceptions.infos.get_mut(&ptr) {
+        Some(info) => {
+            info.refcount = info.refcount.saturating_sub(1);
+            if info.refcount > 0 || info.rethrown {
+                return Ok(());
+            }
+            info.destructor
+        }
+        None => return Ok(()),
+    };
+    data.exceptions.infos.remove(&ptr);
+
+    if destructor != 0 {
+        data.dyn_call_vi
+            .as_ref()
+            .ok_or_else(|| not_exported("dynCall_vi"))?
+            .call(destructor as i32, ptr as i32)?;
+    }
+    ___cxa_free_exception(ctx, ptr);
+
+    Ok(())
+}
+
+/// The error trapping a module lacking the export `name`, which the
+/// handling of its exceptions needs.
+fn not_exported(name: &str) -> RuntimeError {
+    RuntimeError::new(format!(
+        "`{}` must be exported by the module to handle its C++ exceptions",
+        name
+    ))
+}
+
+/// Finds which of `types` can catch the last thrown exception, setting
+/// it as the selector of the landing pad (with `setTempRet0`) and
+/// returning the address of the exception object as that type.
+///
+/// The error of a failing `___cxa_can_catch` is returned.
+fn find_matching_catch(ctx: &mut EmEnv, types: &[u32]) -> Result<u32, RuntimeError> {
+    let data = get_emscripten_data(ctx);
+    let thrown = data.exceptions.last;
+    let thrown_type = match data.exceptions.infos.get(&thrown) {
+        Some(info) if info.ty != 0 => info.ty,
+        _ => {
+            data.temp_ret_0 = 0;
+            return Ok(thrown);
+        }
+    };
+
+    // `___cxa_can_catch` takes the address of the object by pointer,
+    // to adjust it when catching it as a base class
+    if data.exceptions.buffer == 0 {
+        data.exceptions.buffer = data
+            .malloc
+            .as_ref()
+            .ok_or_else(|| not_exported("_malloc"))?
+            .call(4)?;
+    }
+    let memory = ctx.memory(0).clone();
+    let data = get_emscripten_data(ctx);
+    let buffer = data.exceptions.buffer;
+    memory.view::<u32>()[buffer as usize / 4].set(thrown);
+
+    for &ty in types.iter().filter(|&&ty| ty != 0) {
+        let can_catch = data
+            .cxa_can_catch
+            .as_ref()
+            .ok_or_else(|| not_exported("___cxa_can_catch"))?
+            .call(ty as i32, thrown_type as i32, buffer as i32)?;
+        if can_catch != 0 {
+            let adjusted = memory.view::<u32>()[buffer as usize / 4].get();
+            if let Some(info) = data.exceptions.infos.get_mut(&thrown) {
+                info.adjusted.push(adjusted);
+            }
+            data.temp_ret_0 = ty as i32;
+            return Ok(adjusted);
+        }
+    }
+
+    data.temp_ret_0 = thrown_type as i32;
+    Ok(memory.view::<u32>()[buffer as usize / 4].get())
+}
 
 /// emscripten: ___cxa_allocate_exception
 pub fn ___cxa_allocate_exception(ctx: &mut EmEnv, size: u32) -> u32 {
@@ -8,46 +181,159 @@ pub fn ___cxa_allocate_exception(ctx: &mut EmEnv, size: u32) -> u32 {
     env::call_malloc(ctx, size as _)
 }
 
-pub fn ___cxa_current_primary_exception(_ctx: &mut EmEnv) -> u32 {
-    debug!("emscripten::___cxa_current_primary_exception");
-    unimplemented!("emscripten::___cxa_current_primary_exception")
+/// emscripten: ___cxa_free_exception
+pub fn ___cxa_free_exception(ctx: &mut EmEnv, ptr: u32) {
+    debug!("emscripten::___cxa_free_exception({})", ptr);
+    if let Some(free) = get_emscripten_data(ctx).free.as_ref() {
+        // like Emscripten, ignore a failure to free
+        let _ = free.call(ptr);
+    }
 }
 
-pub fn ___cxa_decrement_exception_refcount(_ctx: &mut EmEnv, _a: u32) {
-    debug!("emscripten::___cxa_decrement_exception_refcount({})", _a);
-    unimplemented!("emscripten::___cxa_decrement_exception_refcount({})", _a)
+/// emscripten: ___cxa_throw
+pub fn ___cxa_throw(ctx: &mut EmEnv, ptr: u32, ty: u32, destructor: u32) {
+    debug!("emscripten::___cxa_throw({}, {}, {})", ptr, ty, destructor);
+    let exceptions = &mut get_emscripten_data(ctx).exceptions;
+    exceptions.infos.insert(
+        ptr,
+        ExceptionInfo {
+            ty,
+            destructor,
+            refcount: 0,
+            caught: false,
+            rethrown: false,
+            adjusted: Vec::new(),
+        },
+    );
+    exceptions.last = ptr;
+    exceptions.uncaught += 1;
+    throw(ctx, ptr)
 }
 
-pub fn ___cxa_increment_exception_refcount(_ctx: &mut EmEnv, _a: u32) {
-    debug!("emscripten::___cxa_increment_exception_refcount({})", _a);
-    unimplemented!("emscripten::___cxa_increment_exception_refcount({})", _a)
+/// emscripten: ___cxa_rethrow
+pub fn ___cxa_rethrow(ctx: &mut EmEnv) {
+    debug!("emscripten::___cxa_rethrow");
+    let exceptions = &mut get_emscripten_data(ctx).exceptions;
+    let ptr = exceptions.caught.pop().unwrap_or(0);
+    let ptr = exceptions.de_adjust(ptr);
+    if let Some(info) = exceptions.infos.get_mut(&ptr) {
+        if !info.rethrown {
+            info.rethrown = true;
+            exceptions.caught.push(ptr);
+        }
+    }
+    exceptions.last = ptr;
+    throw(ctx, ptr)
 }
 
-pub fn ___cxa_rethrow_primary_exception(_ctx: &mut EmEnv, _a: u32) {
-    debug!("emscripten::___cxa_rethrow_primary_exception({})", _a);
-    unimplemented!("emscripten::___cxa_rethrow_primary_exception({})", _a)
+/// emscripten: ___resumeException
+#[allow(non_snake_case)]
+pub fn ___resumeException(ctx: &mut EmEnv, ptr: u32) {
+    debug!("emscripten::___resumeException({})", ptr);
+    throw(ctx, ptr)
 }
 
-/// emscripten: ___cxa_throw
-/// TODO: We don't have support for exceptions yet
-pub fn ___cxa_throw(ctx: &mut EmEnv, _ptr: u32, _ty: u32, _destructor: u32) {
-    debug!("emscripten::___cxa_throw");
-    eprintln!("Throwing exceptions not yet implemented: aborting!");
-    _abort(ctx);
+/// emscripten: ___cxa_find_matching_catch_2
+pub fn ___cxa_find_matching_catch_2(ctx: &mut EmEnv) -> Result<u32, RuntimeError> {
+    debug!("emscripten::___cxa_find_matching_catch_2");
+    find_matching_catch(ctx, &[])
+}
+
+/// emscripten: ___cxa_find_matching_catch_3
+pub fn ___cxa_find_matching_catch_3(ctx: &mut EmEnv, ty1: u32) -> Result<u32, RuntimeError> {
+    debug!("emscripten::___cxa_find_matching_catch_3({})", ty1);
+    find_matching_catch(ctx, &[ty1])
+}
+
+/// emscripten: ___cxa_find_matching_catch_4
+pub fn ___cxa_find_matching_catch_4(
+    ctx: &mut EmEnv,
+    ty1: u32,
+    ty2: u32,
+) -> Result<u32, RuntimeError> {
+    debug!("emscripten::___cxa_find_matching_catch_4({}, {})", ty1, ty2);
+    find_matching_catch(ctx, &[ty1, ty2])
 }
 
-pub fn ___cxa_begin_catch(_ctx: &mut EmEnv, _exception_object_ptr: u32) -> i32 {
-    debug!("emscripten::___cxa_begin_catch");
-    -1
+/// emscripten: ___cxa_begin_catch
+pub fn ___cxa_begin_catch(ctx: &mut EmEnv, ptr: u32) -> u32 {
+    debug!("emscripten::___cxa_begin_catch({})", ptr);
+    let exceptions = &mut get_emscripten_data(ctx).exceptions;
+    let original = exceptions.de_adjust(ptr);
+    if let Some(info) = exceptions.infos.get_mut(&original) {
+        if !info.caught {
+            info.caught = true;
+            exceptions.uncaught = exceptions.uncaught.saturating_sub(1);
+        }
+        info.rethrown = false;
+    }
+    exceptions.caught.push(ptr);
+    exceptions.add_ref(original);
+    ptr
 }
 
-pub fn ___cxa_end_catch(_ctx: &mut EmEnv) {
+/// emscripten: ___cxa_end_catch
+pub fn ___cxa_end_catch(ctx: &mut EmEnv) -> Result<(), RuntimeError> {
     debug!("emscripten::___cxa_end_catch");
+    let data = get_emscripten_data(ctx);
+    data.set_threw
+        .as_ref()
+        .ok_or_else(|| not_exported("setThrew"))?
+        .call(0, 0)?;
+    if let Some(ptr) = data.exceptions.caught.pop() {
+        let ptr = data.exceptions.de_adjust(ptr);
+        data.exceptions.last = 0;
+        dec_ref(ctx, ptr)?;
+    }
+
+    Ok(())
+}
+
+/// emscripten: ___cxa_current_primary_exception
+pub fn ___cxa_current_primary_exception(ctx: &mut EmEnv) -> u32 {
+    debug!("emscripten::___cxa_current_primary_exception");
+    let exceptions = &mut get_emscripten_data(ctx).exceptions;
+    let ptr = exceptions.caught.last().copied().unwrap_or(0);
+    if ptr != 0 {
+        let original = exceptions.de_adjust(ptr);
+        exceptions.add_ref(original);
+    }
+    ptr
+}
+
+/// emscripten: ___cxa_increment_exception_refcount
+pub fn ___cxa_increment_exception_refcount(ctx: &mut EmEnv, ptr: u32) {
+    debug!("emscripten::___cxa_increment_exception_refcount({})", ptr);
+    let exceptions = &mut get_emscripten_data(ctx).exceptions;
+    let original = exceptions.de_adjust(ptr);
+    exceptions.add_ref(original);
+}
+
+/// emscripten: ___cxa_decrement_exception_refcount
+pub fn ___cxa_decrement_exception_refcount(ctx: &mut EmEnv, ptr: u32) -> Result<(), RuntimeError> {
+    debug!("emscripten::___cxa_decrement_exception_refcount({})", ptr);
+    let original = get_emscripten_data(ctx).exceptions.de_adjust(ptr);
+    dec_ref(ctx, original)
+}
+
+/// emscripten: ___cxa_rethrow_primary_exception
+pub fn ___cxa_rethrow_primary_exception(ctx: &mut EmEnv, ptr: u32) {
+    debug!("emscripten::___cxa_rethrow_primary_exception({})", ptr);
+    if ptr == 0 {
+        return;
+    }
+    let exceptions = &mut get_emscripten_data(ctx).exceptions;
+    exceptions.caught.push(ptr);
+    if let Some(info) = exceptions.infos.get_mut(&ptr) {
+        info.rethrown = true;
+    }
+    ___cxa_rethrow(ctx)
 }
 
-pub fn ___cxa_uncaught_exception(_ctx: &mut EmEnv) -> i32 {
+/// emscripten: ___cxa_uncaught_exception
+pub fn ___cxa_uncaught_exception(ctx: &mut EmEnv) -> i32 {
     debug!("emscripten::___cxa_uncaught_exception");
-    -1
+    (get_emscripten_data(ctx).exceptions.uncaught > 0) as i32
 }
 
 pub fn ___cxa_pure_virtual(_ctx: &mut EmEnv) {
@@ -0,0 +1,147 @@
+//! The filesystem sandbox of Emscripten modules: path-based syscalls
+//! can only reach the host directories mapped into it.
+
+use crate::errno::{EACCES, ENOENT, ENOTDIR};
+use std::os::raw::c_int;
+use std::path::{Component, Path, PathBuf};
+
+/// The directories of the host an Emscripten module can access, and
+/// its current directory.
+///
+/// Paths given by the module are resolved against its own current
+/// directory, then translated to the mapped directory they fall in.
+/// Paths outside of every mapped directory, including the ones reached
+/// through a symlink, are denied with `EACCES`; without any mapped
+/// directory the path-based syscalls can't reach the filesystem at all.
+///
+/// Host programs would escape the sandbox, so `execvp` and the like
+/// always fail.
+#[derive(Debug, Clone)]
+pub struct EmscriptenFs {
+    /// The mapped directories, as the normalized path the module sees
+    /// them at and the host directory they map to.
+    mapped_dirs: Vec<(PathBuf, PathBuf)>,
+    /// The current directory of the module, as it sees it.
+    current_dir: PathBuf,
+}
+
+impl EmscriptenFs {
+    /// Creates a sandbox with the given directories, as pairs of the
+    /// path the module sees them at and the host directory. Relative
+    /// module paths are relative to the root, which is the initial
+    /// current directory.
+    pub fn new<I>(mapped_dirs: I) -> Self
+    where
+        I: IntoIterator<Item = (String, PathBuf)>,
+    {
+        let root = PathBuf::from("/");
+        let mapped_dirs = mapped_dirs
+            .into_iter()
+            .map(|(guest_dir, host_dir)| (normalize(&root, Path::new(&guest_dir)), host_dir))
+            .collect();
+
+        Self {
+            mapped_dirs,
+            current_dir: root,
+        }
+    }
+
+    /// The current directory of the module, as it sees it.
+    pub fn current_dir(&self) -> &Path {
+        &self.current_dir
+    }
+
+    /// Changes the current directory of the module, without changing
+    /// the one of the host process.
+    pub fn set_current_dir(&mut self, path: &str) -> Result<(), c_int> {
+        let host_path = self.resolve(path, true)?;
+        if !host_path.exists() {
+            return Err(ENOENT);
+        }
+        if !host_path.is_dir() {
+            return Err(ENOTDIR);
+        }
+        self.current_dir = normalize(&self.current_dir, Path::new(path));
+
+        Ok(())
+    }
+
+    /// Translates a path of the module into the host path it refers to.
+    ///
+    /// When `follow_symlinks` is false, the last component of the path
+    /// may be a symlink pointing outside of the sandbox, as it's
+    /// operated on rather than followed (e.g. by `lstat` or `unlink`).
+    pub fn resolve(&self, path: &str, follow_symlinks: bool) -> Result<PathBuf, c_int> {
+        let guest_path = normalize(&self.current_dir, Path::new(path));
+        let (guest_dir, host_dir) = self
+            .mapped_dirs
+            .iter()
+            .filter(|(guest_dir, _)| guest_path.starts_with(guest_dir))
+            .max_by_key(|(guest_dir, _)| guest_dir.components().count())
+            .ok_or(EACCES)?;
+        let relative_path = guest_path.strip_prefix(guest_dir).unwrap();
+        let host_path = if relative_path.as_os_str().is_empty() {
+            host_dir.clone()
+        } else {
+            host_dir.join(relative_path)
+        };
+
+        let checked_path = if follow_symlinks || host_path == *host_dir {
+            host_path.as_path()
+        } else {
+            host_path.parent().unwrap_or(&host_path)
+        };
+        if is_inside(checked_path, host_dir)? {
+            Ok(host_path)
+        } else {
+            Err(EACCES)
+        }
+    }
+
+    /// Translates a host path back into the path the module sees it at,
+    /// if it's in a mapped directory.
+    pub fn guest_path(&self, host_path: &Path) -> Option<PathBuf> {
+        let (guest_dir, host_dir) = self
+            .mapped_dirs
+            .iter()
+            .filter(|(_, host_dir)| host_path.starts_with(host_dir))
+            .max_by_key(|(_, host_dir)| host_dir.components().count())?;
+
+        Some(guest_dir.join(host_path.strip_prefix(host_dir).unwrap()))
+    }
+}
+
+/// Checks that the deepest existing ancestor of `path`, symlinks
+/// resolved, is inside `dir`.
+fn is_inside(path: &Path, dir: &Path) -> Result<bool, c_int> {
+    let dir = dir.canonicalize().map_err(|_| ENOENT)?;
+    let mut path = path;
+    loop {
+        match path.canonicalize() {
+            Ok(canonical_path) => return Ok(canonical_path.starts_with(&dir)),
+            // a dangling symlink, whose target could be created outside
+            Err(_) if path.symlink_metadata().is_ok() => return Ok(false),
+            Err(_) => match path.parent() {
+                Some(parent) => path = parent,
+                None => return Ok(false),
+            },
+        }
+    }
+}
+
+/// Joins `path` to the absolute `base` and resolves its `.` and `..`
+/// components, without going above the root.
+fn normalize(base: &Path, path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in base.join(path).components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+
+    normalized
+}
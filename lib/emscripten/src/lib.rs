@@ -13,6 +13,7 @@
 #[macro_use]
 extern crate log;
 
+use crate::exception::Exceptions;
 use lazy_static::lazy_static;
 use std::cell::UnsafeCell;
 use std::collections::HashMap;
@@ -43,6 +44,7 @@ mod errno;
 mod exception;
 mod exec;
 mod exit;
+mod fs;
 mod inet;
 mod io;
 mod jmp;
@@ -63,6 +65,8 @@ mod unistd;
 mod utils;
 mod varargs;
 
+pub use self::exception::CppException;
+pub use self::fs::EmscriptenFs;
 pub use self::storage::{align_memory, static_alloc};
 pub use self::utils::{
     allocate_cstr_on_stack, allocate_on_stack, get_emscripten_memory_size, get_emscripten_metadata,
@@ -197,7 +201,9 @@ pub struct EmscriptenData<'a> {
     pub stack_save: Option<NativeFunc<'a, (), i32>>,
     pub stack_restore: Option<NativeFunc<'a, i32>>,
     pub set_threw: Option<NativeFunc<'a, (i32, i32)>>,
-    pub mapped_dirs: HashMap<String, PathBuf>,
+    pub cxa_can_catch: Option<NativeFunc<'a, (i32, i32, i32), i32>>,
+    pub(crate) exceptions: Exceptions,
+    pub fs: EmscriptenFs,
 }
 
 impl<'a> EmscriptenData<'a> {
@@ -323,6 +329,11 @@ impl<'a> EmscriptenData<'a> {
             .get_native_function("_setThrew")
             .or(instance.exports.get_native_function("setThrew"))
             .ok();
+        let cxa_can_catch = instance
+            .exports
+            .get_native_function("___cxa_can_catch")
+            .or(instance.exports.get_native_function("__cxa_can_catch"))
+            .ok();
 
         EmscriptenData {
             globals,
@@ -394,7 +405,9 @@ impl<'a> EmscriptenData<'a> {
             stack_save,
             stack_restore,
             set_threw,
-            mapped_dirs,
+            cxa_can_catch,
+            exceptions: Exceptions::default(),
+            fs: EmscriptenFs::new(mapped_dirs),
         }
     }
 }
@@ -469,6 +482,9 @@ pub fn emscripten_call_main(
 }
 
 /// Top level function to execute emscripten
+///
+/// The module can only access the `mapped_dirs` of the host, given as
+/// the path it sees each of them at and the host directory.
 pub fn run_emscripten_instance(
     instance: &mut Instance,
     env: &mut EmEnv,
@@ -910,9 +926,11 @@ pub fn generate_emscripten_env(
         "___cxa_increment_exception_refcount" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_increment_exception_refcount),
         "___cxa_rethrow_primary_exception" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_rethrow_primary_exception),
         "___cxa_throw" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_throw),
+        "___cxa_rethrow" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_rethrow),
         "___cxa_begin_catch" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_begin_catch),
         "___cxa_end_catch" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_end_catch),
         "___cxa_uncaught_exception" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_uncaught_exception),
+        "__ZSt18uncaught_exceptionv" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_uncaught_exception),
         "___cxa_pure_virtual" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_pure_virtual),
 
         // Time
@@ -1007,10 +1025,11 @@ pub fn generate_emscripten_env(
         "__Unwind_Backtrace" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::__Unwind_Backtrace),
         "__Unwind_FindEnclosingFunction" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::__Unwind_FindEnclosingFunction),
         "__Unwind_GetIPInfo" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::__Unwind_GetIPInfo),
-        "___cxa_find_matching_catch_2" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::___cxa_find_matching_catch_2),
-        "___cxa_find_matching_catch_3" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::___cxa_find_matching_catch_3),
-        "___cxa_free_exception" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::___cxa_free_exception),
-        "___resumeException" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::___resumeException),
+        "___cxa_find_matching_catch_2" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_find_matching_catch_2),
+        "___cxa_find_matching_catch_3" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_find_matching_catch_3),
+        "___cxa_find_matching_catch_4" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_find_matching_catch_4),
+        "___cxa_free_exception" => Function::new_native_with_env(store, env.clone(), crate::exception::___cxa_free_exception),
+        "___resumeException" => Function::new_native_with_env(store, env.clone(), crate::exception::___resumeException),
         "_dladdr" => Function::new_native_with_env(store, env.clone(), crate::emscripten_target::_dladdr),
         "_pthread_attr_destroy" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_attr_destroy),
         "_pthread_attr_getstack" => Function::new_native_with_env(store, env.clone(), crate::pthread::_pthread_attr_getstack),
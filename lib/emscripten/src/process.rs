@@ -180,9 +180,11 @@ pub fn _llvm_trap(ctx: &mut EmEnv) {
     abort_with_message(ctx, "abort!");
 }
 
-pub fn _llvm_eh_typeid_for(_ctx: &mut EmEnv, _type_info_addr: u32) -> i32 {
+pub fn _llvm_eh_typeid_for(_ctx: &mut EmEnv, type_info_addr: u32) -> i32 {
     debug!("emscripten::_llvm_eh_typeid_for");
-    -1
+    // the selectors set by `___cxa_find_matching_catch_*` are the
+    // addresses of the `type_info`s
+    type_info_addr as i32
 }
 
 pub fn _system(_ctx: &mut EmEnv, _one: i32) -> c_int {
@@ -11,6 +11,7 @@ pub use self::unix::*;
 pub use self::windows::*;
 
 use crate::{
+    errno::last_errno,
     ptr::{Array, WasmPtr},
     utils::{copy_stat_into_wasm, get_cstr_path, get_current_directory},
     EmEnv,
@@ -23,7 +24,6 @@ use byteorder::{ByteOrder, LittleEndian};
 use libc::{
     c_int,
     c_void,
-    chdir,
     // setsockopt, getppid
     close,
     dup2,
@@ -41,6 +41,7 @@ use libc::{
     rmdir,
     // writev,
     stat,
+    unlink,
     write,
     // ENOTTY,
 };
@@ -71,6 +72,9 @@ pub fn ___syscall3(ctx: &mut EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
     let buf_addr = emscripten_memory_pointer!(ctx.memory(0), buf) as *mut c_void;
     let ret = unsafe { read(fd, buf_addr, count as _) };
     debug!("=> ret: {}", ret);
+    if ret == -1 {
+        return -last_errno();
+    }
     ret as _
 }
 
@@ -82,7 +86,11 @@ pub fn ___syscall4(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_in
     let count: i32 = varargs.get(ctx);
     debug!("=> fd: {}, buf: {}, count: {}", fd, buf, count);
     let buf_addr = emscripten_memory_pointer!(ctx.memory(0), buf) as *const c_void;
-    unsafe { write(fd, buf_addr, count as _) as i32 }
+    let ret = unsafe { write(fd, buf_addr, count as _) };
+    if ret == -1 {
+        return -last_errno();
+    }
+    ret as i32
 }
 
 /// close
@@ -90,31 +98,39 @@ pub fn ___syscall6(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_in
     debug!("emscripten::___syscall6 (close) {}", _which);
     let fd: i32 = varargs.get(ctx);
     debug!("fd: {}", fd);
-    unsafe { close(fd) }
+    if unsafe { close(fd) } == -1 {
+        return -last_errno();
+    }
+    0
 }
 
 // chdir
 pub fn ___syscall12(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall12 (chdir) {}", _which);
     let path_ptr = varargs.get_str(ctx);
-    let real_path_owned = get_cstr_path(ctx, path_ptr as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        path_ptr
+    let path = unsafe { std::ffi::CStr::from_ptr(path_ptr) }.to_string_lossy();
+    // only the current directory of the module changes, not the one of
+    // the host process
+    let ret = match env::get_emscripten_data(ctx).fs.set_current_dir(&path) {
+        Ok(()) => 0,
+        Err(errno) => -errno,
     };
-    let ret = unsafe { chdir(real_path) };
-    debug!(
-        "=> path: {:?}, ret: {}",
-        unsafe { std::ffi::CStr::from_ptr(real_path) },
-        ret
-    );
+    debug!("=> path: {}, ret: {}", path, ret);
     ret
 }
 
-pub fn ___syscall10(_ctx: &mut EmEnv, _one: i32, _two: i32) -> i32 {
-    debug!("emscripten::___syscall10");
-    -1
+// unlink
+pub fn ___syscall10(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
+    debug!("emscripten::___syscall10 (unlink) {}", _which);
+    let pathname_addr = varargs.get_str(ctx);
+    let real_path_owned = match get_cstr_path(ctx, pathname_addr as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
+    };
+    let real_path = real_path_owned.as_ptr();
+    let result = unsafe { unlink(real_path) };
+    debug!("=> path: {:?}, result: {}", real_path_owned, result);
+    result
 }
 
 pub fn ___syscall14(_ctx: &mut EmEnv, _one: i32, _two: i32) -> i32 {
@@ -168,18 +184,16 @@ pub fn ___syscall38(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> i32
     debug!("emscripten::___syscall38 (rename)");
     let old_path = varargs.get_str(ctx);
     let new_path = varargs.get_str(ctx);
-    let real_old_path_owned = get_cstr_path(ctx, old_path as *const _);
-    let real_old_path = if let Some(ref rp) = real_old_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        old_path
+    let real_old_path_owned = match get_cstr_path(ctx, old_path as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
-    let real_new_path_owned = get_cstr_path(ctx, new_path as *const _);
-    let real_new_path = if let Some(ref rp) = real_new_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        new_path
+    let real_old_path = real_old_path_owned.as_ptr();
+    let real_new_path_owned = match get_cstr_path(ctx, new_path as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_new_path = real_new_path_owned.as_ptr();
     let result = unsafe { rename(real_old_path, real_new_path) };
     debug!(
         "=> old_path: {}, new_path: {}, result: {}",
@@ -194,12 +208,11 @@ pub fn ___syscall38(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> i32
 pub fn ___syscall40(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall40 (rmdir)");
     let pathname_addr = varargs.get_str(ctx);
-    let real_path_owned = get_cstr_path(ctx, pathname_addr as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        pathname_addr
+    let real_path_owned = match get_cstr_path(ctx, pathname_addr as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     unsafe { rmdir(real_path) }
 }
 
@@ -354,7 +367,7 @@ pub fn ___syscall183(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> i3
     let buf_offset: WasmPtr<libc::c_char, Array> = varargs.get(ctx);
     let _size: c_int = varargs.get(ctx);
     let path = get_current_directory(ctx);
-    let path_string = path.unwrap().display().to_string();
+    let path_string = path.display().to_string();
     let len = path_string.len();
 
     let buf_writer = buf_offset.deref(ctx.memory(0), 0, len as u32 + 1).unwrap();
@@ -535,12 +548,11 @@ pub fn ___syscall195(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
     let pathname_addr = varargs.get_str(ctx);
     let buf: u32 = varargs.get(ctx);
 
-    let real_path_owned = get_cstr_path(ctx, pathname_addr as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        pathname_addr
+    let real_path_owned = match get_cstr_path(ctx, pathname_addr as *const _, true) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
 
     unsafe {
         let mut _stat: stat = std::mem::zeroed();
@@ -1,4 +1,9 @@
-use crate::{ptr::WasmPtr, varargs::VarArgs};
+use crate::{
+    env::get_emscripten_data,
+    errno::{last_errno, EACCES, EFAULT, EINVAL, EMSGSIZE, ENAMETOOLONG, EOPNOTSUPP},
+    ptr::{Array, WasmPtr},
+    varargs::VarArgs,
+};
 #[cfg(target_os = "macos")]
 use libc::size_t;
 /// NOTE: TODO: These syscalls only support wasm_32 for now because they assume offsets are u32
@@ -12,6 +17,7 @@ use libc::{
     c_ulong,
     c_void,
     chown,
+    close,
     // fcntl, setsockopt, getppid
     connect,
     dup,
@@ -19,6 +25,7 @@ use libc::{
     fchmod,
     fchown,
     fcntl,
+    fd_set,
     // ENOTTY,
     fsync,
     getegid,
@@ -32,8 +39,6 @@ use libc::{
     getsockopt,
     getuid,
     gid_t,
-    in_addr_t,
-    in_port_t,
     ioctl,
     lchown,
     link,
@@ -61,20 +66,29 @@ use libc::{
     sendto,
     setpgid,
     setsockopt,
+    shutdown,
     sockaddr,
+    sockaddr_storage,
+    sockaddr_un,
     socket,
+    socketpair,
     socklen_t,
     stat,
     symlink,
     uid_t,
     uname,
     utsname,
-    EINVAL,
     // sockaddr_in,
+    FD_CLOEXEC,
     FIOCLEX,
     FIONBIO,
+    FIONREAD,
     F_GETFD,
+    F_GETFL,
     F_SETFD,
+    F_SETFL,
+    O_APPEND,
+    O_NONBLOCK,
     SOL_SOCKET,
     TIOCGWINSZ,
     TIOCSPGRP,
@@ -89,6 +103,7 @@ const TCSETSW: u64 = 0x5403;
 // `libc` constants as provided by `emscripten`. Maybe move to own file?
 const WASM_FIONBIO: u32 = 0x5421;
 const WASM_FIOCLEX: u32 = 0x5451;
+const WASM_FIONREAD: u32 = 0x541B;
 const WASM_TIOCSPGRP: u32 = 0x5410;
 const WASM_TIOCGWINSZ: u32 = 0x5413;
 const WASM_TCGETS: u32 = 0x5401;
@@ -96,29 +111,31 @@ const WASM_TCSETSW: u32 = 0x5403;
 
 // Based on @syrusakbary sugerence at
 // https://github.com/wasmerio/wasmer/pull/532#discussion_r300837800
-fn translate_ioctl(wasm_ioctl: u32) -> c_ulong {
-    match wasm_ioctl {
+fn translate_ioctl(wasm_ioctl: u32) -> Option<c_ulong> {
+    Some(match wasm_ioctl {
         WASM_FIOCLEX => FIOCLEX as _,
         WASM_TIOCGWINSZ => TIOCGWINSZ as _,
         WASM_TIOCSPGRP => TIOCSPGRP as _,
         WASM_FIONBIO => FIONBIO as _,
         WASM_TCGETS => TCGETS as _,
         WASM_TCSETSW => TCSETSW as _,
-        _otherwise => {
-            unimplemented!("The ioctl {} is not yet implemented", wasm_ioctl);
-        }
-    }
+        WASM_FIONREAD => FIONREAD as _,
+        _otherwise => return None,
+    })
 }
 
 #[allow(unused_imports)]
 use std::ffi::CStr;
+use std::ffi::OsStr;
 
-use crate::env::EmSockAddr;
 use crate::utils::{self, get_cstr_path};
 use crate::EmEnv;
 #[allow(unused_imports)]
 use std::io::Error;
 use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
 
 // Linking to functions that are not provided by rust libc
 #[cfg(target_os = "macos")]
@@ -149,11 +166,11 @@ use libc::{fdatasync, ftruncate64, lstat, madvise, wait4};
 
 // Another conditional constant for name resolution: Macos et iOS use
 // SO_NOSIGPIPE as a setsockopt flag to disable SIGPIPE emission on socket.
-// Other platforms do otherwise.
+// Other platforms do otherwise, with a flag of every send.
 #[cfg(target_os = "macos")]
-use libc::SO_NOSIGPIPE;
+const SEND_FLAGS: c_int = 0;
 #[cfg(not(target_os = "macos"))]
-const SO_NOSIGPIPE: c_int = 0;
+const SEND_FLAGS: c_int = libc::MSG_NOSIGNAL;
 
 /// open
 pub fn ___syscall5(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
@@ -161,12 +178,11 @@ pub fn ___syscall5(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_in
     let pathname_addr = varargs.get_str(ctx);
     let flags: i32 = varargs.get(ctx);
     let mode: u32 = varargs.get(ctx);
-    let real_path_owned = utils::get_cstr_path(ctx, pathname_addr as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        pathname_addr
+    let real_path_owned = match utils::get_cstr_path(ctx, pathname_addr as *const _, true) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     let _path_str = unsafe { std::ffi::CStr::from_ptr(real_path).to_str().unwrap() };
     let fd = unsafe { open(real_path, flags, mode) };
     debug!(
@@ -185,12 +201,18 @@ pub fn ___syscall9(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_in
 
     let oldname_ptr = varargs.get_str(ctx);
     let newname_ptr = varargs.get_str(ctx);
-    let result = unsafe { link(oldname_ptr, newname_ptr) };
+    let real_oldname_owned = match get_cstr_path(ctx, oldname_ptr as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
+    };
+    let real_newname_owned = match get_cstr_path(ctx, newname_ptr as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
+    };
+    let result = unsafe { link(real_oldname_owned.as_ptr(), real_newname_owned.as_ptr()) };
     debug!(
-        "=> oldname: {}, newname: {}, result: {}",
-        unsafe { std::ffi::CStr::from_ptr(oldname_ptr).to_str().unwrap() },
-        unsafe { std::ffi::CStr::from_ptr(newname_ptr).to_str().unwrap() },
-        result,
+        "=> oldname: {:?}, newname: {:?}, result: {}",
+        real_oldname_owned, real_newname_owned, result,
     );
     result
 }
@@ -213,18 +235,24 @@ pub fn ___syscall83(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_i
 
     let path1 = varargs.get_str(ctx);
     let path2 = varargs.get_str(ctx);
-    let real_path1_owned = utils::get_cstr_path(ctx, path1 as *const _);
-    let real_path1 = if let Some(ref rp) = real_path1_owned {
-        rp.as_c_str().as_ptr()
+    // a relative target is relative to the symlink on the host too; the
+    // sandbox checks where it leads when the symlink is followed
+    let real_path1_owned = if unsafe { *path1 } == b'/' as libc::c_char {
+        match utils::get_cstr_path(ctx, path1 as *const _, true) {
+            Ok(path) => Some(path),
+            Err(errno) => return -errno,
+        }
     } else {
-        path1
+        None
     };
-    let real_path2_owned = utils::get_cstr_path(ctx, path2 as *const _);
-    let real_path2 = if let Some(ref rp) = real_path2_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        path2
+    let real_path1 = real_path1_owned
+        .as_ref()
+        .map_or(path1, |path| path.as_ptr());
+    let real_path2_owned = match utils::get_cstr_path(ctx, path2 as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path2 = real_path2_owned.as_ptr();
     let result = unsafe { symlink(real_path1, real_path2) };
     debug!(
         "=> path1: {}, path2: {}, result: {}",
@@ -242,12 +270,11 @@ pub fn ___syscall85(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> i32
     let buf = varargs.get_str(ctx);
     // let buf_addr: i32 = varargs.get(ctx);
     let buf_size: i32 = varargs.get(ctx);
-    let real_path_owned = get_cstr_path(ctx, pathname_addr as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        pathname_addr
+    let real_path_owned = match get_cstr_path(ctx, pathname_addr as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
 
     let ret = unsafe { libc::readlink(real_path, buf as _, buf_size as _) as i32 };
     if ret == -1 {
@@ -285,12 +312,11 @@ pub fn ___syscall194(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
 pub fn ___syscall198(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall198 (lchown) {}", _which);
     let path_ptr = varargs.get_str(ctx);
-    let real_path_owned = utils::get_cstr_path(ctx, path_ptr as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        path_ptr
+    let real_path_owned = match utils::get_cstr_path(ctx, path_ptr as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     let uid: uid_t = varargs.get(ctx);
     let gid: gid_t = varargs.get(ctx);
     let result = unsafe { lchown(real_path, uid, gid) };
@@ -326,12 +352,11 @@ pub fn ___syscall212(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
     debug!("emscripten::___syscall212 (chown) {}", _which);
 
     let pathname_addr = varargs.get_str(ctx);
-    let real_path_owned = utils::get_cstr_path(ctx, pathname_addr as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        pathname_addr
+    let real_path_owned = match utils::get_cstr_path(ctx, pathname_addr as *const _, true) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     let owner: u32 = varargs.get(ctx);
     let group: u32 = varargs.get(ctx);
 
@@ -355,12 +380,11 @@ pub fn ___syscall219(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
 pub fn ___syscall33(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall33 (access) {}", _which);
     let path = varargs.get_str(ctx);
-    let real_path_owned = utils::get_cstr_path(ctx, path as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        path
+    let real_path_owned = match utils::get_cstr_path(ctx, path as *const _, true) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     let amode: c_int = varargs.get(ctx);
     let result = unsafe { access(real_path, amode) };
     debug!(
@@ -383,12 +407,11 @@ pub fn ___syscall34(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_i
 pub fn ___syscall39(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall39 (mkdir) {}", _which);
     let pathname_addr = varargs.get_str(ctx);
-    let real_path_owned = utils::get_cstr_path(ctx, pathname_addr as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        pathname_addr
+    let real_path_owned = match utils::get_cstr_path(ctx, pathname_addr as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     let mode: u32 = varargs.get(ctx);
     unsafe { mkdir(real_path, mode as _) }
 }
@@ -477,60 +500,348 @@ pub fn ___syscall54(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_i
     debug!("=> fd: {}, op: {}", fd, request);
 
     // Got the equivalents here: https://code.woboq.org/linux/linux/include/uapi/asm-generic/ioctls.h.html
-    match request {
-        WASM_FIOCLEX | WASM_FIONBIO | WASM_TIOCGWINSZ | WASM_TIOCSPGRP | WASM_TCGETS
-        | WASM_TCSETSW => {
-            let argp: u32 = varargs.get(ctx);
-            let argp_ptr = emscripten_memory_pointer!(ctx.memory(0), argp) as *mut c_void;
-            let translated_request = translate_ioctl(request);
-            let ret = unsafe { ioctl(fd, translated_request as _, argp_ptr) };
-            debug!(
-                " => request: {}, translated: {}, return: {}",
-                request, translated_request, ret
-            );
-
-            // TODO: We hardcode the value to have emscripten tests pass, as for some reason
-            // when the capturer is active, ioctl returns -1 instead of 0
-            if request == WASM_TIOCGWINSZ && ret == -1 {
-                return 0;
-            }
-            ret
-        }
-        _ => {
-            debug!(
-                " => not implemented case {} (noop, hardcoded to 0)",
-                request
-            );
-            0
+    let translated_request = match translate_ioctl(request) {
+        Some(translated_request) => translated_request,
+        None => {
+            debug!(" => not implemented case {}", request);
+            return -EINVAL;
         }
+    };
+    let argp: u32 = varargs.get(ctx);
+    let argp_ptr = emscripten_memory_pointer!(ctx.memory(0), argp) as *mut c_void;
+    let ret = unsafe { ioctl(fd, translated_request as _, argp_ptr) };
+    debug!(
+        " => request: {}, translated: {}, return: {}",
+        request, translated_request, ret
+    );
+
+    // TODO: We hardcode the value to have emscripten tests pass, as for some reason
+    // when the capturer is active, ioctl returns -1 instead of 0
+    if request == WASM_TIOCGWINSZ && ret == -1 {
+        return 0;
+    }
+    match host_result(ret as _) {
+        Ok(ret) => ret,
+        Err(errno) => -errno,
     }
 }
 
 const SOCK_NON_BLOCK: i32 = 2048;
 const SOCK_CLOEXC: i32 = 0x80000;
 
+const WASM_AF_INET6: i32 = 10;
+
+/// The `MSG_*` flags of `emscripten` and the host ones they stand for.
+const MSG_FLAGS: [(i32, c_int); 8] = [
+    (0x1, libc::MSG_OOB),
+    (0x2, libc::MSG_PEEK),
+    (0x4, libc::MSG_DONTROUTE),
+    (0x8, libc::MSG_CTRUNC),
+    (0x20, libc::MSG_TRUNC),
+    (0x40, libc::MSG_DONTWAIT),
+    (0x80, libc::MSG_EOR),
+    (0x100, libc::MSG_WAITALL),
+];
+
+/// `struct msghdr` of `emscripten`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct GuestMsgHdr {
+    msg_name: u32,
+    msg_namelen: u32,
+    msg_iov: u32,
+    msg_iovlen: i32,
+    msg_control: u32,
+    msg_controllen: u32,
+    msg_flags: i32,
+}
+
+unsafe impl wasmer::ValueType for GuestMsgHdr {}
+
+/// `struct iovec` of `emscripten`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct GuestIovec {
+    iov_base: u32,
+    iov_len: u32,
+}
+
+unsafe impl wasmer::ValueType for GuestIovec {}
+
+/// `struct timeval` of `emscripten`, whose `time_t` is 32 bits.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct GuestTimeval {
+    tv_sec: i32,
+    tv_usec: i32,
+}
+
+unsafe impl wasmer::ValueType for GuestTimeval {}
+
+/// Turns the result of a host call into the one of a syscall, the
+/// errno being negated.
+fn host_result(ret: isize) -> Result<c_int, c_int> {
+    if ret == -1 {
+        Err(last_errno())
+    } else {
+        Ok(ret as c_int)
+    }
+}
+
+/// Gets a pointer to `len` bytes of the memory at `offset`, failing
+/// with `EFAULT` if they aren't all in bounds.
+fn guest_buffer(ctx: &EmEnv, offset: u32, len: u32) -> Result<*mut c_void, c_int> {
+    let memory = ctx.memory(0);
+    if offset as usize + len as usize > memory.size().bytes().0 {
+        return Err(EFAULT);
+    }
+    Ok(emscripten_memory_pointer!(memory, offset) as *mut c_void)
+}
+
+/// Translates the address family of `emscripten` into the host one.
+fn translate_address_family(family: i32) -> c_int {
+    match family {
+        WASM_AF_INET6 => libc::AF_INET6,
+        otherwise => otherwise,
+    }
+}
+
+/// Translates the address family of the host into the `emscripten` one.
+fn untranslate_address_family(family: c_int) -> i32 {
+    match family {
+        libc::AF_INET6 => WASM_AF_INET6,
+        otherwise => otherwise,
+    }
+}
+
+/// Translates the `MSG_*` flags of `emscripten` into the host ones,
+/// dropping the unknown ones.
+fn translate_msg_flags(flags: i32) -> c_int {
+    MSG_FLAGS
+        .iter()
+        .filter(|(wasm_flag, _)| flags & wasm_flag != 0)
+        .fold(0, |host_flags, (_, host_flag)| host_flags | host_flag)
+}
+
+/// Translates the `MSG_*` flags of the host into the `emscripten` ones.
+fn untranslate_msg_flags(flags: c_int) -> i32 {
+    MSG_FLAGS
+        .iter()
+        .filter(|(_, host_flag)| flags & host_flag != 0)
+        .fold(0, |wasm_flags, (wasm_flag, _)| wasm_flags | wasm_flag)
+}
+
+/// The offset of `sun_path` in `struct sockaddr_un`, after the family,
+/// for both the module and the host.
+const SUN_PATH_OFFSET: usize = 2;
+
+/// Copies `path` into the `sun_path` of `address`, returning the length
+/// of the address, or `None` if it's too long.
+fn set_sun_path(address: &mut sockaddr_un, path: &[u8]) -> Option<socklen_t> {
+    if path.len() >= address.sun_path.len() {
+        return None;
+    }
+    let padded_path = path.iter().chain(std::iter::repeat(&0));
+    for (c, &byte) in address.sun_path.iter_mut().zip(padded_path) {
+        *c = byte as c_char;
+    }
+
+    Some((SUN_PATH_OFFSET + path.len() + 1) as socklen_t)
+}
+
+/// Gets the path of an `AF_UNIX` address of length `address_len`,
+/// which is empty for unnamed and abstract addresses.
+fn sun_path(address: &sockaddr_un, address_len: socklen_t) -> Vec<u8> {
+    let len = (address_len as usize)
+        .saturating_sub(SUN_PATH_OFFSET)
+        .min(address.sun_path.len());
+    address.sun_path[..len]
+        .iter()
+        .map(|&c| c as u8)
+        .take_while(|&byte| byte != 0)
+        .collect()
+}
+
+/// Translates the path of an `AF_UNIX` address of the module into the
+/// host path it refers to in its filesystem sandbox, returning the new
+/// length of the address.
+///
+/// Unnamed and abstract addresses aren't in the filesystem and could
+/// reach any socket of the host, so they're denied.
+fn resolve_unix_address(
+    ctx: &mut EmEnv,
+    address: &mut sockaddr_storage,
+    address_len: socklen_t,
+) -> Result<socklen_t, c_int> {
+    let address = unsafe { &mut *(address as *mut sockaddr_storage as *mut sockaddr_un) };
+    let path = sun_path(address, address_len);
+    if path.is_empty() {
+        return Err(EACCES);
+    }
+    let path = std::str::from_utf8(&path).map_err(|_| EINVAL)?;
+    let host_path = get_emscripten_data(ctx).fs.resolve(path, true)?;
+
+    set_sun_path(address, host_path.as_os_str().as_bytes()).ok_or(ENAMETOOLONG)
+}
+
+/// Translates the path of a host `AF_UNIX` address into the one the
+/// module sees it at, returning the new length of the address. The
+/// addresses outside of the sandbox are turned into unnamed ones.
+fn unresolve_unix_address(
+    ctx: &mut EmEnv,
+    address: &mut sockaddr_storage,
+    address_len: socklen_t,
+) -> socklen_t {
+    let address = unsafe { &mut *(address as *mut sockaddr_storage as *mut sockaddr_un) };
+    let host_path = sun_path(address, address_len);
+    let guest_path = if host_path.is_empty() {
+        None
+    } else {
+        get_emscripten_data(ctx)
+            .fs
+            .guest_path(Path::new(OsStr::from_bytes(&host_path)))
+    };
+
+    guest_path
+        .and_then(|guest_path| set_sun_path(address, guest_path.as_os_str().as_bytes()))
+        .unwrap_or_else(|| {
+            set_sun_path(address, &[]);
+            SUN_PATH_OFFSET as socklen_t
+        })
+}
+
+/// Copies the socket address of the module at `address` into a host one.
+///
+/// The paths of `AF_UNIX` addresses are translated like the ones of the
+/// filesystem syscalls.
+fn read_sockaddr(
+    ctx: &mut EmEnv,
+    address: u32,
+    address_len: u32,
+) -> Result<(sockaddr_storage, socklen_t), c_int> {
+    if address_len < 2 || address_len as usize > mem::size_of::<sockaddr_storage>() {
+        return Err(EINVAL);
+    }
+    let guest_address = guest_buffer(ctx, address, address_len)? as *const u8;
+
+    let mut host_address: sockaddr_storage = unsafe { mem::zeroed() };
+    let family = unsafe {
+        ptr::copy_nonoverlapping(
+            guest_address,
+            &mut host_address as *mut sockaddr_storage as *mut u8,
+            address_len as usize,
+        );
+        (guest_address as *const u16).read_unaligned()
+    };
+    host_address.ss_family = translate_address_family(family as i32) as sa_family_t;
+    let address_len = if host_address.ss_family as c_int == libc::AF_UNIX {
+        resolve_unix_address(ctx, &mut host_address, address_len)?
+    } else {
+        address_len
+    };
+    #[cfg(any(target_os = "freebsd", target_os = "macos"))]
+    {
+        host_address.ss_len = address_len as u8;
+    }
+
+    Ok((host_address, address_len))
+}
+
+/// Copies a host socket address into the module at `address`, truncated
+/// to the length at `address_len`, which is set to its full length.
+/// Nothing is copied if `address` is null. Returns the full length.
+fn write_sockaddr(
+    ctx: &mut EmEnv,
+    host_address: &sockaddr_storage,
+    host_address_len: socklen_t,
+    address: u32,
+    address_len: u32,
+) -> Result<socklen_t, c_int> {
+    let mut host_address = *host_address;
+    let host_address_len = if host_address.ss_family as c_int == libc::AF_UNIX {
+        unresolve_unix_address(ctx, &mut host_address, host_address_len)
+    } else {
+        host_address_len
+    };
+    if address == 0 {
+        return Ok(host_address_len);
+    }
+    let address_len_ptr = guest_buffer(ctx, address_len, 4)? as *mut u32;
+    let len = unsafe { address_len_ptr.read_unaligned() }.min(host_address_len);
+    let guest_address = guest_buffer(ctx, address, len)? as *mut u8;
+
+    let mut bytes = [0u8; mem::size_of::<sockaddr_storage>()];
+    unsafe {
+        ptr::copy_nonoverlapping(
+            &host_address as *const sockaddr_storage as *const u8,
+            bytes.as_mut_ptr(),
+            bytes.len(),
+        );
+    }
+    let family = untranslate_address_family(host_address.ss_family as c_int) as u16;
+    bytes[..2].copy_from_slice(&family.to_le_bytes());
+    unsafe {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), guest_address, len as usize);
+        address_len_ptr.write_unaligned(host_address_len);
+    }
+
+    Ok(host_address_len)
+}
+
+/// Applies the `SOCK_NONBLOCK` and `SOCK_CLOEXEC` flags of a socket
+/// type to the socket `fd`.
+fn set_socket_flags(fd: c_int, ty_and_flags: i32) -> Result<(), c_int> {
+    if ty_and_flags & SOCK_CLOEXC != 0 {
+        host_result(unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) } as _)?;
+    }
+    if ty_and_flags & SOCK_NON_BLOCK != 0 {
+        let flags = host_result(unsafe { fcntl(fd, F_GETFL) } as _)?;
+        host_result(unsafe { fcntl(fd, F_SETFL, flags | O_NONBLOCK) } as _)?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let value: c_int = 1;
+        host_result(unsafe {
+            setsockopt(
+                fd,
+                SOL_SOCKET,
+                libc::SO_NOSIGPIPE,
+                &value as *const c_int as *const c_void,
+                mem::size_of::<c_int>() as socklen_t,
+            )
+        } as _)?;
+    }
+
+    Ok(())
+}
+
+/// Applies the flags of a socket type to the new socket `fd`, closing
+/// it if they can't be.
+fn new_socket(fd: c_int, ty_and_flags: i32) -> Result<c_int, c_int> {
+    if let Err(errno) = set_socket_flags(fd, ty_and_flags) {
+        unsafe { close(fd) };
+        return Err(errno);
+    }
+    Ok(fd)
+}
+
 // socketcall
-#[allow(clippy::cast_ptr_alignment)]
 pub fn ___syscall102(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall102 (socketcall) {}", _which);
     let call: u32 = varargs.get(ctx);
     let mut socket_varargs: VarArgs = varargs.get(ctx);
 
-    // migrating to EmSockAddr, port being separate here is nice, should update that too
-    #[repr(C)]
-    pub struct GuestSockaddrIn {
-        pub sin_family: sa_family_t, // u16
-        pub sin_port: in_port_t,     // u16
-        pub sin_addr: GuestInAddr,   // u32
-        pub sin_zero: [u8; 8],       // u8 * 8
-                                     // 2 + 2 + 4 + 8 = 16
-    }
-
-    #[repr(C)]
-    pub struct GuestInAddr {
-        pub s_addr: in_addr_t, // u32
+    let ret = socketcall(ctx, call, &mut socket_varargs);
+    debug!("=> call: {} = {:?}", call, ret);
+    match ret {
+        Ok(ret) => ret,
+        Err(errno) => -errno,
     }
+}
 
+#[allow(clippy::cast_ptr_alignment)]
+fn socketcall(ctx: &mut EmEnv, call: u32, socket_varargs: &mut VarArgs) -> Result<c_int, c_int> {
     match call {
         1 => {
             debug!("socket: socket");
@@ -538,210 +849,207 @@ pub fn ___syscall102(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
             let domain: i32 = socket_varargs.get(ctx);
             let ty_and_flags: i32 = socket_varargs.get(ctx);
             let protocol: i32 = socket_varargs.get(ctx);
-            let ty = ty_and_flags & (!SOCK_NON_BLOCK) & (!SOCK_CLOEXC);
-            let fd = unsafe { socket(domain, ty, protocol) };
-
-            if ty_and_flags & SOCK_CLOEXC != 0 {
-                // set_cloexec
-                unsafe {
-                    ioctl(fd, translate_ioctl(WASM_FIOCLEX) as _);
-                };
-            }
-
-            if ty_and_flags & SOCK_NON_BLOCK != 0 {
-                // do something here
-                unimplemented!("non blocking sockets");
-            }
-
-            // why is this here?
-            type T = u32;
-            let payload = 1 as *const T as _;
-            unsafe {
-                setsockopt(
-                    fd,
-                    SOL_SOCKET,
-                    SO_NOSIGPIPE,
-                    payload,
-                    mem::size_of::<T>() as socklen_t,
-                );
-            };
-
+            let ty = ty_and_flags & !(SOCK_NON_BLOCK | SOCK_CLOEXC);
+            let fd = host_result(
+                unsafe { socket(translate_address_family(domain), ty, protocol) } as _,
+            )?;
             debug!(
                 "=> domain: {}, type: {}, protocol: {} = fd: {}",
                 domain, ty, protocol, fd
             );
-            fd as _
+            new_socket(fd, ty_and_flags)
         }
         2 => {
             debug!("socket: bind");
             // bind (socket: c_int, address: *const sockaddr, address_len: socklen_t) -> c_int
-            // TODO: Emscripten has a different signature.
-            let socket = socket_varargs.get(ctx);
+            let socket: i32 = socket_varargs.get(ctx);
             let address: u32 = socket_varargs.get(ctx);
-            let address_len = socket_varargs.get(ctx);
-            let address = emscripten_memory_pointer!(ctx.memory(0), address) as *mut sockaddr;
-
-            // Debug received address
-            let _proper_address = address as *const GuestSockaddrIn;
-            debug!(
-                    "=> address.sin_family: {:?}, address.sin_port: {:?}, address.sin_addr.s_addr: {:?}",
-                unsafe { (*_proper_address).sin_family }, unsafe { (*_proper_address).sin_port }, unsafe { (*_proper_address).sin_addr.s_addr }
-                );
-
-            let status = unsafe { bind(socket, address, address_len) };
-            // debug!("=> status: {}", status);
-            debug!(
-                "=> socketfd: {}, address: {:?}, address_len: {} = status: {}",
-                socket, address, address_len, status
-            );
-            status
-            // -1
+            let address_len: u32 = socket_varargs.get(ctx);
+            let (host_address, host_address_len) = read_sockaddr(ctx, address, address_len)?;
+            host_result(unsafe {
+                bind(
+                    socket,
+                    &host_address as *const sockaddr_storage as *const sockaddr,
+                    host_address_len,
+                )
+            } as _)
         }
         3 => {
             debug!("socket: connect");
             // connect (socket: c_int, address: *const sockaddr, len: socklen_t) -> c_int
-            // TODO: Emscripten has a different signature.
-            let socket = socket_varargs.get(ctx);
+            let socket: i32 = socket_varargs.get(ctx);
             let address: u32 = socket_varargs.get(ctx);
-            let address_len = socket_varargs.get(ctx);
-            let address = emscripten_memory_pointer!(ctx.memory(0), address) as *mut sockaddr;
-            unsafe { connect(socket, address, address_len) }
+            let address_len: u32 = socket_varargs.get(ctx);
+            let (host_address, host_address_len) = read_sockaddr(ctx, address, address_len)?;
+            host_result(unsafe {
+                connect(
+                    socket,
+                    &host_address as *const sockaddr_storage as *const sockaddr,
+                    host_address_len,
+                )
+            } as _)
         }
         4 => {
             debug!("socket: listen");
             // listen (socket: c_int, backlog: c_int) -> c_int
-            let socket = socket_varargs.get(ctx);
+            let socket: i32 = socket_varargs.get(ctx);
             let backlog: i32 = socket_varargs.get(ctx);
-            let status = unsafe { listen(socket, backlog) };
-            debug!(
-                "=> socketfd: {}, backlog: {} = status: {}",
-                socket, backlog, status
-            );
-            status
+            host_result(unsafe { listen(socket, backlog) } as _)
         }
-        5 => {
+        5 | 18 => {
             debug!("socket: accept");
             // accept (socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t) -> c_int
+            // accept4 (socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t, flags: c_int) -> c_int
             let socket: i32 = socket_varargs.get(ctx);
-            let address: WasmPtr<EmSockAddr> = socket_varargs.get(ctx);
-            let address_len: WasmPtr<u32> = socket_varargs.get(ctx);
-
-            debug!(
-                "=> socket: {}, address: {:?}, address_len: {}",
-                socket,
-                address.deref(ctx.memory(0)).unwrap().get(),
-                address_len.deref(ctx.memory(0)).unwrap().get()
-            );
-            let address_len_addr =
-                unsafe { address_len.deref_mut(ctx.memory(0)).unwrap().get_mut() };
-            // let mut address_len_addr: socklen_t = 0;
-
-            let mut host_address: sockaddr = sockaddr {
-                sa_family: Default::default(),
-                sa_data: Default::default(),
-                #[cfg(any(target_os = "freebsd", target_os = "macos"))]
-                sa_len: Default::default(),
-            };
-            let fd = unsafe { accept(socket, &mut host_address, address_len_addr) };
-            let address_addr = unsafe { address.deref_mut(ctx.memory(0)).unwrap().get_mut() };
-
-            address_addr.sa_family = host_address.sa_family as _;
-            address_addr.sa_data = host_address.sa_data;
-
-            // why is this here?
-            // set_cloexec
-            unsafe {
-                ioctl(fd, translate_ioctl(WASM_FIOCLEX) as _);
+            let address: u32 = socket_varargs.get(ctx);
+            let address_len: u32 = socket_varargs.get(ctx);
+            let flags: i32 = if call == 18 {
+                socket_varargs.get(ctx)
+            } else {
+                0
             };
 
-            debug!(
-                "address: {:?}, len: {}, result fd = {}",
-                address_addr, address_len_addr, fd
-            );
-
-            fd as _
-        }
-        6 => {
-            debug!("socket: getsockname");
-            // getsockname (socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t) -> c_int
-            let socket: i32 = socket_varargs.get(ctx);
-            let address: WasmPtr<EmSockAddr> = socket_varargs.get(ctx);
-            let address_len: WasmPtr<u32> = socket_varargs.get(ctx);
-            let address_len_addr =
-                unsafe { address_len.deref_mut(ctx.memory(0)).unwrap().get_mut() };
-
-            let mut sock_addr_host: sockaddr = sockaddr {
-                sa_family: Default::default(),
-                sa_data: Default::default(),
-                #[cfg(any(target_os = "freebsd", target_os = "macos"))]
-                sa_len: Default::default(),
-            };
-            let ret = unsafe {
-                getsockname(
+            let mut host_address: sockaddr_storage = unsafe { mem::zeroed() };
+            let mut host_address_len = mem::size_of::<sockaddr_storage>() as socklen_t;
+            let fd = host_result(unsafe {
+                accept(
                     socket,
-                    &mut sock_addr_host as *mut sockaddr,
-                    address_len_addr as *mut u32,
+                    &mut host_address as *mut sockaddr_storage as *mut sockaddr,
+                    &mut host_address_len,
                 )
-            };
-            // translate from host data into emscripten data
-            let mut address_mut = unsafe { address.deref_mut(ctx.memory(0)).unwrap().get_mut() };
-            address_mut.sa_family = sock_addr_host.sa_family as _;
-            address_mut.sa_data = sock_addr_host.sa_data;
-
-            debug!(
-                "=> socket: {}, address, {:?}, address_len: {}, result = {}",
-                socket, address_mut, address_len_addr, ret
-            );
-
-            ret
+            } as _)?;
+            // the accepted sockets of the module aren't inherited by the
+            // processes spawned by the host
+            let fd = new_socket(fd, flags | SOCK_CLOEXC)?;
+            write_sockaddr(ctx, &host_address, host_address_len, address, address_len)?;
+            Ok(fd)
         }
-        7 => {
-            debug!("socket: getpeername");
+        6 | 7 => {
+            debug!("socket: getsockname/getpeername");
+            // getsockname (socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t) -> c_int
             // getpeername (socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t) -> c_int
-            let socket = socket_varargs.get(ctx);
+            let socket: i32 = socket_varargs.get(ctx);
             let address: u32 = socket_varargs.get(ctx);
             let address_len: u32 = socket_varargs.get(ctx);
-            let address = emscripten_memory_pointer!(ctx.memory(0), address) as *mut sockaddr;
-            let address_len_addr =
-                emscripten_memory_pointer!(ctx.memory(0), address_len) as *mut socklen_t;
-            unsafe { getpeername(socket, address, address_len_addr) }
+
+            let mut host_address: sockaddr_storage = unsafe { mem::zeroed() };
+            let mut host_address_len = mem::size_of::<sockaddr_storage>() as socklen_t;
+            let get_name = if call == 6 { getsockname } else { getpeername };
+            let ret = host_result(unsafe {
+                get_name(
+                    socket,
+                    &mut host_address as *mut sockaddr_storage as *mut sockaddr,
+                    &mut host_address_len,
+                )
+            } as _)?;
+            write_sockaddr(ctx, &host_address, host_address_len, address, address_len)?;
+            Ok(ret)
+        }
+        8 => {
+            debug!("socket: socketpair");
+            // socketpair (domain: c_int, ty: c_int, protocol: c_int, sv: *mut c_int) -> c_int
+            let domain: i32 = socket_varargs.get(ctx);
+            let ty_and_flags: i32 = socket_varargs.get(ctx);
+            let protocol: i32 = socket_varargs.get(ctx);
+            let sv: u32 = socket_varargs.get(ctx);
+            let sv_ptr = guest_buffer(ctx, sv, 8)? as *mut [c_int; 2];
+            let ty = ty_and_flags & !(SOCK_NON_BLOCK | SOCK_CLOEXC);
+
+            let mut fds: [c_int; 2] = [0; 2];
+            host_result(unsafe {
+                socketpair(
+                    translate_address_family(domain),
+                    ty,
+                    protocol,
+                    fds.as_mut_ptr(),
+                )
+            } as _)?;
+            if let Err(errno) = fds
+                .iter()
+                .try_for_each(|&fd| set_socket_flags(fd, ty_and_flags))
+            {
+                unsafe {
+                    close(fds[0]);
+                    close(fds[1]);
+                }
+                return Err(errno);
+            }
+            unsafe { sv_ptr.write_unaligned(fds) };
+            Ok(0)
         }
-        11 => {
-            debug!("socket: sendto");
+        9 | 11 => {
+            debug!("socket: send/sendto");
+            // send (socket: c_int, buf: *const c_void, len: size_t, flags: c_int) -> ssize_t
             // sendto (socket: c_int, buf: *const c_void, len: size_t, flags: c_int, addr: *const sockaddr, addrlen: socklen_t) -> ssize_t
-            let socket = socket_varargs.get(ctx);
+            let socket: i32 = socket_varargs.get(ctx);
             let buf: u32 = socket_varargs.get(ctx);
-            let flags = socket_varargs.get(ctx);
-            let len: i32 = socket_varargs.get(ctx);
-            let address: u32 = socket_varargs.get(ctx);
-            let address_len = socket_varargs.get(ctx);
-            let buf_addr = emscripten_memory_pointer!(ctx.memory(0), buf) as _;
-            let address = emscripten_memory_pointer!(ctx.memory(0), address) as *mut sockaddr;
-            unsafe { sendto(socket, buf_addr, flags, len, address, address_len) as i32 }
+            let len: u32 = socket_varargs.get(ctx);
+            let flags: i32 = socket_varargs.get(ctx);
+            let (address, address_len): (u32, u32) = if call == 11 {
+                (socket_varargs.get(ctx), socket_varargs.get(ctx))
+            } else {
+                (0, 0)
+            };
+            let buf_addr = guest_buffer(ctx, buf, len)?;
+            let host_address = if address == 0 {
+                None
+            } else {
+                Some(read_sockaddr(ctx, address, address_len)?)
+            };
+            let (host_address_ptr, host_address_len) = match host_address.as_ref() {
+                Some((host_address, len)) => (
+                    host_address as *const sockaddr_storage as *const sockaddr,
+                    *len,
+                ),
+                None => (ptr::null(), 0),
+            };
+            host_result(unsafe {
+                sendto(
+                    socket,
+                    buf_addr,
+                    len as usize,
+                    translate_msg_flags(flags) | SEND_FLAGS,
+                    host_address_ptr,
+                    host_address_len,
+                )
+            })
         }
-        12 => {
-            debug!("socket: recvfrom");
-            // recvfrom (socket: c_int, buf: *const c_void, len: size_t, flags: c_int, addr: *const sockaddr, addrlen: socklen_t) -> ssize_t
-            let socket = socket_varargs.get(ctx);
+        10 | 12 => {
+            debug!("socket: recv/recvfrom");
+            // recv (socket: c_int, buf: *mut c_void, len: size_t, flags: c_int) -> ssize_t
+            // recvfrom (socket: c_int, buf: *mut c_void, len: size_t, flags: c_int, addr: *mut sockaddr, addrlen: *mut socklen_t) -> ssize_t
+            let socket: i32 = socket_varargs.get(ctx);
             let buf: u32 = socket_varargs.get(ctx);
-            let len: i32 = socket_varargs.get(ctx);
+            let len: u32 = socket_varargs.get(ctx);
             let flags: i32 = socket_varargs.get(ctx);
-            let address: u32 = socket_varargs.get(ctx);
-            let address_len: u32 = socket_varargs.get(ctx);
-            let buf_addr = emscripten_memory_pointer!(ctx.memory(0), buf) as _;
-            let address = emscripten_memory_pointer!(ctx.memory(0), address) as *mut sockaddr;
-            let address_len_addr =
-                emscripten_memory_pointer!(ctx.memory(0), address_len) as *mut socklen_t;
-            unsafe {
+            let (address, address_len): (u32, u32) = if call == 12 {
+                (socket_varargs.get(ctx), socket_varargs.get(ctx))
+            } else {
+                (0, 0)
+            };
+            let buf_addr = guest_buffer(ctx, buf, len)?;
+
+            let mut host_address: sockaddr_storage = unsafe { mem::zeroed() };
+            let mut host_address_len = mem::size_of::<sockaddr_storage>() as socklen_t;
+            let ret = host_result(unsafe {
                 recvfrom(
                     socket,
                     buf_addr,
                     len as usize,
-                    flags,
-                    address,
-                    address_len_addr,
-                ) as i32
-            }
+                    translate_msg_flags(flags),
+                    &mut host_address as *mut sockaddr_storage as *mut sockaddr,
+                    &mut host_address_len,
+                )
+            })?;
+            write_sockaddr(ctx, &host_address, host_address_len, address, address_len)?;
+            Ok(ret)
+        }
+        13 => {
+            debug!("socket: shutdown");
+            // shutdown (socket: c_int, how: c_int) -> c_int
+            let socket: i32 = socket_varargs.get(ctx);
+            let how: i32 = socket_varargs.get(ctx);
+            host_result(unsafe { shutdown(socket, how) } as _)
         }
         14 => {
             debug!("socket: setsockopt");
@@ -749,65 +1057,203 @@ pub fn ___syscall102(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
             //      https://github.com/openbsd/src/blob/master/sys/sys/socket.h#L156
             // setsockopt (socket: c_int, level: c_int, name: c_int, value: *const c_void, option_len: socklen_t) -> c_int
 
-            let socket = socket_varargs.get(ctx);
+            let socket: i32 = socket_varargs.get(ctx);
             let level: i32 = socket_varargs.get(ctx);
-            let level = if level == 1 { SOL_SOCKET } else { level };
             let untranslated_name: i32 = socket_varargs.get(ctx);
             let value: u32 = socket_varargs.get(ctx);
             let option_len: u32 = socket_varargs.get(ctx);
-            let value_addr =
-                emscripten_memory_pointer!(ctx.memory(0), value) as *const libc::c_void;
-            let name: i32 = translate_socket_name_flag(untranslated_name);
-
-            let ret = unsafe { setsockopt(socket, level, name, value_addr, option_len) };
+            let (level, name) = translate_socket_option(level, untranslated_name);
+            let value_addr = guest_buffer(ctx, value, option_len)?;
+
+            let ret = if is_timeval_option(level, name) {
+                if (option_len as usize) < mem::size_of::<GuestTimeval>() {
+                    return Err(EINVAL);
+                }
+                let guest_timeval = unsafe { (value_addr as *const GuestTimeval).read_unaligned() };
+                let timeval = libc::timeval {
+                    tv_sec: guest_timeval.tv_sec as _,
+                    tv_usec: guest_timeval.tv_usec as _,
+                };
+                unsafe {
+                    setsockopt(
+                        socket,
+                        level,
+                        name,
+                        &timeval as *const libc::timeval as *const c_void,
+                        mem::size_of::<libc::timeval>() as socklen_t,
+                    )
+                }
+            } else {
+                unsafe { setsockopt(socket, level, name, value_addr, option_len) }
+            };
 
-            debug!("=> socketfd: {}, level: {}, name: {}, value_addr: {:?}, option_len: {} = status: {}", socket, level, untranslated_name, value_addr, option_len, ret);
-            ret
+            debug!(
+                "=> socketfd: {}, level: {}, name: {}, value_addr: {:?}, option_len: {} = status: {}",
+                socket, level, untranslated_name, value_addr, option_len, ret
+            );
+            host_result(ret as _)
         }
         15 => {
             debug!("socket: getsockopt");
             // getsockopt (sockfd: c_int, level: c_int, optname: c_int, optval: *mut c_void, optlen: *mut socklen_t) -> c_int
-            let socket = socket_varargs.get(ctx);
+            let socket: i32 = socket_varargs.get(ctx);
             let level: i32 = socket_varargs.get(ctx);
-            let level = if level == 1 { SOL_SOCKET } else { level };
             let untranslated_name: i32 = socket_varargs.get(ctx);
-            let name: i32 = translate_socket_name_flag(untranslated_name);
             let value: u32 = socket_varargs.get(ctx);
             let option_len: u32 = socket_varargs.get(ctx);
-            let value_addr = emscripten_memory_pointer!(ctx.memory(0), value) as _;
-            let option_len_addr =
-                emscripten_memory_pointer!(ctx.memory(0), option_len) as *mut socklen_t;
-            unsafe { getsockopt(socket, level, name, value_addr, option_len_addr) }
+            let (level, name) = translate_socket_option(level, untranslated_name);
+            let option_len_addr = guest_buffer(ctx, option_len, 4)? as *mut socklen_t;
+            let mut len = unsafe { option_len_addr.read_unaligned() };
+            let value_addr = guest_buffer(ctx, value, len)?;
+
+            if is_timeval_option(level, name) {
+                let mut timeval: libc::timeval = unsafe { mem::zeroed() };
+                let mut timeval_len = mem::size_of::<libc::timeval>() as socklen_t;
+                host_result(unsafe {
+                    getsockopt(
+                        socket,
+                        level,
+                        name,
+                        &mut timeval as *mut libc::timeval as *mut c_void,
+                        &mut timeval_len,
+                    )
+                } as _)?;
+                let guest_timeval = GuestTimeval {
+                    tv_sec: timeval.tv_sec as _,
+                    tv_usec: timeval.tv_usec as _,
+                };
+                len = len.min(mem::size_of::<GuestTimeval>() as socklen_t);
+                unsafe {
+                    ptr::copy_nonoverlapping(
+                        &guest_timeval as *const GuestTimeval as *const u8,
+                        value_addr as *mut u8,
+                        len as usize,
+                    );
+                }
+            } else {
+                host_result(unsafe { getsockopt(socket, level, name, value_addr, &mut len) } as _)?;
+            }
+            unsafe { option_len_addr.write_unaligned(len) };
+            Ok(0)
         }
         16 => {
             debug!("socket: sendmsg");
             // sendmsg (fd: c_int, msg: *const msghdr, flags: c_int) -> ssize_t
             let socket: i32 = socket_varargs.get(ctx);
-            let msg: u32 = socket_varargs.get(ctx);
+            let msg: WasmPtr<GuestMsgHdr> = socket_varargs.get(ctx);
             let flags: i32 = socket_varargs.get(ctx);
-            let msg_addr = emscripten_memory_pointer!(ctx.memory(0), msg) as *const msghdr;
-            unsafe { sendmsg(socket, msg_addr, flags) as i32 }
+            let guest_msg = msg.deref(ctx.memory(0)).ok_or(EFAULT)?.get();
+            // the layout of control messages depends on the pointer size
+            if guest_msg.msg_controllen != 0 {
+                return Err(EOPNOTSUPP);
+            }
+
+            let mut iovecs = guest_iovecs(ctx, &guest_msg)?;
+            let mut host_address = if guest_msg.msg_name == 0 {
+                None
+            } else {
+                Some(read_sockaddr(
+                    ctx,
+                    guest_msg.msg_name,
+                    guest_msg.msg_namelen,
+                )?)
+            };
+            let mut host_msg: msghdr = unsafe { mem::zeroed() };
+            if let Some((host_address, len)) = host_address.as_mut() {
+                host_msg.msg_name = host_address as *mut sockaddr_storage as *mut c_void;
+                host_msg.msg_namelen = *len;
+            }
+            host_msg.msg_iov = iovecs.as_mut_ptr();
+            host_msg.msg_iovlen = iovecs.len() as _;
+
+            host_result(unsafe {
+                sendmsg(socket, &host_msg, translate_msg_flags(flags) | SEND_FLAGS)
+            })
         }
         17 => {
             debug!("socket: recvmsg");
             // recvmsg (fd: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t
             let socket: i32 = socket_varargs.get(ctx);
-            let msg: u32 = socket_varargs.get(ctx);
+            let msg: WasmPtr<GuestMsgHdr> = socket_varargs.get(ctx);
             let flags: i32 = socket_varargs.get(ctx);
-            let msg_addr = emscripten_memory_pointer!(ctx.memory(0), msg) as *mut msghdr;
-            unsafe { recvmsg(socket, msg_addr, flags) as i32 }
-        }
-        _ => {
-            // others
-            -1
+            let mut guest_msg = msg.deref(ctx.memory(0)).ok_or(EFAULT)?.get();
+
+            let mut iovecs = guest_iovecs(ctx, &guest_msg)?;
+            let mut host_address: sockaddr_storage = unsafe { mem::zeroed() };
+            let mut host_msg: msghdr = unsafe { mem::zeroed() };
+            host_msg.msg_name = &mut host_address as *mut sockaddr_storage as *mut c_void;
+            host_msg.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+            host_msg.msg_iov = iovecs.as_mut_ptr();
+            host_msg.msg_iovlen = iovecs.len() as _;
+
+            let ret =
+                host_result(unsafe { recvmsg(socket, &mut host_msg, translate_msg_flags(flags)) })?;
+
+            if guest_msg.msg_name != 0 {
+                // `msg_namelen` follows `msg_name`
+                guest_msg.msg_namelen = write_sockaddr(
+                    ctx,
+                    &host_address,
+                    host_msg.msg_namelen,
+                    guest_msg.msg_name,
+                    msg.offset() + 4,
+                )?;
+            }
+            // control messages are never received, see `sendmsg`
+            guest_msg.msg_controllen = 0;
+            guest_msg.msg_flags = untranslate_msg_flags(host_msg.msg_flags);
+            msg.deref(ctx.memory(0)).ok_or(EFAULT)?.set(guest_msg);
+            Ok(ret)
         }
+        _ => Err(EINVAL),
+    }
+}
+
+/// Gets the host buffers of the `msg_iov` of a message of the module.
+fn guest_iovecs(ctx: &EmEnv, guest_msg: &GuestMsgHdr) -> Result<Vec<libc::iovec>, c_int> {
+    if guest_msg.msg_iovlen < 0 || guest_msg.msg_iovlen > 1024 {
+        return Err(EMSGSIZE);
+    }
+    if guest_msg.msg_iovlen == 0 {
+        return Ok(Vec::new());
+    }
+    let guest_iovecs = WasmPtr::<GuestIovec, Array>::new(guest_msg.msg_iov)
+        .deref(ctx.memory(0), 0, guest_msg.msg_iovlen as u32)
+        .ok_or(EFAULT)?;
+
+    guest_iovecs
+        .iter()
+        .map(|guest_iovec| {
+            let guest_iovec = guest_iovec.get();
+            Ok(libc::iovec {
+                iov_base: guest_buffer(ctx, guest_iovec.iov_base, guest_iovec.iov_len)?,
+                iov_len: guest_iovec.iov_len as usize,
+            })
+        })
+        .collect()
+}
+
+/// Translates the level and name of a socket option of `emscripten`
+/// into the host ones.
+fn translate_socket_option(level: i32, name: i32) -> (c_int, c_int) {
+    if level == 1 {
+        (SOL_SOCKET, translate_socket_name_flag(name))
+    } else {
+        (level, name)
     }
 }
 
+/// Whether a socket option takes a `struct timeval`, whose layout
+/// differs between the module and the host.
+fn is_timeval_option(level: c_int, name: c_int) -> bool {
+    level == SOL_SOCKET && (name == libc::SO_RCVTIMEO || name == libc::SO_SNDTIMEO)
+}
+
 /// OSX and BSD have completely different values, we must translate from emscripten's Linuxy
 /// value into one that we can pass to native syscalls
 fn translate_socket_name_flag(name: i32) -> i32 {
     match name {
+        1 => libc::SO_DEBUG,
         2 => libc::SO_REUSEADDR,
         3 => libc::SO_TYPE,
         4 => libc::SO_ERROR,
@@ -818,11 +1264,11 @@ fn translate_socket_name_flag(name: i32) -> i32 {
         9 => libc::SO_KEEPALIVE,
         10 => libc::SO_OOBINLINE,
         13 => libc::SO_LINGER,
+        15 => libc::SO_REUSEPORT,
         18 => libc::SO_RCVLOWAT,
         19 => libc::SO_SNDLOWAT,
         20 => libc::SO_RCVTIMEO,
         21 => libc::SO_SNDTIMEO,
-        // SO_DEBUG missing
         30 => libc::SO_ACCEPTCONN,
         otherwise => otherwise,
     }
@@ -855,21 +1301,29 @@ unsafe impl wasmer::ValueType for EmPollFd {}
 /// poll
 pub fn ___syscall168(ctx: &mut EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
     debug!("emscripten::___syscall168(poll)");
-    let fds: WasmPtr<EmPollFd> = varargs.get(ctx);
+    let fds: u32 = varargs.get(ctx);
     let nfds: u32 = varargs.get(ctx);
     let timeout: i32 = varargs.get(ctx);
 
-    let fds_mut = unsafe { fds.deref_mut(ctx.memory(0)).unwrap().get_mut() };
-
-    let ret = unsafe {
-        libc::poll(
-            fds_mut as *mut EmPollFd as *mut libc::pollfd,
-            nfds as _,
-            timeout,
-        )
+    let fds_len = match nfds.checked_mul(mem::size_of::<EmPollFd>() as u32) {
+        Some(fds_len) => fds_len,
+        None => return -EINVAL,
+    };
+    let fds_ptr = match guest_buffer(ctx, fds, fds_len) {
+        Ok(fds_ptr) => fds_ptr,
+        Err(errno) => return -errno,
     };
 
-    ret
+    // `EmPollFd` has the layout of `libc::pollfd`
+    let ret = unsafe { libc::poll(fds_ptr as *mut libc::pollfd, nfds as _, timeout) };
+    debug!(
+        "=> fds: {}, nfds: {}, timeout: {} = {}",
+        fds, nfds, timeout, ret
+    );
+    match host_result(ret as _) {
+        Ok(ret) => ret,
+        Err(errno) => -errno,
+    }
 }
 
 // pread
@@ -945,7 +1399,6 @@ pub fn ___syscall118(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
 }
 
 // select
-#[allow(clippy::cast_ptr_alignment)]
 pub fn ___syscall142(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall142 (newselect) {}", _which);
 
@@ -953,18 +1406,70 @@ pub fn ___syscall142(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
     let readfds: u32 = varargs.get(ctx);
     let writefds: u32 = varargs.get(ctx);
     let exceptfds: u32 = varargs.get(ctx);
-    let _timeout: i32 = varargs.get(ctx);
+    let timeout: u32 = varargs.get(ctx);
+
+    let ret = newselect(ctx, nfds, [readfds, writefds, exceptfds], timeout);
+    debug!(
+        "=> nfds: {}, readfds: {}, writefds: {}, exceptfds: {}, timeout: {} = {:?}",
+        nfds, readfds, writefds, exceptfds, timeout, ret
+    );
+    match ret {
+        Ok(ret) => ret,
+        Err(errno) => -errno,
+    }
+}
 
-    if nfds > 1024 {
-        // EINVAL
-        return -22;
+/// `fd_set`s have the same layout in the module and on the host, with
+/// `FD_SETSIZE` being 1024 in both.
+fn newselect(ctx: &EmEnv, nfds: i32, fd_sets: [u32; 3], timeout: u32) -> Result<c_int, c_int> {
+    if !(0..=1024).contains(&nfds) {
+        return Err(EINVAL);
+    }
+    let mut fd_set_ptrs = [ptr::null_mut::<fd_set>(); 3];
+    for (fd_set_ptr, &fd_set) in fd_set_ptrs.iter_mut().zip(fd_sets.iter()) {
+        if fd_set != 0 {
+            *fd_set_ptr = guest_buffer(ctx, fd_set, 128)? as *mut fd_set;
+        }
     }
-    assert!(exceptfds == 0, "`exceptfds` is not supporrted");
 
-    let readfds_ptr = emscripten_memory_pointer!(ctx.memory(0), readfds) as _;
-    let writefds_ptr = emscripten_memory_pointer!(ctx.memory(0), writefds) as _;
+    let guest_timeval = if timeout == 0 {
+        None
+    } else {
+        let guest_timeval = guest_buffer(ctx, timeout, 8)? as *mut GuestTimeval;
+        Some(guest_timeval)
+    };
+    let mut timeval = guest_timeval.map(|guest_timeval| {
+        let guest_timeval = unsafe { guest_timeval.read_unaligned() };
+        libc::timeval {
+            tv_sec: guest_timeval.tv_sec as _,
+            tv_usec: guest_timeval.tv_usec as _,
+        }
+    });
+    let timeval_ptr = timeval
+        .as_mut()
+        .map_or(ptr::null_mut(), |timeval| timeval as *mut libc::timeval);
+
+    let ret = host_result(unsafe {
+        select(
+            nfds,
+            fd_set_ptrs[0],
+            fd_set_ptrs[1],
+            fd_set_ptrs[2],
+            timeval_ptr,
+        )
+    } as _)?;
 
-    unsafe { select(nfds, readfds_ptr, writefds_ptr, 0 as _, 0 as _) }
+    // like Linux, report the time left
+    if let (Some(guest_timeval), Some(timeval)) = (guest_timeval, timeval) {
+        unsafe {
+            guest_timeval.write_unaligned(GuestTimeval {
+                tv_sec: timeval.tv_sec as _,
+                tv_usec: timeval.tv_usec as _,
+            })
+        };
+    }
+
+    Ok(ret)
 }
 
 /// fdatasync
@@ -1005,12 +1510,11 @@ pub fn ___syscall122(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_
 pub fn ___syscall196(ctx: &mut EmEnv, _which: i32, mut varargs: VarArgs) -> i32 {
     debug!("emscripten::___syscall196 (lstat64) {}", _which);
     let path = varargs.get_str(ctx);
-    let real_path_owned = utils::get_cstr_path(ctx, path as *const _);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        path
+    let real_path_owned = match utils::get_cstr_path(ctx, path as *const _, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     let buf_ptr: u32 = varargs.get(ctx);
     unsafe {
         let mut stat: stat = std::mem::zeroed();
@@ -1108,22 +1612,52 @@ pub fn ___syscall220(ctx: &mut EmEnv, _which: i32, mut varargs: VarArgs) -> i32
     pos as i32
 }
 
+// `fcntl` file status flags as provided by `emscripten`.
+const WASM_O_APPEND: i32 = 0o2000;
+const WASM_O_NONBLOCK: i32 = 0o4000;
+
+/// Translates the file status flags of `emscripten` into the host ones.
+fn translate_file_status_flags(flags: i32) -> c_int {
+    let mut host_flags = flags & !(WASM_O_APPEND | WASM_O_NONBLOCK);
+    if flags & WASM_O_APPEND != 0 {
+        host_flags |= O_APPEND;
+    }
+    if flags & WASM_O_NONBLOCK != 0 {
+        host_flags |= O_NONBLOCK;
+    }
+    host_flags
+}
+
+/// Translates the file status flags of the host into the `emscripten` ones.
+fn untranslate_file_status_flags(flags: c_int) -> i32 {
+    let mut wasm_flags = flags & !(O_APPEND | O_NONBLOCK);
+    if flags & O_APPEND != 0 {
+        wasm_flags |= WASM_O_APPEND;
+    }
+    if flags & O_NONBLOCK != 0 {
+        wasm_flags |= WASM_O_NONBLOCK;
+    }
+    wasm_flags
+}
+
 // fcntl64
 pub fn ___syscall221(ctx: &mut EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall221 (fcntl64) {}", _which);
     let fd: i32 = varargs.get(ctx);
     let cmd: i32 = varargs.get(ctx);
     let arg: i32 = varargs.get(ctx);
-    // (FAPPEND   - 0x08
-    // |FASYNC    - 0x40
-    // |FFSYNC    - 0x80
-    // |FNONBLOCK - 0x04
-    let ret = unsafe { fcntl(fd, cmd, arg) };
-    debug!("=> fd: {}, cmd: {} = {}", fd, cmd, ret);
-    if ret == -1 {
-        debug!("=> last os error: {}", Error::last_os_error(),);
+    // `F_GETFL` and `F_SETFL` have the same values everywhere, but not
+    // the file status flags
+    let ret = match cmd {
+        F_GETFL => host_result(unsafe { fcntl(fd, cmd) } as _).map(untranslate_file_status_flags),
+        F_SETFL => host_result(unsafe { fcntl(fd, cmd, translate_file_status_flags(arg)) } as _),
+        _ => host_result(unsafe { fcntl(fd, cmd, arg) } as _),
+    };
+    debug!("=> fd: {}, cmd: {} = {:?}", fd, cmd, ret);
+    match ret {
+        Ok(ret) => ret,
+        Err(errno) => -errno,
     }
-    ret
 }
 
 /// fallocate
@@ -1,3 +1,4 @@
+use crate::errno::ENOSYS;
 use crate::utils::{copy_cstr_into_wasm, get_cstr_path};
 use crate::varargs::VarArgs;
 use crate::EmEnv;
@@ -18,12 +19,11 @@ pub fn ___syscall5(ctx: &mut EmEnv, which: c_int, mut varargs: VarArgs) -> c_int
     #[cfg(not(feature = "debug"))]
     let _ = which;
     let pathname_addr = varargs.get_str(ctx);
-    let real_path_owned = get_cstr_path(ctx, pathname_addr);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        pathname_addr
+    let real_path_owned = match get_cstr_path(ctx, pathname_addr, true) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     let flags: i32 = varargs.get(ctx);
     let mode: u32 = varargs.get(ctx);
     let path_str = unsafe { std::ffi::CStr::from_ptr(real_path).to_str().unwrap() };
@@ -101,12 +101,11 @@ pub fn ___syscall39(ctx: &mut EmEnv, which: c_int, mut varargs: VarArgs) -> c_in
     #[cfg(not(feature = "debug"))]
     let _ = which;
     let pathname_addr = varargs.get_str(ctx);
-    let real_path_owned = get_cstr_path(ctx, pathname_addr);
-    let real_path = if let Some(ref rp) = real_path_owned {
-        rp.as_c_str().as_ptr()
-    } else {
-        pathname_addr
+    let real_path_owned = match get_cstr_path(ctx, pathname_addr, false) {
+        Ok(path) => path,
+        Err(errno) => return -errno,
     };
+    let real_path = real_path_owned.as_ptr();
     unsafe { mkdir(real_path) }
 }
 
@@ -203,7 +202,7 @@ pub fn ___syscall102(_ctx: &mut EmEnv, which: c_int, mut _varargs: VarArgs) -> c
     debug!("emscripten::___syscall102 (socketcall) {}", which);
     #[cfg(not(feature = "debug"))]
     let _ = which;
-    -1
+    -ENOSYS
 }
 
 /// fsync
@@ -241,7 +240,7 @@ pub fn ___syscall142(_ctx: &mut EmEnv, which: c_int, mut _varargs: VarArgs) -> c
     debug!("emscripten::___syscall142 (newselect) {}", which);
     #[cfg(not(feature = "debug"))]
     let _ = which;
-    -1
+    -ENOSYS
 }
 
 /// fdatasync
@@ -270,7 +269,7 @@ pub fn ___syscall122(_ctx: &mut EmEnv, which: c_int, mut _varargs: VarArgs) -> c
 /// poll
 pub fn ___syscall168(_ctx: &mut EmEnv, _which: i32, _varargs: VarArgs) -> i32 {
     debug!("emscripten::___syscall168(poll) - stub");
-    -1
+    -ENOSYS
 }
 
 /// lstat64
@@ -1,11 +1,12 @@
 use super::env;
 use super::env::get_emscripten_data;
+use crate::errno::EINVAL;
 use crate::storage::align_memory;
 use crate::EmEnv;
 use libc::stat;
 use std::ffi::CStr;
 use std::mem::size_of;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_int};
 use std::path::PathBuf;
 use std::slice;
 use wasmer::{GlobalInit, Memory, Module, Pages};
@@ -223,58 +224,24 @@ pub fn read_string_from_wasm(memory: &Memory, offset: u32) -> String {
     String::from_utf8_lossy(&v).to_owned().to_string()
 }
 
-/// This function trys to find an entry in mapdir
-/// translating paths into their correct value
-pub fn get_cstr_path(ctx: &mut EmEnv, path: *const i8) -> Option<std::ffi::CString> {
-    use std::collections::VecDeque;
-
-    let path_str =
-        unsafe { std::ffi::CStr::from_ptr(path as *const _).to_str().unwrap() }.to_string();
-    let data = get_emscripten_data(ctx);
-    let path = PathBuf::from(path_str);
-    let mut prefix_added = false;
-    let mut components = path.components().collect::<VecDeque<_>>();
-    // TODO(mark): handle absolute/non-canonical/non-relative paths too (this
-    // functionality should be shared among the abis)
-    if components.len() == 1 {
-        components.push_front(std::path::Component::CurDir);
-        prefix_added = true;
-    }
-    let mut cumulative_path = PathBuf::new();
-    for c in components.into_iter() {
-        cumulative_path.push(c);
-        if let Some(val) = data
-            .mapped_dirs
-            .get(&cumulative_path.to_string_lossy().to_string())
-        {
-            let rest_of_path = if !prefix_added {
-                path.strip_prefix(cumulative_path).ok()?
-            } else {
-                &path
-            };
-            let rebased_path = val.join(rest_of_path);
-            return std::ffi::CString::new(rebased_path.to_string_lossy().as_bytes()).ok();
-        }
-    }
-    None
+/// Translates a path given by the module into the host path it refers
+/// to in its filesystem sandbox, or the errno to fail with if it can't
+/// be accessed
+pub fn get_cstr_path(
+    ctx: &mut EmEnv,
+    path: *const i8,
+    follow_symlinks: bool,
+) -> Result<std::ffi::CString, c_int> {
+    let path_str = unsafe { std::ffi::CStr::from_ptr(path as *const _) }
+        .to_str()
+        .map_err(|_| EINVAL)?;
+    let host_path = get_emscripten_data(ctx)
+        .fs
+        .resolve(path_str, follow_symlinks)?;
+    std::ffi::CString::new(host_path.to_string_lossy().as_bytes()).map_err(|_| EINVAL)
 }
 
-/// gets the current directory
-/// handles mapdir logic
-pub fn get_current_directory(ctx: &mut EmEnv) -> Option<PathBuf> {
-    if let Some(val) = get_emscripten_data(ctx).mapped_dirs.get(".") {
-        return Some(val.clone());
-    }
-    std::env::current_dir()
-        .map(|cwd| {
-            if let Some(val) = get_emscripten_data(ctx)
-                .mapped_dirs
-                .get(&cwd.to_string_lossy().to_string())
-            {
-                val.clone()
-            } else {
-                cwd
-            }
-        })
-        .ok()
+/// gets the current directory, as seen by the module
+pub fn get_current_directory(ctx: &mut EmEnv) -> PathBuf {
+    get_emscripten_data(ctx).fs.current_dir().to_path_buf()
 }
@@ -0,0 +1,203 @@
+;; The exception handling of the following C++ program, as compiled by
+;; Emscripten (fastcomp): calls that may throw go through `invoke_*`,
+;; followed by a check of `__THREW__` leading to the landing pad.
+;;
+;;     struct Error { int code; ~Error() { destroyed++; } };
+;;     struct Other {};
+;;
+;;     void fail(int code) { throw Error{code}; }
+;;
+;;     int catch_error(int code) {
+;;       try { fail(code); } catch (Error& e) { return e.code; }
+;;       return -1;
+;;     }
+;;     int catch_all(int code) {
+;;       try { fail(code); } catch (...) { return 1; }
+;;       return 0;
+;;     }
+;;     int catch_other(int code) {
+;;       try { fail(code); } catch (Other&) { return 1; }
+;;       return 0;
+;;     }
+;;     void rethrow(int code) {
+;;       try { fail(code); } catch (Error&) { throw; }
+;;     }
+;;     int catch_rethrown(int code) {
+;;       try { rethrow(code); } catch (Error& e) { return e.code + 1; }
+;;       return -1;
+;;     }
+;;
+;; This module is written by hand following that lowering, as em++ (and
+;; the fastcomp backend whose ABI this crate implements) isn't available
+;; where these tests are run; it should be replaced by the output of em++
+;; for the program above once it can be built here.
+;;
+;; `destroyed` lives at address 16, the last freed pointer at address
+;; 20, and the `type_info`s of `Error` and `Other` at 100 and 200.
+(module
+  (type $v (func))
+  (type $vi (func (param i32)))
+
+  (import "env" "memory" (memory 256 256))
+  (import "env" "table" (table 10 10 funcref))
+  (import "env" "__map_file" (func $map_file (param i32 i32) (result i32)))
+  (import "env" "getTempRet0" (func $getTempRet0 (result i32)))
+  (import "env" "invoke_v" (func $invoke_v (param i32)))
+  (import "env" "invoke_vi" (func $invoke_vi (param i32 i32)))
+  (import "env" "_llvm_eh_typeid_for" (func $typeid_for (param i32) (result i32)))
+  (import "env" "___cxa_allocate_exception" (func $allocate_exception (param i32) (result i32)))
+  (import "env" "___cxa_throw" (func $throw (param i32 i32 i32)))
+  (import "env" "___cxa_rethrow" (func $rethrow_exception))
+  (import "env" "___cxa_begin_catch" (func $begin_catch (param i32) (result i32)))
+  (import "env" "___cxa_end_catch" (func $end_catch))
+  (import "env" "___cxa_find_matching_catch_2" (func $find_matching_catch_2 (result i32)))
+  (import "env" "___cxa_find_matching_catch_3" (func $find_matching_catch_3 (param i32) (result i32)))
+  (import "env" "___cxa_uncaught_exception" (func $uncaught_exception (result i32)))
+  (import "env" "___resumeException" (func $resume_exception (param i32)))
+
+  (elem (i32.const 1) $fail $error_destructor $rethrow $rethrow_exception $trap $failing_destructor $fail_badly)
+
+  (global $threw (mut i32) (i32.const 0))
+  (global $threw_value (mut i32) (i32.const 0))
+  (global $stack (mut i32) (i32.const 4096))
+  (global $heap (mut i32) (i32.const 8192))
+
+  ;; The runtime functions Emscripten exports.
+  (func (export "stackSave") (result i32)
+    (global.get $stack))
+  (func (export "stackRestore") (param $sp i32)
+    (global.set $stack (local.get $sp)))
+  (func (export "setThrew") (param $threw i32) (param $value i32)
+    (if (i32.eqz (global.get $threw))
+      (then
+        (global.set $threw (local.get $threw))
+        (global.set $threw_value (local.get $value)))))
+  (func (export "_malloc") (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (local.get $ptr) (i32.and (i32.add (local.get $size) (i32.const 7)) (i32.const -8))))
+    (local.get $ptr))
+  (func (export "_free") (param $ptr i32)
+    (i32.store (i32.const 20) (local.get $ptr)))
+  (func (export "dynCall_v") (param $index i32)
+    (call_indirect (type $v) (local.get $index)))
+  (func (export "dynCall_vi") (param $index i32) (param $a1 i32)
+    (call_indirect (type $vi) (local.get $a1) (local.get $index)))
+  (func (export "___cxa_can_catch") (param $catch_type i32) (param $thrown_type i32) (param $thrown_ptr i32) (result i32)
+    (i32.eq (local.get $catch_type) (local.get $thrown_type)))
+
+  ;; Returns whether an exception was thrown since the last call.
+  (func $threw (result i32)
+    (local $threw i32)
+    (local.set $threw (global.get $threw))
+    (global.set $threw (i32.const 0))
+    (local.get $threw))
+
+  (func $fail (param $code i32)
+    (local $ptr i32)
+    (local.set $ptr (call $allocate_exception (i32.const 4)))
+    (i32.store (local.get $ptr) (local.get $code))
+    (call $throw (local.get $ptr) (i32.const 100) (i32.const 2)))
+
+  (func $error_destructor (param $ptr i32)
+    (i32.store (i32.const 16) (i32.add (i32.load (i32.const 16)) (i32.const 1))))
+
+  (func $trap
+    unreachable)
+
+  ;; Throws an exception whose destructor traps.
+  (func $fail_badly (param $code i32)
+    (local $ptr i32)
+    (local.set $ptr (call $allocate_exception (i32.const 4)))
+    (i32.store (local.get $ptr) (local.get $code))
+    (call $throw (local.get $ptr) (i32.const 100) (i32.const 6)))
+
+  (func $failing_destructor (param $ptr i32)
+    unreachable)
+
+  (func (export "catch_error") (param $code i32) (result i32)
+    (local $ptr i32)
+    (local $result i32)
+    (call $invoke_vi (i32.const 1) (local.get $code))
+    (if (i32.eqz (call $threw))
+      (then (return (i32.const -1))))
+    (local.set $ptr (call $find_matching_catch_3 (i32.const 100)))
+    (if (i32.ne (call $getTempRet0) (call $typeid_for (i32.const 100)))
+      (then (call $resume_exception (local.get $ptr)) unreachable))
+    (local.set $result (i32.load (call $begin_catch (local.get $ptr))))
+    (call $end_catch)
+    (local.get $result))
+
+  (func (export "catch_all") (param $code i32) (result i32)
+    (call $invoke_vi (i32.const 1) (local.get $code))
+    (if (i32.eqz (call $threw))
+      (then (return (i32.const 0))))
+    (drop (call $begin_catch (call $find_matching_catch_2)))
+    (call $end_catch)
+    (i32.const 1))
+
+  (func (export "catch_failing_destructor") (param $code i32) (result i32)
+    (call $invoke_vi (i32.const 7) (local.get $code))
+    (if (i32.eqz (call $threw))
+      (then (return (i32.const 0))))
+    (drop (call $begin_catch (call $find_matching_catch_2)))
+    (call $end_catch)
+    (i32.const 1))
+
+  (func (export "catch_other") (param $code i32) (result i32)
+    (local $ptr i32)
+    (call $invoke_vi (i32.const 1) (local.get $code))
+    (if (i32.eqz (call $threw))
+      (then (return (i32.const 0))))
+    (local.set $ptr (call $find_matching_catch_3 (i32.const 200)))
+    (if (i32.ne (call $getTempRet0) (call $typeid_for (i32.const 200)))
+      (then (call $resume_exception (local.get $ptr)) unreachable))
+    (drop (call $begin_catch (local.get $ptr)))
+    (call $end_catch)
+    (i32.const 1))
+
+  (func $rethrow (param $code i32)
+    (local $ptr i32)
+    (call $invoke_vi (i32.const 1) (local.get $code))
+    (if (i32.eqz (call $threw))
+      (then (return)))
+    (local.set $ptr (call $find_matching_catch_3 (i32.const 100)))
+    (if (i32.ne (call $getTempRet0) (call $typeid_for (i32.const 100)))
+      (then (call $resume_exception (local.get $ptr)) unreachable))
+    (drop (call $begin_catch (local.get $ptr)))
+    ;; `throw;` in the catch block, whose landing pad ends the catch
+    (call $invoke_v (i32.const 4))
+    (if (i32.eqz (call $threw))
+      (then (return)))
+    (local.set $ptr (call $find_matching_catch_2))
+    (call $end_catch)
+    (call $resume_exception (local.get $ptr))
+    unreachable)
+
+  (func (export "catch_rethrown") (param $code i32) (result i32)
+    (local $ptr i32)
+    (local $result i32)
+    (call $invoke_vi (i32.const 3) (local.get $code))
+    (if (i32.eqz (call $threw))
+      (then (return (i32.const -1))))
+    (local.set $ptr (call $find_matching_catch_3 (i32.const 100)))
+    (if (i32.ne (call $getTempRet0) (call $typeid_for (i32.const 100)))
+      (then (call $resume_exception (local.get $ptr)) unreachable))
+    (local.set $result (i32.add (i32.load (call $begin_catch (local.get $ptr))) (i32.const 1)))
+    (call $end_catch)
+    (local.get $result))
+
+  (func (export "uncaught") (param $code i32)
+    (call $fail (local.get $code)))
+
+  (func (export "uncaught_exception") (result i32)
+    (call $uncaught_exception))
+
+  (func (export "invoke_trap")
+    (call $invoke_v (i32.const 5)))
+
+  (func (export "destroyed") (result i32)
+    (i32.load (i32.const 16)))
+
+  (func (export "last_freed") (result i32)
+    (i32.load (i32.const 20))))
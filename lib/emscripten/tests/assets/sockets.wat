@@ -0,0 +1,24 @@
+;; Exports the syscalls the socket tests make, for them to be called
+;; like Emscripten's libc does, with their arguments in memory.
+(module
+  (type $syscall (func (param i32 i32) (result i32)))
+
+  (import "env" "memory" (memory 256 256))
+  (import "env" "table" (table 0 0 funcref))
+  (import "env" "___syscall3" (func $read (type $syscall)))
+  (import "env" "___syscall4" (func $write (type $syscall)))
+  (import "env" "___syscall6" (func $close (type $syscall)))
+  (import "env" "___syscall54" (func $ioctl (type $syscall)))
+  (import "env" "___syscall102" (func $socketcall (type $syscall)))
+  (import "env" "___syscall142" (func $newselect (type $syscall)))
+  (import "env" "___syscall168" (func $poll (type $syscall)))
+  (import "env" "___syscall221" (func $fcntl64 (type $syscall)))
+
+  (export "read" (func $read))
+  (export "write" (func $write))
+  (export "close" (func $close))
+  (export "ioctl" (func $ioctl))
+  (export "socketcall" (func $socketcall))
+  (export "newselect" (func $newselect))
+  (export "poll" (func $poll))
+  (export "fcntl64" (func $fcntl64)))
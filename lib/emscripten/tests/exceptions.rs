@@ -0,0 +1,108 @@
+use std::ffi::c_void;
+use wasmer::*;
+use wasmer_emscripten::{
+    generate_emscripten_env, CppException, EmEnv, EmscriptenData, EmscriptenGlobals,
+};
+
+/// Calls `function` of a new instance of the `exceptions.wat` guest,
+/// then `destroyed`, returning both results.
+fn call(function: &str, params: &[Val]) -> (Result<Box<[Val]>, RuntimeError>, i32) {
+    call_in(include_str!("assets/exceptions.wat"), function, params)
+}
+
+/// Like `call`, with the guest `wat`.
+fn call_in(wat: &str, function: &str, params: &[Val]) -> (Result<Box<[Val]>, RuntimeError>, i32) {
+    let store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let mut globals = EmscriptenGlobals::new(&store, &module).unwrap();
+    let mut env = EmEnv::new();
+    let import_object = generate_emscripten_env(&store, &mut globals, &mut env);
+    let mut instance = Instance::new(&module, &import_object).unwrap();
+
+    let mut data = EmscriptenData::new(&mut instance, &globals.data, Default::default());
+    env.set_memory(globals.memory.clone());
+    env.set_data(&mut data as *mut _ as *mut c_void);
+
+    let result = instance
+        .exports
+        .get_function(function)
+        .unwrap()
+        .call(params);
+    let destroyed = instance
+        .exports
+        .get_function("destroyed")
+        .unwrap()
+        .call(&[]);
+
+    (result, destroyed.unwrap()[0].unwrap_i32())
+}
+
+#[test]
+fn catch_exception() {
+    let (result, destroyed) = call("catch_error", &[Val::I32(42)]);
+    assert_eq!(result.unwrap().to_vec(), vec![Val::I32(42)]);
+    assert_eq!(destroyed, 1);
+
+    let (result, destroyed) = call("catch_all", &[Val::I32(42)]);
+    assert_eq!(result.unwrap().to_vec(), vec![Val::I32(1)]);
+    assert_eq!(destroyed, 1);
+}
+
+#[test]
+fn rethrow_exception() {
+    // the exception is only destroyed once caught by the outer handler
+    let (result, destroyed) = call("catch_rethrown", &[Val::I32(42)]);
+    assert_eq!(result.unwrap().to_vec(), vec![Val::I32(43)]);
+    assert_eq!(destroyed, 1);
+}
+
+#[test]
+fn uncaught_exception() {
+    let (result, destroyed) = call("uncaught", &[Val::I32(42)]);
+    let error = result.unwrap_err();
+    assert!(error.is::<CppException>());
+    assert_eq!(destroyed, 0);
+
+    // not matching the type of the handler, the exception is resumed
+    let (result, destroyed) = call("catch_other", &[Val::I32(42)]);
+    assert!(result.unwrap_err().is::<CppException>());
+    assert_eq!(destroyed, 0);
+}
+
+#[test]
+fn failing_destructor() {
+    // the trap of the destructor unwinds the module
+    let (result, destroyed) = call("catch_failing_destructor", &[Val::I32(42)]);
+    let error = result.unwrap_err();
+    assert!(!error.is::<CppException>());
+    assert!(
+        error.message().contains("unreachable"),
+        "{}",
+        error.message()
+    );
+    assert_eq!(destroyed, 0);
+}
+
+#[test]
+fn trap_in_invoke() {
+    // only exceptions are caught by `invoke_*`, traps go through
+    let (result, _) = call("invoke_trap", &[]);
+    let error = result.unwrap_err();
+    assert!(!error.is::<CppException>());
+}
+
+#[test]
+fn missing_runtime_function() {
+    // the module traps instead of the host panicking
+    let wat = include_str!("assets/exceptions.wat")
+        .replace(r#"(export "___cxa_can_catch")"#, r#"(export "can_catch")"#);
+    let (result, destroyed) = call_in(&wat, "catch_error", &[Val::I32(42)]);
+    let error = result.unwrap_err();
+    assert!(!error.is::<CppException>());
+    assert!(
+        error.message().contains("___cxa_can_catch"),
+        "{}",
+        error.message()
+    );
+    assert_eq!(destroyed, 0);
+}
@@ -0,0 +1,159 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use wasmer_emscripten::EmscriptenFs;
+
+// The values of Emscripten's libc.
+const ENOENT: i32 = 2;
+const EACCES: i32 = 13;
+const ENOTDIR: i32 = 20;
+
+/// A temporary host directory with `data` and `outside` directories,
+/// `data` being mapped at `/data`.
+struct Sandbox {
+    dir: tempfile::TempDir,
+    fs: EmscriptenFs,
+}
+
+impl Sandbox {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("data")).unwrap();
+        fs::create_dir(dir.path().join("outside")).unwrap();
+        fs::write(dir.path().join("outside/secret"), "secret").unwrap();
+        let fs = EmscriptenFs::new(vec![("/data".to_string(), dir.path().join("data"))]);
+
+        Self { dir, fs }
+    }
+
+    fn host(&self, path: &str) -> PathBuf {
+        self.dir.path().join(path)
+    }
+}
+
+#[test]
+fn parent_dirs_stay_in_the_sandbox() {
+    let sandbox = Sandbox::new();
+    let fs = &sandbox.fs;
+
+    assert_eq!(
+        fs.resolve("/data/file", true),
+        Ok(sandbox.host("data/file"))
+    );
+    assert_eq!(
+        fs.resolve("/data/a/../file", true),
+        Ok(sandbox.host("data/file"))
+    );
+    // `..` stops at the root
+    assert_eq!(
+        fs.resolve("../../data/file", true),
+        Ok(sandbox.host("data/file"))
+    );
+    assert_eq!(fs.resolve("/data/../outside/secret", true), Err(EACCES));
+    assert_eq!(fs.resolve("/data/../../outside/secret", true), Err(EACCES));
+    assert_eq!(fs.resolve("/", true), Err(EACCES));
+}
+
+#[test]
+fn symlinks_cant_escape() {
+    let sandbox = Sandbox::new();
+    let fs = &sandbox.fs;
+    symlink(sandbox.host("outside"), sandbox.host("data/outside")).unwrap();
+    symlink("../outside/secret", sandbox.host("data/secret")).unwrap();
+    fs::create_dir(sandbox.host("data/dir")).unwrap();
+    symlink("../dir", sandbox.host("data/dir/inside")).unwrap();
+
+    assert_eq!(fs.resolve("/data/outside/secret", true), Err(EACCES));
+    assert_eq!(fs.resolve("/data/outside/new", true), Err(EACCES));
+    assert_eq!(fs.resolve("/data/secret", true), Err(EACCES));
+    assert_eq!(
+        fs.resolve("/data/dir/inside/file", true),
+        Ok(sandbox.host("data/dir/inside/file"))
+    );
+    // the symlinks themselves can be operated on
+    assert_eq!(
+        fs.resolve("/data/secret", false),
+        Ok(sandbox.host("data/secret"))
+    );
+    assert_eq!(
+        fs.resolve("/data/outside", false),
+        Ok(sandbox.host("data/outside"))
+    );
+    assert_eq!(fs.resolve("/data/outside/secret", false), Err(EACCES));
+}
+
+#[test]
+fn dangling_symlinks_are_denied() {
+    let sandbox = Sandbox::new();
+    let fs = &sandbox.fs;
+    // the target could be created later, outside of the sandbox
+    symlink(sandbox.host("outside/new"), sandbox.host("data/dangling")).unwrap();
+
+    assert_eq!(fs.resolve("/data/dangling", true), Err(EACCES));
+    assert_eq!(fs.resolve("/data/dangling/file", true), Err(EACCES));
+    assert_eq!(
+        fs.resolve("/data/dangling", false),
+        Ok(sandbox.host("data/dangling"))
+    );
+    // a file that doesn't exist yet can be created
+    assert_eq!(
+        fs.resolve("/data/new/file", true),
+        Ok(sandbox.host("data/new/file"))
+    );
+}
+
+#[test]
+fn the_longest_mapped_dir_is_used() {
+    let sandbox = Sandbox::new();
+    fs::create_dir(sandbox.host("cache")).unwrap();
+    let fs = EmscriptenFs::new(vec![
+        ("/data".to_string(), sandbox.host("data")),
+        ("/data/cache/".to_string(), sandbox.host("cache")),
+    ]);
+
+    assert_eq!(
+        fs.resolve("/data/cache/file", true),
+        Ok(sandbox.host("cache/file"))
+    );
+    assert_eq!(fs.resolve("/data/cache", true), Ok(sandbox.host("cache")));
+    assert_eq!(
+        fs.resolve("/data/other", true),
+        Ok(sandbox.host("data/other"))
+    );
+    // the mapped dirs are matched by component
+    assert_eq!(fs.resolve("/database", true), Err(EACCES));
+}
+
+#[test]
+fn the_current_dir_is_the_module_one() {
+    let mut sandbox = Sandbox::new();
+    fs::create_dir(sandbox.host("data/dir")).unwrap();
+    fs::write(sandbox.host("data/file"), "").unwrap();
+    let host_dir = std::env::current_dir().unwrap();
+
+    assert_eq!(sandbox.fs.set_current_dir("/data/dir"), Ok(()));
+    assert_eq!(sandbox.fs.current_dir(), Path::new("/data/dir"));
+    assert_eq!(
+        sandbox.fs.resolve("file", true),
+        Ok(sandbox.host("data/dir/file"))
+    );
+    assert_eq!(
+        sandbox.fs.resolve("../file", true),
+        Ok(sandbox.host("data/file"))
+    );
+    assert_eq!(sandbox.fs.set_current_dir("../missing"), Err(ENOENT));
+    assert_eq!(sandbox.fs.set_current_dir("../file"), Err(ENOTDIR));
+    assert_eq!(sandbox.fs.set_current_dir("/"), Err(EACCES));
+    assert_eq!(sandbox.fs.current_dir(), Path::new("/data/dir"));
+    assert_eq!(std::env::current_dir().unwrap(), host_dir);
+}
+
+#[test]
+fn nothing_is_reachable_without_mapped_dirs() {
+    let fs = EmscriptenFs::new(vec![]);
+
+    assert_eq!(fs.resolve("/", true), Err(EACCES));
+    assert_eq!(fs.resolve("file", false), Err(EACCES));
+}
@@ -0,0 +1,359 @@
+#![cfg(unix)]
+
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::path::PathBuf;
+use wasmer::*;
+use wasmer_emscripten::{generate_emscripten_env, EmEnv, EmscriptenData, EmscriptenGlobals};
+
+// The values of Emscripten's libc.
+const AF_UNIX: i32 = 1;
+const AF_INET: i32 = 2;
+const SOCK_STREAM: i32 = 1;
+const SOCK_DGRAM: i32 = 2;
+const SOCK_NONBLOCK: i32 = 0o4000;
+const O_NONBLOCK: i32 = 0o4000;
+const F_GETFL: i32 = 3;
+const F_SETFL: i32 = 4;
+const FIONREAD: i32 = 0x541B;
+const POLLIN: i16 = 1;
+const POLLOUT: i16 = 4;
+const EBADF: i32 = 9;
+const EAGAIN: i32 = 11;
+const EACCES: i32 = 13;
+const EFAULT: i32 = 14;
+const EINVAL: i32 = 22;
+
+// Where the arguments of the syscalls are written in the memory.
+const VARARGS: u32 = 0x10000;
+const SOCKET_ARGS: u32 = 0x10100;
+const ADDRESS: u32 = 0x10200;
+const ADDRESS_LEN: u32 = 0x10300;
+const BUFFER: u32 = 0x10400;
+const OTHER_BUFFER: u32 = 0x10500;
+const FDS: u32 = 0x10600;
+const MSG: u32 = 0x10700;
+const IOVECS: u32 = 0x10800;
+
+/// An instance of the `sockets.wat` guest, whose syscalls are called
+/// with their arguments written in its memory.
+struct Guest<'a> {
+    instance: &'a Instance,
+    memory: &'a Memory,
+}
+
+impl Guest<'_> {
+    fn write(&self, offset: u32, bytes: &[u8]) {
+        let view = self.memory.view::<u8>();
+        for (cell, &byte) in view[offset as usize..].iter().zip(bytes) {
+            cell.set(byte);
+        }
+    }
+
+    fn read(&self, offset: u32, len: usize) -> Vec<u8> {
+        let view = self.memory.view::<u8>();
+        view[offset as usize..offset as usize + len]
+            .iter()
+            .map(|cell| cell.get())
+            .collect()
+    }
+
+    fn write_i32s(&self, offset: u32, values: &[i32]) {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+        self.write(offset, &bytes);
+    }
+
+    fn read_i32(&self, offset: u32) -> i32 {
+        let bytes = self.read(offset, 4);
+        i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn syscall(&self, name: &str, args: &[i32]) -> i32 {
+        self.write_i32s(VARARGS, args);
+        let result = self
+            .instance
+            .exports
+            .get_function(name)
+            .unwrap()
+            .call(&[Val::I32(0), Val::I32(VARARGS as i32)])
+            .unwrap();
+        result[0].unwrap_i32()
+    }
+
+    fn socketcall(&self, call: i32, args: &[i32]) -> i32 {
+        self.write_i32s(SOCKET_ARGS, args);
+        self.syscall("socketcall", &[call, SOCKET_ARGS as i32])
+    }
+
+    /// Writes a `sockaddr_in` of 127.0.0.1 at `ADDRESS`.
+    fn write_loopback_address(&self, port: u16) {
+        let mut address = vec![0; 16];
+        address[..2].copy_from_slice(&(AF_INET as u16).to_le_bytes());
+        address[2..4].copy_from_slice(&port.to_be_bytes());
+        address[4..8].copy_from_slice(&[127, 0, 0, 1]);
+        self.write(ADDRESS, &address);
+        self.write_i32s(ADDRESS_LEN, &[16]);
+    }
+
+    /// Writes a `sockaddr_un` of `path` at `ADDRESS`, returning its
+    /// length.
+    fn write_unix_address(&self, path: &[u8]) -> i32 {
+        let mut address = (AF_UNIX as u16).to_le_bytes().to_vec();
+        address.extend_from_slice(path);
+        address.push(0);
+        self.write(ADDRESS, &address);
+        self.write_i32s(ADDRESS_LEN, &[110]);
+        address.len() as i32
+    }
+
+    /// Binds `socket` to a port of 127.0.0.1, returning it.
+    fn bind_loopback(&self, socket: i32) -> u16 {
+        self.write_loopback_address(0);
+        assert_eq!(self.socketcall(2, &[socket, ADDRESS as i32, 16]), 0);
+        assert_eq!(
+            self.socketcall(6, &[socket, ADDRESS as i32, ADDRESS_LEN as i32]),
+            0
+        );
+        assert_eq!(self.read_i32(ADDRESS_LEN), 16);
+        let address = self.read(ADDRESS, 8);
+        assert_eq!(address[..2], (AF_INET as u16).to_le_bytes());
+        assert_eq!(address[4..8], [127, 0, 0, 1]);
+        u16::from_be_bytes([address[2], address[3]])
+    }
+}
+
+fn with_guest(test: impl FnOnce(&Guest)) {
+    with_guest_and_dirs(HashMap::new(), test)
+}
+
+fn with_guest_and_dirs(mapped_dirs: HashMap<String, PathBuf>, test: impl FnOnce(&Guest)) {
+    let store = Store::default();
+    let module = Module::new(&store, include_str!("assets/sockets.wat")).unwrap();
+    let mut globals = EmscriptenGlobals::new(&store, &module).unwrap();
+    let mut env = EmEnv::new();
+    let import_object = generate_emscripten_env(&store, &mut globals, &mut env);
+    let mut instance = Instance::new(&module, &import_object).unwrap();
+
+    let mut data = EmscriptenData::new(&mut instance, &globals.data, mapped_dirs);
+    env.set_memory(globals.memory.clone());
+    env.set_data(&mut data as *mut _ as *mut c_void);
+
+    test(&Guest {
+        instance: &instance,
+        memory: &globals.memory,
+    });
+}
+
+#[test]
+fn tcp_loopback() {
+    with_guest(|guest| {
+        let server = guest.socketcall(1, &[AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0]);
+        assert!(server >= 0);
+        let port = guest.bind_loopback(server);
+        assert_eq!(guest.socketcall(4, &[server, 1]), 0);
+
+        // non-blocking, without any pending connection
+        assert_eq!(guest.socketcall(5, &[server, 0, 0]), -EAGAIN);
+
+        let client = guest.socketcall(1, &[AF_INET, SOCK_STREAM, 0]);
+        assert!(client >= 0);
+        guest.write_loopback_address(port);
+        assert_eq!(guest.socketcall(3, &[client, ADDRESS as i32, 16]), 0);
+
+        guest.write_i32s(FDS, &[server, POLLIN as i32]);
+        assert_eq!(guest.syscall("poll", &[FDS as i32, 1, 1000]), 1);
+        assert_eq!(guest.read(FDS + 6, 2), POLLIN.to_le_bytes());
+
+        guest.write_i32s(ADDRESS_LEN, &[16]);
+        let connection = guest.socketcall(5, &[server, ADDRESS as i32, ADDRESS_LEN as i32]);
+        assert!(connection >= 0);
+        assert_eq!(guest.read(ADDRESS, 2), (AF_INET as u16).to_le_bytes());
+
+        guest.write(BUFFER, b"hello");
+        assert_eq!(guest.socketcall(9, &[client, BUFFER as i32, 5, 0]), 5);
+
+        // the connection is readable, once `select` waits for it
+        let mut read_fds = vec![0; 128];
+        read_fds[connection as usize / 8] |= 1 << (connection % 8);
+        guest.write(FDS, &read_fds);
+        guest.write_i32s(OTHER_BUFFER, &[1, 0]);
+        assert_eq!(
+            guest.syscall(
+                "newselect",
+                &[connection + 1, FDS as i32, 0, 0, OTHER_BUFFER as i32]
+            ),
+            1
+        );
+        assert_eq!(guest.read(FDS, 128), read_fds);
+
+        assert_eq!(
+            guest.syscall("ioctl", &[connection, FIONREAD, OTHER_BUFFER as i32]),
+            0
+        );
+        assert_eq!(guest.read_i32(OTHER_BUFFER), 5);
+        assert_eq!(
+            guest.socketcall(10, &[connection, OTHER_BUFFER as i32, 16, 0]),
+            5
+        );
+        assert_eq!(guest.read(OTHER_BUFFER, 5), b"hello");
+
+        // made non-blocking after its creation
+        let flags = guest.syscall("fcntl64", &[connection, F_GETFL, 0]);
+        assert_eq!(flags & O_NONBLOCK, 0);
+        assert_eq!(
+            guest.syscall("fcntl64", &[connection, F_SETFL, flags | O_NONBLOCK]),
+            0
+        );
+        assert_ne!(
+            guest.syscall("fcntl64", &[connection, F_GETFL, 0]) & O_NONBLOCK,
+            0
+        );
+        assert_eq!(
+            guest.syscall("read", &[connection, OTHER_BUFFER as i32, 16]),
+            -EAGAIN
+        );
+
+        // shutting down the client is seen as the end of the stream
+        assert_eq!(guest.socketcall(13, &[client, 1]), 0);
+        guest.write_i32s(FDS, &[connection, (POLLIN | POLLOUT) as i32]);
+        assert_eq!(guest.syscall("poll", &[FDS as i32, 1, 1000]), 1);
+        assert_eq!(
+            guest.syscall("read", &[connection, OTHER_BUFFER as i32, 16]),
+            0
+        );
+
+        for fd in &[connection, client, server] {
+            assert_eq!(guest.syscall("close", &[*fd]), 0);
+        }
+    });
+}
+
+#[test]
+fn udp_loopback() {
+    with_guest(|guest| {
+        let receiver = guest.socketcall(1, &[AF_INET, SOCK_DGRAM | SOCK_NONBLOCK, 0]);
+        assert!(receiver >= 0);
+        let port = guest.bind_loopback(receiver);
+
+        // non-blocking, without any datagram
+        assert_eq!(
+            guest.socketcall(12, &[receiver, BUFFER as i32, 16, 0, 0, 0]),
+            -EAGAIN
+        );
+
+        let sender = guest.socketcall(1, &[AF_INET, SOCK_DGRAM, 0]);
+        assert!(sender >= 0);
+        let sender_port = guest.bind_loopback(sender);
+        guest.write_loopback_address(port);
+        guest.write(BUFFER, b"hello, world");
+        assert_eq!(
+            guest.socketcall(11, &[sender, BUFFER as i32, 12, 0, ADDRESS as i32, 16]),
+            12
+        );
+
+        // scattered over two buffers by `recvmsg`
+        guest.write_i32s(IOVECS, &[BUFFER as i32, 5, OTHER_BUFFER as i32, 16]);
+        guest.write_i32s(MSG, &[ADDRESS as i32, 16, IOVECS as i32, 2, 0, 0, 0]);
+        guest.write(ADDRESS, &[0; 16]);
+        guest.write_i32s(FDS, &[receiver, POLLIN as i32]);
+        assert_eq!(guest.syscall("poll", &[FDS as i32, 1, 1000]), 1);
+        assert_eq!(guest.socketcall(17, &[receiver, MSG as i32, 0]), 12);
+        assert_eq!(guest.read(BUFFER, 5), b"hello");
+        assert_eq!(guest.read(OTHER_BUFFER, 7), b", world");
+        assert_eq!(guest.read_i32(MSG + 4), 16);
+        let address = guest.read(ADDRESS, 8);
+        assert_eq!(address[..2], (AF_INET as u16).to_le_bytes());
+        assert_eq!(u16::from_be_bytes([address[2], address[3]]), sender_port);
+        assert_eq!(address[4..8], [127, 0, 0, 1]);
+
+        for fd in &[sender, receiver] {
+            assert_eq!(guest.syscall("close", &[*fd]), 0);
+        }
+    });
+}
+
+#[test]
+fn socket_errors() {
+    with_guest(|guest| {
+        // an unknown socketcall
+        assert_eq!(guest.socketcall(42, &[]), -EINVAL);
+        // a closed socket
+        guest.write_loopback_address(1);
+        assert_eq!(guest.socketcall(3, &[1000, ADDRESS as i32, 16]), -EBADF);
+        assert_eq!(guest.syscall("close", &[1000]), -EBADF);
+
+        let socket = guest.socketcall(1, &[AF_INET, SOCK_STREAM, 0]);
+        assert!(socket >= 0);
+        // an address out of the memory
+        assert_eq!(guest.socketcall(2, &[socket, 0x7fff_fff0, 16]), -EFAULT);
+        // an unknown ioctl
+        assert_eq!(guest.syscall("ioctl", &[socket, 0x1234, 0]), -EINVAL);
+        // too many fds for `select`
+        assert_eq!(guest.syscall("newselect", &[2000, 0, 0, 0, 0]), -EINVAL);
+        assert_eq!(guest.syscall("close", &[socket]), 0);
+    });
+}
+
+#[test]
+fn unix_sockets_are_sandboxed() {
+    let data_dir = tempfile::tempdir().unwrap();
+    let host_dir = tempfile::tempdir().unwrap();
+    let _host_listener =
+        std::os::unix::net::UnixListener::bind(host_dir.path().join("host.sock")).unwrap();
+    std::os::unix::fs::symlink(
+        host_dir.path().join("host.sock"),
+        data_dir.path().join("link.sock"),
+    )
+    .unwrap();
+    let mut mapped_dirs = HashMap::new();
+    mapped_dirs.insert("/data".to_string(), data_dir.path().to_path_buf());
+
+    with_guest_and_dirs(mapped_dirs, |guest| {
+        let server = guest.socketcall(1, &[AF_UNIX, SOCK_STREAM, 0]);
+        assert!(server >= 0);
+        let len = guest.write_unix_address(b"/data/guest.sock");
+        assert_eq!(guest.socketcall(2, &[server, ADDRESS as i32, len]), 0);
+        assert!(data_dir.path().join("guest.sock").exists());
+        assert_eq!(guest.socketcall(4, &[server, 1]), 0);
+
+        // the module sees the path it bound the socket to
+        guest.write(ADDRESS, &[0; 110]);
+        guest.write_i32s(ADDRESS_LEN, &[110]);
+        assert_eq!(
+            guest.socketcall(6, &[server, ADDRESS as i32, ADDRESS_LEN as i32]),
+            0
+        );
+        assert_eq!(guest.read_i32(ADDRESS_LEN), len);
+        assert_eq!(guest.read(ADDRESS + 2, 17), b"/data/guest.sock\0");
+
+        let client = guest.socketcall(1, &[AF_UNIX, SOCK_STREAM, 0]);
+        assert!(client >= 0);
+        let len = guest.write_unix_address(b"guest.sock");
+        // relative to the current directory of the module, the root
+        assert_eq!(guest.socketcall(3, &[client, ADDRESS as i32, len]), -EACCES);
+        let len = guest.write_unix_address(b"/data/guest.sock");
+        assert_eq!(guest.socketcall(3, &[client, ADDRESS as i32, len]), 0);
+
+        // the host sockets outside of the mapped dirs can't be reached
+        let other = guest.socketcall(1, &[AF_UNIX, SOCK_STREAM, 0]);
+        assert!(other >= 0);
+        let host_path = host_dir.path().join("host.sock");
+        let len = guest.write_unix_address(host_path.to_str().unwrap().as_bytes());
+        assert_eq!(guest.socketcall(3, &[other, ADDRESS as i32, len]), -EACCES);
+        let len = guest.write_unix_address(b"/data/link.sock");
+        assert_eq!(guest.socketcall(3, &[other, ADDRESS as i32, len]), -EACCES);
+        let len = guest.write_unix_address(b"/data/../host.sock");
+        assert_eq!(guest.socketcall(3, &[other, ADDRESS as i32, len]), -EACCES);
+        // neither can the abstract ones
+        let len = guest.write_unix_address(b"\0abstract");
+        assert_eq!(guest.socketcall(2, &[other, ADDRESS as i32, len]), -EACCES);
+        assert_eq!(guest.socketcall(2, &[other, ADDRESS as i32, 2]), -EACCES);
+
+        for fd in &[other, client, server] {
+            assert_eq!(guest.syscall("close", &[*fd]), 0);
+        }
+    });
+}
@@ -6,16 +6,19 @@ use crate::link::link_module;
 #[cfg(feature = "compiler")]
 use crate::serialize::SerializableCompilation;
 use crate::serialize::SerializableModule;
+#[cfg(feature = "compiler")]
+use crate::tier_up::{LoopCounting, TierUpState};
 use std::sync::{Arc, Mutex};
-use wasmer_compiler::{CompileError, Features, Triple};
+use wasmer_compiler::{Compilation, CompileError, CompiledFunction, Features, Target, Triple};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::{CompileModuleInfo, ModuleEnvironment};
+use wasmer_compiler::{CompileModuleInfo, GenerateMiddlewareChain, ModuleEnvironment};
 use wasmer_engine::{
-    register_frame_info, Artifact, DeserializeError, GlobalFrameInfoRegistration, SerializeError,
+    register_frame_info, Artifact, ArtifactSummary, DeserializeError, GlobalFrameInfoRegistration,
+    SerializeError,
 };
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, SerializableFunctionFrameInfo, Tunables};
-use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     FunctionIndex, LocalFunctionIndex, MemoryIndex, OwnedDataInitializer, SignatureIndex,
     TableIndex,
@@ -24,19 +27,37 @@ use wasmer_vm::{
     FunctionBodyPtr, MemoryStyle, ModuleInfo, TableStyle, VMSharedSignatureIndex, VMTrampoline,
 };
 
+/// The function counting the loop iterations of each local function
+/// of a module compiled by a tiered engine, if it has loops.
+pub(crate) type LoopCounters = PrimaryMap<LocalFunctionIndex, Option<LocalFunctionIndex>>;
+
 /// A compiled wasm module, ready to be instantiated.
 pub struct JITArtifact {
     serializable: SerializableModule,
+    /// The compiled bodies of the local functions.
+    function_bodies: BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
+    /// The entry points of the local functions: the bodies themselves,
+    /// or the tier-up entry stubs if the engine is tiered.
     finished_functions: BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
     finished_function_call_trampolines: BoxedSlice<SignatureIndex, VMTrampoline>,
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
+    #[cfg(feature = "compiler")]
+    tier_up: Option<Arc<TierUpState>>,
 }
 
 impl JITArtifact {
     const MAGIC_HEADER: &'static [u8] = b"\0wasmer-jit";
 
+    /// The version of the serialization format, following the magic
+    /// header. It must be bumped whenever `SerializableModule` changes.
+    ///
+    /// The artifacts serialized before it was introduced can't be
+    /// mistaken for a version: their magic header is followed by the
+    /// number of functions, which would be above the limit of 1 million.
+    const FORMAT_VERSION: &'static [u8] = b"\0v2";
+
     /// Check if the provided bytes look like a serialized `JITArtifact`.
     pub fn is_deserializable(bytes: &[u8]) -> bool {
         bytes.starts_with(Self::MAGIC_HEADER)
@@ -53,7 +74,25 @@ impl JITArtifact {
         let mut inner_jit = jit.inner_mut();
         let features = inner_jit.features();
 
-        let translation = environ.translate(data).map_err(CompileError::Wasm)?;
+        // A tiered engine compiles the module rewritten to count the
+        // iterations of its loops, and keeps the original one to
+        // recompile the hot functions.
+        let loop_counting = if inner_jit.is_tiered() {
+            Some(LoopCounting::instrument(data)?)
+        } else {
+            None
+        };
+        let wasm = match &loop_counting {
+            Some(loop_counting) => &loop_counting.wasm[..],
+            None => data,
+        };
+        let mut translation = environ.translate(wasm).map_err(CompileError::Wasm)?;
+
+        let compiler = inner_jit.compiler()?;
+
+        // We try to apply the middleware first
+        let middlewares = compiler.get_middlewares();
+        middlewares.apply_on_module_info(&mut translation.module)?;
 
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = translation
             .module
@@ -75,8 +114,6 @@ impl JITArtifact {
             table_styles,
         };
 
-        let compiler = inner_jit.compiler()?;
-
         // Compile the Module
         let compilation = compiler.compile_module(
             &jit.target(),
@@ -111,12 +148,20 @@ impl JITArtifact {
             custom_section_relocations: compilation.get_custom_section_relocations(),
             debug: compilation.get_debug(),
         };
+        let target = jit.target();
         let serializable = SerializableModule {
             compilation: serializable_compilation,
             compile_info,
             data_initializers,
+            target_triple: target.triple().to_string(),
+            cpu_features: target
+                .cpu_features()
+                .iter()
+                .map(|feature| feature.to_string())
+                .collect(),
         };
-        Self::from_parts(&mut inner_jit, serializable)
+        let tier_up = loop_counting.map(|loop_counting| (data, loop_counting.counters, target));
+        Self::from_parts_with_wasm(&mut inner_jit, serializable, tier_up)
     }
 
     /// Compile a data buffer into a `JITArtifact`, which may then be instantiated.
@@ -128,14 +173,11 @@ impl JITArtifact {
     }
 
     /// Deserialize a JITArtifact
+    ///
+    /// Like the ones built with [`JITArtifact::from_parts`], the
+    /// deserialized artifacts are never tiered up.
     pub fn deserialize(jit: &JITEngine, bytes: &[u8]) -> Result<Self, DeserializeError> {
-        if !Self::is_deserializable(bytes) {
-            return Err(DeserializeError::Incompatible(
-                "The provided bytes are not wasmer-jit".to_string(),
-            ));
-        }
-
-        let inner_bytes = &bytes[Self::MAGIC_HEADER.len()..];
+        let inner_bytes = Self::strip_headers(bytes)?;
 
         // let r = flexbuffers::Reader::get_root(bytes).map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
         // let serializable = SerializableModule::deserialize(r).map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
@@ -146,10 +188,95 @@ impl JITArtifact {
         Self::from_parts(&mut jit.inner_mut(), serializable).map_err(DeserializeError::Compiler)
     }
 
+    /// Checks the magic header and the format version of a serialized
+    /// `JITArtifact`, and returns the bytes following them.
+    fn strip_headers(bytes: &[u8]) -> Result<&[u8], DeserializeError> {
+        if !Self::is_deserializable(bytes) {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes are not wasmer-jit".to_string(),
+            ));
+        }
+        let bytes = &bytes[Self::MAGIC_HEADER.len()..];
+        if !bytes.starts_with(Self::FORMAT_VERSION) {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes were serialized by an incompatible version of wasmer-jit"
+                    .to_string(),
+            ));
+        }
+        Ok(&bytes[Self::FORMAT_VERSION.len()..])
+    }
+
+    /// Summarize a serialized `JITArtifact`, without loading its code.
+    pub fn summarize(bytes: &[u8]) -> Result<ArtifactSummary, DeserializeError> {
+        let serializable: SerializableModule = bincode::deserialize(Self::strip_headers(bytes)?)
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+        let compilation = serializable.compilation;
+        let eh_frame = compilation.debug.as_ref().map(|debug| debug.eh_frame);
+        let relocations = compilation
+            .function_relocations
+            .values()
+            .chain(compilation.custom_section_relocations.values())
+            .map(Vec::len)
+            .sum();
+        let custom_sections = compilation
+            .custom_sections
+            .iter()
+            .map(|(index, section)| {
+                let name = if Some(index) == eh_frame {
+                    "eh_frame".to_string()
+                } else {
+                    format!("section {}", index.index())
+                };
+                (name, section.bytes.len() as u64)
+            })
+            .collect();
+        Ok(ArtifactSummary {
+            engine: "jit",
+            target: if serializable.cpu_features.is_empty() {
+                serializable.target_triple
+            } else {
+                format!(
+                    "{} ({})",
+                    serializable.target_triple,
+                    serializable.cpu_features.join(", ")
+                )
+            },
+            compile_info: serializable.compile_info,
+            function_sizes: compilation
+                .function_bodies
+                .values()
+                .map(|body| body.body.len() as u64)
+                .collect(),
+            function_call_trampolines: compilation.function_call_trampolines.len(),
+            dynamic_function_trampolines: compilation.dynamic_function_trampolines.len(),
+            relocations,
+            custom_sections,
+            frame_info: !compilation.function_frame_info.is_empty(),
+            unwind_info: eh_frame.is_some(),
+            data_initializers: serializable.data_initializers.len(),
+        })
+    }
+
     /// Construct a `JITArtifact` from component parts.
+    ///
+    /// The parts don't include the original Wasm module, so the
+    /// artifact is never tiered up, even by a tiered engine.
     pub fn from_parts(
         inner_jit: &mut JITEngineInner,
         serializable: SerializableModule,
+    ) -> Result<Self, CompileError> {
+        Self::from_parts_with_wasm(inner_jit, serializable, None)
+    }
+
+    /// Construct a `JITArtifact` from component parts, keeping the
+    /// original Wasm module around if the engine is tiered, along with
+    /// the function counting the loop iterations of each function and
+    /// the target to recompile it for.
+    #[cfg_attr(not(feature = "compiler"), allow(unused_variables))]
+    fn from_parts_with_wasm(
+        inner_jit: &mut JITEngineInner,
+        serializable: SerializableModule,
+        wasm: Option<(&[u8], LoopCounters, &Target)>,
     ) -> Result<Self, CompileError> {
         let (
             finished_functions,
@@ -164,9 +291,37 @@ impl JITArtifact {
             &serializable.compilation.custom_sections,
         )?;
 
+        #[cfg(feature = "compiler")]
+        let tier_up = match wasm {
+            Some((wasm, loop_counters, target)) if inner_jit.is_tiered() => Some(Arc::new(
+                TierUpState::new(
+                    wasm,
+                    loop_counters,
+                    &serializable.compile_info,
+                    target,
+                    &finished_functions,
+                )
+                .map_err(|message| {
+                    CompileError::Resource(format!(
+                        "failed to allocate the entry stubs of the functions: {}",
+                        message
+                    ))
+                })?,
+            )),
+            _ => None,
+        };
+        #[cfg(feature = "compiler")]
+        let finished_function_entries = match &tier_up {
+            Some(tier_up) => tier_up.entries(),
+            None => finished_functions.clone(),
+        };
+        #[cfg(not(feature = "compiler"))]
+        let finished_function_entries = finished_functions.clone();
+
         link_module(
             &serializable.compile_info.module,
             &finished_functions,
+            &finished_function_entries,
             &serializable.compilation.function_jt_offsets,
             serializable.compilation.function_relocations.clone(),
             &custom_sections,
@@ -203,7 +358,15 @@ impl JITArtifact {
 
         inner_jit.publish_eh_frame(eh_frame)?;
 
+        #[cfg(feature = "compiler")]
+        {
+            if let Some(tier_up) = &tier_up {
+                inner_jit.register_tier_up(tier_up);
+            }
+        }
+
         let finished_functions = finished_functions.into_boxed_slice();
+        let finished_function_entries = finished_function_entries.into_boxed_slice();
         let finished_function_call_trampolines =
             finished_function_call_trampolines.into_boxed_slice();
         let finished_dynamic_function_trampolines =
@@ -212,14 +375,33 @@ impl JITArtifact {
 
         Ok(Self {
             serializable,
-            finished_functions,
+            function_bodies: finished_functions,
+            finished_functions: finished_function_entries,
             finished_function_call_trampolines,
             finished_dynamic_function_trampolines,
             signatures,
             frame_info_registration: Mutex::new(None),
+            #[cfg(feature = "compiler")]
+            tier_up,
         })
     }
 
+    /// Returns whether the given function has been recompiled with
+    /// the optimizing compiler of a tiered engine.
+    ///
+    /// This is always `false` for artifacts that were not compiled by
+    /// a tiered engine, including deserialized ones.
+    #[cfg_attr(not(feature = "compiler"), allow(unused_variables))]
+    pub fn is_optimized(&self, index: LocalFunctionIndex) -> bool {
+        #[cfg(feature = "compiler")]
+        {
+            if let Some(tier_up) = &self.tier_up {
+                return tier_up.is_optimized(index);
+            }
+        }
+        false
+    }
+
     /// Get the default extension when serializing this artifact
     pub fn get_default_extension(_triple: &Triple) -> &'static str {
         // `.wjit` is the default extension for all the triples
@@ -248,7 +430,7 @@ impl Artifact for JITArtifact {
         }
 
         let frame_infos = &self.serializable.compilation.function_frame_info;
-        let finished_functions = &self.finished_functions;
+        let finished_functions = &self.function_bodies;
         *info = register_frame_info(
             self.serializable.compile_info.module.clone(),
             finished_functions,
@@ -289,6 +471,32 @@ impl Artifact for JITArtifact {
         &self.signatures
     }
 
+    fn compilation(&self) -> Option<Compilation> {
+        let compilation = &self.serializable.compilation;
+        let functions = compilation
+            .function_bodies
+            .iter()
+            .map(|(index, body)| CompiledFunction {
+                body: body.clone(),
+                relocations: compilation.function_relocations[index].clone(),
+                jt_offsets: compilation.function_jt_offsets[index].clone(),
+                frame_info: match &compilation.function_frame_info[index] {
+                    wasmer_engine::SerializableFunctionFrameInfo::Processed(info) => info.clone(),
+                    wasmer_engine::SerializableFunctionFrameInfo::Unprocessed(info) => {
+                        info.deserialize()
+                    }
+                },
+            })
+            .collect();
+        Some(Compilation::new(
+            functions,
+            compilation.custom_sections.clone(),
+            compilation.function_call_trampolines.clone(),
+            compilation.dynamic_function_trampolines.clone(),
+            compilation.debug.clone(),
+        ))
+    }
+
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
         // let mut s = flexbuffers::FlexbufferSerializer::new();
         // self.serializable.serialize(&mut s).map_err(|e| SerializeError::Generic(format!("{:?}", e)));
@@ -296,8 +504,9 @@ impl Artifact for JITArtifact {
         let bytes = bincode::serialize(&self.serializable)
             .map_err(|e| SerializeError::Generic(format!("{:?}", e)))?;
 
-        // Prepend the header.
+        // Prepend the headers.
         let mut serialized = Self::MAGIC_HEADER.to_vec();
+        serialized.extend(Self::FORMAT_VERSION);
         serialized.extend(bytes);
         Ok(serialized)
     }
@@ -1,10 +1,22 @@
+#[cfg(feature = "compiler")]
+use crate::tier_up::{check_tier_up_middlewares, spawn_tier_up_thread, TierUpState};
 use crate::JITEngine;
+#[cfg(feature = "compiler")]
+use crate::TierUpPolicy;
+#[cfg(feature = "compiler")]
+use std::sync::mpsc::Sender;
+#[cfg(feature = "compiler")]
+use std::sync::Weak;
+#[cfg(feature = "compiler")]
+use wasmer_compiler::CompileError;
 use wasmer_compiler::{CompilerConfig, Features, Target};
 
 /// The JIT builder
 pub struct JIT<'a> {
     #[allow(dead_code)]
     compiler_config: Option<&'a dyn CompilerConfig>,
+    #[cfg(feature = "compiler")]
+    tier_up: Option<Sender<Weak<TierUpState>>>,
     target: Option<Target>,
     features: Option<Features>,
 }
@@ -14,6 +26,8 @@ impl<'a> JIT<'a> {
     pub fn new(compiler_config: &'a dyn CompilerConfig) -> Self {
         Self {
             compiler_config: Some(compiler_config),
+            #[cfg(feature = "compiler")]
+            tier_up: None,
             target: None,
             features: None,
         }
@@ -23,6 +37,8 @@ impl<'a> JIT<'a> {
     pub fn headless() -> Self {
         Self {
             compiler_config: None,
+            #[cfg(feature = "compiler")]
+            tier_up: None,
             target: None,
             features: None,
         }
@@ -40,6 +56,34 @@ impl<'a> JIT<'a> {
         self
     }
 
+    /// Recompile hot functions with an optimizing compiler
+    ///
+    /// Modules are compiled with the compiler of this builder first,
+    /// and the functions that become hot according to the `policy`
+    /// are recompiled in the background with `optimizing_compiler_config`.
+    ///
+    /// Only the modules compiled by the engine are tiered up, not the
+    /// deserialized ones.
+    ///
+    /// Both configurations must have the same middlewares, pushed as the
+    /// same `Arc`s in the same order, so the optimized code is
+    /// instrumented like the baseline code (e.g. it keeps consuming
+    /// metering points). An error is returned otherwise, or if tiered
+    /// compilation isn't supported on this architecture.
+    #[cfg(feature = "compiler")]
+    pub fn tier_up(
+        mut self,
+        optimizing_compiler_config: &'a dyn CompilerConfig,
+        policy: TierUpPolicy,
+    ) -> Result<Self, CompileError> {
+        let optimizing_compiler = optimizing_compiler_config.compiler();
+        if let Some(compiler_config) = self.compiler_config {
+            check_tier_up_middlewares(&*compiler_config.compiler(), &*optimizing_compiler)?;
+        }
+        self.tier_up = Some(spawn_tier_up_thread(optimizing_compiler, policy)?);
+        Ok(self)
+    }
+
     /// Build the `JITEngine` for this configuration
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> JITEngine {
@@ -49,7 +93,10 @@ impl<'a> JIT<'a> {
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
             let compiler = compiler_config.compiler();
-            JITEngine::new(compiler, target, features)
+            match self.tier_up {
+                Some(tier_up) => JITEngine::new_with_tier_up(compiler, tier_up, target, features),
+                None => JITEngine::new(compiler, target, features),
+            }
         } else {
             JITEngine::headless()
         }
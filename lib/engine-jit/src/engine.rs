@@ -1,6 +1,12 @@
 //! JIT compilation.
 
+#[cfg(feature = "compiler")]
+use crate::tier_up::{check_tier_up_middlewares, spawn_tier_up_thread, TierUpPolicy, TierUpState};
 use crate::{CodeMemory, JITArtifact};
+#[cfg(feature = "compiler")]
+use std::sync::mpsc::Sender;
+#[cfg(feature = "compiler")]
+use std::sync::Weak;
 use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::Compiler;
@@ -32,6 +38,7 @@ impl JITEngine {
         Self {
             inner: Arc::new(Mutex::new(JITEngineInner {
                 compiler: Some(compiler),
+                tier_up: None,
                 code_memory: vec![],
                 signatures: SignatureRegistry::new(),
                 features,
@@ -41,6 +48,46 @@ impl JITEngine {
         }
     }
 
+    /// Create a new tiered `JITEngine`.
+    ///
+    /// Modules are compiled with `compiler` first, and the functions
+    /// that become hot according to the `policy` are recompiled in
+    /// the background with `optimizing_compiler`.
+    ///
+    /// Deserialized modules are never recompiled, as they don't carry
+    /// their original Wasm bytes.
+    ///
+    /// Fails if the compilers don't have the same middlewares: the
+    /// optimized code must be instrumented like the baseline code, with
+    /// the `ModuleInfo` transformed by the baseline middlewares. Fails
+    /// too if tiered compilation isn't supported on this architecture.
+    #[cfg(feature = "compiler")]
+    pub fn new_tiered(
+        compiler: Box<dyn Compiler + Send>,
+        optimizing_compiler: Box<dyn Compiler + Send>,
+        policy: TierUpPolicy,
+        target: Target,
+        features: Features,
+    ) -> Result<Self, CompileError> {
+        check_tier_up_middlewares(&*compiler, &*optimizing_compiler)?;
+        let tier_up = spawn_tier_up_thread(optimizing_compiler, policy)?;
+        Ok(Self::new_with_tier_up(compiler, tier_up, target, features))
+    }
+
+    /// Create a tiered `JITEngine` registering its modules into an
+    /// already spawned tier-up thread.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn new_with_tier_up(
+        compiler: Box<dyn Compiler + Send>,
+        tier_up: Sender<Weak<TierUpState>>,
+        target: Target,
+        features: Features,
+    ) -> Self {
+        let engine = Self::new(compiler, target, features);
+        engine.inner_mut().tier_up = Some(tier_up);
+        engine
+    }
+
     /// Create a headless `JITEngine`
     ///
     /// A headless engine is an engine without any compiler attached.
@@ -59,6 +106,8 @@ impl JITEngine {
             inner: Arc::new(Mutex::new(JITEngineInner {
                 #[cfg(feature = "compiler")]
                 compiler: None,
+                #[cfg(feature = "compiler")]
+                tier_up: None,
                 code_memory: vec![],
                 signatures: SignatureRegistry::new(),
                 features: Features::default(),
@@ -132,6 +181,10 @@ impl Engine for JITEngine {
         &self.engine_id
     }
 
+    fn shared_id(&self) -> usize {
+        &*self.inner as *const _ as usize
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
@@ -142,6 +195,10 @@ pub struct JITEngineInner {
     /// The compiler
     #[cfg(feature = "compiler")]
     compiler: Option<Box<dyn Compiler + Send>>,
+    /// The channel to register compiled modules into the tier-up
+    /// thread, if the engine is tiered.
+    #[cfg(feature = "compiler")]
+    tier_up: Option<Sender<Weak<TierUpState>>>,
     /// The features to compile the Wasm module with
     features: Features,
     /// The code memory is responsible of publishing the compiled
@@ -177,6 +234,22 @@ impl JITEngineInner {
         ))
     }
 
+    /// Registers the tiering state of a compiled module, so its hot
+    /// functions get recompiled with the optimizing compiler.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn register_tier_up(&self, state: &Arc<TierUpState>) {
+        if let Some(tier_up) = &self.tier_up {
+            // The tier-up thread only goes away with the engine.
+            let _ = tier_up.send(Arc::downgrade(state));
+        }
+    }
+
+    /// Returns whether the engine is tiered.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn is_tiered(&self) -> bool {
+        self.tier_up.is_some()
+    }
+
     /// The Wasm features
     pub fn features(&self) -> &Features {
         &self.features
@@ -200,76 +273,14 @@ impl JITEngineInner {
         ),
         CompileError,
     > {
-        let function_bodies = functions
-            .values()
-            .chain(function_call_trampolines.values())
-            .chain(dynamic_function_trampolines.values())
-            .collect::<Vec<_>>();
-        let (executable_sections, data_sections): (Vec<_>, _) = custom_sections
-            .values()
-            .partition(|section| section.protection == CustomSectionProtection::ReadExecute);
         self.code_memory.push(CodeMemory::new());
-
-        let (mut allocated_functions, allocated_executable_sections, allocated_data_sections) =
-            self.code_memory
-                .last_mut()
-                .unwrap()
-                .allocate(
-                    function_bodies.as_slice(),
-                    executable_sections.as_slice(),
-                    data_sections.as_slice(),
-                )
-                .map_err(|message| {
-                    CompileError::Resource(format!(
-                        "failed to allocate memory for functions: {}",
-                        message
-                    ))
-                })?;
-
-        let allocated_functions_result = allocated_functions
-            .drain(0..functions.len())
-            .map(|slice| FunctionBodyPtr(slice as *mut [_]))
-            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
-
-        let mut allocated_function_call_trampolines: PrimaryMap<SignatureIndex, VMTrampoline> =
-            PrimaryMap::new();
-        for ptr in allocated_functions
-            .drain(0..function_call_trampolines.len())
-            .map(|slice| slice.as_ptr())
-        {
-            let trampoline =
-                unsafe { std::mem::transmute::<*const VMFunctionBody, VMTrampoline>(ptr) };
-            allocated_function_call_trampolines.push(trampoline);
-        }
-
-        let allocated_dynamic_function_trampolines = allocated_functions
-            .drain(..)
-            .map(|slice| FunctionBodyPtr(slice as *mut [_]))
-            .collect::<PrimaryMap<FunctionIndex, _>>();
-
-        let mut exec_iter = allocated_executable_sections.iter();
-        let mut data_iter = allocated_data_sections.iter();
-        let allocated_custom_sections = custom_sections
-            .iter()
-            .map(|(_, section)| {
-                SectionBodyPtr(
-                    if section.protection == CustomSectionProtection::ReadExecute {
-                        exec_iter.next()
-                    } else {
-                        data_iter.next()
-                    }
-                    .unwrap()
-                    .as_ptr(),
-                )
-            })
-            .collect::<PrimaryMap<SectionIndex, _>>();
-
-        Ok((
-            allocated_functions_result,
-            allocated_function_call_trampolines,
-            allocated_dynamic_function_trampolines,
-            allocated_custom_sections,
-        ))
+        allocate_in(
+            self.code_memory.last_mut().unwrap(),
+            functions,
+            function_call_trampolines,
+            dynamic_function_trampolines,
+            custom_sections,
+        )
     }
 
     /// Make memory containing compiled code executable.
@@ -295,3 +306,87 @@ impl JITEngineInner {
         &self.signatures
     }
 }
+
+/// Allocate compiled functions into `code_memory`.
+#[allow(clippy::type_complexity)]
+pub(crate) fn allocate_in(
+    code_memory: &mut CodeMemory,
+    functions: &PrimaryMap<LocalFunctionIndex, FunctionBody>,
+    function_call_trampolines: &PrimaryMap<SignatureIndex, FunctionBody>,
+    dynamic_function_trampolines: &PrimaryMap<FunctionIndex, FunctionBody>,
+    custom_sections: &PrimaryMap<SectionIndex, CustomSection>,
+) -> Result<
+    (
+        PrimaryMap<LocalFunctionIndex, FunctionBodyPtr>,
+        PrimaryMap<SignatureIndex, VMTrampoline>,
+        PrimaryMap<FunctionIndex, FunctionBodyPtr>,
+        PrimaryMap<SectionIndex, SectionBodyPtr>,
+    ),
+    CompileError,
+> {
+    let function_bodies = functions
+        .values()
+        .chain(function_call_trampolines.values())
+        .chain(dynamic_function_trampolines.values())
+        .collect::<Vec<_>>();
+    let (executable_sections, data_sections): (Vec<_>, _) = custom_sections
+        .values()
+        .partition(|section| section.protection == CustomSectionProtection::ReadExecute);
+    let (mut allocated_functions, allocated_executable_sections, allocated_data_sections) =
+        code_memory
+            .allocate(
+                function_bodies.as_slice(),
+                executable_sections.as_slice(),
+                data_sections.as_slice(),
+            )
+            .map_err(|message| {
+                CompileError::Resource(format!(
+                    "failed to allocate memory for functions: {}",
+                    message
+                ))
+            })?;
+
+    let allocated_functions_result = allocated_functions
+        .drain(0..functions.len())
+        .map(|slice| FunctionBodyPtr(slice as *mut [_]))
+        .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+
+    let mut allocated_function_call_trampolines: PrimaryMap<SignatureIndex, VMTrampoline> =
+        PrimaryMap::new();
+    for ptr in allocated_functions
+        .drain(0..function_call_trampolines.len())
+        .map(|slice| slice.as_ptr())
+    {
+        let trampoline = unsafe { std::mem::transmute::<*const VMFunctionBody, VMTrampoline>(ptr) };
+        allocated_function_call_trampolines.push(trampoline);
+    }
+
+    let allocated_dynamic_function_trampolines = allocated_functions
+        .drain(..)
+        .map(|slice| FunctionBodyPtr(slice as *mut [_]))
+        .collect::<PrimaryMap<FunctionIndex, _>>();
+
+    let mut exec_iter = allocated_executable_sections.iter();
+    let mut data_iter = allocated_data_sections.iter();
+    let allocated_custom_sections = custom_sections
+        .iter()
+        .map(|(_, section)| {
+            SectionBodyPtr(
+                if section.protection == CustomSectionProtection::ReadExecute {
+                    exec_iter.next()
+                } else {
+                    data_iter.next()
+                }
+                .unwrap()
+                .as_ptr(),
+            )
+        })
+        .collect::<PrimaryMap<SectionIndex, _>>();
+
+    Ok((
+        allocated_functions_result,
+        allocated_function_call_trampolines,
+        allocated_dynamic_function_trampolines,
+        allocated_custom_sections,
+    ))
+}
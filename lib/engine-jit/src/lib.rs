@@ -30,6 +30,8 @@ mod code_memory;
 mod engine;
 mod link;
 mod serialize;
+#[cfg(feature = "compiler")]
+mod tier_up;
 mod unwind;
 
 pub use crate::artifact::JITArtifact;
@@ -37,6 +39,8 @@ pub use crate::builder::JIT;
 pub use crate::code_memory::CodeMemory;
 pub use crate::engine::JITEngine;
 pub use crate::link::link_module;
+#[cfg(feature = "compiler")]
+pub use crate::tier_up::TierUpPolicy;
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
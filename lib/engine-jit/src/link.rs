@@ -14,12 +14,13 @@ fn apply_relocation(
     body: usize,
     r: &Relocation,
     allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionBodyPtr>,
+    function_entries: &PrimaryMap<LocalFunctionIndex, FunctionBodyPtr>,
     jt_offsets: &PrimaryMap<LocalFunctionIndex, JumpTableOffsets>,
     allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
 ) {
     let target_func_address: usize = match r.reloc_target {
         RelocationTarget::LocalFunc(index) => {
-            let fatptr: *const [VMFunctionBody] = allocated_functions[index].0;
+            let fatptr: *const [VMFunctionBody] = function_entries[index].0;
             fatptr as *const VMFunctionBody as usize
         }
         RelocationTarget::LibCall(libcall) => libcall.function_pointer(),
@@ -67,9 +68,14 @@ fn apply_relocation(
 
 /// Links a module, patching the allocated functions with the
 /// required relocations and jump tables.
+///
+/// Calls to local functions are resolved against `function_entries`,
+/// which is usually the same as `allocated_functions` unless the calls
+/// have to go through an indirection (such as the tier-up entry stubs).
 pub fn link_module(
     _module: &ModuleInfo,
     allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionBodyPtr>,
+    function_entries: &PrimaryMap<LocalFunctionIndex, FunctionBodyPtr>,
     jt_offsets: &PrimaryMap<LocalFunctionIndex, JumpTableOffsets>,
     function_relocations: Relocations,
     allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
@@ -78,14 +84,28 @@ pub fn link_module(
     for (i, section_relocs) in section_relocations.iter() {
         let body = *allocated_sections[i] as usize;
         for r in section_relocs {
-            apply_relocation(body, r, allocated_functions, jt_offsets, allocated_sections);
+            apply_relocation(
+                body,
+                r,
+                allocated_functions,
+                function_entries,
+                jt_offsets,
+                allocated_sections,
+            );
         }
     }
     for (i, function_relocs) in function_relocations.into_iter() {
         let fatptr: *const [VMFunctionBody] = allocated_functions[i].0;
         let body = fatptr as *const VMFunctionBody as usize;
         for r in function_relocs {
-            apply_relocation(body, r, allocated_functions, jt_offsets, allocated_sections);
+            apply_relocation(
+                body,
+                r,
+                allocated_functions,
+                function_entries,
+                jt_offsets,
+                allocated_sections,
+            );
         }
     }
 }
@@ -42,4 +42,8 @@ pub struct SerializableModule {
     pub compilation: SerializableCompilation,
     pub compile_info: CompileModuleInfo,
     pub data_initializers: Box<[OwnedDataInitializer]>,
+    // The triple and the CPU features of the target the module was
+    // compiled for, only used to summarize the artifact
+    pub target_triple: String,
+    pub cpu_features: Vec<String>,
 }
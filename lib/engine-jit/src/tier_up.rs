@@ -0,0 +1,696 @@
+//! Tiered compilation for the JIT engine.
+//!
+//! A tiered engine compiles every module with a fast baseline compiler
+//! first, and routes every call to a local function through a small
+//! entry stub. Each stub bumps a per-function entry counter and then
+//! jumps to the current body of the function.
+//!
+//! A background thread samples those counters periodically and
+//! recompiles the functions that became hot with an optimizing
+//! compiler. Once the optimized code is published, the stub of each
+//! hot function is atomically repointed to it, so subsequent calls
+//! (direct calls, `call_indirect` and calls through exports) run the
+//! optimized version, while calls already in flight finish in the
+//! baseline code.
+//!
+//! Loop iterations are counted too: before the baseline compilation,
+//! the module is rewritten so that every loop of a function starts by
+//! calling an empty function appended to the module for it, whose
+//! entry stub counts the iterations. A function is hot once its calls
+//! and its loop iterations together cross the threshold. There is no
+//! on-stack replacement though: a single long-running call (e.g. a
+//! `_start` spinning in a loop) makes its function hot, but keeps
+//! running the baseline code until it returns, and only the next calls
+//! run the optimized code.
+//!
+//! The baseline code, and thus the `ModuleInfo` of the artifact,
+//! includes those counting functions and calls, so the middlewares of
+//! the baseline compiler see them too (e.g. they are metered), and the
+//! module offsets reported for baseline frames are the ones of the
+//! rewritten module. The hot functions are recompiled from the original
+//! module, without the counting calls.
+//!
+//! The hot functions are recompiled with the `ModuleInfo` transformed
+//! by the middlewares of the baseline compiler, which the optimizing
+//! compiler must share so both tiers are instrumented the same way.
+//!
+//! Each round of recompilation compiles the whole module, with a
+//! trivial body for the functions that aren't hot, into code memory
+//! owned by the module and released with it. Since the functions
+//! optimized in earlier rounds keep running the code of their round,
+//! the number of rounds per module is bounded by
+//! `TierUpPolicy::max_rounds`: once it's reached, the functions that
+//! become hot stay in the baseline tier.
+//!
+//! Tiering needs the original Wasm module to recompile the hot
+//! functions, so only the modules compiled by the tiered engine itself
+//! are tiered up. Artifacts that are deserialized, or built with
+//! `JITArtifact::from_parts`, run their baseline code for their whole
+//! lifetime.
+//!
+//! Tiering is currently only available on x86-64. On other
+//! architectures the engine behaves as a regular `JITEngine` using the
+//! baseline compiler.
+
+use crate::artifact::LoopCounters;
+use crate::engine::allocate_in;
+use crate::link::link_module;
+use crate::CodeMemory;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+use wasmer_compiler::wasmparser::{
+    BinaryReaderError, ImportSectionEntryType, ModuleReader, Operator, SectionCode,
+};
+use wasmer_compiler::{
+    to_wasm_error, CompileError, CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment,
+    Target,
+};
+use wasmer_engine::{
+    register_frame_info, GlobalFrameInfoRegistration, SerializableFunctionFrameInfo,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::LocalFunctionIndex;
+use wasmer_vm::{FunctionBodyPtr, Mmap, VMFunctionBody};
+
+/// The policy deciding when a function is hot enough to be
+/// recompiled with the optimizing compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TierUpPolicy {
+    /// The number of calls after which a function is considered hot.
+    ///
+    /// Each iteration of the loops inside a function counts as a call.
+    pub threshold: u64,
+    /// How often the background thread samples the call counters.
+    pub sample_interval: Duration,
+    /// The maximum number of times the hot functions of a module are
+    /// recompiled.
+    ///
+    /// Each round allocates code memory for the whole module, which is
+    /// only released with the module.
+    pub max_rounds: usize,
+}
+
+impl Default for TierUpPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 1000,
+            sample_interval: Duration::from_millis(10),
+            max_rounds: 8,
+        }
+    }
+}
+
+/// Whether tiered compilation is supported on this architecture.
+pub(crate) const TIER_UP_SUPPORTED: bool = cfg!(target_arch = "x86_64");
+
+/// The body used for the functions that are not hot when a module is
+/// recompiled: no locals, `unreachable` and `end`. It is valid for any
+/// signature, and is never called since only the stubs of hot functions
+/// are repointed to the optimized code.
+const COLD_FUNCTION_BODY: &[u8] = &[0x00, 0x00, 0x0b];
+
+/// The body of the functions counting loop iterations: no locals and
+/// `end`. Only the calls to them, through their entry stubs, matter.
+const LOOP_COUNTER_BODY: &[u8] = &[0x00, 0x0b];
+
+/// The `call` opcode.
+const CALL_OPCODE: u8 = 0x10;
+
+/// The ids of the sections rewritten to count loop iterations.
+const TYPE_SECTION_ID: u8 = 1;
+const FUNCTION_SECTION_ID: u8 = 3;
+const CODE_SECTION_ID: u8 = 10;
+
+/// A Wasm module rewritten to count the iterations of its loops.
+///
+/// For each local function containing loops, a function with no
+/// parameters nor results is appended to the module, and every loop of
+/// the function starts by calling it. Appending the functions keeps
+/// the indices of all the existing functions and types.
+pub(crate) struct LoopCounting {
+    /// The rewritten module.
+    pub(crate) wasm: Vec<u8>,
+    /// The function counting the loop iterations of each function of
+    /// the original module, if it has loops.
+    pub(crate) counters: LoopCounters,
+}
+
+impl LoopCounting {
+    /// Rewrites the given module to count the iterations of its loops.
+    pub(crate) fn instrument(wasm: &[u8]) -> Result<Self, CompileError> {
+        Self::instrument_module(wasm).map_err(|e| CompileError::Wasm(to_wasm_error(e)))
+    }
+
+    fn instrument_module(wasm: &[u8]) -> Result<Self, BinaryReaderError> {
+        // The offsets of the loop bodies of each function, and the
+        // raw sections we need to rewrite.
+        let mut loops = PrimaryMap::<LocalFunctionIndex, Vec<usize>>::new();
+        let mut num_imported_functions = 0;
+        let mut num_types = 0;
+        let mut sections = vec![];
+
+        let mut reader = ModuleReader::new(wasm)?;
+        while !reader.eof() {
+            let start = reader.current_position();
+            let section = reader.read()?;
+            match section.code {
+                SectionCode::Type => {
+                    num_types = section.get_type_section_reader()?.get_count();
+                }
+                SectionCode::Import => {
+                    for import in section.get_import_section_reader()? {
+                        if let ImportSectionEntryType::Function(_) = import?.ty {
+                            num_imported_functions += 1;
+                        }
+                    }
+                }
+                SectionCode::Code => {
+                    for body in section.get_code_section_reader()? {
+                        let mut offsets = vec![];
+                        let mut operators = body?.get_operators_reader()?;
+                        let mut after_loop = false;
+                        while !operators.eof() {
+                            let (operator, offset) = operators.read_with_offset()?;
+                            if after_loop {
+                                offsets.push(offset);
+                            }
+                            after_loop = matches!(operator, Operator::Loop { .. });
+                        }
+                        loops.push(offsets);
+                    }
+                }
+                _ => {}
+            }
+            match section.code {
+                SectionCode::Type => sections.push((start, TYPE_SECTION_ID, section)),
+                SectionCode::Function => sections.push((start, FUNCTION_SECTION_ID, section)),
+                SectionCode::Code => sections.push((start, CODE_SECTION_ID, section)),
+                _ => {}
+            }
+        }
+
+        let mut counters = PrimaryMap::with_capacity(loops.len());
+        let mut num_counters = 0;
+        for offsets in loops.values() {
+            if offsets.is_empty() {
+                counters.push(None);
+            } else {
+                counters.push(Some(LocalFunctionIndex::new(loops.len() + num_counters)));
+                num_counters += 1;
+            }
+        }
+        if num_counters == 0 {
+            return Ok(Self {
+                wasm: wasm.to_vec(),
+                counters,
+            });
+        }
+
+        let mut instrumented = Vec::with_capacity(wasm.len() + wasm.len() / 8);
+        let mut copied = 0;
+        for (start, id, section) in sections {
+            let range = section.range();
+            instrumented.extend_from_slice(&wasm[copied..start]);
+            copied = range.end;
+
+            let mut contents = vec![];
+            match section.code {
+                SectionCode::Type => {
+                    let types = section.get_type_section_reader()?;
+                    leb128::write::unsigned(&mut contents, u64::from(num_types + 1)).unwrap();
+                    contents.extend_from_slice(&wasm[types.original_position()..range.end]);
+                    // func () -> ()
+                    contents.extend_from_slice(&[0x60, 0x00, 0x00]);
+                }
+                SectionCode::Function => {
+                    let functions = section.get_function_section_reader()?;
+                    leb128::write::unsigned(
+                        &mut contents,
+                        u64::from(functions.get_count() + num_counters as u32),
+                    )
+                    .unwrap();
+                    contents.extend_from_slice(&wasm[functions.original_position()..range.end]);
+                    for _ in 0..num_counters {
+                        leb128::write::unsigned(&mut contents, u64::from(num_types)).unwrap();
+                    }
+                }
+                SectionCode::Code => {
+                    let bodies = section.get_code_section_reader()?;
+                    leb128::write::unsigned(
+                        &mut contents,
+                        u64::from(bodies.get_count() + num_counters as u32),
+                    )
+                    .unwrap();
+                    for ((body, counter), offsets) in bodies
+                        .into_iter()
+                        .zip(counters.values())
+                        .zip(loops.values())
+                    {
+                        let body = body?.range();
+                        let mut instrumented_body = Vec::with_capacity(body.end - body.start);
+                        let mut body_copied = body.start;
+                        if let Some(counter) = counter {
+                            let mut call = vec![CALL_OPCODE];
+                            leb128::write::unsigned(
+                                &mut call,
+                                u64::from(num_imported_functions + counter.index() as u32),
+                            )
+                            .unwrap();
+                            for &offset in offsets {
+                                instrumented_body.extend_from_slice(&wasm[body_copied..offset]);
+                                instrumented_body.extend_from_slice(&call);
+                                body_copied = offset;
+                            }
+                        }
+                        instrumented_body.extend_from_slice(&wasm[body_copied..body.end]);
+                        leb128::write::unsigned(&mut contents, instrumented_body.len() as u64)
+                            .unwrap();
+                        contents.extend_from_slice(&instrumented_body);
+                    }
+                    for _ in 0..num_counters {
+                        leb128::write::unsigned(&mut contents, LOOP_COUNTER_BODY.len() as u64)
+                            .unwrap();
+                        contents.extend_from_slice(LOOP_COUNTER_BODY);
+                    }
+                }
+                _ => unreachable!(),
+            }
+            instrumented.push(id);
+            leb128::write::unsigned(&mut instrumented, contents.len() as u64).unwrap();
+            instrumented.extend_from_slice(&contents);
+        }
+        instrumented.extend_from_slice(&wasm[copied..]);
+
+        Ok(Self {
+            wasm: instrumented,
+            counters,
+        })
+    }
+}
+
+/// The size of each entry stub, in bytes.
+const STUB_SIZE: usize = 16;
+
+/// The size of the data slot backing each entry stub: the entry
+/// counter followed by the address of the current body.
+const SLOT_SIZE: usize = 16;
+
+/// Entry stubs for all the local functions of a module.
+///
+/// The stubs live in read-execute pages, followed by read-write pages
+/// holding one slot per stub. On x86-64 each stub is:
+///
+/// ```text
+/// inc qword ptr [rip + counter]
+/// jmp qword ptr [rip + target]
+/// ```
+///
+/// Repointing a function is a single aligned 8-byte store into its
+/// slot, so it is safe to do while other threads are executing the
+/// stubs.
+struct EntryStubs {
+    mmap: Mmap,
+    slots_offset: usize,
+    len: usize,
+}
+
+impl EntryStubs {
+    /// Creates the entry stubs, pointing each one to the given body.
+    fn new(bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyPtr>) -> Result<Self, String> {
+        if !TIER_UP_SUPPORTED {
+            return Err("tiered compilation is only supported on x86-64".to_string());
+        }
+
+        let page_size = region::page::size();
+        let len = bodies.len();
+        let slots_offset = round_up(len * STUB_SIZE, page_size);
+        let mut mmap = Mmap::with_at_least(slots_offset + len * SLOT_SIZE)?;
+
+        {
+            let (code, slots) = mmap.as_mut_slice().split_at_mut(slots_offset);
+            for (index, body) in bodies.iter() {
+                let stub_offset = index.index() * STUB_SIZE;
+                let slot_offset = slots_offset + index.index() * SLOT_SIZE;
+                let stub = &mut code[stub_offset..stub_offset + STUB_SIZE];
+
+                // inc qword ptr [rip + disp32]
+                let counter_disp = (slot_offset - (stub_offset + 7)) as u32;
+                stub[0..3].copy_from_slice(&[0x48, 0xff, 0x05]);
+                stub[3..7].copy_from_slice(&counter_disp.to_le_bytes());
+                // jmp qword ptr [rip + disp32]
+                let target_disp = (slot_offset + 8 - (stub_offset + 13)) as u32;
+                stub[7..9].copy_from_slice(&[0xff, 0x25]);
+                stub[9..13].copy_from_slice(&target_disp.to_le_bytes());
+                // int3 padding
+                stub[13..].copy_from_slice(&[0xcc; STUB_SIZE - 13]);
+
+                let slot = &mut slots[index.index() * SLOT_SIZE..(index.index() + 1) * SLOT_SIZE];
+                let target = **body as *const VMFunctionBody as u64;
+                slot[8..].copy_from_slice(&target.to_ne_bytes());
+            }
+        }
+
+        if len != 0 {
+            unsafe {
+                region::protect(
+                    mmap.as_mut_ptr(),
+                    slots_offset,
+                    region::Protection::READ_EXECUTE,
+                )
+            }
+            .map_err(|e| format!("unable to make the entry stubs executable: {}", e))?;
+        }
+
+        Ok(Self {
+            mmap,
+            slots_offset,
+            len,
+        })
+    }
+
+    /// The entry point of each function, to be used instead of the
+    /// function bodies for calls.
+    fn entries(&self) -> PrimaryMap<LocalFunctionIndex, FunctionBodyPtr> {
+        (0..self.len)
+            .map(|index| {
+                let stub = unsafe { self.mmap.as_ptr().add(index * STUB_SIZE) };
+                let stub = std::ptr::slice_from_raw_parts(stub as *const VMFunctionBody, STUB_SIZE);
+                FunctionBodyPtr(stub as *mut [VMFunctionBody])
+            })
+            .collect()
+    }
+
+    fn slot(&self, index: LocalFunctionIndex, offset: usize) -> &AtomicU64 {
+        assert!(index.index() < self.len);
+        unsafe {
+            &*(self
+                .mmap
+                .as_ptr()
+                .add(self.slots_offset + index.index() * SLOT_SIZE + offset)
+                as *const AtomicU64)
+        }
+    }
+
+    /// The number of times the function has been entered.
+    fn calls(&self, index: LocalFunctionIndex) -> u64 {
+        self.slot(index, 0).load(Ordering::Relaxed)
+    }
+
+    /// Make the stub of the function jump to another body.
+    fn retarget(&self, index: LocalFunctionIndex, body: FunctionBodyPtr) {
+        let target = *body as *const VMFunctionBody as u64;
+        self.slot(index, 8).store(target, Ordering::Release);
+    }
+}
+
+/// The tier a function is currently running in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tier {
+    Baseline,
+    Optimized,
+    /// The function counts the loop iterations of another function,
+    /// and is never recompiled.
+    LoopCounter,
+    /// The optimizing compiler failed, the function stays in the
+    /// baseline tier and won't be retried.
+    Failed,
+}
+
+/// The tiering state of a compiled module, shared between its
+/// `JITArtifact` and the tier-up thread.
+pub(crate) struct TierUpState {
+    /// The original module, without the loop counting calls.
+    wasm: Vec<u8>,
+    loop_counters: LoopCounters,
+    compile_info: CompileModuleInfo,
+    /// The target the optimized code is compiled for.
+    target: Target,
+    stubs: EntryStubs,
+    tiers: Mutex<PrimaryMap<LocalFunctionIndex, Tier>>,
+    rounds: AtomicUsize,
+    // The frame info of the optimized code is unregistered before the
+    // code memory holding it is released.
+    frame_info_registrations: Mutex<Vec<GlobalFrameInfoRegistration>>,
+    code_memory: Mutex<Vec<CodeMemory>>,
+}
+
+impl TierUpState {
+    /// Creates the tiering state for the given baseline bodies, compiled
+    /// from the original `wasm` module rewritten by `LoopCounting`.
+    pub(crate) fn new(
+        wasm: &[u8],
+        loop_counters: LoopCounters,
+        compile_info: &CompileModuleInfo,
+        target: &Target,
+        bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyPtr>,
+    ) -> Result<Self, String> {
+        let stubs = EntryStubs::new(bodies)?;
+        let mut tiers: PrimaryMap<LocalFunctionIndex, Tier> =
+            bodies.keys().map(|_| Tier::Baseline).collect();
+        for counter in loop_counters.values().flatten() {
+            tiers[*counter] = Tier::LoopCounter;
+        }
+        Ok(Self {
+            wasm: wasm.to_vec(),
+            loop_counters,
+            compile_info: CompileModuleInfo {
+                features: compile_info.features.clone(),
+                module: compile_info.module.clone(),
+                memory_styles: compile_info.memory_styles.clone(),
+                table_styles: compile_info.table_styles.clone(),
+            },
+            target: target.clone(),
+            stubs,
+            tiers: Mutex::new(tiers),
+            rounds: AtomicUsize::new(0),
+            frame_info_registrations: Mutex::new(vec![]),
+            code_memory: Mutex::new(vec![]),
+        })
+    }
+
+    /// The entry point of each function.
+    pub(crate) fn entries(&self) -> PrimaryMap<LocalFunctionIndex, FunctionBodyPtr> {
+        self.stubs.entries()
+    }
+
+    /// Returns whether the function is running optimized code.
+    pub(crate) fn is_optimized(&self, index: LocalFunctionIndex) -> bool {
+        self.tiers.lock().unwrap()[index] == Tier::Optimized
+    }
+
+    /// The number of calls and loop iterations of the function.
+    fn hotness(&self, index: LocalFunctionIndex) -> u64 {
+        let loop_iterations = match self.loop_counters[index] {
+            Some(counter) => self.stubs.calls(counter),
+            None => 0,
+        };
+        self.stubs.calls(index).saturating_add(loop_iterations)
+    }
+
+    /// The baseline functions that crossed the threshold.
+    fn hot_functions(&self, threshold: u64) -> Vec<LocalFunctionIndex> {
+        self.tiers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(index, tier)| **tier == Tier::Baseline && self.hotness(*index) >= threshold)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The number of times the module was recompiled, successfully or
+    /// not.
+    fn rounds(&self) -> usize {
+        self.rounds.load(Ordering::Relaxed)
+    }
+
+    /// Recompiles the hot functions with the optimizing compiler and
+    /// repoints their stubs to the optimized code.
+    fn tier_up(
+        &self,
+        compiler: &dyn Compiler,
+        hot: &[LocalFunctionIndex],
+    ) -> Result<(), CompileError> {
+        self.rounds.fetch_add(1, Ordering::Relaxed);
+        let translation = ModuleEnvironment::new()
+            .translate(&self.wasm)
+            .map_err(CompileError::Wasm)?;
+        // The loop counters were appended to the original module: they
+        // are compiled as cold functions too.
+        let num_local_functions = self.compile_info.module.functions.len()
+            - self.compile_info.module.num_imported_functions;
+        let function_body_inputs = translation
+            .function_body_inputs
+            .iter()
+            .map(|(index, body)| FunctionBodyData {
+                data: if hot.contains(&index) {
+                    body.data
+                } else {
+                    COLD_FUNCTION_BODY
+                },
+                module_offset: body.module_offset,
+            })
+            .chain(
+                (translation.function_body_inputs.len()..num_local_functions).map(|_| {
+                    FunctionBodyData {
+                        data: COLD_FUNCTION_BODY,
+                        module_offset: 0,
+                    }
+                }),
+            )
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+
+        let compilation = compiler.compile_module(
+            &self.target,
+            &self.compile_info,
+            translation.module_translation.as_ref().unwrap(),
+            function_body_inputs,
+        )?;
+        let custom_sections = compilation.get_custom_sections();
+        let frame_infos = compilation
+            .get_frame_info()
+            .values()
+            .map(|frame_info| SerializableFunctionFrameInfo::Processed(frame_info.clone()))
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+
+        let mut code_memory = CodeMemory::new();
+        let (functions, _, _, allocated_sections) = allocate_in(
+            &mut code_memory,
+            &compilation.get_function_bodies(),
+            &PrimaryMap::new(),
+            &PrimaryMap::new(),
+            &custom_sections,
+        )?;
+        link_module(
+            &self.compile_info.module,
+            &functions,
+            &self.stubs.entries(),
+            &compilation.get_jt_offsets(),
+            compilation.get_relocations(),
+            &allocated_sections,
+            &compilation.get_custom_section_relocations(),
+        );
+        let eh_frame = compilation.get_debug().map(|debug| unsafe {
+            std::slice::from_raw_parts(
+                *allocated_sections[debug.eh_frame],
+                custom_sections[debug.eh_frame].bytes.len(),
+            )
+        });
+        code_memory.publish();
+        code_memory
+            .unwind_registry_mut()
+            .publish(eh_frame)
+            .map_err(|e| {
+                CompileError::Resource(format!("Error while publishing the unwind code: {}", e))
+            })?;
+        self.code_memory.lock().unwrap().push(code_memory);
+        let functions = functions.into_boxed_slice();
+
+        // The frame info must be registered before any call can reach
+        // the optimized code, so traps happening there are recognized.
+        if let Some(registration) =
+            register_frame_info(self.compile_info.module.clone(), &functions, frame_infos)
+        {
+            self.frame_info_registrations
+                .lock()
+                .unwrap()
+                .push(registration);
+        }
+
+        let mut tiers = self.tiers.lock().unwrap();
+        for &index in hot {
+            self.stubs
+                .retarget(index, FunctionBodyPtr(functions[index].0));
+            tiers[index] = Tier::Optimized;
+        }
+        Ok(())
+    }
+
+    fn mark_failed(&self, functions: &[LocalFunctionIndex]) {
+        let mut tiers = self.tiers.lock().unwrap();
+        for &index in functions {
+            tiers[index] = Tier::Failed;
+        }
+    }
+}
+
+/// Checks that the optimizing compiler has the same middlewares as the
+/// baseline compiler, pushed as the same `Arc`s in the same order.
+pub(crate) fn check_tier_up_middlewares(
+    compiler: &dyn Compiler,
+    optimizing_compiler: &dyn Compiler,
+) -> Result<(), CompileError> {
+    let middlewares = compiler.get_middlewares();
+    let optimizing_middlewares = optimizing_compiler.get_middlewares();
+    if middlewares.len() == optimizing_middlewares.len()
+        && middlewares
+            .iter()
+            .zip(optimizing_middlewares)
+            .all(|(a, b)| Arc::ptr_eq(a, b))
+    {
+        Ok(())
+    } else {
+        Err(CompileError::Codegen(
+            "the optimizing compiler must have the same middlewares as the baseline compiler"
+                .to_string(),
+        ))
+    }
+}
+
+/// Spawns the thread that samples the registered modules and
+/// recompiles their hot functions with the optimizing `compiler`.
+///
+/// The thread stops once the returned `Sender` (owned by the engine)
+/// is dropped.
+///
+/// Fails if tiered compilation isn't supported on this architecture,
+/// or if the thread can't be spawned.
+pub(crate) fn spawn_tier_up_thread(
+    compiler: Box<dyn Compiler + Send>,
+    policy: TierUpPolicy,
+) -> Result<Sender<Weak<TierUpState>>, CompileError> {
+    if !TIER_UP_SUPPORTED {
+        return Err(CompileError::Codegen(
+            "tiered compilation is only supported on x86-64".to_string(),
+        ));
+    }
+
+    let (sender, receiver) = channel::<Weak<TierUpState>>();
+    thread::Builder::new()
+        .name("wasmer-tier-up".to_string())
+        .spawn(move || {
+            let mut modules = vec![];
+            loop {
+                match receiver.recv_timeout(policy.sample_interval) {
+                    Ok(module) => modules.push(module),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                modules.retain(|module: &Weak<TierUpState>| module.strong_count() > 0);
+                for module in modules.iter().filter_map(Weak::upgrade) {
+                    if module.rounds() >= policy.max_rounds {
+                        continue;
+                    }
+                    let hot = module.hot_functions(policy.threshold);
+                    if hot.is_empty() {
+                        continue;
+                    }
+                    if module.tier_up(&*compiler, &hot).is_err() {
+                        module.mark_failed(&hot);
+                    }
+                }
+            }
+        })
+        .map_err(|e| {
+            CompileError::Resource(format!("unable to spawn the tier-up thread: {}", e))
+        })?;
+    Ok(sender)
+}
+
+fn round_up(size: usize, multiple: usize) -> usize {
+    debug_assert!(multiple.is_power_of_two());
+    (size + (multiple - 1)) & !(multiple - 1)
+}
@@ -4,6 +4,7 @@
 use crate::engine::{NativeEngine, NativeEngineInner};
 use crate::serialize::ModuleMetadata;
 use libloading::{Library, Symbol as LibrarySymbol};
+use object::{BinaryFormat, Object, ObjectSection, Symbol as ObjectSymbol};
 use std::error::Error;
 use std::fs::File;
 use std::io::{Read, Write};
@@ -17,14 +18,17 @@ use tracing::trace;
 use wasmer_compiler::{CompileError, Features, OperatingSystem, Symbol, SymbolRegistry, Triple};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::{
-    CompileModuleInfo, FunctionBodyData, ModuleEnvironment, ModuleTranslationState,
+    CompileModuleInfo, Compiler, FunctionBodyData, GenerateMiddlewareChain, ModuleEnvironment,
+    ModuleTranslationState,
+};
+use wasmer_engine::{
+    Artifact, ArtifactSummary, DeserializeError, InstantiationError, SerializeError,
 };
-use wasmer_engine::{Artifact, DeserializeError, InstantiationError, SerializeError};
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, Tunables};
 #[cfg(feature = "compiler")]
 use wasmer_object::{emit_compilation, emit_data, get_object_for_target};
-use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 #[cfg(feature = "compiler")]
 use wasmer_types::DataInitializer;
 use wasmer_types::{
@@ -98,6 +102,7 @@ impl NativeArtifact {
     fn generate_metadata<'data>(
         data: &'data [u8],
         features: &Features,
+        compiler: &dyn Compiler,
         tunables: &dyn Tunables,
     ) -> Result<
         (
@@ -109,7 +114,12 @@ impl NativeArtifact {
         CompileError,
     > {
         let environ = ModuleEnvironment::new();
-        let translation = environ.translate(data).map_err(CompileError::Wasm)?;
+        let mut translation = environ.translate(data).map_err(CompileError::Wasm)?;
+
+        // We try to apply the middleware first
+        let middlewares = compiler.get_middlewares();
+        middlewares.apply_on_module_info(&mut translation.module)?;
+
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = translation
             .module
             .memories
@@ -148,7 +158,7 @@ impl NativeArtifact {
         let target = engine.target();
         let compiler = engine_inner.compiler()?;
         let (compile_info, function_body_inputs, data_initializers, module_translation) =
-            Self::generate_metadata(data, engine_inner.features(), tunables)?;
+            Self::generate_metadata(data, engine_inner.features(), compiler, tunables)?;
 
         let data_initializers = data_initializers
             .iter()
@@ -537,6 +547,115 @@ impl NativeArtifact {
         Self::from_parts(&mut engine_inner, metadata, shared_path, lib)
             .map_err(DeserializeError::Compiler)
     }
+
+    /// Summarize a shared object produced by the native engine, or an
+    /// object file produced by the object-file engine, without loading it.
+    ///
+    /// Unlike [`NativeArtifact::deserialize`], this reads artifacts
+    /// compiled for any target.
+    pub fn summarize(bytes: &[u8]) -> Result<ArtifactSummary, DeserializeError> {
+        let file = object::File::parse(bytes).map_err(|e| {
+            DeserializeError::Incompatible(format!(
+                "The provided bytes are not in any native format Wasmer can understand: {}",
+                e
+            ))
+        })?;
+        // Mach-O prefixes the names of the symbols with an underscore.
+        let symbol_name = |name: &'_ str| {
+            if file.format() == BinaryFormat::MachO {
+                name.strip_prefix('_').unwrap_or(name).to_string()
+            } else {
+                name.to_string()
+            }
+        };
+        let symbol_bytes = |symbol: &ObjectSymbol, offset: u64, size: u64| {
+            file.section_by_index(symbol.section_index()?)
+                .ok()?
+                .data_range(symbol.address() + offset, size)
+                .ok()?
+        };
+
+        let metadata_symbol = file
+            .symbols()
+            .map(|(_, symbol)| symbol)
+            .find(|symbol| {
+                symbol.name().map(symbol_name).as_deref()
+                    == std::str::from_utf8(WASMER_METADATA_SYMBOL).ok()
+            })
+            .ok_or_else(|| {
+                DeserializeError::CorruptedBinary(
+                    "The provided object file doesn't seem to be generated by Wasmer".to_string(),
+                )
+            })?;
+        let mut readable = symbol_bytes(&metadata_symbol, 0, 10).ok_or_else(|| {
+            DeserializeError::CorruptedBinary("Can't read metadata size".to_string())
+        })?;
+        let metadata_len = leb128::read::unsigned(&mut readable).map_err(|_e| {
+            DeserializeError::CorruptedBinary("Can't read metadata size".to_string())
+        })?;
+        let metadata_slice = symbol_bytes(&metadata_symbol, 10, metadata_len)
+            .ok_or_else(|| DeserializeError::CorruptedBinary("Can't read metadata".to_string()))?;
+        let metadata: ModuleMetadata = bincode::deserialize(metadata_slice)
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+
+        let mut function_sizes = metadata.function_body_lengths.clone();
+        let mut function_call_trampolines = 0;
+        let mut dynamic_function_trampolines = 0;
+        let mut custom_sections = Vec::new();
+        for (_, symbol) in file.symbols() {
+            if symbol.is_undefined() {
+                continue;
+            }
+            let name = match symbol.name() {
+                Some(name) => symbol_name(name),
+                None => continue,
+            };
+            match metadata.name_to_symbol(&name) {
+                Some(Symbol::LocalFunction(index)) => {
+                    if let Some(size) = function_sizes.get_mut(index) {
+                        *size = symbol.size();
+                    }
+                }
+                Some(Symbol::FunctionCallTrampoline(_)) => function_call_trampolines += 1,
+                Some(Symbol::DynamicFunctionTrampoline(_)) => dynamic_function_trampolines += 1,
+                Some(Symbol::Section(index)) => {
+                    custom_sections.push((format!("section {}", index.index()), symbol.size()))
+                }
+                None => {}
+            }
+        }
+        custom_sections.sort();
+
+        let is_object_file = match file.format() {
+            BinaryFormat::Elf => file.segments().next().is_none(),
+            // `MH_OBJECT` file type.
+            BinaryFormat::MachO => bytes.get(12..16) == Some(&1u32.to_le_bytes()[..]),
+            BinaryFormat::Coff => true,
+            _ => false,
+        };
+        Ok(ArtifactSummary {
+            engine: if is_object_file {
+                "object-file"
+            } else {
+                "native"
+            },
+            target: format!("{:?} {:?}", file.architecture(), file.format()),
+            compile_info: metadata.compile_info,
+            function_sizes,
+            function_call_trampolines,
+            dynamic_function_trampolines,
+            relocations: file
+                .sections()
+                .map(|section| section.relocations().count())
+                .sum(),
+            custom_sections,
+            // Frame infos are not yet emitted by the native engine.
+            frame_info: false,
+            unwind_info: file.section_by_name(".eh_frame").is_some()
+                || file.section_by_name("__eh_frame").is_some(),
+            data_initializers: metadata.data_initializers.len(),
+        })
+    }
 }
 
 impl Artifact for NativeArtifact {
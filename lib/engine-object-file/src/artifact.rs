@@ -10,7 +10,8 @@ use std::sync::Arc;
 use wasmer_compiler::{CompileError, Features, OperatingSystem, SymbolRegistry, Triple};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::{
-    CompileModuleInfo, FunctionBodyData, ModuleEnvironment, ModuleTranslationState,
+    CompileModuleInfo, Compiler, FunctionBodyData, GenerateMiddlewareChain, ModuleEnvironment,
+    ModuleTranslationState,
 };
 use wasmer_engine::{Artifact, DeserializeError, InstantiationError, SerializeError};
 #[cfg(feature = "compiler")]
@@ -93,6 +94,7 @@ impl ObjectFileArtifact {
     fn generate_metadata<'data>(
         data: &'data [u8],
         features: &Features,
+        compiler: &dyn Compiler,
         tunables: &dyn Tunables,
     ) -> Result<
         (
@@ -104,7 +106,12 @@ impl ObjectFileArtifact {
         CompileError,
     > {
         let environ = ModuleEnvironment::new();
-        let translation = environ.translate(data).map_err(CompileError::Wasm)?;
+        let mut translation = environ.translate(data).map_err(CompileError::Wasm)?;
+
+        // We try to apply the middleware first
+        let middlewares = compiler.get_middlewares();
+        middlewares.apply_on_module_info(&mut translation.module)?;
+
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = translation
             .module
             .memories
@@ -144,7 +151,7 @@ impl ObjectFileArtifact {
         let target = engine.target();
         let compiler = engine_inner.compiler()?;
         let (compile_info, function_body_inputs, data_initializers, module_translation) =
-            Self::generate_metadata(data, engine_inner.features(), tunables)?;
+            Self::generate_metadata(data, engine_inner.features(), compiler, tunables)?;
 
         let data_initializers = data_initializers
             .iter()
@@ -159,6 +159,10 @@ impl Engine for ObjectFileEngine {
         &self.engine_id
     }
 
+    fn shared_id(&self) -> usize {
+        &*self.inner as *const _ as usize
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
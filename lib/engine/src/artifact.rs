@@ -1,12 +1,14 @@
+use crate::resolver::is_compatible_memory_style;
 use crate::{
-    resolve_imports, InstantiationError, Resolver, RuntimeError, SerializeError, Tunables,
+    resolve_imports, InstantiationError, LinkError, Resolver, RuntimeError, SerializeError,
+    Tunables,
 };
 use std::any::Any;
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
-use wasmer_compiler::Features;
-use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_compiler::{Compilation, Features};
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataInitializer, FunctionIndex, LocalFunctionIndex, MemoryIndex, OwnedDataInitializer,
     SignatureIndex, TableIndex,
@@ -66,6 +68,16 @@ pub trait Artifact: Send + Sync + Upcastable {
     /// Returns the associated VM signatures for this `Artifact`.
     fn signatures(&self) -> &BoxedSlice<SignatureIndex, VMSharedSignatureIndex>;
 
+    /// Returns the compiled functions of this artifact, so they can be
+    /// inspected (for example, disassembled).
+    ///
+    /// By default, it's `None`: the artifacts that don't keep their
+    /// compiled functions, like the ones loading them from a shared
+    /// object, can't return them.
+    fn compilation(&self) -> Option<Compilation> {
+        None
+    }
+
     /// Serializes an artifact into bytes
     fn serialize(&self) -> Result<Vec<u8>, SerializeError>;
 
@@ -105,8 +117,22 @@ pub trait Artifact: Send + Sync + Upcastable {
         .map_err(InstantiationError::Link)?;
         let finished_memories = tunables
             .create_memories(&module, self.memory_styles())
-            .map_err(InstantiationError::Link)?
-            .into_boxed_slice();
+            .map_err(InstantiationError::Link)?;
+        // Sanity-check: Ensure that the memories created by the tunables have
+        // at least the guard-page protections the compiled code expects.
+        for (index, memory) in finished_memories.iter() {
+            let memory_index = module.memory_index(index);
+            let compiled_style = &self.memory_styles()[memory_index];
+            if !is_compatible_memory_style(compiled_style, memory.style()) {
+                return Err(InstantiationError::Link(LinkError::Resource(format!(
+                    "Memory {} has the style {:?}, incompatible with the style {:?} it was compiled for",
+                    memory_index.index(),
+                    memory.style(),
+                    compiled_style
+                ))));
+            }
+        }
+        let finished_memories = finished_memories.into_boxed_slice();
         let finished_tables = tunables
             .create_tables(&module, self.table_styles())
             .map_err(InstantiationError::Link)?
@@ -132,6 +158,32 @@ pub trait Artifact: Send + Sync + Upcastable {
         .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))
     }
 
+    /// Create a new instance of this artifact in the slot of an
+    /// `InstanceHandle` previously created by [`Artifact::instantiate`] and
+    /// released with [`InstanceHandle::reset`], reusing its memories, tables
+    /// and `VMContext`.
+    ///
+    /// # Safety
+    ///
+    /// See [`InstanceHandle::reuse`].
+    unsafe fn reinstantiate(
+        &self,
+        handle: &InstanceHandle,
+        resolver: &dyn Resolver,
+    ) -> Result<(), InstantiationError> {
+        let module = self.module();
+        let imports = resolve_imports(
+            &module,
+            resolver,
+            self.finished_dynamic_function_trampolines(),
+            self.memory_styles(),
+            self.table_styles(),
+        )
+        .map_err(InstantiationError::Link)?;
+        handle.reuse(imports);
+        Ok(())
+    }
+
     /// Finishes the instantiation of a just created `InstanceHandle`.
     ///
     /// # Safety
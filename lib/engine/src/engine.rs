@@ -62,6 +62,18 @@ pub trait Engine {
     /// of trait representation.
     fn id(&self) -> &EngineId;
 
+    /// An identifier shared by this engine and all its clones.
+    ///
+    /// Unlike `id`, it allows to check whether two engines share the
+    /// same compiled code and signature registry, in which case the
+    /// artifacts of one can be used with the other.
+    ///
+    /// By default, it's the `id` of this engine, which is never shared,
+    /// so the artifacts of an engine are only used by the engine itself.
+    fn shared_id(&self) -> usize {
+        self.id().id
+    }
+
     /// Clone the engine
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync>;
 }
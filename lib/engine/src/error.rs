@@ -4,6 +4,7 @@ use std::io;
 use thiserror::Error;
 use wasmer_compiler::CompileError;
 use wasmer_types::ExternType;
+use wasmer_vm::MemoryStyle;
 
 /// The Serialize error can occur when serializing a
 /// compiled Module into a binary.
@@ -54,6 +55,12 @@ pub enum ImportError {
     /// This error occurs when an import was expected but not provided.
     #[error("unknown import. Expected {0:?}")]
     UnknownImport(ExternType),
+
+    /// Incompatible Memory Style.
+    /// This error occurs when an imported memory doesn't provide the
+    /// guarantees the code compiled for the importing module relies on.
+    #[error("incompatible memory style. Expected {0:?} but received {1:?}")]
+    IncompatibleMemoryStyle(MemoryStyle, MemoryStyle),
 }
 
 /// The WebAssembly.LinkError object indicates an error during
@@ -25,6 +25,7 @@ mod engine;
 mod error;
 mod resolver;
 mod serialize;
+mod summary;
 mod trap;
 mod tunables;
 
@@ -38,6 +39,7 @@ pub use crate::resolver::{
     Resolver,
 };
 pub use crate::serialize::SerializableFunctionFrameInfo;
+pub use crate::summary::ArtifactSummary;
 pub use crate::trap::*;
 pub use crate::tunables::Tunables;
 
@@ -2,7 +2,6 @@
 //! references.
 
 use crate::{ImportError, LinkError};
-use more_asserts::assert_ge;
 use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{ExternType, FunctionIndex, ImportIndex, MemoryIndex, TableIndex};
 
@@ -125,6 +124,7 @@ pub fn resolve_imports(
     _table_styles: &PrimaryMap<TableIndex, TableStyle>,
 ) -> Result<Imports, LinkError> {
     let mut function_imports = PrimaryMap::with_capacity(module.num_imported_functions);
+    let mut function_envs = PrimaryMap::with_capacity(module.num_imported_functions);
     let mut table_imports = PrimaryMap::with_capacity(module.num_imported_tables);
     let mut memory_imports = PrimaryMap::with_capacity(module.num_imported_memories);
     let mut global_imports = PrimaryMap::with_capacity(module.num_imported_globals);
@@ -169,6 +169,7 @@ pub fn resolve_imports(
                     body: address,
                     vmctx: f.vmctx,
                 });
+                function_envs.push(f.host_env.clone());
             }
             Export::Table(ref t) => {
                 table_imports.push(VMTableImport {
@@ -183,20 +184,16 @@ pub fn resolve_imports(
                         // guard-page protections the importing module expects it to have.
                         let export_memory_style = m.style();
                         let import_memory_style = &memory_styles[*index];
-                        if let (
-                            MemoryStyle::Static { bound, .. },
-                            MemoryStyle::Static {
-                                bound: import_bound,
-                                ..
-                            },
-                        ) = (export_memory_style.clone(), &import_memory_style)
-                        {
-                            assert_ge!(bound, *import_bound);
+                        if !is_compatible_memory_style(import_memory_style, export_memory_style) {
+                            return Err(LinkError::Import(
+                                module_name.to_string(),
+                                field.to_string(),
+                                ImportError::IncompatibleMemoryStyle(
+                                    import_memory_style.clone(),
+                                    export_memory_style.clone(),
+                                ),
+                            ));
                         }
-                        assert_ge!(
-                            export_memory_style.offset_guard_size(),
-                            import_memory_style.offset_guard_size()
-                        );
                     }
                     _ => {
                         // This should never be reached, as we did compatibility
@@ -222,6 +219,7 @@ pub fn resolve_imports(
 
     Ok(Imports::new(
         function_imports,
+        function_envs,
         table_imports,
         memory_imports,
         global_imports,
@@ -314,3 +312,21 @@ where
         }
     }
 }
+
+/// Checks that a memory with the `export` style can be used by code
+/// compiled for a memory with the `import` style.
+pub(crate) fn is_compatible_memory_style(import: &MemoryStyle, export: &MemoryStyle) -> bool {
+    let bound_is_compatible = match (import, export) {
+        (
+            MemoryStyle::Static { bound, .. },
+            MemoryStyle::Static {
+                bound: export_bound,
+                ..
+            },
+        ) => export_bound >= bound,
+        // The code compiled for a static memory doesn't check bounds.
+        (MemoryStyle::Static { .. }, MemoryStyle::Dynamic { .. }) => false,
+        (MemoryStyle::Dynamic { .. }, _) => true,
+    };
+    bound_is_compatible && export.offset_guard_size() >= import.offset_guard_size()
+}
@@ -0,0 +1,39 @@
+use wasmer_compiler::CompileModuleInfo;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::LocalFunctionIndex;
+
+/// A summary of a serialized [`Artifact`], read without loading nor
+/// linking its code.
+///
+/// It helps debugging artifacts which can't be loaded, for instance
+/// because they were compiled for another target or with other features.
+///
+/// [`Artifact`]: crate::Artifact
+#[derive(Debug)]
+pub struct ArtifactSummary {
+    /// The name of the engine which produced the artifact.
+    pub engine: &'static str,
+    /// The target of the code: its triple and CPU features for JIT
+    /// artifacts, its architecture and binary format for native ones.
+    pub target: String,
+    /// The information the module was compiled with.
+    pub compile_info: CompileModuleInfo,
+    /// The size in bytes of the code of each local function.
+    pub function_sizes: PrimaryMap<LocalFunctionIndex, u64>,
+    /// The number of function call trampolines.
+    pub function_call_trampolines: usize,
+    /// The number of dynamic function trampolines.
+    pub dynamic_function_trampolines: usize,
+    /// The number of relocations of the code.
+    pub relocations: usize,
+    /// The name and the size in bytes of the custom sections emitted
+    /// by the compiler.
+    pub custom_sections: Vec<(String, u64)>,
+    /// Whether the artifact holds the frame information used to
+    /// symbolicate traps.
+    pub frame_info: bool,
+    /// Whether the artifact holds unwind information (`.eh_frame`).
+    pub unwind_info: bool,
+    /// The number of data initializers.
+    pub data_initializers: usize,
+}
@@ -207,6 +207,15 @@ impl RuntimeError {
         }
     }
 
+    /// Attempts to get a reference to the concrete type of the
+    /// `RuntimeError`.
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        match &self.inner.source {
+            RuntimeErrorSource::User(err) => err.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
     /// Returns true if the `RuntimeError` is the same as T
     pub fn is<T: Error + 'static>(&self) -> bool {
         match &self.inner.source {
@@ -214,6 +223,12 @@ impl RuntimeError {
             _ => false,
         }
     }
+
+    /// Returns whether or not these two errors are the same error, such
+    /// as an error and its clone.
+    pub fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
 }
 
 impl fmt::Debug for RuntimeError {
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     GlobalType, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType,
-    TableIndex, TableType,
+    Pages, TableIndex, TableType, Type,
 };
 use wasmer_vm::MemoryError;
 use wasmer_vm::{Global, Memory, ModuleInfo, Table};
@@ -18,6 +18,35 @@ pub trait Tunables {
     /// Construct a `TableStyle` for the provided `TableType`
     fn table_style(&self, table: &TableType) -> TableStyle;
 
+    /// Returns a fingerprint of the tunables, which changes whenever the
+    /// styles they choose may change.
+    ///
+    /// It's used to key the caches of compiled modules. By default, it
+    /// describes the styles chosen for a memory without a maximum, a
+    /// memory with a maximum of one page and a table: tunables choosing
+    /// their styles from anything else should override it.
+    fn fingerprint(&self) -> String {
+        let memory_style =
+            |maximum| match self.memory_style(&MemoryType::new(Pages(1), maximum, false)) {
+                MemoryStyle::Dynamic { offset_guard_size } => {
+                    format!("dynamic(guard: {})", offset_guard_size)
+                }
+                MemoryStyle::Static {
+                    bound,
+                    offset_guard_size,
+                } => format!("static(bound: {}, guard: {})", bound.0, offset_guard_size),
+            };
+        let table_style = match self.table_style(&TableType::new(Type::FuncRef, 1, None)) {
+            TableStyle::CallerChecksSignature => "caller-checks-signature",
+        };
+        format!(
+            "memories: {} {}, tables: {}",
+            memory_style(None),
+            memory_style(Some(Pages(1))),
+            table_style
+        )
+    }
+
     /// Create a memory given a memory type
     fn create_memory(
         &self,
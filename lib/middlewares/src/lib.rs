@@ -0,0 +1,9 @@
+//! The `wasmer-middlewares` crate is a collection of middlewares
+//! that can be pushed to the configuration of any Wasmer compiler.
+
+#![deny(missing_docs, trivial_numeric_casts, unused_extern_crates)]
+#![warn(unused_import_braces)]
+
+pub mod metering;
+
+pub use crate::metering::Metering;
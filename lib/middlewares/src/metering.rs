@@ -0,0 +1,520 @@
+//! `metering` is a middleware for tracking how many operators are
+//! executed in total and putting a limit on the total number of
+//! operators executed.
+
+use std::fmt;
+use std::sync::Arc;
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    CompileError, ExportError, FunctionMiddleware, FunctionMiddlewareGenerator, GlobalInit,
+    GlobalType, Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, Mutability,
+    Type,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{ExportIndex, GlobalIndex};
+use wasmer_vm::ModuleInfo;
+
+/// The name of the exported global holding the remaining points.
+pub const REMAINING_POINTS_EXPORT: &str = "wasmer_metering_remaining_points";
+
+/// The name of the exported global set to 1 once the points are exhausted.
+pub const POINTS_EXHAUSTED_EXPORT: &str = "wasmer_metering_points_exhausted";
+
+#[derive(Clone, Copy)]
+struct MeteringGlobalIndexes {
+    remaining_points: GlobalIndex,
+    points_exhausted: GlobalIndex,
+}
+
+/// The module-level metering middleware.
+///
+/// A `Metering` instance is meant to be pushed to the configuration
+/// of a compiler. The globals holding the points are declared in each
+/// module it compiles, and exported under [`REMAINING_POINTS_EXPORT`]
+/// and [`POINTS_EXHAUSTED_EXPORT`].
+pub struct Metering<F: Fn(&Operator) -> u64 + Send + Sync> {
+    /// Initial limit of points.
+    initial_limit: u64,
+
+    /// Function that maps each operator to a cost in "points".
+    cost_function: Arc<F>,
+
+    /// Identifies the cost function in the keys of cached modules.
+    cache_key: Option<String>,
+}
+
+/// The function-level metering middleware.
+pub struct FunctionMetering<F: Fn(&Operator) -> u64 + Send + Sync> {
+    /// Function that maps each operator to a cost in "points".
+    cost_function: Arc<F>,
+
+    /// The global indexes of the metering points.
+    global_indexes: MeteringGlobalIndexes,
+
+    /// Accumulated cost of the current basic block.
+    accumulated_cost: u64,
+}
+
+/// The points of an instance compiled with a `Metering` middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeteringPoints {
+    /// The instance has this many points left.
+    Remaining(u64),
+
+    /// The instance ran out of points, and its execution was trapped
+    /// before the operators exceeding the limit ran.
+    Exhausted,
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> Metering<F> {
+    /// Creates a `Metering` middleware, granting `initial_limit`
+    /// points to every instance of the module.
+    pub fn new(initial_limit: u64, cost_function: F) -> Self {
+        Self {
+            initial_limit,
+            cost_function: Arc::new(cost_function),
+            cache_key: None,
+        }
+    }
+
+    /// Sets the key identifying the cost function in the caches of
+    /// compiled modules, which must change whenever the cost function
+    /// changes.
+    ///
+    /// Without it, the modules compiled with this middleware aren't
+    /// cached, since functions can't be compared.
+    pub fn with_cache_key(mut self, cache_key: &str) -> Self {
+        self.cache_key = Some(cache_key.to_string());
+        self
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metering")
+            .field("initial_limit", &self.initial_limit)
+            .field("cost_function", &"<function>")
+            .field("cache_key", &self.cache_key)
+            .finish()
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> FunctionMiddlewareGenerator for Metering<F> {
+    fn generate_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        _: LocalFunctionIndex,
+    ) -> Result<Box<dyn FunctionMiddleware>, CompileError> {
+        let global_index = |name| {
+            match module_info.exports.get(name) {
+            Some(ExportIndex::Global(index)) => Ok(*index),
+            _ => Err(CompileError::Codegen(format!(
+                "the metering global `{}` isn't declared, the module info must be transformed before compiling functions",
+                name
+            ))),
+        }
+        };
+
+        Ok(Box::new(FunctionMetering {
+            cost_function: self.cost_function.clone(),
+            global_indexes: MeteringGlobalIndexes {
+                remaining_points: global_index(REMAINING_POINTS_EXPORT)?,
+                points_exhausted: global_index(POINTS_EXHAUSTED_EXPORT)?,
+            },
+            accumulated_cost: 0,
+        }))
+    }
+
+    fn fingerprint(&self) -> Option<String> {
+        self.cache_key.as_ref().map(|cache_key| {
+            format!(
+                "metering initial_limit={} cost_function={}",
+                self.initial_limit, cache_key
+            )
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) -> Result<(), CompileError> {
+        for name in &[REMAINING_POINTS_EXPORT, POINTS_EXHAUSTED_EXPORT] {
+            if module_info.exports.contains_key(*name) {
+                return Err(CompileError::Codegen(format!(
+                    "the module already exports `{}`, which is reserved by the metering middleware",
+                    name
+                )));
+            }
+        }
+
+        // Append a global for the remaining points and initialize it.
+        let remaining_points = module_info
+            .globals
+            .push(GlobalType::new(Type::I64, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I64Const(self.initial_limit as i64));
+        module_info.exports.insert(
+            REMAINING_POINTS_EXPORT.to_string(),
+            ExportIndex::Global(remaining_points),
+        );
+
+        // Append a global for the exhausted points boolean and initialize it.
+        let points_exhausted = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+        module_info.exports.insert(
+            POINTS_EXHAUSTED_EXPORT.to_string(),
+            ExportIndex::Global(points_exhausted),
+        );
+
+        Ok(())
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for FunctionMetering<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionMetering")
+            .field("cost_function", &"<function>")
+            .field("accumulated_cost", &self.accumulated_cost)
+            .finish()
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMetering<F> {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // The cost of an operator is charged before the basic block it
+        // belongs to is left, so that the points are checked once per
+        // block instead of once per operator. The cost is compared to
+        // and subtracted from the remaining points as an `i64`, it
+        // can't exceed `i64::MAX`.
+        self.accumulated_cost = self
+            .accumulated_cost
+            .checked_add((self.cost_function)(&operator))
+            .filter(|cost| *cost <= i64::MAX as u64)
+            .ok_or_else(|| {
+                MiddlewareError::new(
+                    "metering",
+                    format!(
+                        "the cost of a basic block exceeds {} points, at `{:?}`",
+                        i64::MAX,
+                        operator
+                    ),
+                )
+            })?;
+
+        match operator {
+            Operator::Loop { .. }
+            | Operator::End
+            | Operator::Else
+            | Operator::Br { .. }
+            | Operator::BrIf { .. }
+            | Operator::BrTable { .. }
+            | Operator::Unreachable
+            | Operator::Return
+            | Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+                if self.accumulated_cost > 0 =>
+            {
+                self.charge(state);
+            }
+            _ => {}
+        }
+
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMetering<F> {
+    /// Emits the check of the accumulated cost against the remaining
+    /// points, and its subtraction.
+    fn charge<'a>(&mut self, state: &mut MiddlewareReaderState<'a>) {
+        let remaining_points = self.global_indexes.remaining_points.index() as u32;
+        let points_exhausted = self.global_indexes.points_exhausted.index() as u32;
+        let cost = self.accumulated_cost as i64;
+        self.accumulated_cost = 0;
+
+        for operator in vec![
+            // if remaining_points < cost { points_exhausted = 1; throw(); }
+            Operator::GlobalGet {
+                global_index: remaining_points,
+            },
+            Operator::I64Const { value: cost },
+            Operator::I64LtU,
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+            Operator::I32Const { value: 1 },
+            Operator::GlobalSet {
+                global_index: points_exhausted,
+            },
+            Operator::Unreachable,
+            Operator::End,
+            // remaining_points -= cost;
+            Operator::GlobalGet {
+                global_index: remaining_points,
+            },
+            Operator::I64Const { value: cost },
+            Operator::I64Sub,
+            Operator::GlobalSet {
+                global_index: remaining_points,
+            },
+        ] {
+            state.push_operator(operator);
+        }
+    }
+}
+
+/// Returns the points of an `Instance` compiled with a `Metering`
+/// middleware.
+///
+/// ## Errors
+///
+/// Returns an [`ExportError`] if the instance wasn't compiled with a
+/// `Metering` middleware.
+pub fn get_remaining_points(instance: &Instance) -> Result<MeteringPoints, ExportError> {
+    let exhausted = instance
+        .exports
+        .get_global(POINTS_EXHAUSTED_EXPORT)?
+        .get()
+        .i32()
+        .ok_or(ExportError::IncompatibleType)?;
+
+    if exhausted > 0 {
+        return Ok(MeteringPoints::Exhausted);
+    }
+
+    let points = instance
+        .exports
+        .get_global(REMAINING_POINTS_EXPORT)?
+        .get()
+        .i64()
+        .ok_or(ExportError::IncompatibleType)?;
+
+    Ok(MeteringPoints::Remaining(points as u64))
+}
+
+/// Sets the remaining points of an `Instance` compiled with a
+/// `Metering` middleware, which also resets its exhausted state.
+///
+/// ## Errors
+///
+/// Returns an [`ExportError`] if the instance wasn't compiled with a
+/// `Metering` middleware.
+pub fn set_remaining_points(instance: &Instance, points: u64) -> Result<(), ExportError> {
+    let remaining_points = instance.exports.get_global(REMAINING_POINTS_EXPORT)?;
+    let points_exhausted = instance.exports.get_global(POINTS_EXHAUSTED_EXPORT)?;
+
+    remaining_points
+        .set((points as i64).into())
+        .map_err(|_| ExportError::IncompatibleType)?;
+    points_exhausted
+        .set(0i32.into())
+        .map_err(|_| ExportError::IncompatibleType)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, JIT};
+
+    fn cost_function(operator: &Operator) -> u64 {
+        match operator {
+            Operator::LocalGet { .. } | Operator::I32Const { .. } => 1,
+            Operator::I32Add { .. } => 2,
+            _ => 0,
+        }
+    }
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (type $add_t (func (param i32) (result i32)))
+            (func $add_one_f (type $add_t) (param $value i32) (result i32)
+                local.get $value
+                i32.const 1
+                i32.add)
+            (export "add_one" (func $add_one_f)))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn metered_store(limit: u64) -> Store {
+        let metering = Arc::new(Metering::new(limit, cost_function));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        Store::new(&JIT::new(&compiler_config).engine())
+    }
+
+    fn instantiate(limit: u64) -> Instance {
+        let module = Module::new(&metered_store(limit), bytecode()).unwrap();
+        Instance::new(&module, &imports! {}).unwrap()
+    }
+
+    #[test]
+    fn get_remaining_points_works() {
+        let instance = instantiate(10);
+        assert_eq!(
+            get_remaining_points(&instance).unwrap(),
+            MeteringPoints::Remaining(10)
+        );
+
+        // `local.get $value` is 1 point, `i32.const 1` is 1 point and
+        // `i32.add` is 2 points
+        let add_one = instance
+            .exports
+            .get_function("add_one")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+        assert_eq!(add_one.call(1).unwrap(), 2);
+        assert_eq!(
+            get_remaining_points(&instance).unwrap(),
+            MeteringPoints::Remaining(6)
+        );
+
+        add_one.call(1).unwrap();
+        assert_eq!(
+            get_remaining_points(&instance).unwrap(),
+            MeteringPoints::Remaining(2)
+        );
+
+        // the call needing more points than remaining traps
+        assert!(add_one.call(1).is_err());
+        assert_eq!(
+            get_remaining_points(&instance).unwrap(),
+            MeteringPoints::Exhausted
+        );
+    }
+
+    #[test]
+    fn set_remaining_points_works() {
+        let instance = instantiate(10);
+        let add_one = instance
+            .exports
+            .get_function("add_one")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+
+        set_remaining_points(&instance, 4).unwrap();
+        add_one.call(1).unwrap();
+        assert_eq!(
+            get_remaining_points(&instance).unwrap(),
+            MeteringPoints::Remaining(0)
+        );
+        assert!(add_one.call(1).is_err());
+        assert_eq!(
+            get_remaining_points(&instance).unwrap(),
+            MeteringPoints::Exhausted
+        );
+
+        // setting the points again makes the instance runnable
+        set_remaining_points(&instance, 8).unwrap();
+        assert_eq!(
+            get_remaining_points(&instance).unwrap(),
+            MeteringPoints::Remaining(8)
+        );
+        add_one.call(1).unwrap();
+        assert_eq!(
+            get_remaining_points(&instance).unwrap(),
+            MeteringPoints::Remaining(4)
+        );
+    }
+
+    #[test]
+    fn metering_compiles_several_modules() {
+        let store = metered_store(10);
+        let other_bytecode = wat2wasm(
+            br#"
+            (module
+            (global $g (mut i32) (i32.const 0))
+            (func (export "add_one") (param i32) (result i32)
+                local.get 0
+                i32.const 1
+                i32.add))
+            "#,
+        )
+        .unwrap();
+
+        for bytecode in &[bytecode(), other_bytecode.into()] {
+            let module = Module::new(&store, bytecode).unwrap();
+            let instance = Instance::new(&module, &imports! {}).unwrap();
+            let add_one = instance
+                .exports
+                .get_function("add_one")
+                .unwrap()
+                .native::<i32, i32>()
+                .unwrap();
+            add_one.call(1).unwrap();
+            assert_eq!(
+                get_remaining_points(&instance).unwrap(),
+                MeteringPoints::Remaining(6)
+            );
+        }
+    }
+
+    #[test]
+    fn metering_rejects_reserved_exports() {
+        let bytecode = wat2wasm(
+            br#"
+            (module
+            (global (export "wasmer_metering_remaining_points") (mut i64) (i64.const 0)))
+            "#,
+        )
+        .unwrap();
+
+        assert!(Module::new(&metered_store(10), bytecode).is_err());
+    }
+
+    #[test]
+    fn metering_rejects_overflowing_costs() {
+        let metering = Arc::new(Metering::new(10, |operator: &Operator| match operator {
+            Operator::I32Add { .. } => i64::MAX as u64,
+            _ => 1,
+        }));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let store = Store::new(&JIT::new(&compiler_config).engine());
+
+        assert!(Module::new(&store, bytecode()).is_err());
+    }
+
+    #[test]
+    fn points_of_unmetered_instances() {
+        let store = Store::default();
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&module, &imports! {}).unwrap();
+
+        assert!(get_remaining_points(&instance).is_err());
+        assert!(set_remaining_points(&instance, 10).is_err());
+    }
+
+    #[test]
+    fn fingerprint_needs_cache_key() {
+        let metering = Metering::new(10, cost_function);
+        assert_eq!(metering.fingerprint(), None);
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(metering));
+        assert_eq!(compiler_config.fingerprint(), None);
+
+        let metering = Metering::new(10, cost_function).with_cache_key("v1");
+        assert_eq!(
+            metering.fingerprint(),
+            Some("metering initial_limit=10 cost_function=v1".to_string())
+        );
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(metering));
+        assert!(compiler_config.fingerprint().is_some());
+    }
+}
@@ -0,0 +1,336 @@
+use crate::error::ObjectError;
+use crate::module::get_object_for_target;
+use object::write::{StandardSection, Symbol as ObjSymbol, SymbolSection};
+use object::{SymbolFlags, SymbolKind, SymbolScope};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::NamedTempFile;
+use wasmer_compiler::{Architecture, Compilation, FunctionAddressMap, Triple};
+use wasmer_engine::Artifact;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::LocalFunctionIndex;
+use wasmer_vm::ModuleInfo;
+use which::which;
+
+/// The environment variable that overrides the `objdump` used to
+/// disassemble the functions.
+const OBJDUMP_ENV: &str = "WASMER_OBJDUMP";
+
+/// A single disassembled machine instruction.
+#[derive(Debug, PartialEq)]
+struct Instruction {
+    /// The offset of the instruction in the object text section.
+    offset: u64,
+    /// The encoded bytes, as printed by `objdump`.
+    bytes: String,
+    /// The mnemonic and operands.
+    text: String,
+}
+
+/// Disassembles the functions of a `Compilation` or an `Artifact` into
+/// a textual listing, annotated with the function names and the offsets
+/// of the Wasm instructions they were generated from.
+///
+/// The machine code is wrapped into an object file for the target,
+/// which is then disassembled with `llvm-objdump` (or `objdump` if
+/// it's not available). The `WASMER_OBJDUMP` environment variable
+/// can be used to pick another disassembler.
+///
+/// Only the `x86_64` and `aarch64` architectures are supported. GNU
+/// `objdump` can only disassemble the code of the host architecture,
+/// `llvm-objdump` is needed for the other one.
+///
+/// # Usage
+///
+/// ```rust
+/// # use wasmer_compiler::{Compilation, Triple};
+/// # use wasmer_engine::Artifact;
+/// # use wasmer_object::ObjectError;
+/// # use wasmer_vm::ModuleInfo;
+/// use wasmer_object::Disassembler;
+///
+/// # fn disassemble(
+/// #     triple: &Triple,
+/// #     module: &ModuleInfo,
+/// #     compilation: &Compilation,
+/// #     artifact: &dyn Artifact,
+/// # ) -> Result<(), ObjectError> {
+/// let disassembler = Disassembler::new(&triple)?;
+/// let listing = disassembler.disassemble_compilation(&module, &compilation)?;
+/// let listing = disassembler.disassemble_artifact(artifact)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Disassembler {
+    objdump: PathBuf,
+    /// Whether `objdump` is `llvm-objdump`, which takes the target triple.
+    llvm: bool,
+    triple: Triple,
+}
+
+impl Disassembler {
+    /// Creates a disassembler for the given target `Triple`.
+    pub fn new(triple: &Triple) -> Result<Self, ObjectError> {
+        match triple.architecture {
+            Architecture::X86_64 | Architecture::Aarch64(_) => {}
+            architecture => {
+                return Err(ObjectError::UnsupportedArchitecture(format!(
+                    "{}",
+                    architecture
+                )));
+            }
+        }
+        let objdump = match std::env::var_os(OBJDUMP_ENV) {
+            Some(objdump) => PathBuf::from(objdump),
+            None => which("llvm-objdump").or_else(|_| which("objdump")).map_err(|_| {
+                ObjectError::Disassembler(
+                    "Neither `llvm-objdump` nor `objdump` was found; one of them is required to disassemble".to_string(),
+                )
+            })?,
+        };
+        let version = Command::new(&objdump).arg("--version").output()?;
+        let llvm = String::from_utf8_lossy(&version.stdout).contains("LLVM");
+        if !llvm && triple.architecture != Triple::host().architecture {
+            return Err(ObjectError::Disassembler(format!(
+                "`{}` can't disassemble {} code, as GNU objdump only supports the host \
+                 architecture: install `llvm-objdump` or set `{}` to a disassembler for {}",
+                objdump.display(),
+                triple.architecture,
+                OBJDUMP_ENV,
+                triple.architecture,
+            )));
+        }
+        Ok(Self {
+            objdump,
+            llvm,
+            triple: triple.clone(),
+        })
+    }
+
+    /// Disassembles all the functions of `artifact`.
+    ///
+    /// Only the artifacts keeping their compiled functions (see
+    /// [`Artifact::compilation`]), like the JIT ones, can be disassembled.
+    pub fn disassemble_artifact(&self, artifact: &dyn Artifact) -> Result<String, ObjectError> {
+        let compilation = artifact.compilation().ok_or_else(|| {
+            ObjectError::Disassembler(
+                "the artifact doesn't keep its compiled functions".to_string(),
+            )
+        })?;
+        self.disassemble_compilation(artifact.module_ref(), &compilation)
+    }
+
+    /// Disassembles all the functions defined in `compilation`.
+    ///
+    /// Each function starts with a header holding its index, its name
+    /// (if the module has one for it) and its signature. Instructions
+    /// are annotated with the offset of the Wasm instruction that
+    /// generated them, whenever it changes.
+    pub fn disassemble_compilation(
+        &self,
+        module: &ModuleInfo,
+        compilation: &Compilation,
+    ) -> Result<String, ObjectError> {
+        let mut obj = get_object_for_target(&self.triple)?;
+        let section_id = obj.section_id(StandardSection::Text);
+        let mut ranges = Vec::with_capacity(compilation.len());
+        for index in 0..compilation.len() {
+            let index = LocalFunctionIndex::new(index);
+            let body = &compilation.get(index).body.body;
+            let symbol_id = obj.add_symbol(ObjSymbol {
+                name: format!("wasmer_function_{}", index.index()).into_bytes(),
+                value: 0,
+                size: 0,
+                kind: SymbolKind::Text,
+                scope: SymbolScope::Compilation,
+                weak: false,
+                section: SymbolSection::Undefined,
+                flags: SymbolFlags::None,
+            });
+            let offset = obj.add_symbol_data(symbol_id, section_id, body, 16);
+            ranges.push((index, offset, offset + body.len() as u64));
+        }
+        let instructions = self.objdump(&obj.write()?)?;
+
+        let mut listing = String::new();
+        for (index, start, end) in ranges {
+            let func_index = module.func_index(index);
+            let signature = &module.signatures[module.functions[func_index]];
+            let name = module
+                .function_names
+                .get(&func_index)
+                .map(|name| format!(" ${}", name))
+                .unwrap_or_default();
+            writeln!(
+                listing,
+                ";; function {}{} {}",
+                func_index.index(),
+                name,
+                signature
+            )
+            .unwrap();
+
+            let address_map = &compilation.get(index).frame_info.address_map;
+            let mut last_srcloc = None;
+            for instruction in instructions
+                .iter()
+                .filter(|instruction| instruction.offset >= start && instruction.offset < end)
+            {
+                let offset = instruction.offset - start;
+                let annotation = match source_offset(address_map, offset) {
+                    Some(srcloc) if last_srcloc != Some(srcloc) => {
+                        last_srcloc = Some(srcloc);
+                        format!("  ; wasm @{:#x}", srcloc)
+                    }
+                    _ => String::new(),
+                };
+                writeln!(
+                    listing,
+                    "  {:6x}: {:<24} {}{}",
+                    offset, instruction.bytes, instruction.text, annotation
+                )
+                .unwrap();
+            }
+            listing.push('\n');
+        }
+        Ok(listing)
+    }
+
+    /// Runs `objdump` on the object and parses the instructions it prints.
+    fn objdump(&self, object: &[u8]) -> Result<Vec<Instruction>, ObjectError> {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(object)?;
+        file.flush()?;
+        let mut command = Command::new(&self.objdump);
+        command.arg("-d");
+        if self.llvm {
+            command.arg(format!("--triple={}", self.triple));
+        }
+        let output = command.arg(file.path()).output()?;
+        if !output.status.success() {
+            return Err(ObjectError::Disassembler(format!(
+                "`{}` exited with {}: {}",
+                self.objdump.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(parse_objdump(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Parses the instructions printed by `objdump -d`.
+fn parse_objdump(output: &str) -> Vec<Instruction> {
+    let mut instructions: Vec<Instruction> = Vec::new();
+    for line in output.lines() {
+        // Instruction lines look like `  4: 48 89 e5 \tmovq\t%rsp, %rbp`;
+        // the rest (headers and labels) has no hexadecimal address.
+        let (address, rest) = match line.find(':') {
+            Some(colon) => (line[..colon].trim(), &line[colon + 1..]),
+            None => continue,
+        };
+        let offset = match u64::from_str_radix(address, 16) {
+            Ok(offset) => offset,
+            Err(_) => continue,
+        };
+        let rest = rest.trim_start();
+        let (bytes, text) = match rest.find('\t') {
+            Some(tab) => (&rest[..tab], rest[tab + 1..].replace('\t', " ")),
+            None => (rest, String::new()),
+        };
+        let bytes = bytes.trim().to_string();
+        if text.is_empty() {
+            // `objdump` splits long encodings over several lines
+            if let Some(last) = instructions.last_mut() {
+                last.bytes.push(' ');
+                last.bytes.push_str(&bytes);
+            }
+            continue;
+        }
+        instructions.push(Instruction {
+            offset,
+            bytes,
+            text: text.trim().to_string(),
+        });
+    }
+    instructions
+}
+
+/// Returns the offset in the Wasm module of the instruction that
+/// generated the machine code at `offset` in the function body.
+fn source_offset(address_map: &FunctionAddressMap, offset: u64) -> Option<u32> {
+    let offset = offset as usize;
+    address_map
+        .instructions
+        .iter()
+        .find(|map| map.code_offset <= offset && offset < map.code_offset + map.code_len)
+        .filter(|map| !map.srcloc.is_default())
+        .map(|map| map.srcloc.bits())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(offset: u64, bytes: &str, text: &str) -> Instruction {
+        Instruction {
+            offset,
+            bytes: bytes.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_gnu_objdump() {
+        let output = "
+wasm.o:     file format elf64-x86-64
+
+
+Disassembly of section .text:
+
+0000000000000000 <wasmer_function_0>:
+   0:\t48 8d 04 7f          \tlea    (%rdi,%rdi,2),%rax
+   4:\t48 ba 89 67 45 23 01 \tmovabs $0x123456789,%rdx
+   b:\t00 00 00 
+   e:\t48 01 d0             \tadd    %rdx,%rax
+  11:\tc3                   \tret
+";
+        assert_eq!(
+            parse_objdump(output),
+            vec![
+                instruction(0x0, "48 8d 04 7f", "lea    (%rdi,%rdi,2),%rax"),
+                instruction(
+                    0x4,
+                    "48 ba 89 67 45 23 01 00 00 00",
+                    "movabs $0x123456789,%rdx"
+                ),
+                instruction(0xe, "48 01 d0", "add    %rdx,%rax"),
+                instruction(0x11, "c3", "ret"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_llvm_objdump() {
+        let output = "
+wasm.o:\tfile format elf64-littleaarch64
+
+Disassembly of section .text:
+
+0000000000000000 <wasmer_function_0>:
+       0: fd 7b bf a9  \tstp\tx29, x30, [sp, #-16]!
+       4: fd 03 00 91  \tmov\tx29, sp
+       8: c0 03 5f d6  \tret
+";
+        assert_eq!(
+            parse_objdump(output),
+            vec![
+                instruction(0x0, "fd 7b bf a9", "stp x29, x30, [sp, #-16]!"),
+                instruction(0x4, "fd 03 00 91", "mov x29, sp"),
+                instruction(0x8, "c0 03 5f d6", "ret"),
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use object::write::Error as ObjectWriteError;
+use std::io;
 use thiserror::Error;
 
 /// The Object error can occur when creating an object file
@@ -17,4 +18,10 @@ pub enum ObjectError {
     /// The object was provided a not-supported architecture
     #[error("Error when writing the object: {0}")]
     Write(#[from] ObjectWriteError),
+    /// An IO error occurred when handling the object file
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    /// The disassembler could not be run or failed
+    #[error("Disassembler failed: {0}")]
+    Disassembler(String),
 }
@@ -20,8 +20,10 @@
     )
 )]
 
+mod disassemble;
 mod error;
 mod module;
 
+pub use crate::disassemble::Disassembler;
 pub use crate::error::ObjectError;
 pub use crate::module::{emit_compilation, emit_data, get_object_for_target};
@@ -5,7 +5,9 @@ use crate::global::Global;
 use crate::memory::{Memory, MemoryStyle};
 use crate::table::{Table, TableStyle};
 use crate::vmcontext::{VMContext, VMFunctionBody, VMFunctionKind, VMTrampoline};
-use std::sync::Arc;
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Weak};
 use wasmer_types::{FunctionType, MemoryType, TableType};
 
 /// The value of an export passed from one instance to another.
@@ -38,6 +40,10 @@ pub struct ExportFunction {
     /// Address of the function call trampoline owned by the same VMContext that owns the VMFunctionBody.
     /// May be None when the function is an host-function (FunctionType == Dynamic or vmctx == nullptr).
     pub call_trampoline: Option<VMTrampoline>,
+    /// The environment `vmctx` points to when the function is a host
+    /// function owning one, kept alive as long as the function is
+    /// referenced.
+    pub host_env: Option<HostFunctionEnv>,
 }
 
 /// # Safety
@@ -47,6 +53,97 @@ unsafe impl Send for ExportFunction {}
 /// TODO:
 unsafe impl Sync for ExportFunction {}
 
+/// Shared ownership of the environment of a host function.
+///
+/// It's held by the exports of the function, by the instances importing
+/// it and, as table elements only hold the `vmctx` of their functions,
+/// by the tables referencing it. The environment is dropped with the last
+/// of them.
+#[derive(Clone)]
+pub struct HostFunctionEnv {
+    inner: Arc<dyn Any>,
+}
+
+/// # Safety
+/// The environment is never accessed through this type, which only keeps
+/// it alive; it's used by the function like in `ExportFunction`.
+unsafe impl Send for HostFunctionEnv {}
+/// # Safety
+/// Same as above.
+unsafe impl Sync for HostFunctionEnv {}
+
+impl HostFunctionEnv {
+    /// Take ownership of `env`, returning it along with the pointer to
+    /// give to the VM as the `vmctx` of the function.
+    pub fn new<T: 'static>(env: Box<T>) -> (Self, *mut T) {
+        let ptr = Box::into_raw(env);
+        let inner = Arc::new(OwnedEnv(ptr));
+        (Self { inner }, ptr)
+    }
+
+    /// Shares the ownership of `owner`, which isn't given to the VM: it's
+    /// kept alive as long as the functions holding the environment are
+    /// referenced, for example to keep their instance from being reused.
+    pub fn from_owner<T: Any + Send + Sync>(owner: Arc<T>) -> Self {
+        Self { inner: owner }
+    }
+
+    /// Creates a reference to the environment which doesn't keep it
+    /// alive.
+    pub fn downgrade(&self) -> WeakHostFunctionEnv {
+        WeakHostFunctionEnv {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+}
+
+impl fmt::Debug for HostFunctionEnv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HostFunctionEnv").finish()
+    }
+}
+
+impl PartialEq for HostFunctionEnv {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+/// A reference to a `HostFunctionEnv` which doesn't keep it alive.
+#[derive(Clone)]
+pub struct WeakHostFunctionEnv {
+    inner: Weak<dyn Any>,
+}
+
+/// # Safety
+/// Same as `HostFunctionEnv`.
+unsafe impl Send for WeakHostFunctionEnv {}
+/// # Safety
+/// Same as above.
+unsafe impl Sync for WeakHostFunctionEnv {}
+
+impl WeakHostFunctionEnv {
+    /// Returns the environment, if it's still alive.
+    pub fn upgrade(&self) -> Option<HostFunctionEnv> {
+        self.inner.upgrade().map(|inner| HostFunctionEnv { inner })
+    }
+}
+
+impl fmt::Debug for WeakHostFunctionEnv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WeakHostFunctionEnv").finish()
+    }
+}
+
+/// Drops the environment handed out as a raw pointer.
+struct OwnedEnv<T>(*mut T);
+
+impl<T> Drop for OwnedEnv<T> {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.0)) }
+    }
+}
+
 impl From<ExportFunction> for Export {
     fn from(func: ExportFunction) -> Self {
         Self::Function(func)
@@ -7,7 +7,9 @@
 use crate::export::Export;
 use crate::global::Global;
 use crate::imports::Imports;
-use crate::memory::{Memory, MemoryError};
+use crate::memory::{Memory, MemoryError, MemoryStyle};
+use crate::mmap::MmapImage;
+use crate::snapshot::{InstanceSnapshot, SnapshotError, TableElement};
 use crate::table::Table;
 use crate::trap::{catch_traps, init_traps, Trap, TrapCode};
 use crate::vmcontext::{
@@ -15,7 +17,9 @@ use crate::vmcontext::{
     VMFunctionKind, VMGlobalDefinition, VMGlobalImport, VMMemoryDefinition, VMMemoryImport,
     VMSharedSignatureIndex, VMTableDefinition, VMTableImport, VMTrampoline,
 };
-use crate::{ExportFunction, ExportGlobal, ExportMemory, ExportTable};
+use crate::{
+    ExportFunction, ExportGlobal, ExportMemory, ExportTable, HostFunctionEnv, WeakHostFunctionEnv,
+};
 use crate::{FunctionBodyPtr, ModuleInfo, VMOffsets};
 use memoffset::offset_of;
 use more_asserts::assert_lt;
@@ -30,8 +34,8 @@ use std::{mem, ptr, slice};
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit,
-    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, Pages,
-    SignatureIndex, TableIndex, TableInitializer,
+    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
+    MemoryType, Pages, SignatureIndex, TableIndex, TableInitializer,
 };
 
 cfg_if::cfg_if! {
@@ -88,6 +92,15 @@ pub(crate) struct Instance {
     /// Pointers to function call trampolines in executable memory.
     function_call_trampolines: BoxedSlice<SignatureIndex, VMTrampoline>,
 
+    /// Environments of the imported host functions, which must live as
+    /// long as the instance may call them.
+    imported_function_envs: RefCell<BoxedSlice<FunctionIndex, Option<HostFunctionEnv>>>,
+
+    /// The environment shared by the functions of this instance, if any,
+    /// held by their exports and the elements of the tables of other
+    /// instances.
+    host_env: RefCell<Option<WeakHostFunctionEnv>>,
+
     /// Passive elements in this instantiation. As `elem.drop`s happen, these
     /// entries get removed. A missing entry is considered equivalent to an
     /// empty slice.
@@ -245,7 +258,6 @@ impl Instance {
     }
 
     /// Set the indexed global to `VMGlobalDefinition`.
-    #[allow(dead_code)]
     fn set_global(&self, index: LocalGlobalIndex, global: &VMGlobalDefinition) {
         unsafe {
             *self.global_ptr(index).as_ptr() = global.clone();
@@ -294,13 +306,18 @@ impl Instance {
         match export {
             ExportIndex::Function(index) => {
                 let sig_index = &self.module.functions[*index];
-                let (address, vmctx) = if let Some(def_index) = self.module.local_func_index(*index)
-                {
-                    (self.functions[def_index].0 as *const _, self.vmctx_ptr())
-                } else {
-                    let import = self.imported_function(*index);
-                    (import.body, import.vmctx)
-                };
+                let (address, vmctx, host_env) =
+                    if let Some(def_index) = self.module.local_func_index(*index) {
+                        (
+                            self.functions[def_index].0 as *const _,
+                            self.vmctx_ptr(),
+                            self.host_env(),
+                        )
+                    } else {
+                        let import = self.imported_function(*index);
+                        let host_env = self.imported_function_envs.borrow()[*index].clone();
+                        (import.body, import.vmctx, host_env)
+                    };
                 let call_trampoline = Some(self.function_call_trampolines[*sig_index]);
                 let signature = self.module.signatures[*sig_index].clone();
                 ExportFunction {
@@ -313,6 +330,7 @@ impl Instance {
                     signature,
                     vmctx,
                     call_trampoline,
+                    host_env,
                 }
                 .into()
             }
@@ -353,7 +371,7 @@ impl Instance {
     /// Specifically, it provides access to the key-value pairs, where the keys
     /// are export names, and the values are export declarations which can be
     /// resolved `lookup_by_declaration`.
-    pub fn exports(&self) -> indexmap::map::Iter<String, ExportIndex> {
+    pub fn exports(&self) -> indexmap::map::Iter<'_, String, ExportIndex> {
         self.module.exports.iter()
     }
 
@@ -450,14 +468,7 @@ impl Instance {
             .memories
             .get(memory_index)
             .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()));
-        let result = mem.grow(delta.into());
-
-        // Keep current the VMContext pointers used by compiled wasm code.
-        let memory_ptr = self.memories[memory_index].vmmemory();
-        let vmmemory = unsafe { memory_ptr.as_ref() };
-        self.set_memory(memory_index, vmmemory);
-
-        result
+        mem.grow(delta.into())
     }
 
     /// Grow imported memory by the specified amount of pages.
@@ -573,6 +584,24 @@ impl Instance {
         }
     }
 
+    /// Get the environment owned by the function with the given
+    /// `FunctionIndex`, if it's an imported host function owning one or
+    /// a function of this instance sharing one.
+    fn get_function_host_env(&self, index: FunctionIndex) -> Option<HostFunctionEnv> {
+        if index == FunctionIndex::reserved_value() {
+            return None;
+        }
+        if self.module.local_func_index(index).is_some() {
+            return self.host_env();
+        }
+        self.imported_function_envs.borrow()[index].clone()
+    }
+
+    /// Get the environment shared by the functions of this instance.
+    fn host_env(&self) -> Option<HostFunctionEnv> {
+        self.host_env.borrow().as_ref()?.upgrade()
+    }
+
     /// The `table.init` operation: initializes a portion of a table with a
     /// passive element.
     ///
@@ -595,6 +624,10 @@ impl Instance {
         let elem = passive_elements
             .get(&elem_index)
             .map_or_else(|| -> &[VMCallerCheckedAnyfunc] { &[] }, |e| &**e);
+        let elem_functions = self
+            .module
+            .get_passive_element(elem_index)
+            .unwrap_or_default();
 
         if src
             .checked_add(len)
@@ -606,8 +639,9 @@ impl Instance {
 
         // TODO(#983): investigate replacing this get/set loop with a `memcpy`.
         for (dst, src) in (dst..dst + len).zip(src..src + len) {
+            let host_env = self.get_function_host_env(elem_functions[src as usize]);
             table
-                .set(dst, elem[src as usize].clone())
+                .set_with_host_env(dst, elem[src as usize].clone(), host_env)
                 .expect("should never panic because we already did the bounds check above");
         }
 
@@ -844,6 +878,8 @@ impl InstanceHandle {
                 globals: finished_globals,
                 functions: finished_functions,
                 function_call_trampolines: finished_function_call_trampolines,
+                imported_function_envs: RefCell::new(imports.function_envs.clone()),
+                host_env: RefCell::new(None),
                 passive_elements: Default::default(),
                 passive_data,
                 host_state,
@@ -857,6 +893,22 @@ impl InstanceHandle {
                 alloc::handle_alloc_error(layout);
             }
             ptr::write(instance_ptr, instance);
+
+            // Memories can be grown or reset through their exports too.
+            let instance = &mut *instance_ptr;
+            let memories = instance
+                .memories
+                .iter()
+                .map(|(index, memory)| -> Arc<dyn Memory> {
+                    Arc::new(InstanceMemory {
+                        memory: memory.clone(),
+                        vmctx_definition: instance.memory_ptr(index),
+                    })
+                })
+                .collect::<PrimaryMap<LocalMemoryIndex, _>>()
+                .into_boxed_slice();
+            instance.memories = memories;
+
             Self {
                 instance: instance_ptr,
             }
@@ -941,6 +993,245 @@ impl InstanceHandle {
         Ok(())
     }
 
+    /// Makes `host_env` the environment shared by the functions of this
+    /// instance, which their exports and the elements of the tables of
+    /// other instances hold, while the instance itself doesn't.
+    ///
+    /// # Safety
+    ///
+    /// It must be set before the instance is initialized, so that the
+    /// elements of the imported tables hold it.
+    pub unsafe fn set_host_env(&self, host_env: Option<&HostFunctionEnv>) {
+        let instance = self.instance();
+        let host_env = host_env.map(HostFunctionEnv::downgrade);
+        for table in instance.tables.values() {
+            table.set_owner_host_env(self.vmctx_ptr(), host_env.clone());
+        }
+        *instance.host_env.borrow_mut() = host_env;
+    }
+
+    /// Returns the environment shared by the functions of this instance,
+    /// if it was set and is still alive.
+    pub fn host_env(&self) -> Option<HostFunctionEnv> {
+        self.instance().host_env()
+    }
+
+    /// Returns whether the memories, tables and globals defined by this
+    /// instance are only referenced by the instance itself, and not by
+    /// exports or other instances importing them.
+    pub fn is_exclusive(&self) -> bool {
+        let instance = self.instance();
+        instance
+            .memories
+            .values()
+            .all(|memory| Arc::strong_count(memory) == 1)
+            && instance
+                .tables
+                .values()
+                .all(|table| Arc::strong_count(table) == 1)
+            && instance
+                .globals
+                .values()
+                .all(|global| Arc::strong_count(global) == 1)
+    }
+
+    /// Reset the local memories and tables of this instance and drop its
+    /// runtime state, so it can be reused by [`InstanceHandle::reuse`].
+    ///
+    /// # Safety
+    ///
+    /// No wasm code of this instance may be running, and nothing may keep
+    /// using the exports of the previous instantiation afterwards.
+    pub unsafe fn reset(&self) -> Result<(), String> {
+        let instance = self.instance();
+        for (index, memory) in instance.memories.iter() {
+            memory.reset().map_err(|e| e.to_string())?;
+            instance.set_memory(index, memory.vmmemory().as_ref());
+        }
+        for (index, table) in instance.tables.iter() {
+            table.reset()?;
+            instance.set_table(index, table.vmtable().as_ref());
+        }
+        instance.passive_elements.borrow_mut().clear();
+        *instance.passive_data.borrow_mut() = instance.module.passive_data.clone();
+        *instance.host_env.borrow_mut() = None;
+        Ok(())
+    }
+
+    /// Bind a new set of imports to an instance previously [`reset`] and
+    /// initialize its globals and passive segments again.
+    ///
+    /// [`InstanceHandle::finish_instantiation`] must be called afterwards,
+    /// just like for a newly created instance.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call after [`reset`], with imports resolved for the
+    /// same module.
+    ///
+    /// [`reset`]: InstanceHandle::reset
+    pub unsafe fn reuse(&self, imports: Imports) {
+        let instance = self.instance();
+        let module = instance.module_ref();
+        assert_eq!(imports.functions.len(), module.num_imported_functions);
+        assert_eq!(imports.tables.len(), module.num_imported_tables);
+        assert_eq!(imports.memories.len(), module.num_imported_memories);
+        assert_eq!(imports.globals.len(), module.num_imported_globals);
+
+        ptr::copy(
+            imports.functions.values().as_slice().as_ptr(),
+            instance.imported_functions_ptr(),
+            imports.functions.len(),
+        );
+        ptr::copy(
+            imports.tables.values().as_slice().as_ptr(),
+            instance.imported_tables_ptr(),
+            imports.tables.len(),
+        );
+        ptr::copy(
+            imports.memories.values().as_slice().as_ptr(),
+            instance.imported_memories_ptr(),
+            imports.memories.len(),
+        );
+        ptr::copy(
+            imports.globals.values().as_slice().as_ptr(),
+            instance.imported_globals_ptr(),
+            imports.globals.len(),
+        );
+        *instance.imported_function_envs.borrow_mut() = imports.function_envs.clone();
+
+        initialize_passive_elements(instance);
+        initialize_globals(instance);
+    }
+
+    /// Capture the state of the local memories, tables and globals of
+    /// this instance, to create new instances from with
+    /// [`InstanceHandle::restore`].
+    ///
+    /// The tables may only hold functions of this instance: the snapshot
+    /// can't keep the other instances alive.
+    ///
+    /// # Safety
+    ///
+    /// No wasm code of this instance may be running.
+    pub unsafe fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        let instance = self.instance();
+        let module = instance.module_ref();
+
+        let memories = instance
+            .memories
+            .values()
+            .map(|memory| memory.snapshot())
+            .collect::<Result<PrimaryMap<LocalMemoryIndex, _>, _>>()?;
+
+        // Table elements pointing to the functions of the module are
+        // resolved again when restoring, as the instance and its imports
+        // will be different.
+        let functions = module
+            .functions
+            .keys()
+            .map(|index| {
+                let anyfunc = instance.get_caller_checked_anyfunc(index);
+                ((anyfunc.func_ptr as usize, anyfunc.vmctx as usize), index)
+            })
+            .collect::<HashMap<_, _>>();
+        let tables = instance
+            .tables
+            .iter()
+            .map(|(table_index, table)| {
+                (0..table.size())
+                    .map(|i| {
+                        let anyfunc = table.get(i).unwrap();
+                        if anyfunc.func_ptr.is_null() {
+                            Ok(TableElement::Null)
+                        } else if let Some(index) =
+                            functions.get(&(anyfunc.func_ptr as usize, anyfunc.vmctx as usize))
+                        {
+                            Ok(TableElement::Function(*index))
+                        } else {
+                            Err(SnapshotError::ForeignTableElement {
+                                table: table_index.as_u32(),
+                                index: i,
+                            })
+                        }
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let globals = instance
+            .globals
+            .keys()
+            .map(|index| instance.global(index))
+            .collect();
+
+        Ok(InstanceSnapshot {
+            module: instance.module.clone(),
+            memories,
+            tables,
+            globals,
+            passive_elements: instance.passive_elements.borrow().keys().cloned().collect(),
+            passive_data: instance.passive_data.borrow().keys().cloned().collect(),
+        })
+    }
+
+    /// Restore the state captured by [`InstanceHandle::snapshot`] into this
+    /// instance. It replaces [`InstanceHandle::finish_instantiation`], as
+    /// the snapshot already contains the effects of the initializers and
+    /// the start function.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after instantiation.
+    pub unsafe fn restore(&self, snapshot: &InstanceSnapshot) -> Result<(), String> {
+        let instance = self.instance();
+        if !Arc::ptr_eq(&instance.module, &snapshot.module) {
+            return Err("the snapshot was taken from an instance of another module".to_string());
+        }
+
+        for (index, image) in snapshot.memories.iter() {
+            let memory = &instance.memories[index];
+            memory.restore(image).map_err(|e| e.to_string())?;
+            instance.set_memory(index, memory.vmmemory().as_ref());
+        }
+
+        for (index, elements) in snapshot.tables.iter() {
+            let table = &instance.tables[index];
+            let len = u32::try_from(elements.len()).unwrap();
+            if len > table.size() && table.grow(len - table.size()).is_none() {
+                return Err(format!("the table couldn't grow to {} elements", len));
+            }
+            for (i, element) in elements.iter().enumerate() {
+                let (anyfunc, host_env) = match element {
+                    TableElement::Null => (VMCallerCheckedAnyfunc::default(), None),
+                    TableElement::Function(index) => (
+                        instance.get_caller_checked_anyfunc(*index),
+                        instance.get_function_host_env(*index),
+                    ),
+                };
+                table
+                    .set_with_host_env(u32::try_from(i).unwrap(), anyfunc, host_env)
+                    .map_err(|_| "the table is out of bounds".to_string())?;
+            }
+            instance.set_table(index, table.vmtable().as_ref());
+        }
+
+        for (index, global) in snapshot.globals.iter() {
+            instance.set_global(index, global);
+        }
+
+        instance
+            .passive_elements
+            .borrow_mut()
+            .retain(|index, _| snapshot.passive_elements.contains(index));
+        instance
+            .passive_data
+            .borrow_mut()
+            .retain(|index, _| snapshot.passive_data.contains(index));
+
+        Ok(())
+    }
+
     /// Create a new `InstanceHandle` pointing at the instance
     /// pointed to by the given `VMContext` pointer.
     ///
@@ -990,7 +1281,7 @@ impl InstanceHandle {
     /// Specifically, it provides access to the key-value pairs, where the keys
     /// are export names, and the values are export declarations which can be
     /// resolved `lookup_by_declaration`.
-    pub fn exports(&self) -> indexmap::map::Iter<String, ExportIndex> {
+    pub fn exports(&self) -> indexmap::map::Iter<'_, String, ExportIndex> {
         self.instance().exports()
     }
 
@@ -1097,6 +1388,74 @@ impl Clone for InstanceHandle {
 //     }
 // }
 
+/// A memory defined by an instance, which keeps current the copy of its
+/// definition in the `VMContext` of the instance.
+///
+/// The generated code reads the definitions of the local memories from
+/// the `VMContext`, while the memories can be grown by the host through
+/// their exports or by other instances importing them.
+#[derive(Debug)]
+struct InstanceMemory {
+    memory: Arc<dyn Memory>,
+    /// The copy of the definition of `memory` in the `VMContext`.
+    vmctx_definition: NonNull<VMMemoryDefinition>,
+}
+
+/// This is correct because the `VMContext` outlives its memories, and
+/// the definition is only written when the memory changes.
+unsafe impl Send for InstanceMemory {}
+/// This is correct for the same reason.
+unsafe impl Sync for InstanceMemory {}
+
+impl InstanceMemory {
+    /// Copy the current definition of the memory to the `VMContext`.
+    fn update_vmctx_definition(&self) {
+        unsafe {
+            *self.vmctx_definition.as_ptr() = *self.memory.vmmemory().as_ref();
+        }
+    }
+}
+
+impl Memory for InstanceMemory {
+    fn ty(&self) -> &MemoryType {
+        self.memory.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.memory.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.memory.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let result = self.memory.grow(delta);
+        self.update_vmctx_definition();
+        result
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.memory.vmmemory()
+    }
+
+    fn reset(&self) -> Result<(), MemoryError> {
+        let result = self.memory.reset();
+        self.update_vmctx_definition();
+        result
+    }
+
+    fn snapshot(&self) -> Result<MmapImage, MemoryError> {
+        self.memory.snapshot()
+    }
+
+    fn restore(&self, image: &MmapImage) -> Result<(), MemoryError> {
+        let result = self.memory.restore(image);
+        self.update_vmctx_definition();
+        result
+    }
+}
+
 fn check_table_init_bounds(instance: &Instance) -> Result<(), Trap> {
     let module = Arc::clone(&instance.module);
     for init in &module.table_initializers {
@@ -1199,8 +1558,9 @@ fn initialize_tables(instance: &Instance) -> Result<(), Trap> {
 
         for (i, func_idx) in init.elements.iter().enumerate() {
             let anyfunc = instance.get_caller_checked_anyfunc(*func_idx);
+            let host_env = instance.get_function_host_env(*func_idx);
             table
-                .set(u32::try_from(start + i).unwrap(), anyfunc)
+                .set_with_host_env(u32::try_from(start + i).unwrap(), anyfunc, host_env)
                 .unwrap();
         }
     }
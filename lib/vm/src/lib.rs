@@ -29,6 +29,7 @@ mod mmap;
 mod module;
 mod probestack;
 mod sig_registry;
+mod snapshot;
 mod table;
 mod trap;
 mod vmcontext;
@@ -40,11 +41,14 @@ pub use crate::export::*;
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::InstanceHandle;
-pub use crate::memory::{LinearMemory, Memory, MemoryError, MemoryStyle};
-pub use crate::mmap::Mmap;
+pub use crate::memory::{
+    BufferMemory, LinearMemory, Memory, MemoryBuffer, MemoryError, MemoryStyle,
+};
+pub use crate::mmap::{Mmap, MmapImage};
 pub use crate::module::{ExportsIterator, ImportsIterator, ModuleInfo};
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
+pub use crate::snapshot::{InstanceSnapshot, SnapshotError};
 pub use crate::table::{LinearTable, Table, TableStyle};
 pub use crate::trap::*;
 pub use crate::vmcontext::{
@@ -5,7 +5,7 @@
 //!
 //! `LinearMemory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
-use crate::mmap::Mmap;
+use crate::mmap::{Mmap, MmapImage};
 use crate::vmcontext::VMMemoryDefinition;
 use more_asserts::assert_ge;
 use serde::{Deserialize, Serialize};
@@ -14,6 +14,7 @@ use std::cell::UnsafeCell;
 use std::convert::TryInto;
 use std::fmt;
 use std::ptr::NonNull;
+use std::slice;
 use std::sync::Mutex;
 use thiserror::Error;
 use wasmer_types::{Bytes, MemoryType, Pages};
@@ -96,6 +97,33 @@ impl MemoryStyle {
 }
 
 /// Trait for implementing Wasm Memory used by Wasmer.
+///
+/// Custom implementations are plugged into instances by returning them
+/// from `Tunables::create_memory`, or by importing them.
+///
+/// # Invariants
+///
+/// The generated code accesses the memory through the
+/// [`VMMemoryDefinition`] returned by [`Memory::vmmemory`], without
+/// calling the methods of this trait. Instances importing the memory
+/// read the definition through its address, while the instance defining
+/// it reads a copy kept in its `VMContext`, which it updates after calls
+/// to [`Memory::grow`], [`Memory::reset`] and [`Memory::restore`],
+/// whoever makes them. Implementations must make sure that:
+///
+/// * The definition stays at the same address for the lifetime of the
+///   memory, and only changes in `grow`, `reset` and `restore`.
+/// * `current_length` is always [`Memory::size`] in bytes, and the
+///   `current_length` bytes at `base` are valid for reads and writes.
+/// * With [`MemoryStyle::Static`], `base` never moves and the `bound`
+///   pages plus the `offset_guard_size` bytes at `base` are reserved:
+///   the generated code doesn't check bounds and relies on accesses past
+///   `current_length` to fault.
+/// * With [`MemoryStyle::Dynamic`], accesses up to `offset_guard_size`
+///   bytes past `current_length` fault. `base` may move when the memory
+///   grows.
+///
+/// [`LinearMemory`] and [`BufferMemory`] uphold these invariants.
 pub trait Memory: fmt::Debug + Send + Sync {
     /// Returns the memory type for this memory.
     fn ty(&self) -> &MemoryType;
@@ -113,6 +141,102 @@ pub trait Memory: fmt::Debug + Send + Sync {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Reset the memory to `minimum` zeroed pages, so it can be reused by
+    /// a new instantiation.
+    ///
+    /// Memories that can't be recycled return an error, which is the default.
+    fn reset(&self) -> Result<(), MemoryError> {
+        Err(MemoryError::Generic(
+            "this memory doesn't support being reset".to_string(),
+        ))
+    }
+
+    /// Capture the current contents of the memory in an image.
+    fn snapshot(&self) -> Result<MmapImage, MemoryError> {
+        let definition = unsafe { self.vmmemory().as_ref() };
+        let data =
+            unsafe { slice::from_raw_parts(definition.base, definition.current_length as usize) };
+        MmapImage::new(data).map_err(MemoryError::Region)
+    }
+
+    /// Replace the contents of the memory with an image taken by
+    /// [`Memory::snapshot`], growing it to the size of the image.
+    fn restore(&self, image: &MmapImage) -> Result<(), MemoryError> {
+        let pages: Pages = Bytes(image.len()).into();
+        let size = self.size();
+        if pages > size {
+            self.grow(pages - size)?;
+        }
+        let definition = unsafe { self.vmmemory().as_ref() };
+        let data = unsafe { slice::from_raw_parts_mut(definition.base, image.len()) };
+        image.read_into(data).map_err(MemoryError::Region)
+    }
+}
+
+/// Check that the limits of `memory` can be honored.
+fn check_limits(memory: &MemoryType) -> Result<(), MemoryError> {
+    if memory.minimum > Pages::max_value() {
+        return Err(MemoryError::MinimumMemoryTooLarge {
+            min_requested: memory.minimum,
+            max_allowed: Pages::max_value(),
+        });
+    }
+    // `maximum` cannot be set to more than `65536` pages.
+    if let Some(max) = memory.maximum {
+        if max > Pages::max_value() {
+            return Err(MemoryError::MaximumMemoryTooLarge {
+                max_requested: max,
+                max_allowed: Pages::max_value(),
+            });
+        }
+        if max < memory.minimum {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!(
+                    "the maximum ({} pages) is less than the minimum ({} pages)",
+                    max.0, memory.minimum.0
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check that the memory can grow from `current` to `current + delta`
+/// pages, and return the new size.
+fn checked_grow(
+    current: Pages,
+    delta: Pages,
+    maximum: Option<Pages>,
+) -> Result<Pages, MemoryError> {
+    let new_pages = current
+        .checked_add(delta)
+        .ok_or(MemoryError::CouldNotGrow {
+            current,
+            attempted_delta: delta,
+        })?;
+
+    if let Some(maximum) = maximum {
+        if new_pages > maximum {
+            return Err(MemoryError::CouldNotGrow {
+                current,
+                attempted_delta: delta,
+            });
+        }
+    }
+
+    // Wasm linear memories are never allowed to grow beyond what is
+    // indexable. If the memory has no maximum, enforce the greatest
+    // limit here.
+    if new_pages >= Pages::max_value() {
+        // Linear memory size would exceed the index range.
+        return Err(MemoryError::CouldNotGrow {
+            current,
+            attempted_delta: delta,
+        });
+    }
+
+    Ok(new_pages)
 }
 
 /// A linear memory instance.
@@ -156,30 +280,62 @@ struct WasmMmap {
 impl LinearMemory {
     /// Create a new linear memory instance with specified minimum and maximum number of wasm pages.
     pub fn new(memory: &MemoryType, style: &MemoryStyle) -> Result<Self, MemoryError> {
-        if memory.minimum > Pages::max_value() {
-            return Err(MemoryError::MinimumMemoryTooLarge {
-                min_requested: memory.minimum,
-                max_allowed: Pages::max_value(),
-            });
+        check_limits(memory)?;
+
+        let request_bytes = Self::request_bytes(memory, style);
+        let mapped_bytes = memory.minimum.bytes();
+        let alloc = Mmap::accessible_reserved(mapped_bytes.0, request_bytes)
+            .map_err(MemoryError::Region)?;
+        Ok(Self::with_alloc(memory, style, alloc))
+    }
+
+    /// Create a new linear memory instance in `alloc`, a mapping reserved
+    /// beforehand, which is zeroed. It must be at least as large as the
+    /// mappings of [`LinearMemory::new`] for `style`.
+    pub fn new_in(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        mut alloc: Mmap,
+    ) -> Result<Self, MemoryError> {
+        check_limits(memory)?;
+
+        if alloc.len() < Self::request_bytes(memory, style) {
+            return Err(MemoryError::Region(format!(
+                "the mapping of {} bytes is too small for the memory",
+                alloc.len()
+            )));
         }
-        // `maximum` cannot be set to more than `65536` pages.
-        if let Some(max) = memory.maximum {
-            if max > Pages::max_value() {
-                return Err(MemoryError::MaximumMemoryTooLarge {
-                    max_requested: max,
-                    max_allowed: Pages::max_value(),
-                });
-            }
-            if max < memory.minimum {
-                return Err(MemoryError::InvalidMemory {
-                    reason: format!(
-                        "the maximum ({} pages) is less than the minimum ({} pages)",
-                        max.0, memory.minimum.0
-                    ),
-                });
-            }
+        let mapped_bytes = memory.minimum.bytes().0;
+        alloc.reset(mapped_bytes).map_err(MemoryError::Region)?;
+        if mapped_bytes > 0 {
+            alloc
+                .make_accessible(0, mapped_bytes)
+                .map_err(MemoryError::Region)?;
         }
+        Ok(Self::with_alloc(memory, style, alloc))
+    }
+
+    /// Returns the mapping of this memory, to be reused by
+    /// [`LinearMemory::new_in`].
+    pub fn into_mmap(self) -> Mmap {
+        self.mmap.into_inner().unwrap().alloc
+    }
+
+    /// The size in bytes of the mapping of a memory, including its guard.
+    fn request_bytes(memory: &MemoryType, style: &MemoryStyle) -> usize {
+        let offset_guard_bytes = style.offset_guard_size() as usize;
+        let minimum_pages = match style {
+            MemoryStyle::Dynamic { .. } => memory.minimum,
+            MemoryStyle::Static { bound, .. } => {
+                assert_ge!(*bound, memory.minimum);
+                *bound
+            }
+        };
+        let minimum_bytes = minimum_pages.bytes().0;
+        minimum_bytes.checked_add(offset_guard_bytes).unwrap()
+    }
 
+    fn with_alloc(memory: &MemoryType, style: &MemoryStyle, alloc: Mmap) -> Self {
         let offset_guard_bytes = style.offset_guard_size() as usize;
 
         // If we have an offset guard, or if we're doing the static memory
@@ -191,26 +347,13 @@ impl LinearMemory {
                 MemoryStyle::Static { .. } => true,
             };
 
-        let minimum_pages = match style {
-            MemoryStyle::Dynamic { .. } => memory.minimum,
-            MemoryStyle::Static { bound, .. } => {
-                assert_ge!(*bound, memory.minimum);
-                *bound
-            }
-        };
-        let minimum_bytes = minimum_pages.bytes().0;
-        let request_bytes = minimum_bytes.checked_add(offset_guard_bytes).unwrap();
-        let mapped_pages = memory.minimum;
-        let mapped_bytes = mapped_pages.bytes();
-
         let mut mmap = WasmMmap {
-            alloc: Mmap::accessible_reserved(mapped_bytes.0, request_bytes)
-                .map_err(MemoryError::Region)?,
+            alloc,
             size: memory.minimum,
         };
 
         let base_ptr = mmap.alloc.as_mut_ptr();
-        Ok(Self {
+        Self {
             mmap: Mutex::new(mmap),
             maximum: memory.maximum,
             offset_guard_size: offset_guard_bytes,
@@ -221,7 +364,7 @@ impl LinearMemory {
             })),
             memory: *memory,
             style: style.clone(),
-        })
+        }
     }
 }
 
@@ -256,35 +399,9 @@ impl Memory for LinearMemory {
             return Ok(mmap.size);
         }
 
-        let new_pages = mmap
-            .size
-            .checked_add(delta)
-            .ok_or(MemoryError::CouldNotGrow {
-                current: mmap.size,
-                attempted_delta: delta,
-            })?;
+        let new_pages = checked_grow(mmap.size, delta, self.maximum)?;
         let prev_pages = mmap.size;
 
-        if let Some(maximum) = self.maximum {
-            if new_pages > maximum {
-                return Err(MemoryError::CouldNotGrow {
-                    current: mmap.size,
-                    attempted_delta: delta,
-                });
-            }
-        }
-
-        // Wasm linear memories are never allowed to grow beyond what is
-        // indexable. If the memory has no maximum, enforce the greatest
-        // limit here.
-        if new_pages >= Pages::max_value() {
-            // Linear memory size would exceed the index range.
-            return Err(MemoryError::CouldNotGrow {
-                current: mmap.size,
-                attempted_delta: delta,
-            });
-        }
-
         let delta_bytes = delta.bytes().0;
         let prev_bytes = prev_pages.bytes().0;
         let new_bytes = new_pages.bytes().0;
@@ -333,4 +450,191 @@ impl Memory for LinearMemory {
             as *const VMMemoryDefinition as *mut VMMemoryDefinition;
         unsafe { NonNull::new_unchecked(ptr) }
     }
+
+    /// Replace the contents of the memory with a copy-on-write view of
+    /// an image taken by [`Memory::snapshot`].
+    fn restore(&self, image: &MmapImage) -> Result<(), MemoryError> {
+        let pages: Pages = Bytes(image.len()).into();
+        let size = self.size();
+        if pages > size {
+            self.grow(pages - size)?;
+        }
+        let mut mmap_guard = self.mmap.lock().unwrap();
+        let mmap = mmap_guard.borrow_mut();
+        mmap.alloc.map_image(image).map_err(MemoryError::Region)
+    }
+
+    /// Reset the memory to `minimum` zeroed pages, releasing the dirty pages.
+    fn reset(&self) -> Result<(), MemoryError> {
+        let mut mmap_guard = self.mmap.lock().unwrap();
+        let mmap = mmap_guard.borrow_mut();
+        let minimum = self.memory.minimum;
+
+        mmap.alloc
+            .reset(minimum.bytes().0)
+            .map_err(MemoryError::Region)?;
+        mmap.size = minimum;
+        // update memory definition
+        unsafe {
+            let md = &mut *self.vm_memory_definition.get();
+            md.current_length = minimum.bytes().0.try_into().unwrap();
+            md.base = mmap.alloc.as_mut_ptr() as _;
+        }
+
+        Ok(())
+    }
+}
+
+/// A contiguous region of host memory that can back a WebAssembly linear
+/// memory, such as a buffer from a custom allocator, an arena or a
+/// shared-memory segment.
+///
+/// See [`BufferMemory`].
+///
+/// # Safety
+///
+/// The `len` bytes at `as_mut_ptr` must be valid for reads and writes
+/// until the next call to `resize` or until the buffer is dropped. They
+/// must not move when the buffer itself is moved.
+pub unsafe trait MemoryBuffer: fmt::Debug + Send + 'static {
+    /// Returns a pointer to the start of the region.
+    fn as_mut_ptr(&mut self) -> *mut u8;
+
+    /// Returns the size of the region in bytes.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the region is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Resize the region to `new_len` bytes, zeroing the new bytes.
+    ///
+    /// The region is allowed to move.
+    fn resize(&mut self, new_len: usize) -> Result<(), MemoryError>;
+}
+
+unsafe impl MemoryBuffer for Vec<u8> {
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.as_mut_slice().as_mut_ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn resize(&mut self, new_len: usize) -> Result<(), MemoryError> {
+        self.resize(new_len, 0);
+        Ok(())
+    }
+}
+
+/// A linear memory backed by a [`MemoryBuffer`].
+///
+/// The buffer has no guard pages, so the memory always has the
+/// `MemoryStyle::Dynamic` style with no offset guard, and the generated
+/// code checks the bounds of every access.
+#[derive(Debug)]
+pub struct BufferMemory<B: MemoryBuffer> {
+    // The underlying buffer.
+    buffer: Mutex<B>,
+
+    /// The WebAssembly linear memory description.
+    memory: MemoryType,
+
+    /// Our chosen implementation style.
+    style: MemoryStyle,
+
+    /// The owned memory definition used by the generated code
+    vm_memory_definition: Box<UnsafeCell<VMMemoryDefinition>>,
+}
+
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl<B: MemoryBuffer> Sync for BufferMemory<B> {}
+
+impl<B: MemoryBuffer> BufferMemory<B> {
+    /// Create a new memory backed by `buffer`, which is resized to the
+    /// minimum size of the memory.
+    pub fn new(memory: &MemoryType, mut buffer: B) -> Result<Self, MemoryError> {
+        check_limits(memory)?;
+
+        buffer.resize(0)?;
+        buffer.resize(memory.minimum.bytes().0)?;
+        let base_ptr = buffer.as_mut_ptr();
+
+        Ok(Self {
+            buffer: Mutex::new(buffer),
+            memory: *memory,
+            style: MemoryStyle::Dynamic {
+                offset_guard_size: 0,
+            },
+            vm_memory_definition: Box::new(UnsafeCell::new(VMMemoryDefinition {
+                base: base_ptr,
+                current_length: memory.minimum.bytes().0.try_into().unwrap(),
+            })),
+        })
+    }
+
+    /// Resize the buffer to `pages` and update the memory definition.
+    fn resize(&self, buffer: &mut B, pages: Pages) -> Result<(), MemoryError> {
+        buffer.resize(pages.bytes().0)?;
+        // update memory definition
+        unsafe {
+            let md = &mut *self.vm_memory_definition.get();
+            md.current_length = pages.bytes().0.try_into().unwrap();
+            md.base = buffer.as_mut_ptr();
+        }
+        Ok(())
+    }
+}
+
+impl<B: MemoryBuffer> Memory for BufferMemory<B> {
+    /// Returns the type for this memory.
+    fn ty(&self) -> &MemoryType {
+        &self.memory
+    }
+
+    /// Returns the memory style for this memory.
+    fn style(&self) -> &MemoryStyle {
+        &self.style
+    }
+
+    /// Returns the number of allocated wasm pages.
+    fn size(&self) -> Pages {
+        unsafe {
+            let ptr = self.vm_memory_definition.get();
+            Bytes::from((*ptr).current_length).into()
+        }
+    }
+
+    /// Grow memory by the specified amount of wasm pages, resizing the
+    /// buffer.
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let prev_pages = self.size();
+        // Optimization of memory.grow 0 calls.
+        if delta.0 == 0 {
+            return Ok(prev_pages);
+        }
+
+        let new_pages = checked_grow(prev_pages, delta, self.memory.maximum)?;
+        self.resize(&mut buffer, new_pages)?;
+
+        Ok(prev_pages)
+    }
+
+    /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        let _buffer_guard = self.buffer.lock().unwrap();
+        let ptr = self.vm_memory_definition.as_ref() as *const UnsafeCell<VMMemoryDefinition>
+            as *const VMMemoryDefinition as *mut VMMemoryDefinition;
+        unsafe { NonNull::new_unchecked(ptr) }
+    }
+
+    /// Reset the memory to `minimum` zeroed pages.
+    fn reset(&self) -> Result<(), MemoryError> {
+        let mut buffer = self.buffer.lock().unwrap();
+        self.resize(&mut buffer, Pages(0))?;
+        self.resize(&mut buffer, self.memory.minimum)
+    }
 }
@@ -25,6 +25,9 @@ pub struct Mmap {
     // the coordination all happens at the OS layer.
     ptr: usize,
     len: usize,
+    // Whether part of the mapping is a copy-on-write view of an `MmapImage`.
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    cow: bool,
 }
 
 impl Mmap {
@@ -37,6 +40,7 @@ impl Mmap {
         Self {
             ptr: empty.as_ptr() as usize,
             len: 0,
+            cow: false,
         }
     }
 
@@ -85,6 +89,7 @@ impl Mmap {
             Self {
                 ptr: ptr as usize,
                 len: mapping_size,
+                cow: false,
             }
         } else {
             // Reserve the mapping size.
@@ -105,6 +110,7 @@ impl Mmap {
             let mut result = Self {
                 ptr: ptr as usize,
                 len: mapping_size,
+                cow: false,
             };
 
             if accessible_size != 0 {
@@ -155,6 +161,7 @@ impl Mmap {
             Self {
                 ptr: ptr as usize,
                 len: mapping_size,
+                cow: false,
             }
         } else {
             // Reserve the mapping size.
@@ -167,6 +174,7 @@ impl Mmap {
             let mut result = Self {
                 ptr: ptr as usize,
                 len: mapping_size,
+                cow: false,
             };
 
             if accessible_size != 0 {
@@ -227,6 +235,152 @@ impl Mmap {
         Ok(())
     }
 
+    /// Zero the whole mapping and leave only its first `accessible_size` bytes
+    /// accessible, releasing the dirty pages back to the OS.
+    /// `accessible_size` must be a native page-size multiple within `self`.
+    #[cfg(not(target_os = "windows"))]
+    pub fn reset(&mut self, accessible_size: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(accessible_size & (page_size - 1), 0);
+        assert_le!(accessible_size, self.len);
+
+        if self.len == 0 {
+            return Ok(());
+        }
+
+        let ptr = self.ptr as *mut libc::c_void;
+        if self.cow {
+            // Pages of an image would read back as the image contents after
+            // `MADV_DONTNEED`, so replace them with anonymous ones instead.
+            let new_ptr = unsafe {
+                libc::mmap(
+                    ptr,
+                    self.len,
+                    libc::PROT_NONE,
+                    libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+                    -1,
+                    0,
+                )
+            };
+            if new_ptr as isize == -1_isize {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            self.cow = false;
+            if accessible_size != 0 {
+                let ptr = self.ptr as *const u8;
+                unsafe { region::protect(ptr, accessible_size, region::Protection::READ_WRITE) }
+                    .map_err(|e| e.to_string())?;
+            }
+            return Ok(());
+        }
+
+        // Private anonymous pages read back as zero after `MADV_DONTNEED`.
+        if unsafe { libc::madvise(ptr, self.len, libc::MADV_DONTNEED) } != 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        if accessible_size != self.len {
+            let ptr = self.ptr as *const u8;
+            unsafe {
+                region::protect(
+                    ptr.add(accessible_size),
+                    self.len - accessible_size,
+                    region::Protection::NONE,
+                )
+            }
+            .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    /// Zero the whole mapping and leave only its first `accessible_size` bytes
+    /// accessible, releasing the dirty pages back to the OS.
+    /// `accessible_size` must be a native page-size multiple within `self`.
+    #[cfg(target_os = "windows")]
+    pub fn reset(&mut self, accessible_size: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::{VirtualAlloc, VirtualFree};
+        use winapi::um::winnt::{MEM_COMMIT, MEM_DECOMMIT, PAGE_READWRITE};
+        let page_size = region::page::size();
+        assert_eq!(accessible_size & (page_size - 1), 0);
+        assert_le!(accessible_size, self.len);
+
+        if self.len == 0 {
+            return Ok(());
+        }
+
+        // Decommitted pages are zero-filled when they are committed again.
+        if unsafe { VirtualFree(self.ptr as *mut c_void, self.len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        if accessible_size != 0
+            && unsafe {
+                VirtualAlloc(
+                    self.ptr as *mut c_void,
+                    accessible_size,
+                    MEM_COMMIT,
+                    PAGE_READWRITE,
+                )
+            }
+            .is_null()
+        {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Map the contents of `image` at the start of the mapping, making them
+    /// accessible. Writes to those pages don't affect the image.
+    /// The image must fit within `self`'s reserved memory.
+    #[cfg(target_os = "linux")]
+    pub fn map_image(&mut self, image: &MmapImage) -> Result<(), String> {
+        use std::os::unix::io::AsRawFd;
+        assert_le!(image.len(), self.len);
+
+        if image.is_empty() {
+            return Ok(());
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                self.ptr as *mut libc::c_void,
+                image.len(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_FIXED,
+                image.file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr as isize == -1_isize {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        self.cow = true;
+
+        Ok(())
+    }
+
+    /// Copy the contents of `image` at the start of the mapping, making them
+    /// accessible.
+    /// The image must fit within `self`'s reserved memory.
+    #[cfg(not(target_os = "linux"))]
+    pub fn map_image(&mut self, image: &MmapImage) -> Result<(), String> {
+        assert_le!(image.len(), self.len);
+
+        if image.is_empty() {
+            return Ok(());
+        }
+
+        if image.len() != self.len {
+            self.make_accessible(0, image.len())?;
+        }
+        self.as_mut_slice()[..image.len()].copy_from_slice(&image.data);
+
+        Ok(())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
@@ -279,9 +433,102 @@ impl Drop for Mmap {
     }
 }
 
+/// A read-only, page-aligned image of memory contents, which can be
+/// mapped into many `Mmap`s with [`Mmap::map_image`].
+///
+/// On Linux the image lives in a sealed `memfd` and is mapped
+/// copy-on-write; elsewhere it is copied.
+#[derive(Debug)]
+pub struct MmapImage {
+    #[cfg(target_os = "linux")]
+    file: std::fs::File,
+    #[cfg(not(target_os = "linux"))]
+    data: Vec<u8>,
+    len: usize,
+}
+
+impl MmapImage {
+    /// Create a new image holding a copy of `data`, whose length must be
+    /// a native page-size multiple.
+    #[cfg(target_os = "linux")]
+    pub fn new(data: &[u8]) -> Result<Self, String> {
+        use std::io::Write;
+        use std::os::unix::io::FromRawFd;
+        let page_size = region::page::size();
+        assert_eq!(data.len() & (page_size - 1), 0);
+
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_memfd_create,
+                b"wasmer-image\0".as_ptr(),
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            )
+        };
+        if fd == -1 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        let mut file = unsafe { std::fs::File::from_raw_fd(fd as libc::c_int) };
+        file.write_all(data).map_err(|e| e.to_string())?;
+
+        // Seal the image so the mappings can't observe any change to it.
+        let seals =
+            libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+        if unsafe { libc::fcntl(fd as libc::c_int, libc::F_ADD_SEALS, seals) } == -1 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(Self {
+            file,
+            len: data.len(),
+        })
+    }
+
+    /// Create a new image holding a copy of `data`, whose length must be
+    /// a native page-size multiple.
+    #[cfg(not(target_os = "linux"))]
+    pub fn new(data: &[u8]) -> Result<Self, String> {
+        let page_size = region::page::size();
+        assert_eq!(data.len() & (page_size - 1), 0);
+
+        Ok(Self {
+            data: data.to_vec(),
+            len: data.len(),
+        })
+    }
+
+    /// Copy the contents of the image into `dst`, which must be as long
+    /// as the image.
+    #[cfg(target_os = "linux")]
+    pub fn read_into(&self, dst: &mut [u8]) -> Result<(), String> {
+        use std::os::unix::fs::FileExt;
+        assert_eq!(dst.len(), self.len);
+        self.file.read_exact_at(dst, 0).map_err(|e| e.to_string())
+    }
+
+    /// Copy the contents of the image into `dst`, which must be as long
+    /// as the image.
+    #[cfg(not(target_os = "linux"))]
+    pub fn read_into(&self, dst: &mut [u8]) -> Result<(), String> {
+        assert_eq!(dst.len(), self.len);
+        dst.copy_from_slice(&self.data);
+        Ok(())
+    }
+
+    /// Return the length of the image.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return whether the image is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 fn _assert() {
     fn _assert_send_sync<T: Send + Sync>() {}
     _assert_send_sync::<Mmap>();
+    _assert_send_sync::<MmapImage>();
 }
 
 #[cfg(test)]
@@ -0,0 +1,74 @@
+//! Snapshots of the state of an instance.
+//!
+//! An `InstanceSnapshot` is taken with `InstanceHandle::snapshot` and
+//! applied to new instances of the same module with
+//! `InstanceHandle::restore`, instead of running their initializers.
+
+use crate::memory::MemoryError;
+use crate::mmap::MmapImage;
+use crate::module::ModuleInfo;
+use crate::vmcontext::VMGlobalDefinition;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{
+    DataIndex, ElemIndex, FunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex,
+};
+
+/// The state of an instance: its local memories, tables and globals,
+/// and the passive segments it didn't drop yet.
+pub struct InstanceSnapshot {
+    pub(crate) module: Arc<ModuleInfo>,
+    pub(crate) memories: PrimaryMap<LocalMemoryIndex, MmapImage>,
+    pub(crate) tables: PrimaryMap<LocalTableIndex, Box<[TableElement]>>,
+    pub(crate) globals: PrimaryMap<LocalGlobalIndex, VMGlobalDefinition>,
+    pub(crate) passive_elements: HashSet<ElemIndex>,
+    pub(crate) passive_data: HashSet<DataIndex>,
+}
+
+/// An error while taking a snapshot of an instance.
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    /// A memory couldn't be captured.
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+
+    /// A table holds a function of another instance or a host function,
+    /// which the snapshot can't keep alive.
+    #[error("The element {index} of the table {table} is a function of another instance")]
+    ForeignTableElement {
+        /// The index of the table, among the tables defined by the instance.
+        table: u32,
+        /// The index of the element in the table.
+        index: u32,
+    },
+}
+
+impl InstanceSnapshot {
+    /// Return a reference-counting pointer to the module the snapshot
+    /// was taken from.
+    pub fn module(&self) -> &Arc<ModuleInfo> {
+        &self.module
+    }
+}
+
+impl fmt::Debug for InstanceSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstanceSnapshot")
+            .field("memories", &self.memories)
+            .field("tables", &self.tables.len())
+            .field("globals", &self.globals.len())
+            .finish()
+    }
+}
+
+/// An element of a table in a snapshot.
+#[derive(Debug, Clone)]
+pub(crate) enum TableElement {
+    /// A null reference.
+    Null,
+    /// A function of the module, resolved again in the restored instance.
+    Function(FunctionIndex),
+}
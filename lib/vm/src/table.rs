@@ -5,11 +5,13 @@
 //!
 //! `Table` is to WebAssembly tables what `LinearMemory` is to WebAssembly linear memories.
 
+use crate::export::{HostFunctionEnv, WeakHostFunctionEnv};
 use crate::trap::{Trap, TrapCode};
-use crate::vmcontext::{VMCallerCheckedAnyfunc, VMTableDefinition};
+use crate::vmcontext::{VMCallerCheckedAnyfunc, VMContext, VMTableDefinition};
 use serde::{Deserialize, Serialize};
 use std::borrow::{Borrow, BorrowMut};
 use std::cell::UnsafeCell;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ptr::NonNull;
@@ -45,16 +47,59 @@ pub trait Table: fmt::Debug + Send + Sync {
     /// Returns `None` if the index is out of bounds.
     fn get(&self, index: u32) -> Option<VMCallerCheckedAnyfunc>;
 
+    /// Get the environment of the host function at the specified element.
+    ///
+    /// Returns `None` if the index is out of bounds or the element isn't
+    /// a host function owning an environment.
+    fn get_host_env(&self, index: u32) -> Option<HostFunctionEnv>;
+
     /// Set reference to the specified element.
     ///
     /// # Errors
     ///
     /// Returns an error if the index is out of bounds.
-    fn set(&self, index: u32, func: VMCallerCheckedAnyfunc) -> Result<(), Trap>;
+    fn set(&self, index: u32, func: VMCallerCheckedAnyfunc) -> Result<(), Trap> {
+        self.set_with_host_env(index, func, None)
+    }
+
+    /// Set reference to the specified element, along with the environment
+    /// of the function if it's a host function owning one.
+    ///
+    /// Elements only hold the `vmctx` of their function, so the table
+    /// keeps the environment alive until the element is overwritten or
+    /// the table is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is out of bounds.
+    fn set_with_host_env(
+        &self,
+        index: u32,
+        func: VMCallerCheckedAnyfunc,
+        host_env: Option<HostFunctionEnv>,
+    ) -> Result<(), Trap>;
+
+    /// Sets the environment shared by the functions of the instance
+    /// owning the table, whose `vmctx` is given.
+    ///
+    /// The elements holding functions of the owner don't keep it alive,
+    /// as the table would keep its own instance alive, but it's returned
+    /// by [`Table::get_host_env`] for them.
+    ///
+    /// Tables without owners ignore it, which is the default.
+    fn set_owner_host_env(&self, _vmctx: *mut VMContext, _host_env: Option<WeakHostFunctionEnv>) {}
 
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition>;
 
+    /// Reset the table to `minimum` null elements, so it can be reused by
+    /// a new instantiation.
+    ///
+    /// Tables that can't be recycled return an error, which is the default.
+    fn reset(&self) -> Result<(), String> {
+        Err("this table doesn't support being reset".to_string())
+    }
+
     /// Copy `len` elements from `src_table[src_index..]` into `dst_table[dst_index..]`.
     ///
     /// # Errors
@@ -90,11 +135,11 @@ pub trait Table: fmt::Debug + Send + Sync {
         // TODO: investigate replacing this get/set loop with a `memcpy`.
         if dst_index <= src_index {
             for (s, d) in (srcs).zip(dsts) {
-                self.set(d, src_table.get(s).unwrap())?;
+                self.set_with_host_env(d, src_table.get(s).unwrap(), src_table.get_host_env(s))?;
             }
         } else {
             for (s, d) in srcs.rev().zip(dsts.rev()) {
-                self.set(d, src_table.get(s).unwrap())?;
+                self.set_with_host_env(d, src_table.get(s).unwrap(), src_table.get_host_env(s))?;
             }
         }
 
@@ -107,6 +152,11 @@ pub trait Table: fmt::Debug + Send + Sync {
 pub struct LinearTable {
     // TODO: we can remove the mutex by using atomic swaps and preallocating the max table size
     vec: Mutex<Vec<VMCallerCheckedAnyfunc>>,
+    /// The environments of the host functions in `vec`, by element.
+    host_envs: Mutex<HashMap<u32, HostFunctionEnv>>,
+    /// The `vmctx` of the instance owning the table and the environment
+    /// of its functions, which the elements don't hold.
+    owner_host_env: Mutex<Option<(usize, WeakHostFunctionEnv)>>,
     maximum: Option<u32>,
     /// The WebAssembly table description.
     table: TableType,
@@ -142,6 +192,8 @@ impl LinearTable {
         match style {
             TableStyle::CallerChecksSignature => Ok(Self {
                 vec: Mutex::new(vec),
+                host_envs: Mutex::new(HashMap::new()),
+                owner_host_env: Mutex::new(None),
                 maximum: table.maximum,
                 table: *table,
                 style: style.clone(),
@@ -206,23 +258,56 @@ impl Table for LinearTable {
         vec_guard.borrow().get(index as usize).cloned()
     }
 
-    /// Set reference to the specified element.
+    /// Get the environment of the host function at the specified element.
+    fn get_host_env(&self, index: u32) -> Option<HostFunctionEnv> {
+        if let Some(host_env) = self.host_envs.lock().unwrap().get(&index) {
+            return Some(host_env.clone());
+        }
+        let vmctx = self.get(index)?.vmctx as usize;
+        match &*self.owner_host_env.lock().unwrap() {
+            Some((owner, host_env)) if *owner == vmctx => host_env.upgrade(),
+            _ => None,
+        }
+    }
+
+    /// Set reference to the specified element, along with the environment
+    /// of its host function.
     ///
     /// # Errors
     ///
     /// Returns an error if the index is out of bounds.
-    fn set(&self, index: u32, func: VMCallerCheckedAnyfunc) -> Result<(), Trap> {
+    fn set_with_host_env(
+        &self,
+        index: u32,
+        func: VMCallerCheckedAnyfunc,
+        host_env: Option<HostFunctionEnv>,
+    ) -> Result<(), Trap> {
         let mut vec_guard = self.vec.lock().unwrap();
         let vec = vec_guard.borrow_mut();
         match vec.get_mut(index as usize) {
             Some(slot) => {
+                let owned = matches!(
+                    &*self.owner_host_env.lock().unwrap(),
+                    Some((owner, _)) if *owner == func.vmctx as usize
+                );
                 *slot = func;
+                let mut host_envs = self.host_envs.lock().unwrap();
+                match host_env {
+                    Some(host_env) if !owned => host_envs.insert(index, host_env),
+                    _ => host_envs.remove(&index),
+                };
                 Ok(())
             }
             None => Err(Trap::new_from_runtime(TrapCode::TableAccessOutOfBounds)),
         }
     }
 
+    /// Set the environment shared by the functions of the instance owning
+    /// the table.
+    fn set_owner_host_env(&self, vmctx: *mut VMContext, host_env: Option<WeakHostFunctionEnv>) {
+        *self.owner_host_env.lock().unwrap() = host_env.map(|host_env| (vmctx as usize, host_env));
+    }
+
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition> {
         let _vec_guard = self.vec.lock().unwrap();
@@ -230,4 +315,25 @@ impl Table for LinearTable {
             as *const VMTableDefinition as *mut VMTableDefinition;
         unsafe { NonNull::new_unchecked(ptr) }
     }
+
+    /// Reset the table to `minimum` null elements.
+    fn reset(&self) -> Result<(), String> {
+        let mut vec_guard = self.vec.lock().unwrap();
+        let vec = vec_guard.borrow_mut();
+        let minimum = self.table.minimum;
+        vec.clear();
+        self.host_envs.lock().unwrap().clear();
+        *self.owner_host_env.lock().unwrap() = None;
+        vec.resize(
+            usize::try_from(minimum).unwrap(),
+            VMCallerCheckedAnyfunc::default(),
+        );
+        // update table definition
+        unsafe {
+            let td = &mut *self.vm_table_definition.get();
+            td.current_elements = minimum;
+            td.base = vec.as_mut_ptr() as _;
+        }
+        Ok(())
+    }
 }
@@ -265,10 +265,14 @@ mod test_vmglobal_import {
 /// The fields compiled code needs to access to utilize a WebAssembly linear
 /// memory defined within the instance, namely the start address and the
 /// size in bytes.
+///
+/// See the [`Memory`](crate::Memory) trait for the invariants the memory
+/// owning the definition must uphold.
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
 pub struct VMMemoryDefinition {
-    /// The start address which is always valid, even if the memory grows.
+    /// The start address of the memory, which only moves when a memory
+    /// with a dynamic style grows.
     pub base: *mut u8,
 
     /// The current logical size of this linear memory in bytes.
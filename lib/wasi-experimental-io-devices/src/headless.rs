@@ -0,0 +1,184 @@
+//! Renders the framebuffer off-screen, to run and test modules without
+//! a display.
+
+use crate::util::InputEvent;
+use crate::FrameBufferBackend;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The file formats frames can be saved in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// The binary (`P6`) PPM format.
+    Ppm,
+}
+
+impl ImageFormat {
+    /// The extension of the files of this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+/// A frame drawn by a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// The pixels, row by row, as `0RGB` values.
+    pub pixels: Vec<u32>,
+}
+
+impl Frame {
+    /// Gets the pixel at (`x`, `y`), as a `0RGB` value.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.pixels.get((y * self.width + x) as usize).copied()
+    }
+
+    /// The pixels as `RGB` bytes.
+    fn rgb_bytes(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| {
+                let [_, r, g, b] = pixel.to_be_bytes();
+                vec![r, g, b]
+            })
+            .collect()
+    }
+
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.rgb_bytes())
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.rgb_bytes())?;
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P, format: ImageFormat) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        match format {
+            ImageFormat::Png => self.write_png(writer),
+            ImageFormat::Ppm => self.write_ppm(writer),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HeadlessState {
+    last_frame: Option<Frame>,
+    frame_count: usize,
+    inputs: VecDeque<InputEvent>,
+    /// The directory each frame is saved to, and in which format.
+    frames_dir: Option<(PathBuf, ImageFormat)>,
+    /// The error of the last frame that couldn't be saved.
+    save_error: Option<io::Error>,
+}
+
+/// Renders the framebuffer in memory, with scripted input events.
+///
+/// It is a handle: its clones share the same frames and inputs, so
+/// one of them can be given to the module while another inspects what
+/// it drew.
+///
+/// ```
+/// # use wasmer_wasi::WasiState;
+/// # use wasmer_wasi_experimental_io_devices::{initialize_with_backend, HeadlessBackend, InputEvent};
+/// let backend = HeadlessBackend::new();
+/// let module_backend = backend.clone();
+/// let wasi_env = WasiState::new("program")
+///     .setup_fs(Box::new(move |fs| {
+///         initialize_with_backend(fs, Box::new(module_backend.clone()))
+///     }))
+///     .finalize()
+///     .unwrap();
+///
+/// backend.push_input(InputEvent::KeyPress(b'A'));
+/// // ... run the module
+/// let frame = backend.last_frame();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeadlessBackend {
+    state: Arc<Mutex<HeadlessState>>,
+}
+
+impl HeadlessBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Saves every frame drawn by the module into `dir`, as
+    /// `frame-00000.png`, `frame-00001.png`, ...
+    pub fn save_frames<P: Into<PathBuf>>(self, dir: P, format: ImageFormat) -> Self {
+        self.state().frames_dir = Some((dir.into(), format));
+        self
+    }
+
+    fn state(&self) -> MutexGuard<'_, HeadlessState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Queues an input event, to be read by the module.
+    pub fn push_input(&self, input_event: InputEvent) {
+        self.state().inputs.push_back(input_event);
+    }
+
+    /// Gets the last frame drawn by the module.
+    pub fn last_frame(&self) -> Option<Frame> {
+        self.state().last_frame.clone()
+    }
+
+    /// Gets the number of frames drawn by the module.
+    pub fn frame_count(&self) -> usize {
+        self.state().frame_count
+    }
+
+    /// Takes the error of the last frame that couldn't be saved.
+    pub fn take_save_error(&self) -> Option<io::Error> {
+        self.state().save_error.take()
+    }
+}
+
+impl FrameBufferBackend for HeadlessBackend {
+    fn resize(&mut self, _x: u32, _y: u32) {}
+
+    fn draw(&mut self, buffer: &[u32], x: u32, y: u32) {
+        let mut state = self.state();
+        let frame = Frame {
+            width: x,
+            height: y,
+            pixels: buffer.to_vec(),
+        };
+        if let Some((dir, format)) = state.frames_dir.as_ref() {
+            let path = dir.join(format!(
+                "frame-{:05}.{}",
+                state.frame_count,
+                format.extension()
+            ));
+            if let Err(error) = frame.save(path, *format) {
+                state.save_error = Some(error);
+            }
+        }
+        state.last_frame = Some(frame);
+        state.frame_count += 1;
+    }
+
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        self.state().inputs.drain(..).collect()
+    }
+}
@@ -1,27 +1,70 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeSet, VecDeque};
+use std::collections::VecDeque;
 use std::convert::TryInto;
 use std::io::{Read, Seek, SeekFrom, Write};
 use tracing::debug;
 use wasmer_wasi::types::*;
 use wasmer_wasi::{Fd, WasiFile, WasiFs, WasiFsError, ALL_RIGHTS, VIRTUAL_ROOT_FD};
 
-use minifb::{Key, KeyRepeat, MouseButton, Scale, Window, WindowOptions};
-
+mod headless;
 mod util;
+#[cfg(feature = "window")]
+mod window;
+
+pub use headless::{Frame, HeadlessBackend, ImageFormat};
+pub use util::{InputEvent, MouseButton};
+#[cfg(feature = "window")]
+pub use window::WindowBackend;
 
 use util::*;
 
 use std::cell::RefCell;
 std::thread_local! {
-    pub(crate) static FRAMEBUFFER_STATE: RefCell<FrameBufferState> =
-        RefCell::new(FrameBufferState::new()
-);
+    pub(crate) static FRAMEBUFFER_STATE: RefCell<Option<FrameBufferState>> = RefCell::new(None);
 }
 
 pub const MAX_X: u32 = 8192;
 pub const MAX_Y: u32 = 4320;
 
+/// The default size of the framebuffer.
+const DEFAULT_X: u32 = 100;
+const DEFAULT_Y: u32 = 200;
+
+/// Where the framebuffer is drawn, and where its input events come from.
+pub trait FrameBufferBackend {
+    /// Called when the module changes the size of the framebuffer.
+    fn resize(&mut self, x: u32, y: u32);
+
+    /// Called when the module draws `buffer`, whose pixels are `0RGB`
+    /// values, row by row.
+    fn draw(&mut self, buffer: &[u32], x: u32, y: u32);
+
+    /// Gets the input events that happened since the last call.
+    fn poll_input(&mut self) -> Vec<InputEvent>;
+}
+
+/// The backend used by [`initialize`]: a window when the `window`
+/// feature is enabled, otherwise the headless backend.
+fn default_backend() -> Box<dyn FrameBufferBackend> {
+    #[cfg(feature = "window")]
+    {
+        Box::new(WindowBackend::new(DEFAULT_X, DEFAULT_Y))
+    }
+    #[cfg(not(feature = "window"))]
+    {
+        Box::new(HeadlessBackend::new())
+    }
+}
+
+/// Runs `f` with the framebuffer state of this thread, created with
+/// the default backend if it wasn't initialized.
+fn with_framebuffer_state<T>(f: impl FnOnce(&mut FrameBufferState) -> T) -> T {
+    FRAMEBUFFER_STATE.with(|fb| {
+        let mut fb = fb.borrow_mut();
+        f(fb.get_or_insert_with(|| FrameBufferState::new(default_backend())))
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum FrameBufferFileType {
     Buffer,
@@ -30,7 +73,6 @@ pub enum FrameBufferFileType {
     Input,
 }
 
-#[derive(Debug)]
 pub(crate) struct FrameBufferState {
     // double buffered
     pub data_1: Vec<u32>,
@@ -40,63 +82,42 @@ pub(crate) struct FrameBufferState {
     pub y_size: u32,
     pub front_buffer: bool,
 
-    pub window: Window,
+    pub backend: Box<dyn FrameBufferBackend>,
 
-    pub last_mouse_pos: (u32, u32),
     pub inputs: VecDeque<InputEvent>,
-    pub keys_pressed: BTreeSet<minifb::Key>,
 }
 
 impl FrameBufferState {
     /// an arbitrary large number
     const MAX_INPUTS: usize = 128;
 
-    pub fn new() -> Self {
-        let x = 100;
-        let y = 200;
-
-        let window = Self::create_window(x, y);
+    pub fn new(backend: Box<dyn FrameBufferBackend>) -> Self {
+        let (x, y) = (DEFAULT_X, DEFAULT_Y);
 
         Self {
-            data_1: vec![0; x * y],
-            data_2: vec![0; x * y],
+            data_1: vec![0; (x * y) as usize],
+            data_2: vec![0; (x * y) as usize],
 
-            x_size: x as u32,
-            y_size: y as u32,
+            x_size: x,
+            y_size: y,
             front_buffer: true,
 
-            window,
-            last_mouse_pos: (0, 0),
+            backend,
             inputs: VecDeque::with_capacity(Self::MAX_INPUTS),
-            keys_pressed: BTreeSet::new(),
         }
     }
 
-    fn create_window(x: usize, y: usize) -> Window {
-        Window::new(
-            "Wasmer Experimental FrameBuffer",
-            x,
-            y,
-            WindowOptions {
-                resize: true,
-                scale: Scale::FitScreen,
-                ..WindowOptions::default()
-            },
-        )
-        .unwrap()
-    }
-
     pub fn resize(&mut self, x: u32, y: u32) -> Option<()> {
         if x >= MAX_X || y >= MAX_Y {
             return None;
         }
         self.x_size = x;
-        self.y_size = x;
+        self.y_size = y;
 
         self.data_1.resize((x * y) as usize, 0);
         self.data_2.resize((x * y) as usize, 0);
 
-        self.window = Self::create_window(x as usize, y as usize);
+        self.backend.resize(x, y);
 
         Some(())
     }
@@ -111,69 +132,19 @@ impl FrameBufferState {
     }
 
     pub fn fill_input_buffer(&mut self) -> Option<()> {
-        let keys_pressed = self.keys_pressed.iter().cloned().collect::<Vec<Key>>();
-        if !self.window.is_open() {
-            self.push_input_event(InputEvent::WindowClosed)?;
-        }
-        for key in keys_pressed {
-            if self.window.is_key_released(key) {
-                self.keys_pressed.remove(&key);
-                self.push_input_event(InputEvent::KeyRelease(key))?;
-            }
-        }
-        let keys = self.window.get_keys_pressed(KeyRepeat::No)?;
-        for key in keys {
-            self.keys_pressed.insert(key.clone());
-            self.push_input_event(InputEvent::KeyPress(key))?;
-        }
-
-        let mouse_position = self.window.get_mouse_pos(minifb::MouseMode::Clamp)?;
-        if mouse_position.0 as u32 != self.last_mouse_pos.0
-            || mouse_position.1 as u32 != self.last_mouse_pos.1
-        {
-            self.last_mouse_pos = (mouse_position.0 as u32, mouse_position.1 as u32);
-            self.push_input_event(InputEvent::MouseMoved(
-                self.last_mouse_pos.0,
-                self.last_mouse_pos.1,
-            ))?;
-        }
-
-        if self.window.get_mouse_down(MouseButton::Left) {
-            self.push_input_event(InputEvent::MouseEvent(
-                mouse_position.0 as u32,
-                mouse_position.1 as u32,
-                MouseButton::Left,
-            ))?;
-        }
-        if self.window.get_mouse_down(MouseButton::Right) {
-            self.push_input_event(InputEvent::MouseEvent(
-                mouse_position.0 as u32,
-                mouse_position.1 as u32,
-                MouseButton::Right,
-            ))?;
-        }
-        if self.window.get_mouse_down(MouseButton::Middle) {
-            self.push_input_event(InputEvent::MouseEvent(
-                mouse_position.0 as u32,
-                mouse_position.1 as u32,
-                MouseButton::Middle,
-            ))?;
+        for input_event in self.backend.poll_input() {
+            self.push_input_event(input_event)?;
         }
         Some(())
     }
 
     pub fn draw(&mut self) {
-        self.window
-            .update_with_buffer(
-                if self.front_buffer {
-                    &self.data_1[..]
-                } else {
-                    &self.data_2[..]
-                },
-                self.x_size.try_into().unwrap(),
-                self.y_size.try_into().unwrap(),
-            )
-            .expect("Internal error! Failed to draw to framebuffer");
+        let buffer = if self.front_buffer {
+            &self.data_1[..]
+        } else {
+            &self.data_2[..]
+        };
+        self.backend.draw(buffer, self.x_size, self.y_size);
     }
 
     #[inline]
@@ -233,67 +204,64 @@ pub struct FrameBuffer {
 impl Read for FrameBuffer {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         let cursor = self.cursor as usize;
-        FRAMEBUFFER_STATE.with(|fb| {
-            let mut fb_state = fb.borrow_mut();
-            match self.fb_type {
-                FrameBufferFileType::Buffer => {
-                    let mut bytes_copied = 0;
+        with_framebuffer_state(|fb_state| match self.fb_type {
+            FrameBufferFileType::Buffer => {
+                let mut bytes_copied = 0;
 
-                    for i in 0..buf.len() {
-                        if let Some(byte) = fb_state.get_byte(cursor + i) {
-                            buf[i] = byte;
-                            bytes_copied += 1;
-                        } else {
-                            break;
-                        }
+                for i in 0..buf.len() {
+                    if let Some(byte) = fb_state.get_byte(cursor + i) {
+                        buf[i] = byte;
+                        bytes_copied += 1;
+                    } else {
+                        break;
                     }
-
-                    self.cursor += bytes_copied;
-                    Ok(bytes_copied as usize)
                 }
-                FrameBufferFileType::Resolution => {
-                    let resolution_data = format!("{}x{}", fb_state.x_size, fb_state.y_size);
 
-                    let mut bytes = resolution_data.bytes().skip(cursor);
-                    let bytes_to_copy = std::cmp::min(buf.len(), bytes.clone().count());
+                self.cursor += bytes_copied;
+                Ok(bytes_copied as usize)
+            }
+            FrameBufferFileType::Resolution => {
+                let resolution_data = format!("{}x{}", fb_state.x_size, fb_state.y_size);
 
-                    for i in 0..bytes_to_copy {
-                        buf[i] = bytes.next().unwrap();
-                    }
+                let mut bytes = resolution_data.bytes().skip(cursor);
+                let bytes_to_copy = std::cmp::min(buf.len(), bytes.clone().count());
 
-                    self.cursor += bytes_to_copy as u32;
-                    Ok(bytes_to_copy)
+                for i in 0..bytes_to_copy {
+                    buf[i] = bytes.next().unwrap();
                 }
 
-                FrameBufferFileType::Draw => {
-                    if buf.len() == 0 {
-                        Ok(0)
-                    } else {
-                        buf[0] = fb_state.front_buffer as u8 + b'0';
-                        Ok(1)
-                    }
+                self.cursor += bytes_to_copy as u32;
+                Ok(bytes_to_copy)
+            }
+
+            FrameBufferFileType::Draw => {
+                if buf.len() == 0 {
+                    Ok(0)
+                } else {
+                    buf[0] = fb_state.front_buffer as u8 + b'0';
+                    Ok(1)
                 }
+            }
 
-                FrameBufferFileType::Input => {
-                    let mut idx = 0;
-                    fb_state.fill_input_buffer();
-
-                    while let Some(next_elem) = fb_state.inputs.front() {
-                        let remaining_length = buf.len() - idx;
-                        let (tag_byte, data, size) = bytes_for_input_event(*next_elem);
-                        if remaining_length > 1 + size {
-                            buf[idx] = tag_byte;
-                            for i in 0..size {
-                                buf[idx + 1 + i] = data[i];
-                            }
-                            idx += 1 + size;
-                        } else {
-                            break;
+            FrameBufferFileType::Input => {
+                let mut idx = 0;
+                fb_state.fill_input_buffer();
+
+                while let Some(next_elem) = fb_state.inputs.front() {
+                    let remaining_length = buf.len() - idx;
+                    let (tag_byte, data, size) = bytes_for_input_event(*next_elem);
+                    if remaining_length > 1 + size {
+                        buf[idx] = tag_byte;
+                        for i in 0..size {
+                            buf[idx + 1 + i] = data[i];
                         }
-                        fb_state.inputs.pop_front().unwrap();
+                        idx += 1 + size;
+                    } else {
+                        break;
                     }
-                    Ok(idx)
+                    fb_state.inputs.pop_front().unwrap();
                 }
+                Ok(idx)
             }
         })
     }
@@ -335,8 +303,7 @@ impl Seek for FrameBuffer {
 impl Write for FrameBuffer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let cursor = self.cursor as usize;
-        FRAMEBUFFER_STATE.with(|fb| {
-            let mut fb_state = fb.borrow_mut();
+        with_framebuffer_state(|fb_state| {
             match self.fb_type {
                 FrameBufferFileType::Buffer => {
                     let mut bytes_copied = 0;
@@ -428,7 +395,22 @@ impl WasiFile for FrameBuffer {
     }
 }
 
+/// Creates the framebuffer files in `fs`, displaying the framebuffer
+/// with the default backend.
 pub fn initialize(fs: &mut WasiFs) -> Result<(), String> {
+    initialize_with_backend(fs, default_backend())
+}
+
+/// Creates the framebuffer files in `fs`, displaying the framebuffer
+/// with `backend`.
+///
+/// The framebuffer is shared by the modules running on this thread.
+pub fn initialize_with_backend(
+    fs: &mut WasiFs,
+    backend: Box<dyn FrameBufferBackend>,
+) -> Result<(), String> {
+    FRAMEBUFFER_STATE.with(|fb| *fb.borrow_mut() = Some(FrameBufferState::new(backend)));
+
     let frame_buffer_file = Box::new(FrameBuffer {
         fb_type: FrameBufferFileType::Buffer,
         cursor: 0,
@@ -515,3 +497,76 @@ pub fn initialize(fs: &mut WasiFs) -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_buffer(fb_type: FrameBufferFileType) -> FrameBuffer {
+        FrameBuffer { fb_type, cursor: 0 }
+    }
+
+    fn headless_state() -> HeadlessBackend {
+        let backend = HeadlessBackend::new();
+        let module_backend = backend.clone();
+        FRAMEBUFFER_STATE
+            .with(|fb| *fb.borrow_mut() = Some(FrameBufferState::new(Box::new(module_backend))));
+        backend
+    }
+
+    #[test]
+    fn draw_headless() {
+        let backend = headless_state();
+        with_framebuffer_state(|fb_state| fb_state.resize(4, 2)).unwrap();
+
+        let mut resolution = [0; 8];
+        let len = frame_buffer(FrameBufferFileType::Resolution)
+            .read(&mut resolution)
+            .unwrap();
+        assert_eq!(&resolution[..len], b"4x2");
+
+        let pixels: Vec<u8> = (0..8u32)
+            .flat_map(|i| (i * 0x10_20_30).to_le_bytes().to_vec())
+            .collect();
+        let mut buffer = frame_buffer(FrameBufferFileType::Buffer);
+        assert_eq!(buffer.write(&pixels).unwrap(), 32);
+        assert_eq!(backend.frame_count(), 0);
+        assert_eq!(
+            frame_buffer(FrameBufferFileType::Draw).write(b"1").unwrap(),
+            1
+        );
+
+        let frame = backend.last_frame().unwrap();
+        assert_eq!(backend.frame_count(), 1);
+        assert_eq!((frame.width, frame.height), (4, 2));
+        assert_eq!(frame.pixel(1, 1), Some(5 * 0x10_20_30));
+        assert_eq!(frame.pixel(4, 0), None);
+
+        let mut ppm = Vec::new();
+        frame.write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n4 2\n255\n"));
+        assert_eq!(&ppm[ppm.len() - 3..], &[0x70, 0xE1, 0x50]);
+
+        let mut png = Vec::new();
+        frame.write_png(&mut png).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn input_headless() {
+        let backend = headless_state();
+        backend.push_input(InputEvent::KeyPress(b'A'));
+        backend.push_input(InputEvent::MouseMoved(3, 4));
+
+        let mut input = [0; 16];
+        let len = frame_buffer(FrameBufferFileType::Input)
+            .read(&mut input)
+            .unwrap();
+        assert_eq!(&input[..len], &[1, b'A', 2, 3, 0, 0, 0, 4, 0, 0, 0]);
+
+        let len = frame_buffer(FrameBufferFileType::Input)
+            .read(&mut input)
+            .unwrap();
+        assert_eq!(len, 0);
+    }
+}
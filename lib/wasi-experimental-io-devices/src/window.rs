@@ -0,0 +1,232 @@
+//! Displays the framebuffer in a window, with `minifb`.
+
+use crate::util::{InputEvent, MouseButton};
+use crate::FrameBufferBackend;
+use minifb::{Key, KeyRepeat, Scale, Window, WindowOptions};
+use std::collections::BTreeSet;
+use std::convert::TryInto;
+
+/// Displays the framebuffer in a window, and gets the input events of
+/// the keyboard and mouse from it.
+///
+/// The window is only opened when the framebuffer is first used.
+pub struct WindowBackend {
+    window: Option<Window>,
+    x_size: u32,
+    y_size: u32,
+
+    last_mouse_pos: (u32, u32),
+    keys_pressed: BTreeSet<Key>,
+}
+
+impl WindowBackend {
+    pub fn new(x: u32, y: u32) -> Self {
+        Self {
+            window: None,
+            x_size: x,
+            y_size: y,
+
+            last_mouse_pos: (0, 0),
+            keys_pressed: BTreeSet::new(),
+        }
+    }
+
+    fn window(&mut self) -> &mut Window {
+        let (x, y) = (self.x_size as usize, self.y_size as usize);
+        self.window.get_or_insert_with(|| {
+            Window::new(
+                "Wasmer Experimental FrameBuffer",
+                x,
+                y,
+                WindowOptions {
+                    resize: true,
+                    scale: Scale::FitScreen,
+                    ..WindowOptions::default()
+                },
+            )
+            .unwrap()
+        })
+    }
+}
+
+impl FrameBufferBackend for WindowBackend {
+    fn resize(&mut self, x: u32, y: u32) {
+        self.x_size = x;
+        self.y_size = y;
+        self.window = None;
+        self.window();
+    }
+
+    fn draw(&mut self, buffer: &[u32], x: u32, y: u32) {
+        self.window()
+            .update_with_buffer(buffer, x.try_into().unwrap(), y.try_into().unwrap())
+            .expect("Internal error! Failed to draw to framebuffer");
+    }
+
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        let mut events = vec![];
+        let keys_pressed = self.keys_pressed.iter().cloned().collect::<Vec<Key>>();
+        let window = self.window();
+        if !window.is_open() {
+            events.push(InputEvent::WindowClosed);
+        }
+        let mut keys_released = vec![];
+        for key in keys_pressed {
+            if window.is_key_released(key) {
+                keys_released.push(key);
+                events.push(InputEvent::KeyRelease(map_key_to_bytes(key)));
+            }
+        }
+        let keys = window.get_keys_pressed(KeyRepeat::No).unwrap_or_default();
+        let mouse_position = window.get_mouse_pos(minifb::MouseMode::Clamp);
+        let mouse_buttons = [
+            (minifb::MouseButton::Left, MouseButton::Left),
+            (minifb::MouseButton::Right, MouseButton::Right),
+            (minifb::MouseButton::Middle, MouseButton::Middle),
+        ]
+        .iter()
+        .filter(|(button, _)| window.get_mouse_down(*button))
+        .map(|(_, button)| *button)
+        .collect::<Vec<MouseButton>>();
+
+        for key in keys_released {
+            self.keys_pressed.remove(&key);
+        }
+        for key in keys {
+            self.keys_pressed.insert(key);
+            events.push(InputEvent::KeyPress(map_key_to_bytes(key)));
+        }
+
+        if let Some(mouse_position) = mouse_position {
+            let mouse_position = (mouse_position.0 as u32, mouse_position.1 as u32);
+            if mouse_position != self.last_mouse_pos {
+                self.last_mouse_pos = mouse_position;
+                events.push(InputEvent::MouseMoved(mouse_position.0, mouse_position.1));
+            }
+            for button in mouse_buttons {
+                events.push(InputEvent::MouseEvent(
+                    mouse_position.0,
+                    mouse_position.1,
+                    button,
+                ));
+            }
+        }
+
+        events
+    }
+}
+
+pub fn map_key_to_bytes(key: Key) -> u8 {
+    match key {
+        Key::Backspace => 8,
+        Key::Tab => 9,
+        Key::NumPadEnter | Key::Enter => 13,
+        Key::LeftShift | Key::RightShift => 16,
+        Key::LeftCtrl | Key::RightCtrl => 17,
+        Key::LeftAlt | Key::RightAlt => 18,
+        Key::Pause => 19,
+        Key::CapsLock => 20,
+        Key::Escape => 27,
+        Key::Space => 32,
+        Key::PageUp => 33,
+        Key::PageDown => 34,
+        Key::End => 35,
+        Key::Home => 36,
+
+        Key::Left => 37,
+        Key::Up => 38,
+        Key::Right => 39,
+        Key::Down => 40,
+
+        Key::Insert => 45,
+        Key::Delete => 46,
+
+        Key::Key0 => 48,
+        Key::Key1 => 49,
+        Key::Key2 => 50,
+        Key::Key3 => 51,
+        Key::Key4 => 52,
+        Key::Key5 => 53,
+        Key::Key6 => 54,
+        Key::Key7 => 55,
+        Key::Key8 => 56,
+        Key::Key9 => 57,
+
+        Key::A => b'A',
+        Key::B => b'B',
+        Key::C => b'C',
+        Key::D => b'D',
+        Key::E => b'E',
+        Key::F => b'F',
+        Key::G => b'G',
+        Key::H => b'H',
+        Key::I => b'I',
+        Key::J => b'J',
+        Key::K => b'K',
+        Key::L => b'L',
+        Key::M => b'M',
+        Key::N => b'N',
+        Key::O => b'O',
+        Key::P => b'P',
+        Key::Q => b'Q',
+        Key::R => b'R',
+        Key::S => b'S',
+        Key::T => b'T',
+        Key::U => b'U',
+        Key::V => b'V',
+        Key::W => b'W',
+        Key::X => b'X',
+        Key::Y => b'Y',
+        Key::Z => b'Z',
+
+        Key::LeftSuper => 91,
+        Key::RightSuper => 92,
+
+        Key::NumPad0 => 96,
+        Key::NumPad1 => 97,
+        Key::NumPad2 => 98,
+        Key::NumPad3 => 99,
+        Key::NumPad4 => 100,
+        Key::NumPad5 => 101,
+        Key::NumPad6 => 102,
+        Key::NumPad7 => 103,
+        Key::NumPad8 => 104,
+        Key::NumPad9 => 105,
+        Key::NumPadAsterisk => 106,
+        Key::NumPadPlus => 107,
+        Key::NumPadMinus => 109,
+        Key::NumPadDot => 110,
+        Key::NumPadSlash => 111,
+
+        Key::F1 => 112,
+        Key::F2 => 113,
+        Key::F3 => 114,
+        Key::F4 => 115,
+        Key::F5 => 116,
+        Key::F6 => 117,
+        Key::F7 => 118,
+        Key::F8 => 119,
+        Key::F9 => 120,
+        Key::F10 => 121,
+        Key::F11 => 122,
+        Key::F12 => 123,
+
+        Key::NumLock => 144,
+        Key::ScrollLock => 145,
+
+        Key::Semicolon => 186,
+        Key::Equal => 187,
+        Key::Comma => 188,
+        Key::Minus => 189,
+        Key::Period => 190,
+        Key::Slash => 191,
+        Key::Backquote => 192,
+        Key::Backslash => 220,
+        Key::Apostrophe => 220,
+
+        Key::LeftBracket => 219,
+        Key::RightBracket => 221,
+
+        _ => 255,
+    }
+}
@@ -21,6 +21,7 @@ mod utils;
 
 use crate::syscalls::*;
 
+pub use crate::state::devices;
 pub use crate::state::{
     Fd, WasiFile, WasiFs, WasiFsError, WasiState, WasiStateBuilder, WasiStateCreationError,
     ALL_RIGHTS, VIRTUAL_ROOT_FD,
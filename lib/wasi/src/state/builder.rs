@@ -1,6 +1,6 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{WasiFile, WasiFs, WasiFsError, WasiState};
+use crate::state::{DeviceRegistry, WasiFile, WasiFs, WasiFsError, WasiState};
 use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
 use crate::WasiEnv;
 use std::path::{Path, PathBuf};
@@ -42,6 +42,11 @@ pub struct WasiStateBuilder {
     stdout_override: Option<Box<dyn WasiFile>>,
     stderr_override: Option<Box<dyn WasiFile>>,
     stdin_override: Option<Box<dyn WasiFile>>,
+    virtual_files: Vec<(PathBuf, Box<dyn WasiFile>)>,
+    devices: DeviceRegistry,
+    /// The devices to mount, with the path to mount them at if it's not
+    /// their default one.
+    mounted_devices: Vec<(String, Option<PathBuf>)>,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -54,6 +59,9 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
             .field("stdin_override exists", &self.stdin_override.is_some())
+            .field("virtual_files", &self.virtual_files)
+            .field("devices", &self.devices)
+            .field("mounted_devices", &self.mounted_devices)
             .finish()
     }
 }
@@ -77,6 +85,8 @@ pub enum WasiStateCreationError {
     WasiFsSetupError(String),
     #[error(transparent)]
     WasiFsError(WasiFsError),
+    #[error("unknown device: `{0}`")]
+    UnknownDevice(String),
 }
 
 fn validate_mapped_dir_alias(alias: &str) -> Result<(), WasiStateCreationError> {
@@ -298,6 +308,53 @@ impl WasiStateBuilder {
         self
     }
 
+    /// Add a file which isn't backed by the host filesystem at `path`,
+    /// relative to the root. The missing parent directories are created
+    /// in memory.
+    pub fn virtual_file<FilePath>(&mut self, path: FilePath, file: Box<dyn WasiFile>) -> &mut Self
+    where
+        FilePath: AsRef<Path>,
+    {
+        self.virtual_files.push((path.as_ref().to_path_buf(), file));
+
+        self
+    }
+
+    /// Register a device, which can then be mounted with [`WasiStateBuilder::device`].
+    /// `factory` creates the file of the device each time it's mounted, at
+    /// `default_path` unless another path is given.
+    ///
+    /// A device with the same name is replaced, including the built-in ones.
+    pub fn register_device<F>(&mut self, name: &str, default_path: &str, factory: F) -> &mut Self
+    where
+        F: Fn() -> Box<dyn WasiFile> + Send + 'static,
+    {
+        self.devices.register(name, default_path, Box::new(factory));
+
+        self
+    }
+
+    /// Mount the device `name` at its default path.
+    ///
+    /// The built-in devices are `null`, `zero`, `urandom` and `tty`, mounted
+    /// at `/dev/null`, `/dev/zero`, `/dev/urandom` and `/dev/tty`.
+    pub fn device(&mut self, name: &str) -> &mut Self {
+        self.mounted_devices.push((name.to_string(), None));
+
+        self
+    }
+
+    /// Mount the device `name` at `path`, relative to the root.
+    pub fn device_at<FilePath>(&mut self, name: &str, path: FilePath) -> &mut Self
+    where
+        FilePath: AsRef<Path>,
+    {
+        self.mounted_devices
+            .push((name.to_string(), Some(path.as_ref().to_path_buf())));
+
+        self
+    }
+
     /// Setup the WASI filesystem before running
     // TODO: improve ergonomics on this function
     pub fn setup_fs(
@@ -380,6 +437,20 @@ impl WasiStateBuilder {
                 .swap_file(__WASI_STDERR_FILENO, stderr_override)
                 .map_err(WasiStateCreationError::WasiFsError)?;
         }
+        for (path, file) in self.virtual_files.drain(..) {
+            wasi_fs
+                .add_virtual_file(&path, file)
+                .map_err(WasiStateCreationError::WasiFsError)?;
+        }
+        for (name, path) in self.mounted_devices.iter() {
+            let (default_path, file) = self
+                .devices
+                .create(name)
+                .ok_or_else(|| WasiStateCreationError::UnknownDevice(name.clone()))?;
+            wasi_fs
+                .add_virtual_file(path.as_deref().unwrap_or(default_path), file)
+                .map_err(WasiStateCreationError::WasiFsError)?;
+        }
         if let Some(f) = &self.setup_fs_fn {
             f(&mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
         }
@@ -540,4 +611,113 @@ mod test {
             _ => assert!(false),
         }
     }
+
+    #[test]
+    fn virtual_files() {
+        let state = create_wasi_state("test_prog")
+            .virtual_file("/dir/file", Box::new(crate::state::Stdout))
+            .virtual_file("dir/other_file", Box::new(crate::state::Stdout))
+            .build()
+            .unwrap();
+        let fs = &state.fs;
+        let root = fs.get_fd(crate::state::VIRTUAL_ROOT_FD).unwrap().inode;
+        let dir = match &fs.inodes[root].kind {
+            crate::state::Kind::Root { entries } => entries["dir"],
+            _ => unreachable!(),
+        };
+        match &fs.inodes[dir].kind {
+            crate::state::Kind::Root { entries } => {
+                assert!(entries.contains_key("file"));
+                assert!(entries.contains_key("other_file"));
+                // the files are only opened by the program
+                assert!(fs.fd_map.values().all(|fd| fd.inode != entries["file"]));
+            }
+            _ => panic!("the virtual directory should be in memory"),
+        }
+
+        let output = create_wasi_state("test_prog")
+            .virtual_file("/dir/file", Box::new(crate::state::Stdout))
+            .virtual_file("/dir/file", Box::new(crate::state::Stdout))
+            .build();
+        match output {
+            Err(WasiStateCreationError::WasiFsError(WasiFsError::AlreadyExists)) => (),
+            _ => panic!("a virtual file can't be added twice"),
+        }
+    }
+
+    /// Gets the open file at `path`, relative to the root.
+    fn lookup<'a>(fs: &'a WasiFs, path: &[&str]) -> &'a dyn WasiFile {
+        let mut inode = fs.get_fd(crate::state::VIRTUAL_ROOT_FD).unwrap().inode;
+        for name in path {
+            inode = match &fs.inodes[inode].kind {
+                crate::state::Kind::Root { entries } | crate::state::Kind::Dir { entries, .. } => {
+                    entries[*name]
+                }
+                _ => panic!("{:?} should be a directory", name),
+            };
+        }
+        match &fs.inodes[inode].kind {
+            crate::state::Kind::File {
+                handle: Some(handle),
+                ..
+            } => handle.as_ref(),
+            _ => panic!("{:?} should be an open file", path),
+        }
+    }
+
+    #[test]
+    fn devices() {
+        use crate::state::devices::{Null, Urandom, Zero};
+        use crate::state::{poll, PollEvent, PollEventSet};
+        use std::io::{Read, Write};
+
+        let state = create_wasi_state("test_prog")
+            .register_device("channel", "/rpc/channel", || Box::new(Null))
+            .device("zero")
+            .device("null")
+            .device_at("urandom", "/dev/random")
+            .device("channel")
+            .build()
+            .unwrap();
+        let fs = &state.fs;
+
+        let mut buf = [1; 4];
+        let zero = lookup(fs, &["dev", "zero"]);
+        let null = lookup(fs, &["dev", "null"]);
+        let channel = lookup(fs, &["rpc", "channel"]);
+        assert!(zero.downcast_ref::<Zero>().is_some());
+        assert!(null.downcast_ref::<Null>().is_some());
+        assert!(channel.downcast_ref::<Null>().is_some());
+        assert!(lookup(fs, &["dev", "random"])
+            .downcast_ref::<Urandom>()
+            .is_some());
+        assert_eq!(Zero.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0; 4]);
+        assert_eq!(Null.read(&mut buf).unwrap(), 0);
+        assert_eq!(Null.write(&buf).unwrap(), 4);
+
+        let poll_in = PollEvent::PollIn as PollEventSet;
+        let poll_out = PollEvent::PollOut as PollEventSet;
+        let files = [zero, null];
+        let mut seen_events = [0; 2];
+        assert_eq!(
+            poll(&files, &[poll_in, poll_in | poll_out], &mut seen_events),
+            Ok(2)
+        );
+        assert_eq!(seen_events, [poll_in, poll_in | poll_out]);
+
+        let output = create_wasi_state("test_prog").device("nope").build();
+        match output {
+            Err(WasiStateCreationError::UnknownDevice(name)) => assert_eq!(name, "nope"),
+            _ => panic!("an unknown device can't be mounted"),
+        }
+        let output = create_wasi_state("test_prog")
+            .device("null")
+            .device_at("zero", "/dev/null")
+            .build();
+        match output {
+            Err(WasiStateCreationError::WasiFsError(WasiFsError::AlreadyExists)) => (),
+            _ => panic!("two devices can't be mounted at the same path"),
+        }
+    }
 }
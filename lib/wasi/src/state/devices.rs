@@ -0,0 +1,230 @@
+//! Character devices implemented by the host, which can be mounted at
+//! virtual paths of the WASI filesystem (e.g. `/dev/null`).
+//!
+//! Besides the built-in devices, any [`WasiFile`] can be registered as
+//! a device with [`WasiStateBuilder::register_device`]. A device not
+//! backed by a host file descriptor reports its readiness to
+//! `poll_oneoff` with [`WasiFile::poll_readiness`].
+//!
+//! [`WasiStateBuilder::register_device`]: super::WasiStateBuilder::register_device
+
+pub use crate::state::{PollEvent, PollEventSet};
+use crate::state::{Stdin, WasiFile, WasiFsError};
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Creates a new instance of a device, each time it's mounted.
+pub type DeviceFactory = Box<dyn Fn() -> Box<dyn WasiFile> + Send>;
+
+/// The devices which can be mounted, by name.
+pub(crate) struct DeviceRegistry {
+    devices: BTreeMap<String, (PathBuf, DeviceFactory)>,
+}
+
+impl Default for DeviceRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            devices: BTreeMap::new(),
+        };
+        registry.register("null", "/dev/null", Box::new(|| Box::new(Null)));
+        registry.register("zero", "/dev/zero", Box::new(|| Box::new(Zero)));
+        registry.register("urandom", "/dev/urandom", Box::new(|| Box::new(Urandom)));
+        registry.register("tty", "/dev/tty", Box::new(|| Box::new(Tty)));
+        registry
+    }
+}
+
+impl fmt::Debug for DeviceRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.devices.iter().map(|(name, (path, _))| (name, path)))
+            .finish()
+    }
+}
+
+impl DeviceRegistry {
+    /// Registers the device `name`, replacing any device with the same
+    /// name.
+    pub fn register(&mut self, name: &str, default_path: &str, factory: DeviceFactory) {
+        self.devices
+            .insert(name.to_string(), (PathBuf::from(default_path), factory));
+    }
+
+    /// Creates an instance of the device `name`, with the path it's
+    /// mounted at by default.
+    pub fn create(&self, name: &str) -> Option<(&Path, Box<dyn WasiFile>)> {
+        self.devices
+            .get(name)
+            .map(|(path, factory)| (path.as_path(), factory()))
+    }
+}
+
+/// Implements the parts of `WasiFile` shared by the devices, which have
+/// no size nor timestamps.
+macro_rules! device_file {
+    () => {
+        fn last_accessed(&self) -> __wasi_timestamp_t {
+            0
+        }
+        fn last_modified(&self) -> __wasi_timestamp_t {
+            0
+        }
+        fn created_time(&self) -> __wasi_timestamp_t {
+            0
+        }
+        fn size(&self) -> u64 {
+            0
+        }
+        fn set_len(&mut self, _new_size: __wasi_filesize_t) -> Result<(), WasiFsError> {
+            Err(WasiFsError::PermissionDenied)
+        }
+        fn unlink(&mut self) -> Result<(), WasiFsError> {
+            Ok(())
+        }
+    };
+}
+
+/// Seeking a device does nothing, like for character devices on Linux.
+macro_rules! device_seek {
+    ($device:ty) => {
+        impl Seek for $device {
+            fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+                Ok(0)
+            }
+        }
+    };
+}
+
+/// Discards the data written to a device.
+macro_rules! device_sink {
+    ($device:ty) => {
+        impl Write for $device {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+/// `/dev/null`: reads nothing and discards what is written.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Null;
+
+impl Read for Null {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+device_seek!(Null);
+device_sink!(Null);
+
+#[typetag::serde]
+impl WasiFile for Null {
+    device_file!();
+
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Ok(0)
+    }
+}
+
+/// `/dev/zero`: reads zeros and discards what is written.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Zero;
+
+impl Read for Zero {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        for byte in buf.iter_mut() {
+            *byte = 0;
+        }
+        Ok(buf.len())
+    }
+}
+
+device_seek!(Zero);
+device_sink!(Zero);
+
+#[typetag::serde]
+impl WasiFile for Zero {
+    device_file!();
+
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Ok(usize::MAX)
+    }
+}
+
+/// `/dev/urandom`: reads random bytes from the host and discards what
+/// is written.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Urandom;
+
+impl Read for Urandom {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        getrandom::getrandom(buf).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(buf.len())
+    }
+}
+
+device_seek!(Urandom);
+device_sink!(Urandom);
+
+#[typetag::serde]
+impl WasiFile for Urandom {
+    device_file!();
+
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Ok(usize::MAX)
+    }
+}
+
+/// `/dev/tty`: the terminal of the host process, reading its standard
+/// input and writing to its standard output.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tty;
+
+impl Read for Tty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::stdin().read(buf)
+    }
+}
+
+device_seek!(Tty);
+
+impl Write for Tty {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stdout().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for Tty {
+    device_file!();
+
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Stdin.bytes_available()
+    }
+
+    #[cfg(unix)]
+    fn poll_readiness(&self, events: PollEventSet) -> PollEventSet {
+        // only reading can block, until the host's standard input is ready
+        let poll_in = PollEvent::PollIn as PollEventSet;
+        let mut ready = events & !poll_in;
+        if events & poll_in != 0 {
+            let mut seen_events = [0];
+            if crate::state::poll(&[&Stdin], &[poll_in], &mut seen_events).is_ok() {
+                ready |= seen_events[0] & poll_in;
+            }
+        }
+        ready
+    }
+}
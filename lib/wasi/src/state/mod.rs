@@ -16,9 +16,11 @@
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
 mod builder;
+pub mod devices;
 mod types;
 
 pub use self::builder::*;
+pub(crate) use self::devices::DeviceRegistry;
 pub use self::types::*;
 use crate::syscalls::types::*;
 use generational_arena::Arena;
@@ -30,7 +32,7 @@ use std::{
     cell::Cell,
     fs,
     io::Write,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     time::SystemTime,
 };
 use tracing::debug;
@@ -655,6 +657,103 @@ impl WasiFs {
         }
     }
 
+    /// Adds a file which isn't backed by the host filesystem at `path`,
+    /// relative to the root, and returns its inode.
+    ///
+    /// No file descriptor is allocated: the WASI program gets one each
+    /// time it opens the file with `path_open`, and closing it keeps the
+    /// file around. The missing parent directories are created in
+    /// memory; like the root, their entries can't be changed by the WASI
+    /// program.
+    pub fn add_virtual_file(
+        &mut self,
+        path: &Path,
+        file: Box<dyn WasiFile>,
+    ) -> Result<Inode, WasiFsError> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(WasiFsError::InvalidInput)?
+            .to_string();
+        let mut dir_inode = self
+            .get_fd(VIRTUAL_ROOT_FD)
+            .map_err(WasiFsError::from_wasi_err)?
+            .inode;
+
+        for component in path.parent().into_iter().flat_map(Path::components) {
+            let component = match component {
+                Component::Normal(component) => component
+                    .to_str()
+                    .ok_or(WasiFsError::InvalidInput)?
+                    .to_string(),
+                Component::RootDir | Component::CurDir => continue,
+                _ => return Err(WasiFsError::InvalidInput),
+            };
+            let entry = match &self.inodes[dir_inode].kind {
+                Kind::Dir { entries, .. } | Kind::Root { entries } => {
+                    entries.get(&component).cloned()
+                }
+                _ => return Err(WasiFsError::BaseNotDirectory),
+            };
+
+            dir_inode = match entry {
+                Some(inode) => inode,
+                None => {
+                    let stat = __wasi_filestat_t {
+                        st_filetype: __WASI_FILETYPE_DIRECTORY,
+                        ..__wasi_filestat_t::default()
+                    };
+                    let kind = Kind::Root {
+                        entries: HashMap::new(),
+                    };
+                    let inode = self.create_inode_with_stat(kind, false, component.clone(), stat);
+                    self.add_entry(dir_inode, component, inode)?;
+
+                    inode
+                }
+            };
+        }
+
+        match &self.inodes[dir_inode].kind {
+            Kind::Dir { entries, .. } | Kind::Root { entries } => {
+                if entries.contains_key(&name) {
+                    return Err(WasiFsError::AlreadyExists);
+                }
+            }
+            _ => return Err(WasiFsError::BaseNotDirectory),
+        }
+
+        // An empty host path marks the file as virtual.
+        let kind = Kind::File {
+            handle: Some(file),
+            path: PathBuf::new(),
+            fd: None,
+        };
+        let inode = self
+            .create_inode(kind, false, name.clone())
+            .map_err(|_| WasiFsError::IOError)?;
+        self.add_entry(dir_inode, name, inode)?;
+
+        Ok(inode)
+    }
+
+    /// Adds `inode` to the entries of the directory `dir_inode`.
+    fn add_entry(
+        &mut self,
+        dir_inode: Inode,
+        name: String,
+        inode: Inode,
+    ) -> Result<(), WasiFsError> {
+        match &mut self.inodes[dir_inode].kind {
+            Kind::Dir { entries, .. } | Kind::Root { entries } => {
+                entries.insert(name, inode);
+
+                Ok(())
+            }
+            _ => Err(WasiFsError::BaseNotDirectory),
+        }
+    }
+
     /// Change the backing of a given file descriptor
     /// Returns the old backing
     /// TODO: add examples
@@ -1457,6 +1556,11 @@ impl WasiFs {
         let is_preopened = inodeval_mut.is_preopened;
 
         match &mut inodeval_mut.kind {
+            // Virtual files can't be reopened from the host, so they
+            // stay open for the next `path_open`.
+            Kind::File { path, .. } if path.as_os_str().is_empty() => {
+                self.fd_map.remove(&fd);
+            }
             Kind::File { ref mut handle, .. } => {
                 let mut empty_handle = None;
                 std::mem::swap(handle, &mut empty_handle);
@@ -210,6 +210,13 @@ pub trait WasiFile: fmt::Debug + Send + Write + Read + Seek + 'static + Upcastab
     fn get_raw_fd(&self) -> Option<i32> {
         None
     }
+
+    /// Used for polling the files without a host fd.  Returns the events of `events` the
+    /// file is ready for, this function must not block.
+    /// Default implementation returns `events`, so the file is always ready
+    fn poll_readiness(&self, events: PollEventSet) -> PollEventSet {
+        events
+    }
 }
 
 // Implementation of `Upcastable` taken from https://users.rust-lang.org/t/why-does-downcasting-not-work-for-subtraits/33286/7 .
@@ -360,7 +367,11 @@ impl PollEventBuilder {
     }
 }
 
-#[cfg(unix)]
+/// Polls `selfs` for `events`, writing the events each of them is ready
+/// for in `seen_events`, and returns the number of files ready.
+///
+/// The files with a host fd are polled by the host, the other ones with
+/// [`WasiFile::poll_readiness`].
 pub(crate) fn poll(
     selfs: &[&dyn WasiFile],
     events: &[PollEventSet],
@@ -369,24 +380,52 @@ pub(crate) fn poll(
     if !(selfs.len() == events.len() && events.len() == seen_events.len()) {
         return Err(WasiFsError::InvalidInput);
     }
-    let mut fds = selfs
+    let mut host_fds = vec![];
+    let mut ready = 0;
+    for (i, s) in selfs.iter().enumerate() {
+        match s.get_raw_fd() {
+            Some(host_fd) => host_fds.push((i, host_fd)),
+            None => {
+                seen_events[i] = s.poll_readiness(events[i]);
+                if seen_events[i] != 0 {
+                    ready += 1;
+                }
+            }
+        }
+    }
+    if !host_fds.is_empty() {
+        // don't wait for the host if a file is already ready
+        let timeout = if ready > 0 { 0 } else { 1 };
+        ready += poll_host_fds(&host_fds, events, seen_events, timeout)?;
+    }
+    Ok(ready)
+}
+
+/// Polls the host fds of `host_fds`, which are paired with their index
+/// in `events` and `seen_events`.
+#[cfg(unix)]
+fn poll_host_fds(
+    host_fds: &[(usize, i32)],
+    events: &[PollEventSet],
+    seen_events: &mut [PollEventSet],
+    timeout: i32,
+) -> Result<u32, WasiFsError> {
+    let mut fds = host_fds
         .iter()
-        .enumerate()
-        .filter_map(|(i, s)| s.get_raw_fd().map(|rfd| (i, rfd)))
-        .map(|(i, host_fd)| libc::pollfd {
+        .map(|&(i, host_fd)| libc::pollfd {
             fd: host_fd,
             events: poll_event_set_to_platform_poll_events(events[i]),
             revents: 0,
         })
         .collect::<Vec<_>>();
-    let result = unsafe { libc::poll(fds.as_mut_ptr(), selfs.len() as _, 1) };
+    let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout) };
 
     if result < 0 {
         // TODO: check errno and return value
         return Err(WasiFsError::IOError);
     }
     // convert result and write back values
-    for (i, fd) in fds.into_iter().enumerate() {
+    for (&(i, _), fd) in host_fds.iter().zip(fds) {
         seen_events[i] = platform_poll_events_to_pollevent_set(fd.revents);
     }
     // unwrap is safe because we check for negative values above
@@ -394,11 +433,12 @@ pub(crate) fn poll(
 }
 
 #[cfg(not(unix))]
-pub(crate) fn poll(
-    _selfs: &[&dyn WasiFile],
+fn poll_host_fds(
+    _host_fds: &[(usize, i32)],
     _events: &[PollEventSet],
     _seen_events: &mut [PollEventSet],
-) -> Result<(), WasiFsError> {
+    _timeout: i32,
+) -> Result<u32, WasiFsError> {
     unimplemented!("HostFile::poll in WasiFile is not implemented for non-Unix-like targets yet");
 }
 
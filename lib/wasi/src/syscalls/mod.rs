@@ -1726,13 +1726,37 @@ pub fn path_open(
             } => {
                 if let Some(special_fd) = fd {
                     // short circuit if we're dealing with a special file
-                    assert!(handle.is_some());
+                    if handle.is_none() {
+                        return __WASI_EBADF;
+                    }
                     fd_cell.set(*special_fd);
                     return __WASI_ESUCCESS;
                 }
                 if o_flags & __WASI_O_DIRECTORY != 0 {
                     return __WASI_ENOTDIR;
                 }
+                if path.as_os_str().is_empty() {
+                    // a virtual file: every fd shares its handle
+                    if o_flags & __WASI_O_EXCL != 0 {
+                        return __WASI_EEXIST;
+                    }
+                    if handle.is_none() {
+                        return __WASI_EBADF;
+                    }
+                    open_flags |= Fd::READ;
+                    if adjusted_rights & __WASI_RIGHT_FD_WRITE != 0 {
+                        open_flags |= Fd::WRITE;
+                    }
+                    let new_fd = wasi_try!(state.fs.create_fd(
+                        adjusted_rights,
+                        fs_rights_inheriting,
+                        fs_flags,
+                        open_flags,
+                        inode,
+                    ));
+                    fd_cell.set(new_fd);
+                    return __WASI_ESUCCESS;
+                }
                 if o_flags & __WASI_O_EXCL != 0 && path.exists() {
                     return __WASI_EEXIST;
                 }
@@ -9,6 +9,7 @@ mod middlewares;
 mod multi_value_imports;
 mod native_functions;
 mod serialize;
+mod tier_up;
 mod traps;
 mod utils;
 mod wasi;
@@ -2,7 +2,7 @@ use crate::utils::get_store_with_middlewares;
 use anyhow::Result;
 
 use std::sync::Arc;
-use wasmer::wasmparser::{Operator, Result as WpResult};
+use wasmer::wasmparser::Operator;
 use wasmer::*;
 
 #[derive(Debug)]
@@ -16,10 +16,14 @@ struct Add2Mul {
 }
 
 impl FunctionMiddlewareGenerator for Add2MulGen {
-    fn generate<'a>(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
-        Box::new(Add2Mul {
+    fn generate_for_module(
+        &self,
+        _: &ModuleInfo,
+        _: LocalFunctionIndex,
+    ) -> Result<Box<dyn FunctionMiddleware>, CompileError> {
+        Ok(Box::new(Add2Mul {
             value_off: self.value_off,
-        })
+        }))
     }
 }
 
@@ -28,7 +32,7 @@ impl FunctionMiddleware for Add2Mul {
         &mut self,
         operator: Operator<'a>,
         state: &mut MiddlewareReaderState<'a>,
-    ) -> WpResult<()> {
+    ) -> Result<(), MiddlewareError> {
         match operator {
             Operator::I32Add => {
                 state.push_operator(Operator::I32Mul);
@@ -56,8 +60,12 @@ struct Fusion {
 }
 
 impl FunctionMiddlewareGenerator for FusionGen {
-    fn generate<'a>(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
-        Box::new(Fusion { state: 0 })
+    fn generate_for_module(
+        &self,
+        _: &ModuleInfo,
+        _: LocalFunctionIndex,
+    ) -> Result<Box<dyn FunctionMiddleware>, CompileError> {
+        Ok(Box::new(Fusion { state: 0 }))
     }
 }
 
@@ -66,7 +74,7 @@ impl FunctionMiddleware for Fusion {
         &mut self,
         operator: Operator<'a>,
         state: &mut MiddlewareReaderState<'a>,
-    ) -> WpResult<()> {
+    ) -> Result<(), MiddlewareError> {
         match (operator, self.state) {
             (Operator::I32Add, 0) => {
                 self.state = 1;
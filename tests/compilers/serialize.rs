@@ -18,6 +18,43 @@ fn test_serialize() -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "test-jit")]
+#[test]
+fn test_summarize_records_the_target() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, "(module)")?;
+    let summary = wasmer_engine_jit::JITArtifact::summarize(&module.serialize()?)?;
+    let target = store.engine().target();
+    assert!(summary.target.starts_with(&target.triple().to_string()));
+    for feature in target.cpu_features().iter() {
+        assert!(summary.target.contains(&feature.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "test-jit")]
+#[test]
+fn test_deserialize_rejects_other_format_versions() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, "(module)")?;
+    let serialized_bytes = module.serialize()?;
+
+    // Drop the format version, like in the artifacts serialized before
+    // it was introduced.
+    let magic_header_len = b"\0wasmer-jit".len();
+    let mut legacy_bytes = serialized_bytes[..magic_header_len].to_vec();
+    legacy_bytes.extend(&serialized_bytes[magic_header_len + b"\0v2".len()..]);
+    assert!(matches!(
+        wasmer_engine_jit::JITArtifact::summarize(&legacy_bytes),
+        Err(DeserializeError::Incompatible(_))
+    ));
+    assert!(matches!(
+        unsafe { Module::deserialize(&store, &legacy_bytes) },
+        Err(DeserializeError::Incompatible(_))
+    ));
+    Ok(())
+}
+
 #[test]
 fn test_deserialize() -> Result<()> {
     let store = get_store(false);
@@ -0,0 +1,236 @@
+#![cfg(all(feature = "test-jit", feature = "singlepass", feature = "cranelift"))]
+
+use anyhow::Result;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use wasmer::wasmparser::Operator;
+use wasmer::*;
+use wasmer_compiler::CompileError;
+use wasmer_compiler_cranelift::Cranelift;
+use wasmer_compiler_singlepass::Singlepass;
+use wasmer_engine_jit::{JITArtifact, TierUpPolicy, JIT};
+use wasmer_middlewares::metering::{get_remaining_points, MeteringPoints};
+use wasmer_middlewares::Metering;
+use wasmer_types::entity::EntityRef;
+
+#[test]
+fn hot_functions_are_tiered_up() -> Result<()> {
+    let baseline = Singlepass::default();
+    let optimizing = Cranelift::default();
+    let policy = TierUpPolicy {
+        threshold: 10,
+        sample_interval: Duration::from_millis(1),
+        ..TierUpPolicy::default()
+    };
+    let engine = JIT::new(&baseline).tier_up(&optimizing, policy)?.engine();
+    let store = Store::new(&engine);
+    let wat = r#"(module
+        (func $add_one (param i32) (result i32)
+           (i32.add (local.get 0) (i32.const 1)))
+        (func (export "add_one") (param i32) (result i32)
+           (call $add_one (local.get 0)))
+        (func (export "cold") (result i32)
+           (i32.const 42))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let add_one: NativeFunc<i32, i32> = instance.exports.get_native_function("add_one")?;
+    let cold: NativeFunc<(), i32> = instance.exports.get_native_function("cold")?;
+    let artifact = module.artifact().downcast_ref::<JITArtifact>().unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut value = 0;
+    while !artifact.is_optimized(LocalFunctionIndex::new(0)) {
+        assert!(
+            Instant::now() < deadline,
+            "the hot function was not tiered up"
+        );
+        value = add_one.call(value)?;
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert!(value >= 10);
+
+    // Both the direct call and the export now go through the optimized code.
+    assert_eq!(add_one.call(value)?, value + 1);
+    assert!(!artifact.is_optimized(LocalFunctionIndex::new(2)));
+    assert_eq!(cold.call()?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn functions_with_hot_loops_are_tiered_up() -> Result<()> {
+    let baseline = Singlepass::default();
+    let optimizing = Cranelift::default();
+    let policy = TierUpPolicy {
+        threshold: 1000,
+        sample_interval: Duration::from_millis(1),
+        ..TierUpPolicy::default()
+    };
+    let engine = JIT::new(&baseline).tier_up(&optimizing, policy)?.engine();
+    let store = Store::new(&engine);
+    let wat = r#"(module
+        (import "host" "increment" (func $increment (param i32) (result i32)))
+        (func (export "sum") (param $n i32) (result i32)
+           (local $sum i32)
+           (block $done
+             (loop $loop
+               (br_if $done (i32.eqz (local.get $n)))
+               (local.set $sum (i32.add (local.get $sum) (local.get $n)))
+               (local.set $n (call $increment (i32.sub (local.get $n) (i32.const 2))))
+               (br $loop)))
+           (local.get $sum))
+        (func (export "no_loop") (result i32)
+           (i32.const 42))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let increment = Function::new_native(&store, |x: i32| x + 1);
+    let import_object = imports! {
+        "host" => {
+            "increment" => increment,
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+    let sum: NativeFunc<i32, i32> = instance.exports.get_native_function("sum")?;
+    let no_loop: NativeFunc<(), i32> = instance.exports.get_native_function("no_loop")?;
+    let artifact = module.artifact().downcast_ref::<JITArtifact>().unwrap();
+
+    // A single call looping more than the threshold makes `sum` hot.
+    assert_eq!(sum.call(10_000)?, 50_005_000);
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !artifact.is_optimized(LocalFunctionIndex::new(0)) {
+        assert!(
+            Instant::now() < deadline,
+            "the hot function was not tiered up"
+        );
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert_eq!(sum.call(100)?, 5050);
+    assert!(!artifact.is_optimized(LocalFunctionIndex::new(1)));
+    assert_eq!(no_loop.call()?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn deserialized_modules_are_not_tiered_up() -> Result<()> {
+    let baseline = Singlepass::default();
+    let optimizing = Cranelift::default();
+    let policy = TierUpPolicy {
+        threshold: 10,
+        sample_interval: Duration::from_millis(1),
+        ..TierUpPolicy::default()
+    };
+    let engine = JIT::new(&baseline).tier_up(&optimizing, policy)?.engine();
+    let store = Store::new(&engine);
+    let wat = r#"(module
+        (func (export "add_one") (param i32) (result i32)
+           (i32.add (local.get 0) (i32.const 1)))
+)"#;
+    let serialized = Module::new(&store, wat)?.serialize()?;
+    let module = unsafe { Module::deserialize(&store, &serialized)? };
+    let instance = Instance::new(&module, &imports! {})?;
+    let add_one: NativeFunc<i32, i32> = instance.exports.get_native_function("add_one")?;
+    let artifact = module.artifact().downcast_ref::<JITArtifact>().unwrap();
+
+    let mut value = 0;
+    for _ in 0..100 {
+        value = add_one.call(value)?;
+    }
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(value, 100);
+    assert!(!artifact.is_optimized(LocalFunctionIndex::new(0)));
+
+    Ok(())
+}
+
+#[test]
+fn tiering_up_stops_after_the_maximum_rounds() -> Result<()> {
+    let baseline = Singlepass::default();
+    let optimizing = Cranelift::default();
+    let policy = TierUpPolicy {
+        threshold: 10,
+        sample_interval: Duration::from_millis(1),
+        max_rounds: 0,
+    };
+    let engine = JIT::new(&baseline).tier_up(&optimizing, policy)?.engine();
+    let store = Store::new(&engine);
+    let wat = r#"(module
+        (func (export "add_one") (param i32) (result i32)
+           (i32.add (local.get 0) (i32.const 1)))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let add_one: NativeFunc<i32, i32> = instance.exports.get_native_function("add_one")?;
+    let artifact = module.artifact().downcast_ref::<JITArtifact>().unwrap();
+
+    let mut value = 0;
+    for _ in 0..100 {
+        value = add_one.call(value)?;
+    }
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(value, 100);
+    assert!(!artifact.is_optimized(LocalFunctionIndex::new(0)));
+
+    Ok(())
+}
+
+fn remaining_points(instance: &Instance) -> u64 {
+    match get_remaining_points(instance).unwrap() {
+        MeteringPoints::Remaining(points) => points,
+        MeteringPoints::Exhausted => panic!("the instance ran out of points"),
+    }
+}
+
+#[test]
+fn tiered_up_functions_keep_their_middlewares() -> Result<()> {
+    let metering = Arc::new(Metering::new(1_000_000, |_: &Operator| 1));
+    let mut baseline = Singlepass::default();
+    baseline.push_middleware(metering.clone());
+    let mut optimizing = Cranelift::default();
+    optimizing.push_middleware(metering);
+    let policy = TierUpPolicy {
+        threshold: 10,
+        sample_interval: Duration::from_millis(1),
+        ..TierUpPolicy::default()
+    };
+    let engine = JIT::new(&baseline).tier_up(&optimizing, policy)?.engine();
+    let store = Store::new(&engine);
+    let wat = r#"(module
+        (func (export "add_one") (param i32) (result i32)
+           (i32.add (local.get 0) (i32.const 1)))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let add_one: NativeFunc<i32, i32> = instance.exports.get_native_function("add_one")?;
+    let artifact = module.artifact().downcast_ref::<JITArtifact>().unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut value = 0;
+    while !artifact.is_optimized(LocalFunctionIndex::new(0)) {
+        assert!(
+            Instant::now() < deadline,
+            "the hot function was not tiered up"
+        );
+        value = add_one.call(value)?;
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    // The optimized code still consumes points.
+    let points = remaining_points(&instance);
+    assert_eq!(add_one.call(value)?, value + 1);
+    assert!(remaining_points(&instance) < points);
+
+    Ok(())
+}
+
+#[test]
+fn tiering_up_needs_the_same_middlewares() {
+    let mut baseline = Singlepass::default();
+    baseline.push_middleware(Arc::new(Metering::new(1_000_000, |_: &Operator| 1)));
+    let optimizing = Cranelift::default();
+    let result = JIT::new(&baseline).tier_up(&optimizing, TierUpPolicy::default());
+    assert!(matches!(result, Err(CompileError::Codegen(_))));
+}
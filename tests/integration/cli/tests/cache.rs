@@ -0,0 +1,111 @@
+//! CLI tests for the cache subcommand.
+
+use anyhow::{bail, Context};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use wasmer_integration_tests_cli::*;
+
+const FIRST_KEY: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+const SECOND_KEY: &str = "0202020202020202020202020202020202020202020202020202020202020202";
+
+/// Runs `wasmer cache` with `args`, using `cache_dir` as the cache
+/// directory, and returns its standard output and error.
+fn run_cache(cache_dir: &Path, args: &[&str]) -> anyhow::Result<(String, String)> {
+    let output = Command::new(get_wasmer_path())
+        .arg("cache")
+        .args(args)
+        .env("WASMER_CACHE_DIR", cache_dir)
+        .env_remove("WASMER_CACHE_MAX_SIZE")
+        .env_remove("WASMER_CACHE_MAX_ENTRIES")
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "wasmer cache {} failed with: stderr: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok((
+        String::from_utf8(output.stdout)?,
+        String::from_utf8(output.stderr)?,
+    ))
+}
+
+/// Creates a cache with two entries of the Cranelift compiler, returning
+/// the directory given to `WASMER_CACHE_DIR`.
+fn populate_cache() -> anyhow::Result<tempfile::TempDir> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let (stdout, _) = run_cache(temp_dir.path(), &["dir"])?;
+    let compiler_dir = PathBuf::from(stdout.trim()).join("cranelift");
+    fs::create_dir_all(&compiler_dir)?;
+    fs::write(compiler_dir.join(FIRST_KEY), vec![0; 1024])?;
+    fs::write(compiler_dir.join(SECOND_KEY), vec![0; 2048])?;
+    Ok(temp_dir)
+}
+
+#[test]
+fn cache_list_works() -> anyhow::Result<()> {
+    let temp_dir = populate_cache()?;
+    let (stdout, _) = run_cache(temp_dir.path(), &["list"])?;
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "unexpected output: {}", stdout);
+    for key in &[FIRST_KEY, SECOND_KEY] {
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.starts_with(key) && line.contains("cranelift")),
+            "{} is not listed in: {}",
+            key,
+            stdout
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn cache_usage_works() -> anyhow::Result<()> {
+    let temp_dir = populate_cache()?;
+    let (stdout, _) = run_cache(temp_dir.path(), &["usage"])?;
+    let cranelift = stdout
+        .lines()
+        .find(|line| line.starts_with("cranelift"))
+        .context("no usage of the cranelift cache")?;
+    assert!(
+        cranelift.contains("2 modules"),
+        "unexpected output: {}",
+        stdout
+    );
+    let total = stdout
+        .lines()
+        .find(|line| line.starts_with("total"))
+        .context("no total usage")?;
+    assert!(total.contains("2 modules"), "unexpected output: {}", stdout);
+    Ok(())
+}
+
+#[test]
+fn cache_prune_works() -> anyhow::Result<()> {
+    let temp_dir = populate_cache()?;
+
+    // Without a budget, nothing is removed.
+    let (_, stderr) = run_cache(temp_dir.path(), &["prune"])?;
+    assert!(stderr.contains("Removed 0 cached modules"), "{}", stderr);
+
+    let (_, stderr) = run_cache(temp_dir.path(), &["prune", "--max-entries", "1"])?;
+    assert!(stderr.contains("Removed 1 cached modules"), "{}", stderr);
+    let (stdout, _) = run_cache(temp_dir.path(), &["list"])?;
+    assert_eq!(stdout.lines().count(), 1, "unexpected output: {}", stdout);
+
+    let (_, stderr) = run_cache(temp_dir.path(), &["prune", "--max-size", "0"])?;
+    assert!(stderr.contains("Removed 1 cached modules"), "{}", stderr);
+    let (stdout, _) = run_cache(temp_dir.path(), &["usage"])?;
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line.starts_with("total") && line.contains("0 modules")),
+        "unexpected output: {}",
+        stdout
+    );
+    Ok(())
+}
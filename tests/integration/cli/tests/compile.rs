@@ -171,3 +171,172 @@ fn object_file_engine_works() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Whether a disassembler is available for `wasmer compile --emit-asm`.
+fn objdump_is_available() -> bool {
+    ["llvm-objdump", "objdump"].iter().any(|objdump| {
+        Command::new(objdump)
+            .arg("--version")
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    })
+}
+
+#[test]
+fn emit_asm_works() -> anyhow::Result<()> {
+    if !objdump_is_available() {
+        eprintln!("skipping `emit_asm_works`: no objdump found");
+        return Ok(());
+    }
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let operating_dir: PathBuf = temp_dir.path().to_owned();
+    let wasm_path = operating_dir.join("add.wat");
+    fs::write(
+        &wasm_path,
+        r#"(module
+  (func $add (export "add") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add))"#,
+    )?;
+    let asm_path = operating_dir.join("add.s");
+
+    let output = Command::new(get_wasmer_path())
+        .current_dir(&operating_dir)
+        .arg("compile")
+        .arg(&wasm_path)
+        .arg(Compiler::Cranelift.to_flag())
+        .arg(Engine::Jit.to_flag())
+        .arg("-o")
+        .arg(operating_dir.join("add.wjit"))
+        .arg("--emit-asm")
+        .arg(&asm_path)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "wasmer compile failed with: stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let listing = fs::read_to_string(&asm_path)?;
+    assert!(listing.starts_with(";; function 0 $add [I32, I32] -> [I32]\n"));
+    assert!(listing.contains("; wasm @0x"));
+    Ok(())
+}
+
+/// Runs `wasmer compile --pre-init init` on the module `wat`, writing the
+/// pre-initialized module to `pre_init.wasm` in `operating_dir`.
+fn run_pre_init(operating_dir: &Path, wat: &str) -> anyhow::Result<std::process::Output> {
+    let wasm_path = operating_dir.join("module.wat");
+    fs::write(&wasm_path, wat)?;
+    Ok(Command::new(get_wasmer_path())
+        .current_dir(operating_dir)
+        .arg("compile")
+        .arg(&wasm_path)
+        .arg(Compiler::Cranelift.to_flag())
+        .arg(Engine::Jit.to_flag())
+        .arg("-o")
+        .arg(operating_dir.join("module.wjit"))
+        .arg("--pre-init")
+        .arg("init")
+        .arg("--pre-init-output")
+        .arg(operating_dir.join("pre_init.wasm"))
+        .output()?)
+}
+
+/// Runs the pre-initialized module with `wasmer run` and returns its
+/// standard output.
+fn run_pre_initialized(operating_dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new(get_wasmer_path())
+        .current_dir(operating_dir)
+        .arg("run")
+        .arg(operating_dir.join("pre_init.wasm"))
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "wasmer run failed with: stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn pre_init_works() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let operating_dir: PathBuf = temp_dir.path().to_owned();
+    let output = run_pre_init(
+        &operating_dir,
+        r#"(module
+  (global $counter (mut i32) (i32.const 0))
+  (func (export "init") (global.set $counter (i32.const 42)))
+  (func (export "get") (result i32) (global.get $counter)))"#,
+    )?;
+    if !output.status.success() {
+        bail!(
+            "wasmer compile failed with: stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    assert!(operating_dir.join("module.wjit").exists());
+
+    let stdout = run_pre_initialized(&operating_dir, &["--invoke", "get"])?;
+    assert_eq!(stdout.trim(), "42");
+    Ok(())
+}
+
+#[test]
+fn pre_init_works_with_wasi() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let operating_dir: PathBuf = temp_dir.path().to_owned();
+    // `init` writes to the memory and prints it, which needs WASI to be
+    // bound to the memory of the instance.
+    let output = run_pre_init(
+        &operating_dir,
+        r#"(module
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 16) "cold\n")
+  (func $print
+    (i32.store (i32.const 0) (i32.const 16))
+    (i32.store (i32.const 4) (i32.const 5))
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8))))
+  (func (export "init")
+    (i32.store (i32.const 16) (i32.const 0x6d726177))
+    (call $print))
+  (func (export "_start") (call $print)))"#,
+    )?;
+    if !output.status.success() {
+        bail!(
+            "wasmer compile failed with: stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    assert!(String::from_utf8_lossy(&output.stdout).contains("warm\n"));
+
+    let stdout = run_pre_initialized(&operating_dir, &[])?;
+    assert_eq!(stdout, "warm\n");
+    Ok(())
+}
+
+#[test]
+fn pre_init_fails_on_dropped_segments() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let operating_dir: PathBuf = temp_dir.path().to_owned();
+    let output = run_pre_init(
+        &operating_dir,
+        r#"(module
+  (memory 1)
+  (data $data "data")
+  (func (export "init") (data.drop $data)))"#,
+    )?;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("dropped"));
+    assert!(!operating_dir.join("pre_init.wasm").exists());
+    Ok(())
+}